@@ -0,0 +1,81 @@
+//! Benchmark del tokenizer su un'espressione di ~100k caratteri.
+//!
+//! Confronta l'implementazione attuale (cursore sui byte, avanzamento O(1)) con quella
+//! precedente, che ricavava ogni carattere risezionando la stringa di input.
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use mathsolver::Tokenizer;
+
+/// Implementazione precedente del tokenizer, mantenuta solo come riferimento per il confronto.
+///
+/// Riproduce il ciclo originale: ogni carattere viene ricavato con `input[position..].chars().next()`
+/// e la posizione avanza di un byte alla volta.
+mod legacy {
+    use mathsolver::{Span, Token};
+
+    /// Tokenizza `input` restituendo token e intervalli, come il tokenizer originale.
+    pub fn tokenize(input: &str) -> (Vec<Token>, Vec<Span>) {
+        let mut tokens = Vec::new();
+        let mut spans = Vec::new();
+        let mut position = 0;
+        let current_char = |position: usize| input[position..].chars().next().unwrap();
+
+        while position < input.len() {
+            let c = current_char(position);
+            let start = position;
+
+            if c.is_whitespace() {
+                position += 1;
+                continue;
+            }
+
+            if c.is_ascii_digit() || c == '.' {
+                while position < input.len() && (current_char(position).is_ascii_digit() || current_char(position) == '.') {
+                    position += 1;
+                }
+                tokens.push(Token::Number(input[start..position].parse().unwrap()));
+            } else if c.is_ascii_alphabetic() || c == '_' {
+                while position < input.len() && (current_char(position).is_ascii_alphanumeric() || current_char(position) == '_') {
+                    position += 1;
+                }
+                tokens.push(Token::Identifier(input[start..position].to_string()));
+            } else {
+                tokens.push(match c {
+                    '+' => Token::Plus,
+                    '*' => Token::Multiply,
+                    '=' => Token::Equals,
+                    _ => unreachable!("carattere non previsto nel benchmark: {}", c),
+                });
+                position += 1;
+            }
+            spans.push(start..position);
+        }
+
+        (tokens, spans)
+    }
+}
+
+/// Costruisce un'espressione di ~100k caratteri (es. "1.5+2*1.5+2*...").
+fn large_expression() -> String {
+    format!("{}1 =", "1.5+2*".repeat(17_000))
+}
+
+fn bench_tokenizer(c: &mut Criterion) {
+    let input = large_expression();
+
+    // Entrambe le implementazioni devono produrre gli stessi token e intervalli (input ASCII)
+    let (expected_tokens, expected_spans) = legacy::tokenize(&input);
+    let mut tokenizer = Tokenizer::new(&input);
+    assert_eq!(tokenizer.tokenize().unwrap(), expected_tokens);
+    assert_eq!(tokenizer.spans(), expected_spans.as_slice());
+
+    let mut group = c.benchmark_group("tokenizer_100k");
+    group.bench_function("legacy_str_slicing", |b| b.iter(|| legacy::tokenize(black_box(&input))));
+    group.bench_function("byte_cursor", |b| b.iter(|| Tokenizer::new(black_box(&input)).tokenize().unwrap()));
+    group.finish();
+}
+
+criterion_group!(benches, bench_tokenizer);
+criterion_main!(benches);
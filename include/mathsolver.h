@@ -0,0 +1,27 @@
+/*
+ * Interfaccia C di MathSolver (feature `capi`).
+ *
+ * Compilazione della libreria statica:
+ *   cargo rustc --lib --release --features capi --crate-type staticlib
+ */
+#ifndef MATHSOLVER_H
+#define MATHSOLVER_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define MATHSOLVER_OK          0
+#define MATHSOLVER_ERR_NULL   -1  /* puntatore nullo */
+#define MATHSOLVER_ERR_UTF8   -2  /* espressione non UTF-8 */
+#define MATHSOLVER_ERR_SYNTAX -3  /* errore di tokenizzazione o di sintassi */
+#define MATHSOLVER_ERR_MATH   -4  /* errore matematico (es. divisione per zero) */
+
+/* Valuta `expr` e scrive il risultato in `*out`; restituisce 0 o un codice negativo. */
+int mathsolver_eval(const char *expr, double *out);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* MATHSOLVER_H */
@@ -0,0 +1,276 @@
+use core::ops::RangeInclusive;
+
+/// Funzioni predefinite richiamabili nelle espressioni.
+///
+/// Una funzione si applica con la forma prefissa `sqrt(16)`, con gli argomenti separati da `,`
+/// (`powmod(2, 10, 1000)`); le funzioni unarie ammettono anche la forma postfissa `16 sqrt`.
+///
+/// Derive:
+/// - `Debug`, `Clone`, `Copy`, `PartialEq`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Function {
+    /// Radice quadrata.
+    Sqrt,
+
+    /// Valore assoluto.
+    Abs,
+
+    /// Seno (argomento nella modalità angolare corrente).
+    Sin,
+
+    /// Coseno (argomento nella modalità angolare corrente).
+    Cos,
+
+    /// Tangente (argomento nella modalità angolare corrente).
+    Tan,
+
+    /// Arcoseno (risultato nella modalità angolare corrente).
+    Asin,
+
+    /// Arcocoseno (risultato nella modalità angolare corrente).
+    Acos,
+
+    /// Arcotangente (risultato nella modalità angolare corrente).
+    Atan,
+
+    /// Arcotangente a due argomenti `atan2(y, x)`: l'angolo del punto `(x, y)`, in `(-π, π]`
+    /// (risultato nella modalità angolare corrente).
+    Atan2,
+
+    /// Seno iperbolico (argomento reale, indipendente dalla modalità angolare).
+    Sinh,
+
+    /// Coseno iperbolico.
+    Cosh,
+
+    /// Tangente iperbolica.
+    Tanh,
+
+    /// Inversa del seno iperbolico.
+    Asinh,
+
+    /// Inversa del coseno iperbolico, definita per `x >= 1`.
+    Acosh,
+
+    /// Inversa della tangente iperbolica, definita per `-1 < x < 1`.
+    Atanh,
+
+    /// Logaritmo naturale.
+    Ln,
+
+    /// Logaritmo: `log(x)` in base 10, `log(x, b)` in base `b`.
+    Log,
+
+    /// Logaritmo in base 10.
+    Log10,
+
+    /// Esponenziale `e^x`.
+    Exp,
+
+    /// Parte intera inferiore.
+    Floor,
+
+    /// Parte intera superiore.
+    Ceil,
+
+    /// Arrotondamento all'intero più vicino; a metà strada si allontana dallo zero (`round(2.5)` → 3, `round(-2.5)` → -3).
+    Round,
+
+    /// Troncamento verso lo zero (`trunc(-2.9)` → -2).
+    Trunc,
+
+    /// Resto della divisione `mod(a, m)`, con il segno stabilito da `ModuloMode`
+    /// (`mod(-10, 3)` → -1 troncato, 2 euclideo).
+    Mod,
+
+    /// Potenza modulare `powmod(base, esponente, modulo)`, calcolata su interi.
+    Powmod,
+
+    /// Massimo comun divisore di due interi (`gcd(0, 0)` → 0).
+    Gcd,
+
+    /// Minimo comune multiplo di due interi (0 se uno degli argomenti è 0).
+    Lcm,
+
+    /// Funzione gamma di Eulero, definita anche per i non interi (`gamma(0.5)` → √π).
+    Gamma,
+
+    /// Fattoriale generalizzato `factorial(x) = gamma(x + 1)` (`factorial(0.5)` ≈ 0.886).
+    Factorial,
+
+    /// Limita un valore a un intervallo: `clamp(x, lo, hi)`, con `lo <= hi`.
+    Clamp,
+
+    /// Segno: -1, 0 o 1 (`sign(-0)` → 0).
+    Sign,
+
+    /// Variazione percentuale `pct_change(da, a) = (a - da) / da * 100` (`pct_change(100, 150)` → 50).
+    PctChange,
+
+    /// Percentuale di un valore `pct_of(p, x) = p / 100 * x` (`pct_of(20, 50)` → 10).
+    PctOf,
+
+    /// Conversione da radianti a gradi (`deg(pi)` → 180), indipendente dalla modalità angolare.
+    Deg,
+
+    /// Conversione da gradi a radianti (`rad(180)` → π), indipendente dalla modalità angolare.
+    Rad,
+
+    /// Somma degli elementi di una lista (`sum([1, 2, 3])` → 6).
+    Sum,
+
+    /// Prodotto degli elementi di una lista (`product([2, 3, 4])` → 24).
+    Product,
+
+    /// Media aritmetica degli elementi di una lista (`mean([1, 2, 3, 4])` → 2.5).
+    Mean,
+
+    /// Minimo degli elementi di una lista.
+    Min,
+
+    /// Massimo degli elementi di una lista.
+    Max,
+}
+
+/// Tutte le funzioni predefinite, nell'ordine di dichiarazione.
+const FUNCTIONS: [Function; 40] = [
+    Function::Sqrt, Function::Abs,
+    Function::Sin, Function::Cos, Function::Tan,
+    Function::Asin, Function::Acos, Function::Atan, Function::Atan2,
+    Function::Sinh, Function::Cosh, Function::Tanh,
+    Function::Asinh, Function::Acosh, Function::Atanh,
+    Function::Ln, Function::Log, Function::Log10, Function::Exp,
+    Function::Floor, Function::Ceil, Function::Round, Function::Trunc,
+    Function::Mod, Function::Powmod, Function::Gcd, Function::Lcm,
+    Function::Gamma, Function::Factorial,
+    Function::Clamp, Function::Sign,
+    Function::PctChange, Function::PctOf,
+    Function::Deg, Function::Rad,
+    Function::Sum, Function::Product, Function::Mean, Function::Min, Function::Max,
+];
+
+impl Function {
+    /// Cerca la funzione predefinita con il nome indicato.
+    ///
+    /// # Ritorna
+    /// - `Some(Function)` se `name` è il nome di una funzione predefinita.
+    /// - `None` altrimenti (es. il nome di una variabile).
+    pub fn from_name(name: &str) -> Option<Self> {
+        FUNCTIONS.into_iter().find(|function| function.name() == name)
+    }
+
+    /// Restituisce il nome con cui la funzione compare nelle espressioni.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Function::Sqrt => "sqrt",
+            Function::Abs => "abs",
+            Function::Sin => "sin",
+            Function::Cos => "cos",
+            Function::Tan => "tan",
+            Function::Asin => "asin",
+            Function::Acos => "acos",
+            Function::Atan => "atan",
+            Function::Atan2 => "atan2",
+            Function::Sinh => "sinh",
+            Function::Cosh => "cosh",
+            Function::Tanh => "tanh",
+            Function::Asinh => "asinh",
+            Function::Acosh => "acosh",
+            Function::Atanh => "atanh",
+            Function::Ln => "ln",
+            Function::Log => "log",
+            Function::Log10 => "log10",
+            Function::Exp => "exp",
+            Function::Floor => "floor",
+            Function::Ceil => "ceil",
+            Function::Round => "round",
+            Function::Trunc => "trunc",
+            Function::Mod => "mod",
+            Function::Powmod => "powmod",
+            Function::Gcd => "gcd",
+            Function::Lcm => "lcm",
+            Function::Gamma => "gamma",
+            Function::Factorial => "factorial",
+            Function::Clamp => "clamp",
+            Function::Sign => "sign",
+            Function::PctChange => "pct_change",
+            Function::PctOf => "pct_of",
+            Function::Deg => "deg",
+            Function::Rad => "rad",
+            Function::Sum => "sum",
+            Function::Product => "product",
+            Function::Mean => "mean",
+            Function::Min => "min",
+            Function::Max => "max",
+        }
+    }
+
+    /// Restituisce l'intervallo del numero di argomenti accettati dalla funzione.
+    ///
+    /// Solo le funzioni che accettano un argomento possono essere usate in forma postfissa.
+    /// Per le funzioni di aggregazione è il numero di elementi della lista, almeno uno.
+    pub fn arity(&self) -> RangeInclusive<usize> {
+        match self {
+            _ if self.is_aggregate() => 1..=usize::MAX,
+            Function::Log => 1..=2,
+            Function::Mod | Function::Gcd | Function::Lcm | Function::Atan2 | Function::PctChange | Function::PctOf => 2..=2,
+            Function::Powmod | Function::Clamp => 3..=3,
+            _ => 1..=1,
+        }
+    }
+
+    /// Restituisce `true` per le funzioni trigonometriche dirette (`sin`, `cos`, `tan`),
+    /// le sole che ammettono la notazione `sin^2(x)`.
+    pub fn is_trigonometric(&self) -> bool {
+        matches!(self, Function::Sin | Function::Cos | Function::Tan)
+    }
+
+    /// Restituisce `true` per le funzioni di aggregazione (`sum`, `product`, `mean`, `min`, `max`),
+    /// il cui unico argomento è una lista tra parentesi quadre: `mean([1, 2, 3])`.
+    pub fn is_aggregate(&self) -> bool {
+        matches!(self, Function::Sum | Function::Product | Function::Mean | Function::Min | Function::Max)
+    }
+
+    /// Restituisce `true` se la funzione accetta un solo argomento (e quindi la forma postfissa).
+    pub fn is_unary(&self) -> bool {
+        !self.is_aggregate() && self.arity().contains(&1)
+    }
+}
+
+/// Implementazione del trait `Display` per `Function`: stampa il nome della funzione.
+impl core::fmt::Display for Function {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Divide un identificatore formato dal nome di una funzione seguito da cifre (es. `sin2`).
+///
+/// # Ritorna
+/// - `Some((Function, f64))` con la funzione e il numero formato dalle cifre finali.
+/// - `None` se l'identificatore non termina con cifre o la parte iniziale non è una funzione.
+pub(crate) fn split_function_digits(name: &str) -> Option<(Function, f64)> {
+    let digits_start = name.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+    if digits_start == name.len() { return None; }
+
+    let function = Function::from_name(&name[..digits_start])?;
+    let value = name[digits_start..].parse::<f64>().ok()?;
+    Some((function, value))
+}
+
+/// Sezione aurea φ = (1 + √5) / 2 (`core::f64::consts::PHI` non è ancora stabile).
+const GOLDEN_RATIO: f64 = 1.618_033_988_749_895;
+
+/// Restituisce il valore di una costante predefinita (`pi`, `e`, `tau` = 2π, `phi` = sezione aurea).
+///
+/// Le costanti vengono risolte dopo l'ambiente: una variabile assegnata con lo stesso nome ha la precedenza.
+/// I nomi sono sensibili alle maiuscole: `Tau` o `PHI` restano variabili ordinarie.
+pub fn constant(name: &str) -> Option<f64> {
+    match name {
+        "pi" => Some(core::f64::consts::PI),
+        "e" => Some(core::f64::consts::E),
+        "tau" => Some(core::f64::consts::TAU),
+        "phi" => Some(GOLDEN_RATIO),
+        _ => None,
+    }
+}
@@ -0,0 +1,87 @@
+//! Interfaccia C della libreria (feature `capi`).
+//!
+//! Espone `mathsolver_eval` per incorporare il valutatore in applicazioni C/C++; la dichiarazione
+//! corrispondente si trova in `include/mathsolver.h`.
+
+use core::ffi::{c_char, c_int, CStr};
+
+use crate::compile::compile;
+use crate::error::CalcError;
+use crate::expr::Env;
+
+/// Valutazione riuscita: il risultato è stato scritto in `*out`.
+pub const MATHSOLVER_OK: c_int = 0;
+/// Uno dei puntatori ricevuti è nullo.
+pub const MATHSOLVER_ERR_NULL: c_int = -1;
+/// L'espressione non è una stringa UTF-8 valida.
+pub const MATHSOLVER_ERR_UTF8: c_int = -2;
+/// Errore di tokenizzazione o di sintassi (`CalcError::Token`).
+pub const MATHSOLVER_ERR_SYNTAX: c_int = -3;
+/// Errore matematico durante la valutazione (`CalcError::Math`).
+pub const MATHSOLVER_ERR_MATH: c_int = -4;
+
+/// Valuta l'espressione `expr` (stringa C terminata da `\0`, senza variabili) e ne scrive il risultato in `*out`.
+///
+/// L'input segue le regole di `compile` (`=` finale facoltativo, niente assegnazioni).
+///
+/// # Ritorna
+/// - `MATHSOLVER_OK` (0) se la valutazione riesce.
+/// - Un codice negativo (`MATHSOLVER_ERR_*`) altrimenti; in questo caso `*out` non viene modificato.
+///
+/// # Safety
+/// `expr`, se non nullo, deve puntare a una stringa terminata da `\0` valida per tutta la chiamata;
+/// `out`, se non nullo, deve puntare a un `double` scrivibile.
+///
+/// # Esempio (C)
+/// ```c
+/// double result;
+/// if (mathsolver_eval("2 ^ 10", &result) == 0) { printf("%g\n", result); }
+/// ```
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mathsolver_eval(expr: *const c_char, out: *mut f64) -> c_int {
+    if expr.is_null() || out.is_null() { return MATHSOLVER_ERR_NULL; }
+
+    // SAFETY: `expr` non è nullo e il chiamante garantisce che sia una stringa terminata da `\0`
+    let Ok(input) = unsafe { CStr::from_ptr(expr) }.to_str() else { return MATHSOLVER_ERR_UTF8; };
+
+    match compile(input).and_then(|expr| expr.eval(&Env::new())) {
+        Ok(value) => {
+            // SAFETY: `out` non è nullo e il chiamante garantisce che sia scrivibile
+            unsafe { *out = value; }
+            MATHSOLVER_OK
+        }
+        Err(CalcError::Token(_)) => MATHSOLVER_ERR_SYNTAX,
+        Err(CalcError::Math(_)) => MATHSOLVER_ERR_MATH,
+    }
+}
+
+/// Modulo di test per l'interfaccia C.
+#[cfg(test)]
+mod tests {
+    use super::*; // Importa tutti i membri del modulo superiore (il codice da testare)
+    use core::ptr;
+
+    /// Chiama `mathsolver_eval` con una stringa C, restituendo il codice e il valore di `out` (inizialmente `-1`).
+    fn eval(expr: &CStr) -> (c_int, f64) {
+        let mut out = -1.0;
+        let code = unsafe { mathsolver_eval(expr.as_ptr(), &mut out) };
+        (code, out)
+    }
+
+    /// Verifica una valutazione riuscita e gli errori di sintassi e matematici.
+    #[test]
+    fn test_mathsolver_eval() {
+        assert_eq!(eval(c"2 + 3 * 4"), (MATHSOLVER_OK, 14.0));
+        assert_eq!(eval(c"2 +"), (MATHSOLVER_ERR_SYNTAX, -1.0));
+        assert_eq!(eval(c"1 / 0 ="), (MATHSOLVER_ERR_MATH, -1.0));
+    }
+
+    /// Verifica la gestione dei puntatori nulli e dell'input non UTF-8.
+    #[test]
+    fn test_mathsolver_eval_invalid_arguments() {
+        let mut out = 0.0;
+        assert_eq!(unsafe { mathsolver_eval(ptr::null(), &mut out) }, MATHSOLVER_ERR_NULL);
+        assert_eq!(unsafe { mathsolver_eval(c"1".as_ptr(), ptr::null_mut()) }, MATHSOLVER_ERR_NULL);
+        assert_eq!(eval(c"1 + \xff"), (MATHSOLVER_ERR_UTF8, -1.0));
+    }
+}
@@ -0,0 +1,398 @@
+use crate::error::{CalcError, CalcResult};
+use crate::expr::{Env, Expr};
+use crate::parser::MathExpressionParser;
+use crate::prelude::*;
+use crate::token::Span;
+use crate::tokenizer::Tokenizer;
+
+/// Espressione già analizzata, pronta per essere valutata più volte.
+///
+/// Si ottiene con `compile()`. Conserva l'albero sintattico `Expr`: ogni chiamata a `eval()`
+/// lo visita senza ripetere tokenizzazione e parsing.
+///
+/// Derive:
+/// - `Debug`, `Clone`, `PartialEq`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompiledExpr {
+    /// Albero sintattico dell'espressione.
+    expr: Expr,
+}
+
+impl CompiledExpr {
+    /// Valuta l'espressione risolvendo le variabili in `env`.
+    ///
+    /// È l'operazione economica: visita soltanto l'albero già costruito da `compile()`.
+    ///
+    /// # Ritorna
+    /// - `Ok(f64)` con il valore dell'espressione.
+    /// - `Err(CalcError)` per errori matematici o variabili assenti da `env`
+    ///   (`TokenError::UnknownVariable`, senza posizione).
+    pub fn eval(&self, env: &Env) -> CalcResult {
+        self.expr.eval(env)
+    }
+
+    /// Restituisce l'albero sintattico dell'espressione.
+    pub fn expr(&self) -> &Expr {
+        &self.expr
+    }
+}
+
+/// Tokenizza e analizza `input` una sola volta, producendo una `CompiledExpr` riutilizzabile.
+///
+/// È l'operazione costosa: conviene eseguirla una volta e chiamare poi `CompiledExpr::eval()`
+/// per ogni insieme di valori delle variabili (es. per tracciare `f(x)` su molti punti).
+///
+/// L'input è una singola espressione con `=` finale facoltativo; le assegnazioni non sono ammesse.
+///
+/// # Ritorna
+/// - `Ok(CompiledExpr)` se l'input è sintatticamente valido.
+/// - `Err(CalcError)` con l'errore di tokenizzazione o di sintassi.
+///
+/// # Esempio
+/// ```
+/// let f = compile("x^2 + 1 =")?;
+/// for x in [1.0, 2.0, 3.0] {
+///     let env = Env::from([("x".to_string(), x)]);
+///     println!("f({}) = {}", x, f.eval(&env)?);
+/// }
+/// ```
+pub fn compile(input: &str) -> Result<CompiledExpr, CalcError> {
+    compile_configured(input, false)
+}
+
+/// Compila `input` come `compile()`, analizzando come chiamate anche le funzioni sconosciute.
+///
+/// Un identificatore seguito da `(` che non è una funzione predefinita diventa `Expr::UserCall`
+/// invece di una moltiplicazione implicita (`double(x)` non è `double * x`): l'albero si valuta poi
+/// con `Expr::eval_with_callbacks`, che risolve la chiamata con la callback `func`.
+///
+/// # Esempio
+/// ```
+/// let f = compile_with_calls("double(x) + 1")?;
+/// let value = f.expr().eval_with_callbacks(
+///     |name| (name == "x").then_some(3.0),
+///     |name, args| (name == "double").then(|| Ok(args[0] * 2.0)),
+/// );
+/// assert_eq!(value, Ok(7.0));
+/// ```
+pub fn compile_with_calls(input: &str) -> Result<CompiledExpr, CalcError> {
+    compile_configured(input, true)
+}
+
+/// Tokenizza e analizza `input`, con le chiamate esterne abilitate o meno (`set_external_calls`).
+fn compile_configured(input: &str, external_calls: bool) -> Result<CompiledExpr, CalcError> {
+    let mut tokenizer = Tokenizer::new(input);
+    let tokens = tokenizer.tokenize()?;
+    let mut parser = MathExpressionParser::with_spans(tokens, tokenizer.spans().to_vec());
+    parser.set_external_calls(external_calls);
+    let expr = parser.parse_expression()?;
+
+    Ok(CompiledExpr { expr })
+}
+
+/// Valuta la stessa espressione per ogni riga di una tabella di valori delle variabili.
+///
+/// L'input viene analizzato una sola volta con `compile()` e la `CompiledExpr` è riusata per ogni
+/// riga (es. per tracciare `f(x)` su un intervallo o una tabella di dati).
+///
+/// # Ritorna
+/// - `Ok(Vec<CalcResult>)` con un risultato per riga, nello stesso ordine: l'errore di una riga
+///   (es. una variabile mancante) non interrompe la valutazione delle altre.
+/// - `Err(CalcError)` se l'espressione stessa non è valida; in questo caso nessuna riga viene valutata.
+///
+/// A differenza di un semplice `Vec<CalcResult>`, l'errore di parsing non viene copiato in ogni riga:
+/// non dipende dai valori delle variabili, quindi il chiamante lo gestisce una volta sola con `?` e
+/// gli errori nel vettore restano solo quelli propri di ciascuna riga.
+///
+/// # Esempio
+/// ```
+/// let rows = [Env::from([("x".to_string(), 2.0)]), Env::from([("x".to_string(), 3.0)])];
+/// assert_eq!(evaluate_batch("x^2", &rows)?, vec![Ok(4.0), Ok(9.0)]);
+/// ```
+pub fn evaluate_batch(input: &str, rows: &[Env]) -> Result<Vec<CalcResult>, CalcError> {
+    let compiled = compile(input)?;
+    Ok(rows.iter().map(|env| compiled.eval(env)).collect())
+}
+
+/// Campiona un'espressione in una variabile su un intervallo, ad esempio per tracciarne il grafico.
+///
+/// L'intervallo `[start, end]` è diviso in `steps` parti uguali: si ottengono `steps + 1` punti,
+/// estremi inclusi (con `steps` pari a zero solo `start`). I valori sono calcolati con `evaluate_batch`,
+/// quindi l'espressione viene analizzata una sola volta.
+///
+/// # Ritorna
+/// - `Ok(Vec<(f64, Option<f64>)>)` con le coppie `(x, f(x))` in ordine; `f(x)` è `None` nei punti in cui
+///   la valutazione fallisce (es. `1/x` in 0), così che il grafico possa interrompersi.
+/// - `Err(CalcError)` se l'espressione non è valida; in questo caso nessun punto viene calcolato.
+///
+/// Un'espressione non valida non produce una serie di punti tutti a `None`: sarebbe indistinguibile
+/// da una funzione mai definita nell'intervallo (es. `sqrt(x)` per `x` negativo), mentre l'errore
+/// segnala che non c'è nulla da tracciare.
+///
+/// # Esempio
+/// ```
+/// let points = sample("1/x", "x", -1.0, 1.0, 2)?;
+/// assert_eq!(points, vec![(-1.0, Some(-1.0)), (0.0, None), (1.0, Some(1.0))]);
+/// ```
+pub fn sample(expr: &str, var: &str, start: f64, end: f64, steps: usize) -> Result<Vec<(f64, Option<f64>)>, CalcError> {
+    let xs: Vec<f64> = (0..=steps)
+        .map(|i| match i {
+            0 => start,
+            i if i == steps => end, // Evita che l'ultimo punto manchi `end` per gli arrotondamenti
+            i => start + (end - start) * (i as f64 / steps as f64),
+        })
+        .collect();
+
+    let rows: Vec<Env> = xs.iter().map(|&x| Env::from_iter([(var.to_string(), x)])).collect();
+    let values = evaluate_batch(expr, &rows)?;
+    Ok(xs.into_iter().zip(values.into_iter().map(Result::ok)).collect())
+}
+
+/// Tokenizza, analizza e valuta `input` in un solo passo, restituendo in caso di errore anche
+/// l'intervallo del sorgente che lo ha causato (es. per sottolinearlo in un editor).
+///
+/// Il `=` finale è facoltativo; sono ammesse anche le sequenze separate da `;`, come in
+/// `MathExpressionParser::evaluate_last`, valutate in un ambiente vuoto.
+///
+/// # Ritorna
+/// - `Ok(f64)` con il valore dell'espressione.
+/// - `Err((CalcError, Some(Span)))` per gli errori con posizione: l'intervallo, in colonne di caratteri,
+///   copre il token interessato (es. l'intero nome di una variabile sconosciuta) oppure il singolo
+///   carattere non riconosciuto dal tokenizer.
+/// - `Err((CalcError, None))` per gli errori senza posizione (es. `MathError`, `UnexpectedEnd`).
+///
+/// # Esempio
+/// ```
+/// assert_eq!(evaluate_str("2 + 3 * 4"), Ok(14.0));
+/// let (err, span) = evaluate_str("2 * foo").unwrap_err();
+/// assert_eq!(span, Some(4..7));
+/// ```
+pub fn evaluate_str(input: &str) -> Result<f64, (CalcError, Option<Span>)> {
+    let mut tokenizer = Tokenizer::new(input);
+    let tokens = tokenizer.tokenize().map_err(|e| {
+        let span = e.position().map(|position| position..position + 1);
+        (CalcError::from(e), span)
+    })?;
+
+    let spans = tokenizer.spans().to_vec();
+    MathExpressionParser::with_spans(tokens, spans.clone()).evaluate_last().map_err(|err| {
+        let span = err.position().map(|position| {
+            spans
+                .iter()
+                .find(|span| span.start == position)
+                .cloned()
+                .unwrap_or(position..position + 1)
+        });
+        (err, span)
+    })
+}
+
+/// Valuta `input` come `evaluate_str`, restituendo `None` per qualsiasi errore.
+///
+/// Pensata per i cicli in cui gli errori sono attesi e scartati (es. filtrare molti input candidati):
+/// non copia gli intervalli del sorgente né calcola la posizione dell'errore. Il `CalcError` viene
+/// comunque costruito dal parser (con il suo eventuale messaggio) e poi scartato. L'API principale
+/// resta quella con `Result`, che spiega il motivo del fallimento.
+///
+/// # Esempio
+/// ```
+/// assert_eq!(try_evaluate("2 + 3 * 4"), Some(14.0));
+/// assert_eq!(try_evaluate("1 / 0"), None);
+/// ```
+pub fn try_evaluate(input: &str) -> Option<f64> {
+    let tokens = Tokenizer::new(input).tokenize().ok()?;
+    MathExpressionParser::new(tokens).evaluate_last().ok()
+}
+
+/// Valuta `input` come `evaluate_str` (tokenizzazione, analisi e valutazione), misurandone la durata.
+///
+/// Pensata per la diagnostica delle prestazioni, es. per confrontare i tempi prima e dopo una modifica
+/// del tokenizer. La durata include l'intera pipeline ed è misurata con `std::time::Instant`:
+/// disponibile solo con la feature `std`.
+///
+/// # Ritorna
+/// La coppia `(CalcResult, Duration)`: l'esito, uguale a quello di `evaluate_str` senza l'intervallo
+/// dell'errore, e il tempo impiegato.
+///
+/// # Esempio
+/// ```
+/// let (result, elapsed) = evaluate_timed("2 + 3 * 4");
+/// assert_eq!(result, Ok(14.0));
+/// println!("valutata in {:?}", elapsed);
+/// ```
+#[cfg(feature = "std")]
+pub fn evaluate_timed(input: &str) -> (CalcResult, std::time::Duration) {
+    let start = std::time::Instant::now();
+    let mut tokenizer = Tokenizer::new(input);
+    let result = tokenizer.tokenize().map_err(CalcError::from).and_then(|tokens| {
+        // Con gli intervalli del sorgente le posizioni degli errori sono in caratteri, come in `evaluate_str`
+        MathExpressionParser::with_spans(tokens, tokenizer.spans().to_vec()).evaluate_last()
+    });
+    (result, start.elapsed())
+}
+
+/// Analizza una stringa in un albero sintattico con `str::parse`, con le stesse regole di `compile`
+/// (`=` finale facoltativo, niente assegnazioni).
+///
+/// # Esempio
+/// ```
+/// let expr: Expr = "2+3*4".parse()?;
+/// assert_eq!(expr.eval(&Env::new()), Ok(14.0));
+/// ```
+impl core::str::FromStr for Expr {
+    type Err = CalcError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        compile(input).map(|compiled| compiled.expr)
+    }
+}
+
+/// Modulo di test per la compilazione e la valutazione ripetuta delle espressioni.
+#[cfg(test)]
+mod tests {
+    use super::*; // Importa tutti i membri del modulo superiore (il codice da testare)
+    use crate::error::{OperandSide, TokenError};
+    use crate::token::{Token, TokenKind};
+
+    /// Costruisce un ambiente a partire da coppie (nome, valore).
+    fn env(bindings: &[(&str, f64)]) -> Env {
+        bindings.iter().map(|&(name, value)| (name.to_string(), value)).collect()
+    }
+
+    /// Verifica che la stessa espressione compilata dia risultati diversi con ambienti diversi.
+    #[test]
+    fn test_compiled_expr_with_several_environments() {
+        let compiled = compile("2x^2 + y =").unwrap();
+
+        assert_eq!(compiled.eval(&env(&[("x", 1.0), ("y", 0.0)])), Ok(2.0));
+        assert_eq!(compiled.eval(&env(&[("x", 3.0), ("y", 1.0)])), Ok(19.0));
+        assert_eq!(compiled.eval(&env(&[("x", -2.0), ("y", -8.0)])), Ok(0.0));
+    }
+
+    /// Verifica gli errori di compilazione e di valutazione.
+    #[test]
+    fn test_compile_and_eval_errors() {
+        assert!(compile("2 + =").is_err());
+        assert!(compile("x = 2").is_err());
+
+        let compiled = compile("1 / x").unwrap();
+        assert_eq!(
+            compiled.eval(&env(&[])),
+            Err(CalcError::Token(TokenError::UnknownVariable { name: "x".into(), position: None }))
+        );
+        assert_eq!(compiled.eval(&env(&[("x", 0.0)])), Err(crate::error::MathError::DivisionByZero.into()));
+    }
+
+    /// Verifica la valutazione di `x^2` per `x` da 0 a 5 e l'isolamento degli errori delle singole righe.
+    #[test]
+    fn test_evaluate_batch() {
+        let rows: Vec<Env> = (0..=5).map(|x| env(&[("x", x as f64)])).collect();
+        assert_eq!(
+            evaluate_batch("x^2", &rows),
+            Ok(vec![Ok(0.0), Ok(1.0), Ok(4.0), Ok(9.0), Ok(16.0), Ok(25.0)])
+        );
+
+        let rows = [env(&[("x", 2.0), ("y", 1.0)]), env(&[("x", 2.0), ("y", 2.0)]), env(&[("x", 1.0)])];
+        assert_eq!(
+            evaluate_batch("x / (y - 1)", &rows),
+            Ok(vec![
+                Err(crate::error::MathError::DivisionByZero.into()),
+                Ok(2.0),
+                Err(TokenError::UnknownVariable { name: "y".into(), position: None }.into()),
+            ])
+        );
+
+        assert!(evaluate_batch("x +", &rows).is_err());
+        assert_eq!(evaluate_batch("x", &[]), Ok(vec![]));
+    }
+
+    /// Verifica il campionamento di `x^2` e di `1/x`, con gli estremi inclusi e i punti non validi.
+    #[test]
+    fn test_sample() {
+        assert_eq!(
+            sample("x^2", "x", -2.0, 2.0, 4),
+            Ok(vec![(-2.0, Some(4.0)), (-1.0, Some(1.0)), (0.0, Some(0.0)), (1.0, Some(1.0)), (2.0, Some(4.0))])
+        );
+
+        let points = sample("1/x", "x", -1.0, 1.0, 4).unwrap();
+        assert_eq!(points.len(), 5);
+        assert_eq!(points[2], (0.0, None));
+        assert_eq!(points[3], (0.5, Some(2.0)));
+
+        let points = sample("t", "t", 0.0, 1.0, 3).unwrap();
+        assert_eq!(points.last(), Some(&(1.0, Some(1.0))));
+        assert_eq!(sample("x + 1", "x", 5.0, 9.0, 0), Ok(vec![(5.0, Some(6.0))]));
+        assert!(sample("x +", "x", 0.0, 1.0, 10).is_err());
+    }
+
+    /// Verifica `evaluate_str` senza `=` finale e gli intervalli restituiti con gli errori.
+    #[test]
+    fn test_evaluate_str() {
+        assert_eq!(evaluate_str("2 + 3 * 4"), Ok(14.0));
+        assert_eq!(evaluate_str("2 + 3 * 4 ="), Ok(14.0));
+
+        assert_eq!(
+            evaluate_str("2 * foo + 1"),
+            Err((TokenError::UnknownVariable { name: "foo".into(), position: Some(4) }.into(), Some(4..7)))
+        );
+        assert_eq!(
+            evaluate_str("10 @ 3"),
+            Err((TokenError::InvalidOperator { operator: '@', position: 3 }.into(), Some(3..4)))
+        );
+        assert_eq!(evaluate_str("1 / 0"), Err((crate::error::MathError::DivisionByZero.into(), None)));
+
+        // Un operatore in sospeso alla fine: manca il suo operando destro, evidenziato sull'operatore
+        let (err, span) = evaluate_str("2 +").unwrap_err();
+        assert!(matches!(err, CalcError::Token(TokenError::MissingOperand { operator: Token::Plus, side: OperandSide::Right, position: 2, .. })));
+        assert_eq!(span, Some(2..3));
+        assert_eq!(evaluate_str("a = 1; 3 * a ^").unwrap_err().1, Some(13..14));
+        assert!(matches!(evaluate_str("sqrt("), Err((CalcError::Token(TokenError::UnexpectedEnd { .. }), None))));
+    }
+
+    /// Verifica che `try_evaluate` restituisca il valore degli input validi e `None` per ogni errore.
+    #[test]
+    fn test_try_evaluate() {
+        assert_eq!(try_evaluate("2 + 3 * 4"), Some(14.0));
+        assert_eq!(try_evaluate("a = 3; a ^ 2 ="), Some(9.0));
+        assert_eq!(try_evaluate("sqrt(16) ="), Some(4.0));
+
+        assert_eq!(try_evaluate("1 / 0"), None); // errore matematico
+        assert_eq!(try_evaluate("2 * foo"), None); // variabile sconosciuta
+        assert_eq!(try_evaluate("10 @ 3"), None); // errore del tokenizer
+        assert_eq!(try_evaluate("(2 + 3"), None); // errore di sintassi
+        assert_eq!(try_evaluate(""), None);
+        assert_eq!(try_evaluate("define f(x) = 2x"), None); // una definizione non ha un valore
+        assert_eq!(try_evaluate("define f(x) = 2x; f(4)"), Some(8.0));
+    }
+
+    /// Verifica che `evaluate_timed` restituisca lo stesso esito di `evaluate_str` insieme a una durata
+    /// non superiore a quella misurata attorno alla chiamata.
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_evaluate_timed() {
+        for input in ["2 + 3 * 4", "a = 3; a ^ 2 =", "1 / 0", "2 * foo", "10 @ 3", "(2 + 3"] {
+            let start = std::time::Instant::now();
+            let (result, elapsed) = evaluate_timed(input);
+            let total = start.elapsed();
+
+            // Anche per gli input non validi l'errore è accompagnato dalla durata della chiamata
+            assert_eq!(result, evaluate_str(input).map_err(|(err, _)| err), "{}", input);
+            assert!(elapsed <= total, "{}: {:?} > {:?}", input, elapsed, total);
+        }
+    }
+
+    /// Verifica l'analisi con `str::parse`, con e senza `=` finale.
+    #[test]
+    fn test_parse_from_str() {
+        let expr: Expr = "2+3*4".parse().unwrap();
+        assert_eq!(expr.eval(&env(&[])), Ok(14.0));
+        assert_eq!("2+3*4 =".parse::<Expr>(), Ok(expr));
+        assert_eq!("x^2".parse::<Expr>().unwrap().eval(&env(&[("x", 3.0)])), Ok(9.0));
+
+        let err = "2 +".parse::<Expr>().unwrap_err();
+        assert!(matches!(err, CalcError::Token(TokenError::MissingOperand { operator: Token::Plus, position: 2, .. })));
+        assert!(err.expected_tokens().contains(&TokenKind::Number));
+        assert!("x = 2".parse::<Expr>().is_err());
+        assert!("2 @ 3".parse::<Expr>().is_err());
+    }
+}
@@ -0,0 +1,163 @@
+use crate::error::{CalcError, MathError, OperandSide, TokenError};
+use crate::prelude::*;
+
+/// Rappresenta un errore mostrando l'input originale con un `^` sotto la colonna dell'errore.
+///
+/// Il risultato occupa due righe: la riga dell'input che contiene l'errore e, sotto,
+/// il cursore `^` seguito dal messaggio dell'errore (come nei messaggi di `rustc`).
+/// Gli errori senza posizione (es. `MathError`, `UnexpectedEnd`) ricadono sul semplice `Display`.
+///
+/// Le posizioni sono colonne in caratteri: l'errore deve provenire da un parser costruito con
+/// `MathExpressionParser::with_spans`, altrimenti indicano l'indice del token.
+///
+/// # Esempio
+/// ```
+/// let err = CalcError::Token(TokenError::InvalidOperator { operator: '%', position: 2 });
+/// assert_eq!(
+///     render_error("2 % 3 =", &err),
+///     "2 % 3 =\n  ^ Errore di parsing: Errore: operatore non valido '%' alla posizione 2"
+/// );
+/// ```
+pub fn render_error(input: &str, err: &CalcError) -> String {
+    let Some(position) = err.position() else { return err.to_string(); };
+
+    // Individua la riga che contiene la posizione (le posizioni contano anche i '\n')
+    let mut line_start = 0;
+    let mut lines = input.split('\n').peekable();
+    let line = loop {
+        let line = lines.next().unwrap_or_default();
+        let length = line.chars().count();
+        if position <= line_start + length || lines.peek().is_none() { break line; }
+        line_start += length + 1;
+    };
+
+    // Le tabulazioni vengono ripetute per mantenere l'allineamento del cursore
+    let padding: String = line
+        .chars()
+        .take(position.saturating_sub(line_start))
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+
+    format!("{}\n{}^ {}", line, padding, err)
+}
+
+/// Suggerisce in linguaggio semplice la causa probabile di un errore e come correggerlo.
+///
+/// # Ritorna
+/// - `Some(String)` con il suggerimento, per gli errori più comuni.
+/// - `None` se non c'è un suggerimento utile oltre al messaggio dell'errore.
+///
+/// # Esempio
+/// ```
+/// let err = CalcError::Token(TokenError::UnmatchedParenthesis { found: '(', position: 0, expected: vec![] });
+/// assert_eq!(suggest_fix(&err).unwrap(), "Hai dimenticato una parentesi chiusa ')'?");
+/// ```
+pub fn suggest_fix(err: &CalcError) -> Option<String> {
+    let suggestion = match err {
+        CalcError::Token(TokenError::UnmatchedParenthesis { found: '(', .. }) => {
+            "Hai dimenticato una parentesi chiusa ')'?".to_string()
+        }
+        CalcError::Token(TokenError::UnmatchedParenthesis { .. }) => {
+            "C'è una parentesi chiusa di troppo, oppure manca la '(' corrispondente?".to_string()
+        }
+        CalcError::Token(TokenError::UnexpectedEnd { .. }) => {
+            "Hai dimenticato il simbolo '=' alla fine dell'espressione?".to_string()
+        }
+        CalcError::Token(TokenError::InvalidOperator { operator, .. }) => {
+            format!("Il carattere '{}' non è supportato: gli operatori ammessi sono + - * / ^ $", operator)
+        }
+        CalcError::Token(TokenError::MissingOperand { operator, side: OperandSide::Left, .. }) => {
+            format!("Manca un valore prima di '{}': hai scritto un operatore di troppo?", operator)
+        }
+        CalcError::Token(TokenError::MissingOperand { operator, side: OperandSide::Right, .. }) => {
+            format!("Manca un valore dopo '{}': hai scritto due operatori di seguito?", operator)
+        }
+        CalcError::Token(TokenError::UnknownVariable { name, .. }) => {
+            format!("Assegna un valore a '{}' prima di usarla (es. {} = 2; ...), oppure controlla il nome", name, name)
+        }
+        CalcError::Token(TokenError::InvalidNumber(_)) => {
+            "Controlla il formato del numero: un solo punto decimale e '_' solo tra due cifre".to_string()
+        }
+        CalcError::Math(MathError::DivisionByZero) => {
+            "Il divisore (o l'indice della radice, o la base di una potenza negativa) vale zero: controlla il denominatore".to_string()
+        }
+        CalcError::Math(MathError::IndeterminateForm { .. }) => {
+            "0^0 non ha un valore definito: usa ZeroPowerMode::One per la convenzione 0^0 = 1".to_string()
+        }
+        CalcError::Math(MathError::EvenRootOfNegative { .. } | MathError::NegativeRoot { .. }) => {
+            "Le radici di numeri negativi sono definite solo con indice intero dispari".to_string()
+        }
+        _ => return None,
+    };
+    Some(suggestion)
+}
+
+/// Spiega un errore per intero: il messaggio con il cursore (`render_error`) e, se disponibile,
+/// il suggerimento di correzione (`suggest_fix`) su una riga aggiuntiva.
+pub fn explain_error(input: &str, err: &CalcError) -> String {
+    let rendered = render_error(input, err);
+    match suggest_fix(err) {
+        Some(suggestion) => format!("{}\nSuggerimento: {}", rendered, suggestion),
+        None => rendered,
+    }
+}
+
+/// Modulo di test per la rappresentazione degli errori con cursore.
+#[cfg(test)]
+mod tests {
+    use super::*; // Importa tutti i membri del modulo superiore (il codice da testare)
+    use crate::parser::MathExpressionParser;
+    use crate::tokenizer::Tokenizer;
+
+    /// Tokenizza e valuta l'input, restituendo l'errore rappresentato con `render_error`.
+    fn render(input: &str) -> String {
+        let mut tokenizer = Tokenizer::new(input);
+        let err = match tokenizer.tokenize() {
+            Ok(tokens) => MathExpressionParser::with_spans(tokens, tokenizer.spans().to_vec()).evaluate().unwrap_err(),
+            Err(e) => e.into(),
+        };
+        render_error(input, &err)
+    }
+
+    /// Verifica il cursore sotto una parentesi non chiusa.
+    #[test]
+    fn test_render_unmatched_parenthesis() {
+        assert_eq!(
+            render("2 * (1 + 2 ="),
+            "2 * (1 + 2 =\n    ^ Errore di parsing: Errore: parentesi '(' non bilanciata alla posizione 4"
+        );
+    }
+
+    /// Verifica il cursore sotto un operatore non valido, anche dopo una tabulazione.
+    #[test]
+    fn test_render_invalid_operator() {
+        assert_eq!(
+            render("12 % 5 ="),
+            "12 % 5 =\n   ^ Errore di parsing: Errore: operatore non valido '%' alla posizione 3"
+        );
+        assert_eq!(
+            render("1\t@ 2 ="),
+            "1\t@ 2 =\n \t^ Errore di parsing: Errore: operatore non valido '@' alla posizione 2"
+        );
+    }
+
+    /// Verifica la spiegazione completa di una parentesi non chiusa.
+    #[test]
+    fn test_explain_unmatched_parenthesis() {
+        let input = "(1 + 2 =";
+        let err = CalcError::Token(TokenError::UnmatchedParenthesis { found: '(', position: 0, expected: vec![] });
+        assert_eq!(
+            explain_error(input, &err),
+            format!("{}\nSuggerimento: Hai dimenticato una parentesi chiusa ')'?", render(input))
+        );
+        assert_eq!(suggest_fix(&CalcError::Math(MathError::OverflowError)), None);
+    }
+
+    /// Verifica che gli errori senza posizione ricadano sul `Display`.
+    #[test]
+    fn test_render_without_position() {
+        let err = CalcError::Math(MathError::DivisionByZero);
+        assert_eq!(render_error("1 / 0 =", &err), err.to_string());
+        assert_eq!(render("1 / 0 ="), err.to_string());
+    }
+}
@@ -0,0 +1,532 @@
+use crate::builtins::Function;
+use crate::prelude::*;
+use crate::token::{Token, TokenKind};
+
+/// Tipi di errore che possono verificarsi durante l'esecuzione di calcoli matematici.
+///
+/// Viene usato nel valutatore di espressioni aritmetiche per segnalare
+/// errori come divisioni per zero o limiti computazionali.
+///
+/// Derive:
+/// - `Debug`: consente la stampa dell'errore per log o debug.
+/// - `PartialEq`: confrontare errori nei test o nel flusso di controllo.
+#[derive(Debug, PartialEq)]
+#[allow(unused)]
+pub enum MathError {
+    /// Divisione per zero.
+    DivisionByZero,
+
+    /// Il risultato ha superato i limiti superiori rappresentabili.
+    OverflowError,
+
+    /// Il risultato è sceso sotto i limiti inferiori rappresentabili.
+    UnderflowError,
+
+    /// L'espressione contiene troppi elementi o nidificazioni, ad esempio chiamate di funzioni
+    /// definite dall'utente oltre `MAX_CALL_DEPTH` o più operazioni di `ParserConfig::step_budget`.
+    ExpressionTooComplex,
+
+    /// Potenza con base o esponente non valido
+    InvalidExponentiation { base: f64, exponent: f64, },
+
+    /// Radice di numero negativo con indice frazionario.
+    NegativeRoot { base: f64, root: f64, },
+
+    /// Radice con indice pari di numero negativo (non definita nei reali).
+    EvenRootOfNegative { base: f64, root: f64, },
+
+    /// Radice con base o indice non valido
+    InvalidRoot { base: f64, root: f64, },
+
+    /// Argomento fuori dal dominio di una funzione (es. `ln(0)`, `asin(2)`).
+    DomainError { function: Function, argument: f64 },
+
+    /// Argomento non intero per una funzione definita sugli interi (es. `gcd(2.5, 5)`).
+    NonIntegerArgument { function: Function, argument: f64 },
+
+    /// Logaritmo in base arbitraria con argomento non positivo o base non valida (non positiva o uguale a 1).
+    InvalidLogarithm { value: f64, base: f64 },
+
+    /// Fattoriale di un numero negativo o non intero (es. `(-3)!`, `2.5!`), o `factorial`/`gamma`
+    /// in un polo (es. `factorial(-1)`, `gamma(0)`, entrambi riportati come `-1!`).
+    InvalidFactorial { value: f64 },
+
+    /// Valore non intero in una modalità numerica intera (es. `1.5` o `pi` con `NumericMode::Int64`).
+    NonInteger { value: f64 },
+
+    /// Risultato il cui valore assoluto supera il limite impostato con `MathExpressionParser::set_max_magnitude`
+    /// (es. `10^101` con limite `1e100`), anche se rappresentabile.
+    MagnitudeExceeded { value: f64, limit: f64 },
+
+    /// Operazione che produce `NaN` da operandi numerici (es. `inf - inf` con `DivByZeroMode::Infinity`).
+    /// `op` è il simbolo dell'operatore (es. `"-"`).
+    NotANumber { op: String },
+
+    /// Forma indeterminata, senza un valore definito (es. `0^0` con `ZeroPowerMode::Indeterminate`).
+    /// `form` è la forma nella notazione del sorgente (es. `"0^0"`).
+    IndeterminateForm { form: String },
+}
+
+/// Lato di un operatore binario a cui si riferisce un operando.
+///
+/// Derive:
+/// - `Debug`, `Clone`, `Copy`, `PartialEq`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OperandSide {
+    /// Operando a sinistra dell'operatore (es. `?` in `? * 3`).
+    Left,
+
+    /// Operando a destra dell'operatore (es. `?` in `2 + ?`).
+    Right,
+}
+
+/// Implementazione del trait `Display` per `OperandSide`.
+impl core::fmt::Display for OperandSide {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            OperandSide::Left => write!(f, "sinistro"),
+            OperandSide::Right => write!(f, "destro"),
+        }
+    }
+}
+
+/// Tipi di errore che possono verificarsi durante la fase di tokenizzazione o parsing.
+/// Usato per indicare errori di sintassi o input invalido.
+///
+/// Derive:
+/// - `Debug`: consente la stampa dell'errore per log o debug.
+/// - `PartialEq`: confrontare errori nei test o nel flusso di controllo.
+#[derive(Debug, PartialEq)]
+#[allow(unused)]
+pub enum TokenError {
+    /// Numero malformato o non valido (es. "1..2").
+    InvalidNumber(String),
+
+    /// L'input termina in modo inaspettato (es. Parentesi non chiusa).
+    /// Include i tipi di token che avrebbero potuto seguire (vuoto se non noti, es. commento non chiuso).
+    UnexpectedEnd { expected: Vec<TokenKind> },
+
+    /// Espressione invalida in senso sintattico.
+    InvalidExpression(String),
+
+    /// Operatore non riconosciuto (es. '%', '€', ecc.), include carattere e posizione nel sorgente.
+    InvalidOperator { operator: char, position: usize },
+
+    /// Parentesi chiusa senza apertura o viceversa, include la parentesi non bilanciata, la sua posizione nel sorgente
+    /// e i tipi di token ammessi nel punto dell'errore (vuoto per gli errori di `check_balanced`).
+    UnmatchedParenthesis { found: char, position: usize, expected: Vec<TokenKind> },
+
+    /// Token inaspettato trovato in una certa posizione del parsing, include token, posizione nel sorgente
+    /// e i tipi di token ammessi al suo posto.
+    UnexpectedToken { token: Token, position: usize, expected: Vec<TokenKind> },
+
+    /// Variabile non definita nell'ambiente, include nome e posizione nel sorgente.
+    /// La posizione è assente quando si valuta un albero `Expr` senza il sorgente (es. `CompiledExpr::eval`).
+    UnknownVariable { name: String, position: Option<usize> },
+
+    /// Operatore binario privo di un operando (es. "2 ++ 3", "(* 3)").
+    /// Include l'operatore, il lato dell'operando mancante, la posizione in cui era atteso
+    /// e i tipi di token che possono iniziare un operando.
+    MissingOperand { operator: Token, side: OperandSide, position: usize, expected: Vec<TokenKind> },
+
+    /// Errore sintattico generico, con descrizione (es. funzione senza parentesi, tabulazione in modalità rigorosa,
+    /// operatore all'inizio dell'espressione come `* 2`).
+    SyntaxError(String),
+}
+
+/// Implementazione del trait `Display` per `MathError`.
+///
+/// Permette la conversione leggibile dell'errore in una stringa,
+/// utile per l'output verso l'utente o log.
+///
+/// Inoltre, ogni ramo logga l'errore con `error_log!`,
+/// che è abilitato solo con la feature `logging` (in `debug` come in `release`).
+impl core::fmt::Display for MathError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            MathError::DivisionByZero => {
+                error_log!("Errore: divisione per zero");
+                write!(f, "Errore matematico: divisione per zero")
+            },
+            MathError::OverflowError => {
+                error_log!("Errore: overflow numerico");
+                write!(f, "Errore matematico: overflow numerico")
+            },
+            MathError::UnderflowError => {
+                error_log!("Errore: underflow numerico");
+                write!(f, "Errore matematico: underflow numerico")
+            },
+            MathError::MagnitudeExceeded { value, limit } => {
+                error_log!("Errore: limite di grandezza superato (valore: {}, limite: {})", value, limit);
+                write!(f, "Errore: il risultato {} supera in valore assoluto il limite consentito ({})", value, limit)
+            },
+            MathError::ExpressionTooComplex => {
+                error_log!("Errore: espressione troppo complessa");
+                write!(f, "Errore: espressione troppo complessa")
+            },
+            MathError::InvalidExponentiation { base, exponent } => {
+                error_log!("Errore: potenza non valida (base: {}, esponente: {})", base, exponent);
+                write!(f, "Errore: potenza non valida ({} ^ {})", base, exponent)
+            },
+            MathError::NegativeRoot { base, root } => {
+                error_log!("Errore: radice con indice frazionario di numero negativo (base: {}, indice: {})", base, root);
+                write!(f, "Errore: radice frazionaria di numero negativo ({} $ {})", base, root)
+            },
+            MathError::EvenRootOfNegative { base, root } => {
+                error_log!("Errore: radice con indice pari di numero negativo (base: {}, indice: {})", base, root);
+                write!(f, "Errore: radice con indice pari di numero negativo ({} $ {})", base, root)
+            },
+            MathError::InvalidRoot { base, root } => {
+                error_log!("Errore: radice non valida (base: {}, indice: {})", base, root);
+                write!(f, "Errore: radice non valida ({} $ {})", base, root)
+            },
+            MathError::DomainError { function, argument } => {
+                error_log!("Errore: argomento fuori dominio (funzione: {}, argomento: {})", function, argument);
+                write!(f, "Errore: argomento fuori dal dominio di '{}' ({})", function, argument)
+            },
+            MathError::NonIntegerArgument { function, argument } => {
+                error_log!("Errore: argomento non intero (funzione: {}, argomento: {})", function, argument);
+                write!(f, "Errore: la funzione '{}' richiede argomenti interi ({})", function, argument)
+            },
+            MathError::InvalidLogarithm { value, base } => {
+                error_log!("Errore: logaritmo non valido (argomento: {}, base: {})", value, base);
+                write!(f, "Errore: logaritmo non valido (log({}, {})): serve argomento positivo e base positiva diversa da 1", value, base)
+            },
+            MathError::InvalidFactorial { value } => {
+                error_log!("Errore: fattoriale non valido (valore: {})", value);
+                write!(f, "Errore: fattoriale definito solo per interi non negativi ({}!)", value)
+            },
+            MathError::NonInteger { value } => {
+                error_log!("Errore: valore non intero in modalità intera (valore: {})", value);
+                write!(f, "Errore: valore non intero in modalità intera ({})", value)
+            },
+            MathError::NotANumber { op } => {
+                error_log!("Errore: risultato NaN (operatore: {})", op);
+                write!(f, "Errore: l'operazione '{}' non produce un numero (NaN)", op)
+            },
+            MathError::IndeterminateForm { form } => {
+                error_log!("Errore: forma indeterminata {}", form);
+                write!(f, "Errore matematico: {} è una forma indeterminata", form)
+            },
+        }
+    }
+}
+
+impl TokenError {
+    /// Restituisce la posizione nel sorgente a cui si riferisce l'errore, se nota.
+    pub fn position(&self) -> Option<usize> {
+        match self {
+            TokenError::InvalidOperator { position, .. }
+            | TokenError::UnmatchedParenthesis { position, .. }
+            | TokenError::UnexpectedToken { position, .. }
+            | TokenError::MissingOperand { position, .. } => Some(*position),
+            TokenError::UnknownVariable { position, .. } => *position,
+            _ => None,
+        }
+    }
+
+    /// Restituisce i tipi di token che sarebbero stati validi nel punto dell'errore di sintassi,
+    /// utili per il completamento automatico.
+    ///
+    /// Il set è compilato dal parser quando manca un operando (es. dopo `2 +`: numero, identificatore,
+    /// `(`, `-` unario, `√`, `$`) oppure quando un'espressione completa è seguita da un token inatteso
+    /// o termina senza la chiusura richiesta (es. dopo `(2 + 3`: un operatore o `)`). È vuoto per gli
+    /// altri errori.
+    pub fn expected_tokens(&self) -> &[TokenKind] {
+        match self {
+            TokenError::UnexpectedEnd { expected }
+            | TokenError::UnmatchedParenthesis { expected, .. }
+            | TokenError::UnexpectedToken { expected, .. }
+            | TokenError::MissingOperand { expected, .. } => expected,
+            _ => &[],
+        }
+    }
+}
+
+/// Implementazione del trait `Display` per `TokenError`.
+///
+/// Permette la conversione leggibile dell'errore in una stringa,
+/// utile per l'output verso l'utente o log.
+///
+/// Inoltre, ogni ramo logga l'errore con `error_log!`,
+/// che è abilitato solo con la feature `logging` (in `debug` come in `release`).
+impl core::fmt::Display for TokenError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            TokenError::InvalidNumber(msg) => {
+                error_log!("Numero non valido: {}", msg);
+                write!(f, "Numero non valido: {}", msg)
+            },
+            TokenError::UnmatchedParenthesis { found, position, .. } => {
+                error_log!("Parentesi non bilanciata '{}' alla posizione {}", found, position);
+                write!(f, "Errore: parentesi '{}' non bilanciata alla posizione {}", found, position)
+            },
+            TokenError::UnexpectedEnd { .. } => {
+                error_log!("Errore: fine espressione inaspettata");
+                write!(f, "Errore: espressione terminata inaspettatamente")
+            },
+            TokenError::InvalidExpression(msg) => {
+                error_log!("Errore: espressione non valida ({})", msg);
+                write!(f, "Errore: espressione non valida - {}", msg)
+            },
+            TokenError::InvalidOperator { operator, position } => {
+                error_log!("Operatore non valido: '{}' alla posizione {}", operator, position);
+                write!(f, "Errore: operatore non valido '{}' alla posizione {}", operator, position)
+            },
+            TokenError::UnexpectedToken { token, position, .. } => {
+                error_log!("Token inatteso '{}' alla posizione {}", token, position);
+                write!(f, "Errore: token inatteso '{}' alla posizione {}", token, position)
+            },
+            TokenError::UnknownVariable { name, position: Some(position) } => {
+                error_log!("Variabile sconosciuta '{}' alla posizione {}", name, position);
+                write!(f, "Errore: variabile sconosciuta '{}' alla posizione {}", name, position)
+            },
+            TokenError::UnknownVariable { name, position: None } => {
+                error_log!("Variabile sconosciuta '{}'", name);
+                write!(f, "Errore: variabile sconosciuta '{}'", name)
+            },
+            TokenError::MissingOperand { operator, side, position, .. } => {
+                error_log!("Operando {} mancante per '{}' alla posizione {}", side, operator, position);
+                write!(f, "Errore: manca l'operando {} dell'operatore '{}' alla posizione {}", side, operator, position)
+            },
+            TokenError::SyntaxError(msg) => {
+                error_log!("Errore di sintassi: {}", msg);
+                write!(f, "Errore di sintassi: {}", msg)
+            }
+        }
+    }
+}
+
+/// Implementazione del trait `Error` per `MathError`.
+///
+/// Consente di trattare `MathError` come un errore standard, 
+/// ad esempio per l'uso con `?`.
+impl core::error::Error for MathError {}
+
+/// Implementazione del trait `Error` per `TokenError`.
+///
+/// Consente di trattare `TokenError` come un errore standard, 
+/// ad esempio per l'uso con `?`.
+impl core::error::Error for TokenError {}
+
+/// Rappresenta un errore generico durante il calcolo.
+///
+/// Permette di unificare gli errori matematici (`MathError`)
+/// e gli errori di tokenizzazione/parsing (`TokenError`).
+///
+/// - `Debug`, `PartialEq`.
+#[derive(Debug, PartialEq)]
+pub enum CalcError {
+    // Errore matematico
+    Math(MathError),
+    // Errore durante il parsing
+    Token(TokenError),
+}
+
+/// Conversione automatica da `MathError` a `CalcError`.
+/// Permette di usare `?` in funzioni che restituiscono `CalcResult`.
+impl From<MathError> for CalcError {
+    fn from(e: MathError) -> Self {
+        CalcError::Math(e)
+    }
+}
+
+/// Conversione automatica da `CalcError` a `MathError`.
+/// Permette di `?` in funzioni che restituiscono `CalcResult`.
+impl From<TokenError> for CalcError {
+    fn from(e: TokenError) -> Self {
+        CalcError::Token(e)
+    }
+}
+
+impl CalcError {
+    /// Restituisce la posizione nel sorgente a cui si riferisce l'errore, se nota.
+    ///
+    /// Gli errori matematici non hanno posizione: riguardano valori, non token.
+    pub fn position(&self) -> Option<usize> {
+        match self {
+            CalcError::Math(_) => None,
+            CalcError::Token(e) => e.position(),
+        }
+    }
+
+    /// Restituisce i tipi di token ammessi nel punto di un errore di sintassi (vedi `TokenError::expected_tokens`).
+    ///
+    /// # Esempio
+    /// ```
+    /// let err = compile("2 +").unwrap_err();
+    /// assert!(err.expected_tokens().contains(&TokenKind::Number));
+    /// ```
+    pub fn expected_tokens(&self) -> &[TokenKind] {
+        match self {
+            CalcError::Math(_) => &[],
+            CalcError::Token(e) => e.expected_tokens(),
+        }
+    }
+
+    /// Restituisce il codice stabile del tipo di errore, da usare al posto dei messaggi di `Display`.
+    ///
+    /// # Esempio
+    /// ```
+    /// let err = CalcError::Math(MathError::DivisionByZero);
+    /// assert_eq!(err.code(), ErrorCode::DivByZero);
+    /// ```
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            CalcError::Math(e) => match e {
+                MathError::DivisionByZero => ErrorCode::DivByZero,
+                MathError::OverflowError => ErrorCode::Overflow,
+                MathError::UnderflowError => ErrorCode::Underflow,
+                MathError::MagnitudeExceeded { .. } => ErrorCode::MagnitudeExceeded,
+                MathError::ExpressionTooComplex => ErrorCode::ExpressionTooComplex,
+                MathError::InvalidExponentiation { .. } => ErrorCode::InvalidExponentiation,
+                MathError::NegativeRoot { .. } => ErrorCode::NegativeRoot,
+                MathError::EvenRootOfNegative { .. } => ErrorCode::EvenRootOfNegative,
+                MathError::InvalidRoot { .. } => ErrorCode::InvalidRoot,
+                MathError::DomainError { .. } => ErrorCode::Domain,
+                MathError::NonIntegerArgument { .. } => ErrorCode::NonIntegerArgument,
+                MathError::InvalidLogarithm { .. } => ErrorCode::InvalidLogarithm,
+                MathError::InvalidFactorial { .. } => ErrorCode::InvalidFactorial,
+                MathError::NonInteger { .. } => ErrorCode::NonInteger,
+                MathError::NotANumber { .. } => ErrorCode::NotANumber,
+                MathError::IndeterminateForm { .. } => ErrorCode::IndeterminateForm,
+            },
+            CalcError::Token(e) => match e {
+                TokenError::InvalidNumber(_) => ErrorCode::SyntaxInvalidNumber,
+                TokenError::UnexpectedEnd { .. } => ErrorCode::SyntaxUnexpectedEnd,
+                TokenError::InvalidExpression(_) => ErrorCode::SyntaxInvalidExpression,
+                TokenError::InvalidOperator { .. } => ErrorCode::SyntaxInvalidOperator,
+                TokenError::UnmatchedParenthesis { .. } => ErrorCode::SyntaxUnmatchedParenthesis,
+                TokenError::UnexpectedToken { .. } => ErrorCode::SyntaxUnexpectedToken,
+                TokenError::UnknownVariable { .. } => ErrorCode::UnknownVariable,
+                TokenError::MissingOperand { .. } => ErrorCode::SyntaxMissingOperand,
+                TokenError::SyntaxError(_) => ErrorCode::Syntax,
+            },
+        }
+    }
+}
+
+/// Codice stabile del tipo di un `CalcError`, indipendente dal testo dei messaggi.
+///
+/// Ogni variante di `MathError` e `TokenError` ha il proprio codice; i codici degli errori
+/// sintattici iniziano con `Syntax`. L'enum è `#[non_exhaustive]`: nuovi errori aggiungono nuovi codici.
+///
+/// Derive:
+/// - `Debug`, `Clone`, `Copy`, `PartialEq`, `Eq`, `Hash`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorCode {
+    /// `MathError::DivisionByZero`.
+    DivByZero,
+    /// `MathError::OverflowError`.
+    Overflow,
+    /// `MathError::UnderflowError`.
+    Underflow,
+    /// `MathError::MagnitudeExceeded`.
+    MagnitudeExceeded,
+    /// `MathError::ExpressionTooComplex`.
+    ExpressionTooComplex,
+    /// `MathError::InvalidExponentiation`.
+    InvalidExponentiation,
+    /// `MathError::NegativeRoot`.
+    NegativeRoot,
+    /// `MathError::EvenRootOfNegative`.
+    EvenRootOfNegative,
+    /// `MathError::InvalidRoot`.
+    InvalidRoot,
+    /// `MathError::DomainError`.
+    Domain,
+    /// `MathError::NonIntegerArgument`.
+    NonIntegerArgument,
+    /// `MathError::InvalidLogarithm`.
+    InvalidLogarithm,
+    /// `MathError::InvalidFactorial`.
+    InvalidFactorial,
+    /// `MathError::NonInteger`.
+    NonInteger,
+    /// `MathError::NotANumber`.
+    NotANumber,
+    /// `MathError::IndeterminateForm`.
+    IndeterminateForm,
+    /// `TokenError::InvalidNumber`.
+    SyntaxInvalidNumber,
+    /// `TokenError::UnexpectedEnd`.
+    SyntaxUnexpectedEnd,
+    /// `TokenError::InvalidExpression`.
+    SyntaxInvalidExpression,
+    /// `TokenError::InvalidOperator`.
+    SyntaxInvalidOperator,
+    /// `TokenError::UnmatchedParenthesis`.
+    SyntaxUnmatchedParenthesis,
+    /// `TokenError::UnexpectedToken`.
+    SyntaxUnexpectedToken,
+    /// `TokenError::UnknownVariable`.
+    UnknownVariable,
+    /// `TokenError::MissingOperand`.
+    SyntaxMissingOperand,
+    /// `TokenError::SyntaxError`.
+    Syntax,
+}
+
+/// Implementazione di `Display` per `CalcError`.
+///
+/// Produce un messaggio leggibile combinando `MathError` e `TokenError`.
+/// I messaggi dettagliati vengono delegati ai rispettivi `Display`.
+impl core::fmt::Display for CalcError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            CalcError::Math(e) => write!(f, "Errore matematico: {}", e),
+            CalcError::Token(e) => write!(f, "Errore di parsing: {}", e),
+        }
+    }
+}
+
+/// Implementazione del trait `Error` per `CalcError`.
+///
+/// Consente di trattare `CalcError` come un errore standard, 
+/// ad esempio per l'uso con `?`.
+impl core::error::Error for CalcError {}
+
+/// Alias per il tipo di risultato restituito dalle funzioni di calcolo.
+///
+/// - `Ok(f64)`: rappresenta il risultato numerico del calcolo.
+/// - `Err(CalcError)`: rappresenta un errore che può essere:
+///   - `MathError`: errori aritmetici (es. Divisione per zero, overflow).
+///   - `TokenError`: errori di sintassi o di parsing dell'espressione.
+pub type CalcResult = Result<f64, CalcError>;
+
+/// Modulo di test per la rappresentazione degli errori.
+#[cfg(test)]
+mod tests {
+    use super::*; // Importa tutti i membri del modulo superiore (il codice da testare)
+
+    /// Verifica il messaggio di errore di `UnexpectedToken`.
+    #[test]
+    fn test_unexpected_token_display() {
+        let err = TokenError::UnexpectedToken { token: Token::Multiply, position: 5, expected: vec![TokenKind::Number] };
+        assert_eq!(err.to_string(), "Errore: token inatteso '*' alla posizione 5");
+    }
+
+    /// Verifica che il messaggio di `InvalidRoot` descriva una radice, con l'operatore `$`.
+    #[test]
+    fn test_invalid_root_display() {
+        let err = MathError::InvalidRoot { base: -8.0, root: 1e30 };
+        assert_eq!(err.to_string(), "Errore: radice non valida (-8 $ 1000000000000000000000000000000)");
+    }
+
+    /// Verifica i codici stabili di errori rappresentativi, matematici e sintattici.
+    #[test]
+    fn test_error_codes() {
+        let code = |err: CalcError| err.code();
+
+        assert_eq!(code(MathError::DivisionByZero.into()), ErrorCode::DivByZero);
+        assert_eq!(code(MathError::OverflowError.into()), ErrorCode::Overflow);
+        assert_eq!(code(MathError::DomainError { function: Function::Ln, argument: 0.0 }.into()), ErrorCode::Domain);
+        assert_eq!(code(TokenError::UnexpectedToken { token: Token::Multiply, position: 5, expected: vec![TokenKind::Number] }.into()), ErrorCode::SyntaxUnexpectedToken);
+        assert_eq!(code(TokenError::UnmatchedParenthesis { found: ')', position: 3, expected: vec![] }.into()), ErrorCode::SyntaxUnmatchedParenthesis);
+        assert_eq!(code(TokenError::UnknownVariable { name: "x".into(), position: None }.into()), ErrorCode::UnknownVariable);
+        assert_eq!(code(TokenError::UnexpectedEnd { expected: vec![] }.into()), ErrorCode::SyntaxUnexpectedEnd);
+        assert_eq!(code(MathError::NotANumber { op: "-".into() }.into()), ErrorCode::NotANumber);
+        assert_eq!(code(MathError::IndeterminateForm { form: "0^0".into() }.into()), ErrorCode::IndeterminateForm);
+    }
+}
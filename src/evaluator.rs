@@ -0,0 +1,832 @@
+use core::cell::Cell;
+use core::ops::RangeInclusive;
+
+use crate::builtins::{constant, Function};
+use crate::error::{CalcError, CalcResult, MathError, TokenError};
+use crate::expr::{BinaryOp, Env, Expr, UnaryOp};
+use crate::format::MAX_EXACT_INTEGER;
+use crate::functions::{FunctionTable, UserFunction, MAX_CALL_DEPTH};
+use crate::parser::{AngleMode, DivByZeroMode, ModuloMode, NumericMode, OverflowMode, ZeroPowerMode};
+use crate::prelude::*;
+
+/// Callback che risolve le variabili non definite né predefinite (`Expr::eval_with_callbacks`).
+pub(crate) type VariableCallback<'a> = &'a dyn Fn(&str) -> Option<f64>;
+
+/// Callback che risolve le chiamate di funzioni non definite dall'utente (`Expr::eval_with_callbacks`).
+pub(crate) type FunctionCallback<'a> = &'a dyn Fn(&str, &[f64]) -> Option<CalcResult>;
+
+/// Valutatore di alberi sintattici `Expr`.
+///
+/// Applica gli operatori con le validazioni numeriche (divisione per zero, radici di numeri
+/// negativi, overflow) e risolve le variabili nell'ambiente ricevuto.
+pub(crate) struct Evaluator<'a> {
+    /// Ambiente delle variabili (nome → valore).
+    env: &'a Env,
+    /// Parametri della funzione utente in valutazione, con precedenza su `env` (`None` fuori dalle funzioni).
+    scope: Option<&'a Env>,
+    /// Funzioni definite dall'utente, risolte nelle chiamate `Expr::UserCall`.
+    functions: Option<&'a FunctionTable>,
+    /// Numero di chiamate di funzioni utente in corso, limitato da `MAX_CALL_DEPTH`.
+    depth: usize,
+    /// Contatore delle operazioni eseguite e numero massimo ammesso (`None`: nessun limite).
+    /// Il contatore è condiviso con i valutatori dei corpi delle funzioni utente.
+    step_budget: Option<(&'a Cell<usize>, usize)>,
+    /// Risolve le variabili assenti da parametri, ambiente e costanti predefinite.
+    variable_callback: Option<VariableCallback<'a>>,
+    /// Risolve le chiamate `Expr::UserCall` di funzioni assenti da `functions`.
+    function_callback: Option<FunctionCallback<'a>>,
+    /// Unità degli angoli per le funzioni trigonometriche.
+    angle_mode: AngleMode,
+    /// Comportamento della divisione per zero.
+    div_by_zero: DivByZeroMode,
+    /// Comportamento dei controlli di overflow e underflow.
+    overflow_mode: OverflowMode,
+    /// Convenzione per `0^0`.
+    zero_power: ZeroPowerMode,
+    /// Segno del resto di `mod`.
+    modulo: ModuloMode,
+    /// Limiti dei valori in `eval_integer` (`Int64` o `Int128`).
+    numeric_mode: NumericMode,
+    /// Valore assoluto massimo dei risultati (`None`: nessun limite oltre a quelli di `f64`).
+    max_magnitude: Option<f64>,
+}
+
+impl<'a> Evaluator<'a> {
+    /// Costruisce un valutatore che risolve le variabili in `env`, con gli angoli in radianti.
+    pub(crate) fn new(env: &'a Env) -> Self {
+        Self { env, scope: None, functions: None, depth: 0, step_budget: None, variable_callback: None, function_callback: None, angle_mode: AngleMode::Radians, div_by_zero: DivByZeroMode::Error, overflow_mode: OverflowMode::default(),
+            zero_power: ZeroPowerMode::default(), modulo: ModuloMode::default(), numeric_mode: NumericMode::default(), max_magnitude: None }
+    }
+
+    /// Imposta l'unità degli angoli usata dalle funzioni trigonometriche.
+    pub(crate) fn with_angle_mode(mut self, angle_mode: AngleMode) -> Self {
+        self.angle_mode = angle_mode;
+        self
+    }
+
+    /// Imposta il comportamento della divisione per zero.
+    pub(crate) fn with_div_by_zero_mode(mut self, mode: DivByZeroMode) -> Self {
+        self.div_by_zero = mode;
+        self
+    }
+
+    /// Imposta il comportamento dei controlli di overflow e underflow.
+    pub(crate) fn with_overflow_mode(mut self, mode: OverflowMode) -> Self {
+        self.overflow_mode = mode;
+        self
+    }
+
+    /// Imposta la convenzione per `0^0`.
+    pub(crate) fn with_zero_power_mode(mut self, mode: ZeroPowerMode) -> Self {
+        self.zero_power = mode;
+        self
+    }
+
+    /// Imposta il segno del resto di `mod`.
+    pub(crate) fn with_modulo_mode(mut self, mode: ModuloMode) -> Self {
+        self.modulo = mode;
+        self
+    }
+
+    /// Imposta la modalità numerica, che determina i limiti dei valori in `eval_integer`.
+    pub(crate) fn with_numeric_mode(mut self, mode: NumericMode) -> Self {
+        self.numeric_mode = mode;
+        self
+    }
+
+    /// Imposta il valore assoluto massimo dei risultati, verificato da `check_overflow`.
+    pub(crate) fn with_max_magnitude(mut self, limit: Option<f64>) -> Self {
+        self.max_magnitude = limit;
+        self
+    }
+
+    /// Imposta la tabella delle funzioni definite dall'utente.
+    pub(crate) fn with_functions(mut self, functions: &'a FunctionTable) -> Self {
+        self.functions = Some(functions);
+        self
+    }
+
+    /// Limita a `budget` le operazioni valutate, contate in `steps` (vedi `step`).
+    pub(crate) fn with_step_budget(mut self, steps: &'a Cell<usize>, budget: usize) -> Self {
+        self.step_budget = Some((steps, budget));
+        self
+    }
+
+    /// Imposta i callback che risolvono variabili e funzioni sconosciute, consultati dopo quelli predefiniti.
+    pub(crate) fn with_callbacks(mut self, variables: VariableCallback<'a>, functions: FunctionCallback<'a>) -> Self {
+        self.variable_callback = Some(variables);
+        self.function_callback = Some(functions);
+        self
+    }
+
+    /// Valuta ricorsivamente un albero sintattico.
+    ///
+    /// # Ritorna
+    /// - `Ok(f64)` con il valore dell'espressione.
+    /// - `Err(CalcError)` in caso di errore matematico o di variabile non definita.
+    ///   La posizione di `TokenError::UnknownVariable` è `None`: l'albero non conserva il sorgente.
+    pub(crate) fn eval(&self, expr: &Expr) -> CalcResult {
+        self.step(expr)?;
+        match expr {
+            Expr::Number(n) => Ok(*n),
+
+            // I parametri hanno la precedenza sull'ambiente, che a sua volta prevale sulle costanti predefinite;
+            // il callback esterno viene consultato per ultimo
+            Expr::Variable(name) => match self.scope.and_then(|scope| scope.get(name)).or_else(|| self.env.get(name)).copied()
+                .or_else(|| constant(name)).or_else(|| self.variable_callback.and_then(|callback| callback(name))) {
+                Some(value) => Ok(value),
+                None => Err(TokenError::UnknownVariable { name: name.clone(), position: None }.into()),
+            },
+
+            Expr::Unary(UnaryOp::Neg, operand) => {
+                let val = self.eval(operand)?;
+
+                info_log!("Negazione di {}", val);
+                Ok(-val)
+            }
+
+            Expr::Unary(UnaryOp::Factorial, operand) => {
+                let val = self.eval(operand)?;
+
+                info_log!("Fattoriale di {}", val);
+                self.evaluate_factorial(val)
+            }
+
+            Expr::Binary(op, lhs, rhs) => {
+                let lhs = self.eval(lhs)?; // Left-Hand Side
+                let rhs = self.eval(rhs)?; // Right-Hand Side
+                self.apply_binary(*op, lhs, rhs)
+            }
+
+            Expr::Call(function, args) => {
+                let args = args.iter().map(|arg| self.eval(arg)).collect::<Result<Vec<_>, _>>()?;
+                self.apply_function(*function, &args)
+            }
+
+            Expr::UserCall(name, args) => {
+                let args = args.iter().map(|arg| self.eval(arg)).collect::<Result<Vec<_>, _>>()?;
+                if let Some(result) = self.call_external(name, &args) { return result; }
+                let (function, scope) = self.bind_arguments(name, &args)?;
+                self.enter(&scope).eval(&function.body)
+            }
+        }
+    }
+
+    /// Conta un'operazione (nodo diverso da numeri e variabili) nel limite impostato con `with_step_budget`.
+    ///
+    /// # Ritorna
+    /// - `Ok(())` se il limite non è impostato o non è ancora superato.
+    /// - `Err(MathError::ExpressionTooComplex)` se l'operazione supererebbe il limite.
+    fn step(&self, expr: &Expr) -> Result<(), CalcError> {
+        let Some((steps, budget)) = self.step_budget else { return Ok(()) };
+        if matches!(expr, Expr::Number(_) | Expr::Variable(_)) { return Ok(()); }
+
+        if steps.get() >= budget {
+            error_log!("Limite di {} operazioni superato", budget);
+            return Err(MathError::ExpressionTooComplex.into());
+        }
+        steps.set(steps.get() + 1);
+        Ok(())
+    }
+
+    /// Chiama la funzione `name` tramite il callback esterno, se non è definita nella tabella delle funzioni utente.
+    ///
+    /// # Ritorna
+    /// - `Some(CalcResult)` con l'esito del callback.
+    /// - `None` se la funzione è definita dall'utente, se non c'è un callback o se il callback non la conosce.
+    fn call_external(&self, name: &str, args: &[f64]) -> Option<CalcResult> {
+        if self.functions.is_some_and(|functions| functions.contains(name)) { return None; }
+
+        info_log!("Chiamata esterna di {}({:?})", name, args);
+        self.function_callback.and_then(|callback| callback(name, args))
+    }
+
+    /// Risolve la funzione utente `name` e lega i suoi parametri ai valori degli argomenti.
+    ///
+    /// # Ritorna
+    /// - `Ok((UserFunction, Env))` con la definizione e l'ambiente locale dei parametri.
+    /// - `Err(TokenError::SyntaxError)` se la funzione non è definita o il numero di argomenti non corrisponde.
+    /// - `Err(MathError::ExpressionTooComplex)` se la chiamata supererebbe `MAX_CALL_DEPTH` (es. ricorsione infinita).
+    fn bind_arguments(&self, name: &str, args: &[f64]) -> Result<(&'a UserFunction, Env), CalcError> {
+        let Some(function) = self.functions.and_then(|functions| functions.get(name)) else {
+            return Err(TokenError::SyntaxError(format!("La funzione '{}' non è definita", name)).into());
+        };
+        if function.params.len() != args.len() {
+            let message = format!("La funzione '{}' richiede {} argomenti, trovati {}", name, function.params.len(), args.len());
+            return Err(TokenError::SyntaxError(message).into());
+        }
+        if self.depth >= MAX_CALL_DEPTH {
+            error_log!("Profondità massima delle chiamate superata in '{}'", name);
+            return Err(MathError::ExpressionTooComplex.into());
+        }
+
+        info_log!("Chiamata di {}({:?})", name, args);
+        Ok((function, function.params.iter().cloned().zip(args.iter().copied()).collect()))
+    }
+
+    /// Costruisce il valutatore del corpo di una funzione utente, con i parametri in `scope`.
+    fn enter<'b>(&self, scope: &'b Env) -> Evaluator<'b>
+    where
+        'a: 'b,
+    {
+        Evaluator { scope: Some(scope), depth: self.depth + 1, ..*self }
+    }
+
+    /// Valuta ricorsivamente un albero sintattico in aritmetica intera.
+    ///
+    /// Le operazioni sono controllate: ogni valore deve restare nei limiti di `NumericMode`
+    /// (`i64` per `Int64`, altrimenti `i128`). La divisione è intera, troncata verso zero (`7 / 2` → `3`).
+    /// Radici e funzioni vengono calcolate in `f64` e il loro risultato deve essere intero (es. `sqrt(16)`).
+    ///
+    /// # Ritorna
+    /// - `Ok(i128)` con il valore esatto dell'espressione.
+    /// - `Err(MathError::OverflowError)` se un valore esce dai limiti.
+    /// - `Err(MathError::NonInteger)` per letterali, variabili o risultati di funzioni non interi.
+    /// - Gli altri errori di `eval` (divisione per zero, fattoriale di un negativo, variabile non definita).
+    pub(crate) fn eval_integer(&self, expr: &Expr) -> Result<i128, CalcError> {
+        self.step(expr)?;
+        match expr {
+            Expr::Number(n) => self.to_integer(*n),
+
+            Expr::Variable(_) => self.to_integer(self.eval(expr)?),
+
+            Expr::Unary(UnaryOp::Neg, operand) => self.check_integer(self.eval_integer(operand)?.checked_neg()),
+
+            Expr::Unary(UnaryOp::Factorial, operand) => {
+                let n = self.eval_integer(operand)?;
+                if n < 0 { return Err(MathError::InvalidFactorial { value: n as f64 }.into()); }
+
+                info_log!("Fattoriale intero di {}", n);
+                (2..=n).try_fold(1, |acc: i128, k| self.check_integer(acc.checked_mul(k)))
+            }
+
+            Expr::Binary(op, lhs, rhs) => {
+                let lhs = self.eval_integer(lhs)?; // Left-Hand Side
+                let rhs = self.eval_integer(rhs)?; // Right-Hand Side
+                self.apply_binary_integer(*op, lhs, rhs)
+            }
+
+            Expr::Call(function, args) => {
+                let args = args.iter().map(|arg| self.eval_integer(arg).map(|n| n as f64)).collect::<Result<Vec<_>, _>>()?;
+                self.to_integer(self.apply_function(*function, &args)?)
+            }
+
+            Expr::UserCall(name, args) => {
+                let args = args.iter().map(|arg| self.eval_integer(arg).map(|n| n as f64)).collect::<Result<Vec<_>, _>>()?;
+                if let Some(result) = self.call_external(name, &args) { return self.to_integer(result?); }
+                let (function, scope) = self.bind_arguments(name, &args)?;
+                self.enter(&scope).eval_integer(&function.body)
+            }
+        }
+    }
+
+    /// Applica un operatore binario a due operandi interi già valutati, con aritmetica controllata.
+    fn apply_binary_integer(&self, op: BinaryOp, lhs: i128, rhs: i128) -> Result<i128, CalcError> {
+        info_log!("Operazione intera: {:?}({}, {})", op, lhs, rhs);
+
+        let result = match op {
+            BinaryOp::Add => lhs.checked_add(rhs),
+            BinaryOp::Sub => lhs.checked_sub(rhs),
+            BinaryOp::Mul => lhs.checked_mul(rhs),
+            BinaryOp::Div if rhs == 0 => return Err(MathError::DivisionByZero.into()),
+            BinaryOp::Div => lhs.checked_div(rhs),
+            BinaryOp::Pow if lhs == 0 && rhs <= 0 => {
+                self.check_zero_power(rhs as f64)?;
+                if rhs < 0 { return Err(MathError::DivisionByZero.into()); } // Nessun infinito tra gli interi
+                Some(1)
+            }
+            BinaryOp::Pow if rhs < 0 => {
+                return Err(MathError::InvalidExponentiation { base: lhs as f64, exponent: rhs as f64 }.into());
+            }
+            BinaryOp::Pow => u32::try_from(rhs).ok().and_then(|exponent| lhs.checked_pow(exponent)),
+            BinaryOp::Root => return self.to_integer(self.evaluate_root(lhs as f64, rhs as f64)?),
+            BinaryOp::Less | BinaryOp::LessEqual | BinaryOp::Greater | BinaryOp::GreaterEqual | BinaryOp::Equal => {
+                Some(op.compare(lhs, rhs) as i128)
+            }
+        };
+
+        self.check_integer(result)
+    }
+
+    /// Verifica che il risultato di un'operazione controllata sia presente e nei limiti di `NumericMode`.
+    ///
+    /// # Ritorna
+    /// - `Ok(i128)` con il valore.
+    /// - `Err(MathError::OverflowError)` se l'operazione è andata in overflow (`None`) o il valore è fuori dai limiti.
+    fn check_integer(&self, value: Option<i128>) -> Result<i128, CalcError> {
+        match value {
+            Some(n) if self.integer_range().contains(&n) => Ok(n),
+            _ => Err(MathError::OverflowError.into()),
+        }
+    }
+
+    /// Restituisce l'intervallo dei valori ammessi in aritmetica intera.
+    fn integer_range(&self) -> RangeInclusive<i128> {
+        match self.numeric_mode {
+            NumericMode::Int64 => i64::MIN as i128..=i64::MAX as i128,
+            NumericMode::Float | NumericMode::Int128 => i128::MIN..=i128::MAX,
+        }
+    }
+
+    /// Converte un valore `f64` in intero, verificando che non abbia parte frazionaria e che sia nei limiti.
+    fn to_integer(&self, value: f64) -> Result<i128, CalcError> {
+        if value.fract() != 0.0 { return Err(MathError::NonInteger { value }.into()); }
+
+        // La conversione `as` satura: oltre 2^127 il valore non è rappresentabile in i128
+        if value.abs() >= 2f64.powi(127) { return Err(MathError::OverflowError.into()); }
+        self.check_integer(Some(value as i128))
+    }
+
+    /// Applica una funzione predefinita agli argomenti già valutati.
+    ///
+    /// Il parser garantisce che il numero di argomenti sia compreso in `Function::arity()`.
+    ///
+    /// # Errori gestiti
+    /// - `MathError::EvenRootOfNegative` per `sqrt` di un numero negativo.
+    /// - `MathError::DomainError` per argomenti fuori dominio (`ln`/`log` di valori non positivi,
+    ///   `asin`/`acos` fuori da `[-1, 1]`, `acosh` sotto 1, `atanh` fuori da `(-1, 1)`).
+    /// - `MathError::InvalidLogarithm` per `log(x, b)` con `x <= 0`, `b <= 0` o `b == 1`.
+    /// - `MathError::OverflowError` tramite `check_overflow` (es. `exp(1000)`) e
+    ///   `MathError::UnderflowError` tramite `check_underflow` (es. `exp(-1000)`).
+    /// - Gli errori di `modular_pow` per `powmod`.
+    /// - `MathError::NonIntegerArgument` per `gcd` e `lcm` con argomenti non interi.
+    /// - `MathError::InvalidFactorial` per `gamma` e `factorial` nei poli (interi negativi, e `gamma(0)`).
+    /// - `MathError::DomainError` per `clamp(x, lo, hi)` con `lo > hi` (o un estremo `NaN`), riportando `lo`.
+    /// - `MathError::DomainError` per `pct_change` con valore iniziale nullo.
+    fn apply_function(&self, function: Function, args: &[f64]) -> CalcResult {
+        let x = args[0];
+        info_log!("Funzione: {}({})", function, x);
+
+        let domain_error = || -> CalcResult { Err(MathError::DomainError { function, argument: x }.into()) };
+
+        let result = match function {
+            Function::Sqrt if x < 0.0 => return Err(MathError::EvenRootOfNegative { base: x, root: 2.0 }.into()),
+            Function::Sqrt => x.sqrt(),
+            Function::Abs => x.abs(),
+            Function::Sin => self.angle_to_radians(x).sin(),
+            Function::Cos => self.angle_to_radians(x).cos(),
+            Function::Tan => self.angle_to_radians(x).tan(),
+            Function::Asin | Function::Acos if !(-1.0..=1.0).contains(&x) => return domain_error(),
+            Function::Asin => self.angle_from_radians(x.asin()),
+            Function::Acos => self.angle_from_radians(x.acos()),
+            Function::Atan => self.angle_from_radians(x.atan()),
+            Function::Atan2 => self.angle_from_radians(x.atan2(args[1])),
+            Function::Sinh => x.sinh(),
+            Function::Cosh => x.cosh(),
+            Function::Tanh => x.tanh(),
+            Function::Asinh => x.asinh(),
+            Function::Acosh if x < 1.0 => return domain_error(),
+            Function::Acosh => x.acosh(),
+            Function::Atanh if x.abs() >= 1.0 => return domain_error(),
+            Function::Atanh => x.atanh(),
+            Function::Log if args.len() == 2 => return self.evaluate_logarithm(x, args[1]),
+            Function::Ln | Function::Log | Function::Log10 if x <= 0.0 => return domain_error(),
+            Function::Ln => x.ln(),
+            Function::Log | Function::Log10 => x.log10(),
+            Function::Exp => return self.check_underflow(self.check_overflow(x.exp())?, x.is_finite()),
+            Function::Floor => x.floor(),
+            Function::Ceil => x.ceil(),
+            Function::Round => x.round(), // Metà strada lontano dallo zero, come `f64::round`
+            Function::Trunc => x.trunc(),
+            Function::Mod => return self.evaluate_modulo(x, args[1]),
+            Function::Powmod => return modular_pow(x, args[1], args[2]),
+            Function::Gcd | Function::Lcm => return gcd_lcm(function, x, args[1]),
+            Function::Gamma => return self.evaluate_gamma(x),
+            Function::Factorial => return self.evaluate_gamma(x + 1.0),
+            // `f64::clamp` va in panico se `lo > hi` o un estremo è NaN
+            Function::Clamp if args[1].is_nan() || args[2].is_nan() || args[1] > args[2] => {
+                return Err(MathError::DomainError { function, argument: args[1] }.into());
+            }
+            Function::Clamp => x.clamp(args[1], args[2]),
+            Function::Sign if x == 0.0 => 0.0, // Anche -0: `signum` restituirebbe -1
+            Function::Sign => x.signum(),
+            Function::PctChange if x == 0.0 => return domain_error(), // Variazione da zero non definita
+            Function::PctChange => (args[1] - x) / x * 100.0,
+            Function::PctOf => x / 100.0 * args[1],
+            Function::Deg => x.to_degrees(),
+            Function::Rad => x.to_radians(),
+            Function::Sum => args.iter().sum(),
+            Function::Product => args.iter().product(),
+            Function::Mean => args.iter().sum::<f64>() / args.len() as f64,
+            Function::Min => args.iter().copied().fold(f64::INFINITY, f64::min),
+            Function::Max => args.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+        };
+
+        self.check_overflow(result)
+    }
+
+    /// Calcola il logaritmo di `value` in base `base`.
+    ///
+    /// # Ritorna
+    /// - `Ok(f64)` con il logaritmo.
+    /// - `Err(MathError::InvalidLogarithm)` se `value <= 0`, `base <= 0` o `base == 1`.
+    fn evaluate_logarithm(&self, value: f64, base: f64) -> CalcResult {
+        if value <= 0.0 || base <= 0.0 || base == 1.0 {
+            return Err(MathError::InvalidLogarithm { value, base }.into());
+        }
+
+        info_log!("Logaritmo: log({}, {})", value, base);
+
+        // Le basi 2 e 10 hanno funzioni dedicate, esatte sulle potenze (`log(1000, 10)` → 3, non 2.9999999999999996)
+        let result = match base {
+            2.0 => value.log2(),
+            10.0 => value.log10(),
+            _ => value.log(base),
+        };
+        self.check_overflow(result)
+    }
+
+    /// Converte un angolo dalla modalità corrente in radianti.
+    fn angle_to_radians(&self, angle: f64) -> f64 {
+        match self.angle_mode {
+            AngleMode::Radians => angle,
+            AngleMode::Degrees => angle.to_radians(),
+        }
+    }
+
+    /// Converte un angolo in radianti nella modalità corrente.
+    fn angle_from_radians(&self, angle: f64) -> f64 {
+        match self.angle_mode {
+            AngleMode::Radians => angle,
+            AngleMode::Degrees => angle.to_degrees(),
+        }
+    }
+
+    /// Applica un operatore binario a due operandi già valutati.
+    fn apply_binary(&self, op: BinaryOp, lhs: f64, rhs: f64) -> CalcResult {
+        match op {
+            BinaryOp::Add => {
+                info_log!("Operazione: {} + {}", lhs, rhs);
+                self.check_overflow(check_nan(op, lhs, rhs, lhs + rhs)?)
+            }
+            BinaryOp::Sub => {
+                info_log!("Operazione: {} - {}", lhs, rhs);
+                self.check_overflow(check_nan(op, lhs, rhs, lhs - rhs)?)
+            }
+            BinaryOp::Mul => {
+                info_log!("Moltiplicazione: {} * {}", lhs, rhs);
+                let product = check_nan(op, lhs, rhs, lhs * rhs)?;
+                self.check_underflow(self.check_overflow(product)?, nonzero_finite(lhs) && nonzero_finite(rhs))
+            }
+            BinaryOp::Div => {
+                // n / 0 --> Errore, NaN o semantica IEEE a seconda della modalità
+                if rhs == 0.0 {
+                    match self.div_by_zero {
+                        DivByZeroMode::Error => return Err(MathError::DivisionByZero.into()),
+                        DivByZeroMode::Nan => return Ok(f64::NAN),
+                        DivByZeroMode::Infinity => {}
+                    }
+                }
+
+                info_log!("Divisione: {} / {}", lhs, rhs);
+                let quotient = check_nan(op, lhs, rhs, lhs / rhs)?;
+                self.check_underflow(self.check_overflow(quotient)?, nonzero_finite(lhs) && rhs.is_finite())
+            }
+            BinaryOp::Pow => {
+                info_log!("Esponenziale: {} ^ {}", lhs, rhs);
+                self.evaluate_exponentiation(lhs, rhs)
+            }
+            BinaryOp::Root => {
+                info_log!("Radice: {} $ {}", lhs, rhs);
+                self.evaluate_root(lhs, rhs)
+            }
+            BinaryOp::Less | BinaryOp::LessEqual | BinaryOp::Greater | BinaryOp::GreaterEqual | BinaryOp::Equal => {
+                info_log!("Confronto: {:?}({}, {})", op, lhs, rhs);
+                Ok(if op.compare(lhs, rhs) { 1.0 } else { 0.0 })
+            }
+        }
+    }
+
+    /// Calcola l'esponenziale tra due numeri, ossia `base ^ esponente`.
+    ///
+    /// Questo metodo calcola la potenza della base elevata all'esponente e verifica se il risultato
+    /// è valido (non è `NaN`, né infinito in `OverflowMode::Strict`).
+    /// Gestisce anche eventuali overflow o underflow numerici tramite i metodi `check_overflow` e `check_underflow`.
+    ///
+    /// # Parametri
+    /// - `base`: f64 — la base su cui applicare l'esponenziale.
+    /// - `exponent`: f64 — l'esponente a cui elevare la base.
+    ///
+    /// Le potenze di zero sono gestite esplicitamente, senza affidarsi a `powf`:
+    /// `0^0` segue `ZeroPowerMode` e `0^n` con `n < 0` è una divisione per zero secondo `DivByZeroMode`.
+    ///
+    /// # Ritorna
+    /// - `Ok(f64)` se il calcolo è valido e il risultato non è fuori dai limiti numerici.
+    /// - `Err(MathError)` in caso di errore, come esponenziali che generano `NaN` o valori infiniti.
+    /// - `Err(MathError::IndeterminateForm)` per `0^0` con `ZeroPowerMode::Indeterminate`.
+    /// - `Err(MathError::DivisionByZero)` per `0^n` con `n < 0` e `DivByZeroMode::Error`.
+    fn evaluate_exponentiation(&self, base: f64, exponent: f64) -> CalcResult {
+        if base == 0.0 {
+            self.check_zero_power(exponent)?;
+            if exponent < 0.0 && self.div_by_zero == DivByZeroMode::Nan { return Ok(f64::NAN); }
+        }
+
+        // Calcola la potenza: base elevato all'esponente
+        let result = base.powf(exponent);
+
+        // Se il risultato è NaN, o infinito in modalità rigorosa, restituiamo un errore.
+        // `0^n` con `n < 0` è una divisione per zero, già gestita secondo `DivByZeroMode`
+        if result.is_nan() || (result.is_infinite() && base != 0.0 && self.overflow_mode == OverflowMode::Strict) {
+            return Err(MathError::InvalidExponentiation { base, exponent }.into());
+        }
+
+        self.check_underflow(self.check_overflow(result)?, nonzero_finite(base) && exponent.is_finite())
+    }
+
+    /// Verifica una potenza di base zero con esponente `exponent`: `0^0` con `ZeroPowerMode::Indeterminate`
+    /// è una forma indeterminata, `0^n` con `n < 0` una divisione per zero (salvo `DivByZeroMode` diversa da `Error`).
+    fn check_zero_power(&self, exponent: f64) -> Result<(), CalcError> {
+        if exponent == 0.0 && self.zero_power == ZeroPowerMode::Indeterminate {
+            return Err(MathError::IndeterminateForm { form: "0^0".into() }.into());
+        }
+        if exponent < 0.0 && self.div_by_zero == DivByZeroMode::Error {
+            return Err(MathError::DivisionByZero.into());
+        }
+        Ok(())
+    }
+
+    /// Calcola il resto di `x` diviso `m` secondo `ModuloMode`.
+    ///
+    /// Con `m = 0` il resto non è definito: errore con `DivByZeroMode::Error`, altrimenti `NaN`.
+    ///
+    /// # Esempio
+    /// ```
+    /// // Troncato: -10 = -3 * 3 - 1; euclideo: -10 = -4 * 3 + 2
+    /// assert_eq!(evaluate("mod(-10, 3) ="), Ok(-1.0));
+    /// ```
+    fn evaluate_modulo(&self, x: f64, m: f64) -> CalcResult {
+        if m == 0.0 {
+            if self.div_by_zero == DivByZeroMode::Error { return Err(MathError::DivisionByZero.into()); }
+            return Ok(f64::NAN);
+        }
+
+        let remainder = x % m; // Segno del dividendo
+        Ok(match self.modulo {
+            ModuloMode::Truncated => remainder,
+            // Come `f64::rem_euclid`, disponibile solo con `std`
+            ModuloMode::Euclidean if remainder < 0.0 => remainder + m.abs(),
+            ModuloMode::Euclidean => remainder,
+        })
+    }
+
+    /// Calcola il fattoriale `n!` di un intero non negativo.
+    ///
+    /// Oltre `170!` il prodotto non è rappresentabile in `f64`: il risultato infinito
+    /// viene gestito da `check_overflow` secondo `OverflowMode`.
+    ///
+    /// # Ritorna
+    /// - `Ok(f64)` con il fattoriale.
+    /// - `Err(MathError::InvalidFactorial)` se `n` è negativo o non intero.
+    fn evaluate_factorial(&self, n: f64) -> CalcResult {
+        if n < 0.0 || n.fract() != 0.0 { return Err(MathError::InvalidFactorial { value: n }.into()); }
+
+        // Il ciclo si interrompe appena il prodotto diventa infinito
+        let mut result = 1.0_f64;
+        let mut k = 2.0;
+        while k <= n && result.is_finite() {
+            result *= k;
+            k += 1.0;
+        }
+
+        self.check_overflow(result)
+    }
+
+    /// Calcola la funzione gamma `Γ(z)`, su cui si basa la forma funzionale `factorial(x) = Γ(x + 1)`.
+    ///
+    /// Per gli interi positivi il valore è calcolato esattamente come `(z - 1)!` con `evaluate_factorial`;
+    /// negli altri casi con l'approssimazione di Lanczos (`lanczos_gamma`).
+    ///
+    /// # Ritorna
+    /// - `Ok(f64)` con `Γ(z)`; i valori troppo grandi sono gestiti da `check_overflow`.
+    /// - `Err(MathError::InvalidFactorial)` nei poli, gli interi `z <= 0`: il valore riportato è
+    ///   l'argomento del fattoriale corrispondente, `z - 1` (es. `factorial(-1)` e `gamma(0)` → `-1!`).
+    fn evaluate_gamma(&self, z: f64) -> CalcResult {
+        if z.fract() == 0.0 {
+            if z <= 0.0 { return Err(MathError::InvalidFactorial { value: z - 1.0 }.into()); }
+            return self.evaluate_factorial(z - 1.0);
+        }
+
+        self.check_overflow(lanczos_gamma(z))
+    }
+
+    /// Calcola la radice di un numero, ossia `base $ root`.
+    ///
+    /// Questo metodo gestisce il calcolo della radice di `base` con indice `root`.
+    /// Se la base è negativa e la radice non è un intero dispari, restituisce un errore (`MathError::EvenRootOfNegative`).
+    /// Se la base è negativa e la radice è frazionaria, restituisce un errore (`MathError::NegativeRoot`).
+    /// Se la base è negativa e l'indice supera in valore assoluto 2^53 (o non è finito), la sua parità non è
+    /// determinabile in `f64` e restituisce un errore (`MathError::InvalidRoot`).
+    /// Inoltre, gestisce il caso della divisione per zero nel caso in cui `root` sia uguale a zero.
+    ///
+    /// # Parametri
+    /// - `base`: f64 — la base su cui calcolare la radice.
+    /// - `root`: f64 — l'indice della radice da calcolare.
+    ///
+    /// # Ritorna
+    /// - `Ok(f64)` se il calcolo è valido e il risultato non è fuori dai limiti numerici.
+    /// - `Err(MathError)` in caso di errore, come divisione per zero o radice di un numero negativo con indice pari.
+    fn evaluate_root(&self, base: f64, root: f64) -> CalcResult {
+        // Controlla se la radice è zero, il che porterebbe a divisione per zero
+        if root == 0.0 { return Err(MathError::DivisionByZero.into()); }
+
+        // Gestisce il caso di base negativa
+        if base < 0.0 {
+
+            // Oltre 2^53 gli f64 sono tutti interi pari: la parità dell'indice scritto andrebbe persa
+            if root.is_nan() || root.abs() > MAX_EXACT_INTEGER { return Err(MathError::InvalidRoot { base, root }.into()); }
+
+            // Se la radice è frazionaria, non possiamo calcolare la radice di un numero negativo
+            if root.fract() != 0.0 { return Err(MathError::NegativeRoot { base, root }.into()); }
+
+            // Se la radice è pari e la base è negativa, restituiamo un errore (resto esatto, senza conversioni)
+            if root % 2.0 == 0.0 { return Err(MathError::EvenRootOfNegative { base, root }.into()); }
+
+            // Calcola la radice per base negativa
+            let result = -(-base).powf(1.0 / root);
+            return self.check_overflow(result);
+        }
+
+        // Calcola la radice per base positiva
+        let result = base.powf(1.0 / root);
+
+        // Se il risultato è NaN o infinito, restituiamo un errore
+        if result.is_nan() || result.is_infinite() { return Err(MathError::InvalidRoot { base, root }.into()); }
+
+        self.check_overflow(result)
+    }
+
+    /// Verifica se il valore è valido, controllando eventuali condizioni di overflow.
+    ///
+    /// Il comportamento dipende da `OverflowMode`:
+    /// - `Strict`: un valore infinito è un errore di overflow.
+    /// - `Saturate`: un valore infinito viene limitato a `f64::MAX` / `f64::MIN`.
+    /// - `Allow`: il valore viene restituito invariato.
+    ///
+    /// Con `DivByZeroMode::Infinity` gli infiniti sono valori ammessi e restano invariati in ogni modalità.
+    /// I valori subnormali sono piccoli ma rappresentabili, quindi non sono mai un errore.
+    ///
+    /// Se è impostato un valore assoluto massimo (`with_max_magnitude`), il valore risultante deve
+    /// rispettarlo anche se finito; il limite stesso è ammesso, gli infiniti lo superano sempre.
+    ///
+    /// # Ritorna
+    /// - `Ok(f64)` con il valore, eventualmente limitato.
+    /// - `Err(CalcError)` in caso di overflow in modalità `Strict`, o `MathError::MagnitudeExceeded`
+    ///   se il valore supera il limite impostato.
+    fn check_overflow(&self, val: f64) -> Result<f64, CalcError> {
+        // Infinito
+        let val = if val.is_infinite() && self.div_by_zero != DivByZeroMode::Infinity {
+            match self.overflow_mode {
+                OverflowMode::Strict => return Err(MathError::OverflowError.into()),
+                OverflowMode::Saturate => if val > 0.0 { f64::MAX } else { f64::MIN },
+                OverflowMode::Allow => val,
+            }
+        } else {
+            val
+        };
+
+        if let Some(limit) = self.max_magnitude && val.abs() > limit {
+            return Err(MathError::MagnitudeExceeded { value: val, limit }.into());
+        }
+        Ok(val)
+    }
+
+    /// Verifica l'underflow: un risultato esattamente zero prodotto da operandi per cui non dovrebbe esserlo.
+    ///
+    /// Vale solo per prodotti, quozienti e potenze (es. `2^-600 * 2^-600`): una somma che dà zero
+    /// (`1 - 1`) è un risultato esatto, non un underflow.
+    ///
+    /// # Parametri
+    /// - `val`: il risultato calcolato.
+    /// - `expected_nonzero`: `true` se, in aritmetica esatta, il risultato non sarebbe zero.
+    ///
+    /// # Ritorna
+    /// - `Err(MathError::UnderflowError)` se il risultato si è azzerato in modalità `Strict`.
+    /// - `Ok(f64)` altrimenti; nelle altre modalità l'azzeramento viene accettato.
+    fn check_underflow(&self, val: f64, expected_nonzero: bool) -> CalcResult {
+        if val == 0.0 && expected_nonzero && self.overflow_mode == OverflowMode::Strict {
+            return Err(MathError::UnderflowError.into());
+        }
+        Ok(val)
+    }
+}
+
+/// Verifica che un'operazione aritmetica non abbia prodotto `NaN` da operandi numerici
+/// (es. `inf - inf`, `0 * inf`, `0 / 0` con `DivByZeroMode::Infinity`).
+///
+/// Un `NaN` già presente in un operando (es. prodotto da `DivByZeroMode::Nan`) viene propagato
+/// senza errore: è un valore richiesto esplicitamente, non un risultato inatteso.
+fn check_nan(op: BinaryOp, lhs: f64, rhs: f64, result: f64) -> CalcResult {
+    if result.is_nan() && !lhs.is_nan() && !rhs.is_nan() {
+        return Err(MathError::NotANumber { op: op.symbol().to_string() }.into());
+    }
+    Ok(result)
+}
+
+/// Restituisce `true` se `value` è finito e diverso da zero.
+fn nonzero_finite(value: f64) -> bool {
+    value != 0.0 && value.is_finite()
+}
+
+/// Coefficienti dell'approssimazione di Lanczos con `g = 7` e 9 termini (precisione relativa ~1e-15).
+const LANCZOS_G: f64 = 7.0;
+const LANCZOS_COEFFICIENTS: [f64; 9] = [
+    0.999_999_999_999_809_9,
+    676.520_368_121_885_1,
+    -1_259.139_216_722_402_8,
+    771.323_428_777_653_1,
+    -176.615_029_162_140_6,
+    12.507_343_278_686_905,
+    -0.138_571_095_265_720_12,
+    9.984_369_578_019_572e-6,
+    1.505_632_735_149_311_6e-7,
+];
+
+/// Approssima `Γ(z)` con il metodo di Lanczos, usando la formula di riflessione per `z < 0.5`.
+///
+/// Il chiamante esclude i poli (interi non positivi).
+fn lanczos_gamma(z: f64) -> f64 {
+    use core::f64::consts::PI;
+
+    // Formula di riflessione: Γ(z) Γ(1 - z) = π / sin(πz)
+    if z < 0.5 { return PI / ((PI * z).sin() * lanczos_gamma(1.0 - z)); }
+
+    let z = z - 1.0;
+    let series = LANCZOS_COEFFICIENTS[1..].iter().enumerate()
+        .fold(LANCZOS_COEFFICIENTS[0], |acc, (i, c)| acc + c / (z + i as f64 + 1.0));
+    let t = z + LANCZOS_G + 0.5;
+
+    // t^(z + 0.5) è diviso in due fattori per non andare in overflow prima di moltiplicare per e^-t
+    let half_power = t.powf((z + 0.5) / 2.0);
+    (2.0 * PI).sqrt() * half_power * (half_power * (-t).exp()) * series
+}
+
+/// Calcola il massimo comun divisore (`Function::Gcd`) o il minimo comune multiplo (`Function::Lcm`) di due interi.
+///
+/// Il risultato è sempre non negativo; `gcd(0, 0)` e `lcm(n, 0)` valgono 0.
+///
+/// # Errori gestiti
+/// - `MathError::NonIntegerArgument` se un argomento non è un intero rappresentabile in `i64`.
+fn gcd_lcm(function: Function, a: f64, b: f64) -> CalcResult {
+    let to_integer = |value: f64| -> Result<u128, CalcError> {
+        if value.fract() != 0.0 || value.abs() >= 2f64.powi(63) || !value.is_finite() {
+            return Err(MathError::NonIntegerArgument { function, argument: value }.into());
+        }
+        Ok((value as i128).unsigned_abs())
+    };
+    let (a, b) = (to_integer(a)?, to_integer(b)?);
+
+    // Algoritmo di Euclide
+    let (mut x, mut y) = (a, b);
+    while y != 0 {
+        (x, y) = (y, x % y);
+    }
+    let gcd = x;
+
+    // |a|, |b| < 2^63: il prodotto non supera i limiti di u128
+    let result = match function {
+        Function::Lcm if gcd == 0 => 0,
+        Function::Lcm => a / gcd * b,
+        _ => gcd,
+    };
+
+    info_log!("{}({}, {}) = {}", function, a, b, result);
+    Ok(result as f64)
+}
+
+/// Calcola `base ^ exponent mod modulus` con l'esponenziazione binaria su interi.
+///
+/// A differenza di `powf`, non calcola mai la potenza intera: i valori intermedi restano
+/// minori del modulo, quindi esponenti molto grandi non producono overflow.
+/// Il risultato è sempre in `[0, |modulus|)`.
+///
+/// # Errori gestiti
+/// - `MathError::DomainError` se un operando non è un intero rappresentabile in `i64`,
+///   se l'esponente è negativo o se il modulo è zero.
+fn modular_pow(base: f64, exponent: f64, modulus: f64) -> CalcResult {
+    let to_integer = |value: f64| -> Result<i128, CalcError> {
+        if value.fract() != 0.0 || value.abs() >= 2f64.powi(63) || !value.is_finite() {
+            return Err(MathError::DomainError { function: Function::Powmod, argument: value }.into());
+        }
+        Ok(value as i128)
+    };
+
+    let (mut base, mut exponent, modulus) = (to_integer(base)?, to_integer(exponent)?, to_integer(modulus)?);
+    if exponent < 0 || modulus == 0 {
+        let argument = if modulus == 0 { 0.0 } else { exponent as f64 };
+        return Err(MathError::DomainError { function: Function::Powmod, argument }.into());
+    }
+
+    let modulus = modulus.abs();
+    let mut result = 1 % modulus;
+    base = base.rem_euclid(modulus);
+
+    // (modulus - 1)^2 < 2^126: i prodotti non superano mai i limiti di i128
+    while exponent > 0 {
+        if exponent & 1 == 1 { result = result * base % modulus; }
+        base = base * base % modulus;
+        exponent >>= 1;
+    }
+
+    info_log!("Potenza modulare: {}", result);
+    Ok(result as f64)
+}
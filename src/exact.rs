@@ -0,0 +1,213 @@
+use core::str::FromStr;
+
+use bigdecimal::{BigDecimal, One, ToPrimitive, Zero};
+
+use crate::builtins::constant;
+use crate::compile::compile;
+use crate::error::{CalcError, MathError, TokenError};
+use crate::expr::{BinaryOp, Env, Expr, UnaryOp};
+use crate::prelude::*;
+
+/// Numero massimo di cifre di una potenza o di un fattoriale calcolati esattamente.
+///
+/// Oltre questo limite (es. `2^10000000`) la valutazione produce `MathError::ExpressionTooComplex`
+/// invece di occupare memoria e tempo senza controllo.
+pub const MAX_EXACT_DIGITS: u64 = 100_000;
+
+/// Tokenizza, analizza e valuta `input` in precisione arbitraria, con `BigDecimal` al posto di `f64`.
+///
+/// L'input è una singola espressione con `=` finale facoltativo, come in `compile()`; le variabili
+/// ammesse sono solo le costanti predefinite. Vedi `Expr::eval_exact` per le operazioni esatte.
+///
+/// # Ritorna
+/// - `Ok(BigDecimal)` con il valore dell'espressione.
+/// - `Err(CalcError)` con l'errore di tokenizzazione, di sintassi o di valutazione.
+///
+/// # Esempio
+/// ```
+/// assert_eq!(evaluate_exact("0.1 + 0.2 =")?, BigDecimal::from_str("0.3")?);
+/// assert_eq!(evaluate_exact("2^100")?.to_string(), "1267650600228229401496703205376");
+/// ```
+pub fn evaluate_exact(input: &str) -> Result<BigDecimal, CalcError> {
+    compile(input)?.expr().eval_exact(&Env::new())
+}
+
+impl Expr {
+    /// Valuta l'albero sintattico in precisione arbitraria, risolvendo le variabili in `env`.
+    ///
+    /// # Regole
+    /// - I letterali sono convertiti dalla loro forma decimale più breve: `0.1` vale esattamente 1/10,
+    ///   non il valore binario più vicino, così `0.1 + 0.2` è esattamente `0.3`.
+    /// - Somma, sottrazione, moltiplicazione, negazione e confronti sono esatti.
+    /// - Le potenze con esponente intero e il fattoriale sono esatti fino a `MAX_EXACT_DIGITS` cifre
+    ///   (es. `2^200`, `50!`); un esponente negativo passa per la divisione.
+    /// - La divisione è esatta se il quoziente ha uno sviluppo decimale finito, altrimenti è arrotondata
+    ///   a 100 cifre significative (es. `1/3`).
+    /// - Radici, potenze con esponente non intero e funzioni sono calcolate in `f64` sugli argomenti
+    ///   già valutati, con gli angoli in radianti: il risultato ha la precisione di `f64`.
+    ///
+    /// # Ritorna
+    /// - `Ok(BigDecimal)` con il valore dell'espressione.
+    /// - `Err(CalcError)` per errori matematici o variabili non definite in `env`.
+    ///
+    /// # Esempio
+    /// ```
+    /// let expr = compile("x * 3")?.expr().clone();
+    /// let env = Env::from([("x".to_string(), 0.1)]);
+    /// assert_eq!(expr.eval_exact(&env)?, BigDecimal::from_str("0.3")?);
+    /// ```
+    pub fn eval_exact(&self, env: &Env) -> Result<BigDecimal, CalcError> {
+        match self {
+            Expr::Number(n) => decimal(*n),
+            Expr::Variable(name) => match env.get(name).copied().or_else(|| constant(name)) {
+                Some(value) => decimal(value),
+                None => Err(TokenError::UnknownVariable { name: name.clone(), position: None }.into()),
+            },
+
+            Expr::Unary(UnaryOp::Neg, operand) => Ok(-operand.eval_exact(env)?),
+            Expr::Unary(UnaryOp::Factorial, operand) => factorial(&operand.eval_exact(env)?),
+
+            Expr::Binary(op, lhs, rhs) => {
+                let (lhs, rhs) = (lhs.eval_exact(env)?, rhs.eval_exact(env)?);
+                match op {
+                    BinaryOp::Add => Ok(lhs + rhs),
+                    BinaryOp::Sub => Ok(lhs - rhs),
+                    BinaryOp::Mul => Ok(lhs * rhs),
+                    BinaryOp::Div if rhs.is_zero() => Err(MathError::DivisionByZero.into()),
+                    BinaryOp::Div => Ok(lhs / rhs),
+                    // Esponente intero su base non nulla: le forme `0^n` seguono le convenzioni di `f64`
+                    BinaryOp::Pow if rhs.is_integer() && !lhs.is_zero() => power(&lhs, &rhs),
+                    BinaryOp::Pow | BinaryOp::Root => approximate(Expr::Binary(*op, Box::new(number(&lhs)?), Box::new(number(&rhs)?))),
+                    BinaryOp::Less | BinaryOp::LessEqual | BinaryOp::Greater | BinaryOp::GreaterEqual | BinaryOp::Equal => {
+                        Ok(if op.compare(&lhs, &rhs) { BigDecimal::one() } else { BigDecimal::zero() })
+                    }
+                }
+            }
+
+            Expr::Call(function, args) => {
+                let args = args.iter().map(|arg| number(&arg.eval_exact(env)?)).collect::<Result<Vec<_>, _>>()?;
+                approximate(Expr::Call(*function, args))
+            }
+            Expr::UserCall(name, args) => {
+                let args = args.iter().map(|arg| number(&arg.eval_exact(env)?)).collect::<Result<Vec<_>, _>>()?;
+                approximate(Expr::UserCall(name.clone(), args))
+            }
+        }
+    }
+}
+
+/// Converte un valore `f64` nel decimale della sua rappresentazione più breve (`0.1` → 1/10).
+///
+/// # Ritorna
+/// - `Err(MathError::OverflowError)` per i valori infiniti, `Err(MathError::NotANumber)` per `NaN`.
+fn decimal(value: f64) -> Result<BigDecimal, CalcError> {
+    if value.is_nan() { return Err(MathError::NotANumber { op: "=".into() }.into()); }
+    if value.is_infinite() { return Err(MathError::OverflowError.into()); }
+
+    // `Display` di `f64` non usa mai la notazione esponenziale: la stringa è sempre un decimale valido
+    BigDecimal::from_str(&value.to_string()).map_err(|_| MathError::OverflowError.into())
+}
+
+/// Converte un valore esatto nel numero letterale `f64` più vicino, per le operazioni approssimate.
+fn number(value: &BigDecimal) -> Result<Expr, CalcError> {
+    match value.to_f64() {
+        Some(n) if n.is_finite() => Ok(Expr::Number(n)),
+        _ => Err(MathError::OverflowError.into()),
+    }
+}
+
+/// Valuta in `f64` un nodo con operandi già numerici, riportando il risultato in decimale.
+fn approximate(expr: Expr) -> Result<BigDecimal, CalcError> {
+    decimal(expr.eval(&Env::new())?)
+}
+
+/// Calcola esattamente `base^exponent` per un esponente intero, con l'elevamento a potenza per quadrati.
+///
+/// # Ritorna
+/// - `Err(MathError::ExpressionTooComplex)` se il risultato supererebbe `MAX_EXACT_DIGITS` cifre.
+fn power(base: &BigDecimal, exponent: &BigDecimal) -> Result<BigDecimal, CalcError> {
+    let too_complex = || CalcError::from(MathError::ExpressionTooComplex);
+    let n = exponent.to_i64().ok_or_else(too_complex)?;
+    if base.digits().saturating_mul(n.unsigned_abs()) > MAX_EXACT_DIGITS { return Err(too_complex()); }
+
+    let (mut result, mut square, mut remaining) = (BigDecimal::one(), base.clone(), n.unsigned_abs());
+    while remaining > 0 {
+        if remaining & 1 == 1 { result = &result * &square; }
+        remaining >>= 1;
+        if remaining > 0 { square = square.square(); }
+    }
+
+    Ok(if n < 0 { BigDecimal::one() / result } else { result })
+}
+
+/// Calcola esattamente `n!` per un intero non negativo.
+///
+/// # Ritorna
+/// - `Err(MathError::InvalidFactorial)` se `n` è negativo o non intero.
+/// - `Err(MathError::ExpressionTooComplex)` se il risultato supererebbe `MAX_EXACT_DIGITS` cifre.
+fn factorial(n: &BigDecimal) -> Result<BigDecimal, CalcError> {
+    let invalid = || CalcError::from(MathError::InvalidFactorial { value: n.to_f64().unwrap_or(f64::NAN) });
+    if !n.is_integer() { return Err(invalid()); }
+    let n = n.to_u64().ok_or_else(invalid)?;
+
+    let mut result = BigDecimal::one();
+    for k in 2..=n {
+        result *= BigDecimal::from(k);
+        if result.digits() > MAX_EXACT_DIGITS { return Err(MathError::ExpressionTooComplex.into()); }
+    }
+    Ok(result)
+}
+
+/// Modulo di test per la valutazione in precisione arbitraria.
+#[cfg(test)]
+mod tests {
+    use super::*; // Importa tutti i membri del modulo superiore (il codice da testare)
+
+    /// Converte un letterale decimale in `BigDecimal`.
+    fn big(digits: &str) -> BigDecimal {
+        BigDecimal::from_str(digits).unwrap()
+    }
+
+    /// Verifica l'esattezza delle operazioni decimali, dove `f64` introduce errori di arrotondamento.
+    #[test]
+    fn test_exact_decimals() {
+        assert_ne!(0.1 + 0.2, 0.3);
+        assert_eq!(evaluate_exact("0.1 + 0.2 ="), Ok(big("0.3")));
+        assert_eq!(evaluate_exact("0.1 * 3 - 0.3"), Ok(big("0")));
+        assert_eq!(evaluate_exact("0.1 + 0.2 == 0.3"), Ok(big("1")));
+        assert_eq!(evaluate_exact("1 / 8"), Ok(big("0.125")));
+        assert_eq!(evaluate_exact("2^-3"), Ok(big("0.125")));
+        assert_eq!(evaluate_exact("1.1^2"), Ok(big("1.21")));
+
+        // Le variabili dell'ambiente seguono la stessa conversione dei letterali
+        let env = Env::from_iter([("x".to_string(), 0.1)]);
+        assert_eq!(compile("x * 3").unwrap().expr().eval_exact(&env), Ok(big("0.3")));
+    }
+
+    /// Verifica fattoriali e potenze intere oltre la precisione di `f64`.
+    #[test]
+    fn test_exact_large_integers() {
+        let factorial_30 = evaluate_exact("30! =").unwrap();
+        assert_eq!(factorial_30.to_string(), "265252859812191058636308480000000");
+        assert_ne!(factorial_30, decimal(compile("30!").unwrap().eval(&Env::new()).unwrap()).unwrap()); // in f64 le ultime cifre sono perse
+
+        assert_eq!(evaluate_exact("2^100").unwrap().to_string(), "1267650600228229401496703205376");
+        assert_eq!(evaluate_exact("2^100 + 1 - 2^100"), Ok(big("1")));
+        assert_eq!(evaluate_exact("(-3)^3"), Ok(big("-27")));
+    }
+
+    /// Verifica le operazioni approssimate in `f64` e gli errori.
+    #[test]
+    fn test_exact_fallbacks_and_errors() {
+        assert_eq!(evaluate_exact("sqrt(16) + 27 $ 3"), Ok(big("7")));
+        assert_eq!(evaluate_exact("max([0.1 + 0.2, 0.25])"), Ok(big("0.3")));
+        assert_eq!(evaluate_exact("4 ^ 0.5"), Ok(big("2")));
+
+        assert_eq!(evaluate_exact("1 / (0.1 - 0.1)"), Err(MathError::DivisionByZero.into()));
+        assert_eq!(evaluate_exact("2.5!"), Err(MathError::InvalidFactorial { value: 2.5 }.into()));
+        assert_eq!(evaluate_exact("(-1)!"), Err(MathError::InvalidFactorial { value: -1.0 }.into()));
+        assert_eq!(evaluate_exact("2^1000000"), Err(MathError::ExpressionTooComplex.into()));
+        assert_eq!(evaluate_exact("x + 1"), Err(TokenError::UnknownVariable { name: "x".into(), position: None }.into()));
+        assert!(matches!(evaluate_exact("2 +"), Err(CalcError::Token(_))));
+    }
+}
@@ -0,0 +1,463 @@
+use alloc::collections::BTreeSet;
+
+use crate::builtins::{constant, Function};
+use crate::error::CalcResult;
+use crate::evaluator::Evaluator;
+use crate::prelude::*;
+use crate::token::Token;
+
+/// Operatori binari dell'albero sintattico.
+///
+/// Derive:
+/// - `Debug`, `Clone`, `Copy`, `PartialEq`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOp {
+    /// Somma (`+`).
+    Add,
+
+    /// Sottrazione (`-`).
+    Sub,
+
+    /// Moltiplicazione, esplicita (`*`) o implicita (`2x`).
+    Mul,
+
+    /// Divisione (`/`).
+    Div,
+
+    /// Potenza (`^`).
+    Pow,
+
+    /// Radice ennesima (`$`): `a $ n` è la radice di indice `n` di `a`.
+    Root,
+
+    /// Confronto minore (`<`): vale `1` se vero, `0` se falso, come gli altri confronti.
+    Less,
+
+    /// Confronto minore o uguale (`<=`).
+    LessEqual,
+
+    /// Confronto maggiore (`>`).
+    Greater,
+
+    /// Confronto maggiore o uguale (`>=`).
+    GreaterEqual,
+
+    /// Uguaglianza esatta (`==`): `0.1 + 0.2 == 0.3` è falso in `f64`.
+    Equal,
+}
+
+impl BinaryOp {
+    /// Restituisce l'operatore di confronto corrispondente a un token, se lo è.
+    pub(crate) fn comparison(token: &Token) -> Option<Self> {
+        match token {
+            Token::Less => Some(BinaryOp::Less),
+            Token::LessEqual => Some(BinaryOp::LessEqual),
+            Token::Greater => Some(BinaryOp::Greater),
+            Token::GreaterEqual => Some(BinaryOp::GreaterEqual),
+            Token::EqualEqual => Some(BinaryOp::Equal),
+            _ => None,
+        }
+    }
+
+    /// Restituisce il simbolo dell'operatore nel sorgente (es. `"^"`).
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Pow => "^",
+            BinaryOp::Root => "$",
+            BinaryOp::Less => "<",
+            BinaryOp::LessEqual => "<=",
+            BinaryOp::Greater => ">",
+            BinaryOp::GreaterEqual => ">=",
+            BinaryOp::Equal => "==",
+        }
+    }
+
+    /// Confronta due valori secondo l'operatore, che deve essere un confronto.
+    pub(crate) fn compare<T: PartialOrd>(&self, lhs: T, rhs: T) -> bool {
+        match self {
+            BinaryOp::Less => lhs < rhs,
+            BinaryOp::LessEqual => lhs <= rhs,
+            BinaryOp::Greater => lhs > rhs,
+            BinaryOp::GreaterEqual => lhs >= rhs,
+            BinaryOp::Equal => lhs == rhs,
+            _ => unreachable!("{:?} non è un operatore di confronto", self),
+        }
+    }
+}
+
+/// Operatori unari dell'albero sintattico.
+///
+/// Derive:
+/// - `Debug`, `Clone`, `Copy`, `PartialEq`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnaryOp {
+    /// Negazione (`-x`).
+    Neg,
+
+    /// Fattoriale postfisso (`n!`), definito per interi non negativi.
+    Factorial,
+}
+
+/// Ambiente delle variabili: associa a ogni nome il suo valore.
+///
+/// Con la feature `std` (predefinita) è una `HashMap`; senza, una `BTreeMap` di `alloc`.
+#[cfg(feature = "std")]
+pub type Env = std::collections::HashMap<String, f64>;
+
+/// Ambiente delle variabili: associa a ogni nome il suo valore.
+///
+/// Con la feature `std` (predefinita) è una `HashMap`; senza, una `BTreeMap` di `alloc`.
+#[cfg(not(feature = "std"))]
+pub type Env = alloc::collections::BTreeMap<String, f64>;
+
+/// Albero sintattico di un'espressione, prodotto da `MathExpressionParser`.
+///
+/// L'albero rispetta già precedenza e associatività degli operatori: la valutazione
+/// non deve più consultare i token, e può essere ripetuta con ambienti diversi.
+///
+/// Derive:
+/// - `Debug`, `Clone`, `PartialEq`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    /// Numero letterale.
+    Number(f64),
+
+    /// Riferimento a una variabile, risolto nell'ambiente al momento della valutazione
+    /// (o tra le costanti predefinite, come `pi`).
+    Variable(String),
+
+    /// Operatore unario applicato a un operando.
+    Unary(UnaryOp, Box<Expr>),
+
+    /// Operatore binario applicato agli operandi sinistro e destro.
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+
+    /// Chiamata di una funzione predefinita con i suoi argomenti.
+    Call(Function, Vec<Expr>),
+
+    /// Chiamata di una funzione definita dall'utente (`define f(x) = ...`), risolta nella
+    /// tabella delle funzioni al momento della valutazione.
+    UserCall(String, Vec<Expr>),
+}
+
+impl Expr {
+    /// Valuta l'albero sintattico risolvendo le variabili in `env`, con gli angoli in radianti.
+    ///
+    /// # Ritorna
+    /// - `Ok(f64)` con il valore dell'espressione.
+    /// - `Err(CalcError)` per errori matematici o variabili non definite in `env`.
+    ///
+    /// # Esempio
+    /// ```
+    /// let expr = Expr::Binary(BinaryOp::Mul, Box::new(Expr::Number(2.0)), Box::new(Expr::Variable("x".into())));
+    /// let env = Env::from([("x".to_string(), 3.0)]);
+    /// assert_eq!(expr.eval(&env), Ok(6.0));
+    /// ```
+    pub fn eval(&self, env: &Env) -> CalcResult {
+        Evaluator::new(env).eval(self)
+    }
+
+    /// Valuta l'albero sintattico risolvendo variabili e funzioni sconosciute con due callback,
+    /// es. per leggere i valori da una sorgente di dati esterna invece che da un `Env`.
+    ///
+    /// Le costanti predefinite (`pi`, `e`, ...) e le funzioni predefinite (`sin`, `max`, ...) vengono
+    /// consultate per prime: `var` riceve solo le variabili rimanenti, `func` le chiamate `Expr::UserCall`
+    /// con gli argomenti già valutati. Gli angoli sono in radianti.
+    ///
+    /// Per default il parser riconosce come chiamate solo le funzioni definite con `define`: gli alberi con
+    /// chiamate esterne si analizzano con `compile_with_calls` oppure si costruiscono con `Expr::call`.
+    ///
+    /// # Ritorna
+    /// - `Ok(f64)` con il valore dell'espressione.
+    /// - `Err(CalcError)` per gli errori matematici e per quelli restituiti da `func`.
+    /// - `Err(TokenError::UnknownVariable)` se `var` restituisce `None`, `Err(TokenError::SyntaxError)`
+    ///   se `func` restituisce `None`.
+    ///
+    /// # Esempio
+    /// ```
+    /// let x = 3.0;
+    /// let expr = Expr::call("double", vec![Expr::var("x")]) + Expr::var("pi");
+    /// let value = expr.eval_with_callbacks(
+    ///     |name| (name == "x").then_some(x),
+    ///     |name, args| (name == "double").then(|| Ok(args[0] * 2.0)),
+    /// );
+    /// assert_eq!(value, Ok(6.0 + core::f64::consts::PI));
+    /// ```
+    pub fn eval_with_callbacks(&self, var: impl Fn(&str) -> Option<f64>, func: impl Fn(&str, &[f64]) -> Option<CalcResult>) -> CalcResult {
+        let env = Env::new();
+        Evaluator::new(&env).with_callbacks(&var, &func).eval(self)
+    }
+
+    /// Costruisce il numero letterale `value`, per comporre alberi con gli operatori (`Expr::num(2.0) + Expr::num(3.0)`).
+    pub fn num(value: f64) -> Expr {
+        Expr::Number(value)
+    }
+
+    /// Costruisce il riferimento alla variabile (o costante predefinita) `name`.
+    pub fn var(name: impl Into<String>) -> Expr {
+        Expr::Variable(name.into())
+    }
+
+    /// Costruisce la chiamata della funzione `name`: `Expr::Call` per le funzioni predefinite,
+    /// altrimenti `Expr::UserCall`, risolta durante la valutazione.
+    pub fn call(name: impl Into<String>, args: Vec<Expr>) -> Expr {
+        let name = name.into();
+        match Function::from_name(&name) {
+            Some(function) => Expr::Call(function, args),
+            None => Expr::UserCall(name, args),
+        }
+    }
+
+    /// Restituisce l'albero del reciproco `1 / self`, senza analizzare di nuovo il sorgente
+    /// (es. il tasto "1/x" di una calcolatrice).
+    ///
+    /// La valutazione coincide con quella di `1 / (...)` scritto come stringa: il reciproco
+    /// di un'espressione nulla produce `DivisionByZero`.
+    ///
+    /// # Esempio
+    /// ```
+    /// let f = compile("2 + 2")?;
+    /// assert_eq!(f.expr().clone().reciprocal().eval(&Env::new()), Ok(0.25));
+    /// ```
+    pub fn reciprocal(self) -> Expr {
+        Expr::Binary(BinaryOp::Div, Box::new(Expr::Number(1.0)), Box::new(self))
+    }
+
+    /// Restituisce l'albero dell'opposto `-self`, senza analizzare di nuovo il sorgente
+    /// (es. il tasto "+/-" di una calcolatrice).
+    ///
+    /// # Esempio
+    /// ```
+    /// let f = compile("2 * 3")?;
+    /// assert_eq!(f.expr().clone().negate().eval(&Env::new()), Ok(-6.0));
+    /// ```
+    pub fn negate(self) -> Expr {
+        Expr::Unary(UnaryOp::Neg, Box::new(self))
+    }
+
+    /// Restituisce in ordine alfabetico le variabili libere dell'espressione, cioè i nomi da
+    /// fornire nell'ambiente per poterla valutare.
+    ///
+    /// Le costanti predefinite (`pi`, `e`) e i nomi delle funzioni non sono variabili.
+    ///
+    /// # Esempio
+    /// ```
+    /// let f = compile("a*b + sin(c)")?;
+    /// assert_eq!(f.expr().variables(), BTreeSet::from(["a".to_string(), "b".to_string(), "c".to_string()]));
+    /// ```
+    pub fn variables(&self) -> BTreeSet<String> {
+        let mut names = BTreeSet::new();
+        self.collect_variables(&mut names);
+        names
+    }
+
+    /// Aggiunge a `names` le variabili libere del sottoalbero.
+    fn collect_variables(&self, names: &mut BTreeSet<String>) {
+        match self {
+            Expr::Number(_) => {}
+            Expr::Variable(name) => {
+                if constant(name).is_none() && Function::from_name(name).is_none() { names.insert(name.clone()); }
+            }
+            Expr::Unary(_, operand) => operand.collect_variables(names),
+            Expr::Binary(_, lhs, rhs) => {
+                lhs.collect_variables(names);
+                rhs.collect_variables(names);
+            }
+            Expr::Call(_, args) | Expr::UserCall(_, args) => {
+                for arg in args { arg.collect_variables(names); }
+            }
+        }
+    }
+}
+
+/// Costruzione di alberi con gli operatori aritmetici: `a + b` produce `Expr::Binary(BinaryOp::Add, a, b)`.
+///
+/// L'albero segue la precedenza di Rust, che per `+ - * /` e la negazione coincide con quella del
+/// parser: `Expr::num(2.0) + Expr::num(3.0) * Expr::var("x")` equivale a `2 + 3 * x`.
+///
+/// # Esempio
+/// ```
+/// let expr = -Expr::var("x") + Expr::num(2.0) * Expr::var("x");
+/// assert_eq!(expr.eval(&Env::from([("x".to_string(), 4.0)])), Ok(4.0));
+/// ```
+impl core::ops::Add for Expr {
+    type Output = Expr;
+
+    fn add(self, rhs: Expr) -> Expr {
+        Expr::Binary(BinaryOp::Add, Box::new(self), Box::new(rhs))
+    }
+}
+
+impl core::ops::Sub for Expr {
+    type Output = Expr;
+
+    fn sub(self, rhs: Expr) -> Expr {
+        Expr::Binary(BinaryOp::Sub, Box::new(self), Box::new(rhs))
+    }
+}
+
+impl core::ops::Mul for Expr {
+    type Output = Expr;
+
+    fn mul(self, rhs: Expr) -> Expr {
+        Expr::Binary(BinaryOp::Mul, Box::new(self), Box::new(rhs))
+    }
+}
+
+impl core::ops::Div for Expr {
+    type Output = Expr;
+
+    fn div(self, rhs: Expr) -> Expr {
+        Expr::Binary(BinaryOp::Div, Box::new(self), Box::new(rhs))
+    }
+}
+
+impl core::ops::Neg for Expr {
+    type Output = Expr;
+
+    fn neg(self) -> Expr {
+        self.negate()
+    }
+}
+
+/// Valuta un'espressione già analizzata per ogni valore della variabile `var`.
+///
+/// È la primitiva per tracciare il grafico di `f(x)`: l'albero viene analizzato una sola volta
+/// e visitato per ciascun valore, riusando lo stesso ambiente.
+///
+/// # Ritorna
+/// Un risultato per ogni elemento di `values`, nello stesso ordine: un errore in un punto
+/// (es. divisione per zero) non interrompe la valutazione degli altri.
+///
+/// # Esempio
+/// ```
+/// let f = compile("x^2")?;
+/// assert_eq!(evaluate_over(f.expr(), "x", &[0.0, 1.0, 2.0]), vec![Ok(0.0), Ok(1.0), Ok(4.0)]);
+/// ```
+pub fn evaluate_over(expr: &Expr, var: &str, values: &[f64]) -> Vec<CalcResult> {
+    let mut env = Env::from([(var.to_string(), 0.0)]);
+
+    values
+        .iter()
+        .map(|&value| {
+            env.insert(var.to_string(), value);
+            expr.eval(&env)
+        })
+        .collect()
+}
+
+/// Modulo di test per la valutazione degli alberi sintattici.
+#[cfg(test)]
+mod tests {
+    use super::*; // Importa tutti i membri del modulo superiore (il codice da testare)
+    use crate::compile::{compile, compile_with_calls};
+    use crate::error::{CalcError, MathError, TokenError};
+
+    /// Verifica la valutazione di `x^2` su più valori di `x`.
+    #[test]
+    fn test_evaluate_over_values() {
+        let square = compile("x^2").unwrap();
+        assert_eq!(
+            evaluate_over(square.expr(), "x", &[0.0, 1.0, 2.0, 3.0]),
+            vec![Ok(0.0), Ok(1.0), Ok(4.0), Ok(9.0)]
+        );
+    }
+
+    /// Verifica l'elenco delle variabili libere, escluse costanti e funzioni.
+    #[test]
+    fn test_variables() {
+        let names = |input: &str| compile(input).unwrap().expr().variables().into_iter().collect::<Vec<_>>();
+
+        assert_eq!(names("a*b + sin(c)"), vec!["a", "b", "c"]);
+        assert_eq!(names("2 pi r + e^x + x"), vec!["r", "x"]);
+        assert!(names("sqrt(16) + 1").is_empty());
+    }
+
+    /// Verifica che `negate` e `reciprocal` valutino come le stringhe equivalenti.
+    #[test]
+    fn test_negate_and_reciprocal() {
+        let env = Env::from([("x".to_string(), 4.0)]);
+        for input in ["x", "2^x - 1", "-x / 8", "sqrt(x) * 3"] {
+            let expr = compile(input).unwrap().expr().clone();
+            let value = expr.eval(&env).unwrap();
+
+            assert_eq!(expr.clone().negate().eval(&env), Ok(-value));
+            assert_eq!(expr.clone().negate().eval(&env), compile(&format!("-({})", input)).unwrap().eval(&env));
+            assert_eq!(expr.clone().reciprocal().eval(&env), compile(&format!("1 / ({})", input)).unwrap().eval(&env));
+        }
+
+        let zero = compile("x - x").unwrap().expr().clone();
+        assert_eq!(zero.reciprocal().eval(&env), Err(MathError::DivisionByZero.into()));
+    }
+
+    /// Verifica che gli alberi costruiti con gli operatori coincidano con quelli analizzati dal sorgente.
+    #[test]
+    fn test_operator_builder() {
+        let env = Env::from([("x".to_string(), 3.0)]);
+        let (two, x) = (Expr::num(2.0), Expr::var("x"));
+
+        let cases = [
+            (Expr::num(2.0) + Expr::num(3.0), "2 + 3"),
+            (two.clone() + Expr::num(3.0) * x.clone(), "2 + 3 * x"),
+            ((two.clone() + x.clone()) / (x.clone() - Expr::num(1.0)), "(2 + x) / (x - 1)"),
+            (-x.clone() * two.clone(), "-x * 2"),
+            (x.clone() - -two, "x - -2"),
+        ];
+        for (built, source) in cases {
+            let parsed = compile(source).unwrap();
+            assert_eq!(&built, parsed.expr());
+            assert_eq!(built.eval(&env), parsed.eval(&env));
+        }
+
+        assert_eq!((Expr::num(1.0) / (x.clone() - x)).eval(&env), Err(MathError::DivisionByZero.into()));
+    }
+
+    /// Verifica la risoluzione di variabili e funzioni tramite callback, dopo quelle predefinite.
+    #[test]
+    fn test_eval_with_callbacks() {
+        let x = 4.0;
+        let variables = |name: &str| (name == "x").then_some(x);
+        let functions = |name: &str, args: &[f64]| match (name, args) {
+            ("double", [value]) => Some(Ok(value * 2.0)),
+            ("double", _) => Some(Err(TokenError::SyntaxError("double richiede un argomento".into()).into())),
+            _ => None,
+        };
+        let eval = |expr: &Expr| expr.eval_with_callbacks(variables, functions);
+
+        assert_eq!(eval(&compile("x^2 + 1").unwrap().expr().clone()), Ok(17.0));
+        assert_eq!(eval(&Expr::call("double", vec![Expr::var("x") + Expr::num(1.0)])), Ok(10.0));
+        assert_eq!(eval(&Expr::call("sqrt", vec![Expr::var("x")])), Ok(2.0));
+        assert_eq!(eval(&(Expr::var("pi") - Expr::var("pi"))), Ok(0.0));
+
+        // Le costanti predefinite hanno la precedenza sul callback
+        assert_eq!(Expr::var("e").eval_with_callbacks(|_| Some(0.0), functions), Ok(core::f64::consts::E));
+
+        assert!(matches!(eval(&Expr::var("y")), Err(CalcError::Token(TokenError::UnknownVariable { .. }))));
+        assert!(matches!(eval(&Expr::call("double", vec![])), Err(CalcError::Token(TokenError::SyntaxError(_)))));
+        assert!(matches!(eval(&Expr::call("triple", vec![Expr::num(1.0)])), Err(CalcError::Token(TokenError::SyntaxError(_)))));
+        assert_eq!(eval(&(Expr::num(1.0) / (Expr::var("x") - Expr::num(4.0)))), Err(MathError::DivisionByZero.into()));
+
+        // Dal sorgente: con `compile_with_calls` la chiamata sconosciuta arriva alla callback `func`
+        let parsed = compile_with_calls("double(x + 1) * sqrt(x) - double(2)").unwrap();
+        assert_eq!(eval(parsed.expr()), Ok(16.0));
+        assert_eq!(compile_with_calls("double(x, 1)").unwrap().expr(), &Expr::call("double", vec![Expr::var("x"), Expr::num(1.0)]));
+        assert!(matches!(eval(compile_with_calls("double(x, 1)").unwrap().expr()), Err(CalcError::Token(TokenError::SyntaxError(_)))));
+        assert!(matches!(eval(compile_with_calls("triple(x)").unwrap().expr()), Err(CalcError::Token(TokenError::SyntaxError(_)))));
+        // Senza l'opzione resta una moltiplicazione implicita
+        assert_eq!(compile("double(x)").unwrap().expr(), &(Expr::var("double") * Expr::var("x")));
+    }
+
+    /// Verifica che un errore in un punto non interrompa gli altri.
+    #[test]
+    fn test_evaluate_over_keeps_going_after_error() {
+        let reciprocal = compile("1 / x").unwrap();
+        assert_eq!(
+            evaluate_over(reciprocal.expr(), "x", &[2.0, 0.0, 4.0]),
+            vec![Ok(0.5), Err(MathError::DivisionByZero.into()), Ok(0.25)]
+        );
+    }
+}
@@ -0,0 +1,320 @@
+use core::ops::Range;
+
+use crate::prelude::*;
+
+/// Intervallo predefinito dei valori assoluti mostrati in notazione decimale da `format_auto`.
+pub const DEFAULT_FIXED_RANGE: Range<f64> = 1e-4..1e9;
+
+/// Precisione predefinita di `format_auto`: decimali in notazione decimale, cifre significative in quella scientifica.
+pub const DEFAULT_PRECISION: usize = 3;
+
+/// Massimo valore assoluto sotto il quale ogni intero è rappresentato esattamente in `f64` (2^53).
+pub(crate) const MAX_EXACT_INTEGER: f64 = 9_007_199_254_740_992.0;
+
+/// Stile dei risultati in notazione decimale, da usare con `FormatMode::format`.
+///
+/// Derive:
+/// - `Debug`, `Clone`, `Copy`, `PartialEq`, `Default`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum FormatMode {
+    /// Numero fisso di decimali, anche per gli interi (`4.000`), come `format_auto`.
+    #[default]
+    FixedDecimals,
+
+    /// Senza zeri finali: gli interi sono stampati senza punto decimale (`4`), gli altri valori
+    /// perdono solo gli zeri in coda (`2.500` → `2.5`, `0.333` resta invariato).
+    TrimTrailingZeros,
+}
+
+impl FormatMode {
+    /// Formatta un risultato come `format_auto_with_precision`, applicando lo stile scelto alla notazione decimale.
+    ///
+    /// La notazione scientifica non cambia: `6.93e+11` resta tale in entrambi gli stili.
+    ///
+    /// # Esempio
+    /// ```
+    /// let trim = FormatMode::TrimTrailingZeros;
+    /// assert_eq!(trim.format(4.0, &DEFAULT_FIXED_RANGE, 3), "4");
+    /// assert_eq!(trim.format(1.0 / 3.0, &DEFAULT_FIXED_RANGE, 3), "0.333");
+    /// ```
+    pub fn format(&self, value: f64, fixed_range: &Range<f64>, precision: usize) -> String {
+        let formatted = format_auto_with_precision(value, fixed_range, precision);
+        if *self == FormatMode::FixedDecimals || formatted.contains('e') || !value.is_finite() {
+            return formatted;
+        }
+
+        match format_integral(value) {
+            Some(integral) => integral,
+            // Un valore che si arrotonda a un intero (es. 2.0004 con 3 decimali) perde anche il punto
+            None if formatted.contains('.') => formatted.trim_end_matches('0').trim_end_matches('.').to_string(),
+            None => formatted,
+        }
+    }
+}
+
+/// Formatta `value` senza punto decimale se è un intero rappresentato esattamente (es. `4.0` → `4`).
+///
+/// # Ritorna
+/// - `Some(String)` per i valori interi con valore assoluto fino a 2^53; lo zero negativo diventa `0`.
+/// - `None` per i valori con parte frazionaria, i valori non finiti e gli interi oltre 2^53,
+///   per i quali le cifre meno significative non sono esatte.
+pub fn format_integral(value: f64) -> Option<String> {
+    if !value.is_finite() || value.fract() != 0.0 || value.abs() > MAX_EXACT_INTEGER {
+        return None;
+    }
+    Some(format!("{}", value as i64))
+}
+
+/// Formatta `value` in notazione scientifica con `sig_figs` cifre significative (es. `6.93e+11`).
+///
+/// L'esponente ha sempre il segno e almeno due cifre (`e+09`, `e-07`), così che valori
+/// dello stesso ordine di grandezza risultino allineati. Con `sig_figs` pari a zero si usa una cifra.
+/// I valori non finiti (`NaN`, `±inf`) vengono stampati come da `Display`.
+///
+/// # Esempio
+/// ```
+/// assert_eq!(format_scientific(1234567890.0, 3), "1.23e+09");
+/// assert_eq!(format_scientific(0.0000001234, 3), "1.23e-07");
+/// ```
+pub fn format_scientific(value: f64, sig_figs: usize) -> String {
+    if !value.is_finite() {
+        return value.to_string();
+    }
+
+    // `{:e}` produce es. "1.23e9": si riformatta l'esponente con segno e due cifre
+    let formatted = format!("{:.*e}", sig_figs.saturating_sub(1), value);
+    let (mantissa, exponent) = formatted.split_once('e').expect("`{:e}` contiene sempre 'e'");
+    let exponent: i32 = exponent.parse().expect("esponente intero");
+
+    format!("{}e{:+03}", mantissa, exponent)
+}
+
+/// Formatta `value` in notazione ingegneristica con `sig_figs` cifre significative (es. `12.3e+03`).
+///
+/// Come `format_scientific`, ma l'esponente è sempre un multiplo di 3 e la mantissa è in `[1, 1000)`,
+/// così che il valore corrisponda direttamente ai prefissi SI (kilo, mega, milli, ...).
+///
+/// # Esempio
+/// ```
+/// assert_eq!(format_engineering(12345.0, 3), "12.3e+03");
+/// assert_eq!(format_engineering(0.00012345, 3), "123e-06");
+/// ```
+pub fn format_engineering(value: f64, sig_figs: usize) -> String {
+    if !value.is_finite() {
+        return value.to_string();
+    }
+
+    // L'arrotondamento avviene in notazione scientifica, così che es. 999.96 diventi 1.00e+03
+    let sig_figs = sig_figs.max(1);
+    let formatted = format!("{:.*e}", sig_figs - 1, value);
+    let (mantissa, exponent) = formatted.split_once('e').expect("`{:e}` contiene sempre 'e'");
+    let (mantissa, exponent): (f64, i32) = (mantissa.parse().expect("mantissa decimale"), exponent.parse().expect("esponente intero"));
+
+    // Si sposta il punto decimale di 0, 1 o 2 posizioni fino a un esponente multiplo di 3
+    let shift = exponent.rem_euclid(3);
+    let decimals = (sig_figs - 1).saturating_sub(shift as usize);
+    format!("{:.*}e{:+03}", decimals, mantissa * 10f64.powi(shift), exponent - shift)
+}
+
+/// Notazione dei risultati, da usare con `Notation::format`.
+///
+/// Derive:
+/// - `Debug`, `Clone`, `Copy`, `PartialEq`, `Default`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Notation {
+    /// Decimale o scientifica in base all'ordine di grandezza, come `format_auto_with_precision`.
+    #[default]
+    Auto,
+
+    /// Sempre decimale, con `precision` decimali (`1234567890.000`).
+    Decimal,
+
+    /// Sempre scientifica, con `precision` cifre significative (`1.23e+03`).
+    Scientific,
+
+    /// Sempre ingegneristica, con `precision` cifre significative (`1.23e+03`, `12.3e+03`).
+    Engineering,
+}
+
+impl Notation {
+    /// Formatta un risultato nella notazione scelta; `fixed_range` è usato solo da `Notation::Auto`.
+    ///
+    /// # Esempio
+    /// ```
+    /// assert_eq!(Notation::Scientific.format(1234.5, &DEFAULT_FIXED_RANGE, 3), "1.23e+03");
+    /// assert_eq!(Notation::Decimal.format(693e9, &DEFAULT_FIXED_RANGE, 1), "693000000000.0");
+    /// ```
+    pub fn format(&self, value: f64, fixed_range: &Range<f64>, precision: usize) -> String {
+        match self {
+            Notation::Auto => format_auto_with_precision(value, fixed_range, precision),
+            Notation::Decimal => format!("{:.*}", precision, value),
+            Notation::Scientific => format_scientific(value, precision),
+            Notation::Engineering => format_engineering(value, precision),
+        }
+    }
+}
+
+/// Formatta un risultato scegliendo la notazione in base al suo ordine di grandezza.
+///
+/// - Se `|value|` è in `fixed_range` (o è zero, o non è finito): notazione decimale con 3 decimali (`5.000`).
+/// - Altrimenti: notazione scientifica con 3 cifre significative (`6.93e+11`), dato che
+///   `1e12` o `1e-7` a 3 decimali sarebbero illeggibili o ridotti a `0.000`.
+///
+/// # Esempio
+/// ```
+/// assert_eq!(format_auto(5.0, &DEFAULT_FIXED_RANGE), "5.000");
+/// assert_eq!(format_auto(693e9, &DEFAULT_FIXED_RANGE), "6.93e+11");
+/// ```
+pub fn format_auto(value: f64, fixed_range: &Range<f64>) -> String {
+    format_auto_with_precision(value, fixed_range, DEFAULT_PRECISION)
+}
+
+/// Formatta un risultato come `format_auto`, con `precision` decimali in notazione decimale
+/// e `precision` cifre significative in quella scientifica (almeno una).
+///
+/// # Esempio
+/// ```
+/// assert_eq!(format_auto_with_precision(1.0 / 3.0, &DEFAULT_FIXED_RANGE, 6), "0.333333");
+/// assert_eq!(format_auto_with_precision(693e9, &DEFAULT_FIXED_RANGE, 5), "6.9300e+11");
+/// ```
+pub fn format_auto_with_precision(value: f64, fixed_range: &Range<f64>, precision: usize) -> String {
+    if value == 0.0 || !value.is_finite() || fixed_range.contains(&value.abs()) {
+        format!("{:.*}", precision, value)
+    } else {
+        format_scientific(value, precision)
+    }
+}
+
+/// Arrotonda `value` al numero "gradevole" più vicino della forma `m × 10^n`, con `m ∈ {1, 2, 5}`.
+///
+/// Utile per le etichette degli assi nei grafici (es. 4.3 → 5, 0.023 → 0.02).
+///
+/// # Algoritmo
+/// 1. Si scompone `|value|` in `f × 10^n` con `f ∈ [1, 10)`, dove `n = floor(log10(|value|))`.
+/// 2. Si sceglie tra `1`, `2`, `5` e `10` il candidato più vicino a `f` in valore assoluto.
+///    A parità di distanza vince il candidato maggiore (es. 1.5 → 2, 3.5 → 5, 7.5 → 10).
+/// 3. Si ricompone il risultato con il segno originale.
+///
+/// Lo zero e i valori non finiti (`NaN`, `±inf`) vengono restituiti invariati.
+/// Valori come `0.025` non sono della forma richiesta: `0.023` viene quindi arrotondato a `0.02`.
+///
+/// # Esempio
+/// ```
+/// assert_eq!(nice_round(4.3), 5.0);
+/// assert_eq!(nice_round(-0.023), -0.02);
+/// ```
+pub fn nice_round(value: f64) -> f64 {
+    if value == 0.0 || !value.is_finite() {
+        return value;
+    }
+
+    let magnitude = value.abs();
+    let exponent = magnitude.log10().floor() as i32;
+    let scale = 10f64.powi(exponent.abs());
+
+    // Mantissa normalizzata in [1, 10)
+    let fraction = if exponent >= 0 { magnitude / scale } else { magnitude * scale };
+
+    // Candidato più vicino; scorrendo in ordine crescente, `<=` privilegia il maggiore a parità
+    let mut nice = 1.0;
+    for candidate in [2.0, 5.0, 10.0] {
+        if (candidate - fraction).abs() <= (nice - fraction).abs() {
+            nice = candidate;
+        }
+    }
+
+    // Per esponenti negativi si divide per una potenza esatta, evitando errori come 5 * 0.01 != 0.05
+    let rounded = if exponent >= 0 { nice * scale } else { nice / scale };
+    rounded.copysign(value)
+}
+
+/// Modulo di test per le funzioni di formattazione e arrotondamento.
+#[cfg(test)]
+mod tests {
+    use super::*; // Importa tutti i membri del modulo superiore (il codice da testare)
+
+    /// Verifica l'arrotondamento ai valori 1/2/5 × 10^n.
+    #[test]
+    fn test_nice_round() {
+        assert_eq!(nice_round(4.3), 5.0);
+        assert_eq!(nice_round(0.023), 0.02);
+        assert_eq!(nice_round(0.0047), 0.005);
+        assert_eq!(nice_round(1.0), 1.0);
+        assert_eq!(nice_round(7.6), 10.0);
+        assert_eq!(nice_round(150.0), 200.0); // parità tra 100 e 200: vince il maggiore
+        assert_eq!(nice_round(-4.3), -5.0);
+        assert_eq!(nice_round(0.0), 0.0);
+        assert!(nice_round(f64::NAN).is_nan());
+    }
+
+    /// Verifica la notazione scientifica di valori molto grandi e molto piccoli.
+    #[test]
+    fn test_format_scientific() {
+        assert_eq!(format_scientific(1234567890.0, 3), "1.23e+09");
+        assert_eq!(format_scientific(0.0000001234, 3), "1.23e-07");
+        assert_eq!(format_scientific(-1234567890.0, 5), "-1.2346e+09");
+        assert_eq!(format_scientific(1234567890.0, 0), "1e+09");
+        assert_eq!(format_scientific(f64::INFINITY, 3), "inf");
+    }
+
+    /// Verifica la notazione ingegneristica, con esponenti multipli di 3 e arrotondamento della mantissa.
+    #[test]
+    fn test_format_engineering() {
+        assert_eq!(format_engineering(12345.0, 3), "12.3e+03");
+        assert_eq!(format_engineering(1234.0, 3), "1.23e+03");
+        assert_eq!(format_engineering(123456.0, 3), "123e+03");
+        assert_eq!(format_engineering(0.00012345, 3), "123e-06");
+        assert_eq!(format_engineering(-0.5, 2), "-500e-03");
+        assert_eq!(format_engineering(999.96, 4), "1.000e+03");
+        assert_eq!(format_engineering(0.0, 3), "0.00e+00");
+        assert_eq!(format_engineering(12345.0, 0), "10e+03");
+        assert_eq!(format_engineering(f64::NEG_INFINITY, 3), "-inf");
+
+        assert_eq!(Notation::Decimal.format(1234567890.0, &DEFAULT_FIXED_RANGE, 2), "1234567890.00");
+        assert_eq!(Notation::Scientific.format(1234.5, &DEFAULT_FIXED_RANGE, 3), "1.23e+03");
+        assert_eq!(Notation::Engineering.format(1234567890.0, &DEFAULT_FIXED_RANGE, 3), "1.23e+09");
+        assert_eq!(Notation::default().format(693e9, &DEFAULT_FIXED_RANGE, 3), "6.93e+11");
+    }
+
+    /// Verifica la scelta automatica della notazione in base all'ordine di grandezza.
+    #[test]
+    fn test_format_auto() {
+        assert_eq!(format_auto(1234.5, &DEFAULT_FIXED_RANGE), "1234.500");
+        assert_eq!(format_auto(0.0, &DEFAULT_FIXED_RANGE), "0.000");
+        assert_eq!(format_auto(1234567890.0, &DEFAULT_FIXED_RANGE), "1.23e+09");
+        assert_eq!(format_auto(0.0000001234, &DEFAULT_FIXED_RANGE), "1.23e-07");
+        assert_eq!(format_auto(1234567890.0, &(0.0..f64::INFINITY)), "1234567890.000");
+    }
+
+    /// Verifica lo stile senza zeri finali su interi, decimali periodici e notazione scientifica.
+    #[test]
+    fn test_trim_trailing_zeros() {
+        let value = |input: &str| crate::compile::compile(input).unwrap().eval(&Default::default()).unwrap();
+        let trim = |value: f64| FormatMode::TrimTrailingZeros.format(value, &DEFAULT_FIXED_RANGE, DEFAULT_PRECISION);
+
+        assert_eq!(trim(value("2+2")), "4");
+        assert_eq!(trim(value("1/3")), "0.333");
+        assert_eq!(trim(value("5/2")), "2.5");
+        assert_eq!(trim(value("-2*3")), "-6");
+        assert_eq!(trim(-0.0), "0");
+        assert_eq!(trim(2.0004), "2");
+        assert_eq!(trim(693e9), "6.93e+11");
+        assert_eq!(trim(f64::INFINITY), "inf");
+
+        // Lo stile predefinito resta quello di `format_auto`
+        assert_eq!(FormatMode::default().format(4.0, &DEFAULT_FIXED_RANGE, DEFAULT_PRECISION), "4.000");
+
+        assert_eq!(format_integral(42.0), Some("42".into()));
+        assert_eq!(format_integral(0.5), None);
+        assert_eq!(format_integral(1e300), None);
+    }
+
+    /// Verifica la precisione esplicita nelle due notazioni.
+    #[test]
+    fn test_format_auto_with_precision() {
+        assert_eq!(format_auto_with_precision(1.0 / 3.0, &DEFAULT_FIXED_RANGE, 6), "0.333333");
+        assert_eq!(format_auto_with_precision(2.5, &DEFAULT_FIXED_RANGE, 0), "2");
+        assert_eq!(format_auto_with_precision(1234567890.0, &DEFAULT_FIXED_RANGE, 5), "1.2346e+09");
+        assert_eq!(format_auto_with_precision(1234567890.0, &DEFAULT_FIXED_RANGE, 0), "1e+09");
+    }
+}
@@ -0,0 +1,87 @@
+use alloc::collections::BTreeMap;
+
+use crate::expr::Expr;
+use crate::prelude::*;
+
+/// Profondità massima delle chiamate annidate di funzioni definite dall'utente.
+///
+/// Limita la ricorsione (es. `define f(x) = f(x - 1)`): oltre questo limite la valutazione
+/// produce `MathError::ExpressionTooComplex` invece di esaurire lo stack.
+pub const MAX_CALL_DEPTH: usize = 64;
+
+/// Funzione definita dall'utente con `define f(x, y) = ...`.
+///
+/// Derive:
+/// - `Debug`, `Clone`, `PartialEq`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserFunction {
+    /// Nomi dei parametri, nell'ordine degli argomenti.
+    pub params: Vec<String>,
+    /// Corpo della funzione, valutato con i parametri legati agli argomenti della chiamata.
+    pub body: Expr,
+}
+
+/// Tabella delle funzioni definite dall'utente (nome → definizione).
+///
+/// Come l'ambiente delle variabili, è posseduta dal parser e può essere condivisa tra input
+/// valutati separatamente (`MathExpressionParser::set_function_table` / `take_function_table`).
+///
+/// Derive:
+/// - `Debug`, `Clone`, `PartialEq`, `Default`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FunctionTable {
+    /// Definizioni, ordinate per nome.
+    functions: BTreeMap<String, UserFunction>,
+}
+
+impl FunctionTable {
+    /// Costruisce una tabella vuota.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Definisce (o ridefinisce) la funzione `name`.
+    ///
+    /// # Ritorna
+    /// La definizione precedente con lo stesso nome, se presente.
+    ///
+    /// # Esempio
+    /// ```
+    /// let mut table = FunctionTable::new();
+    /// table.define("f", vec!["x".into()], Expr::var("x") * Expr::num(2.0));
+    /// assert!(table.get("f").is_some());
+    /// ```
+    pub fn define(&mut self, name: impl Into<String>, params: Vec<String>, body: Expr) -> Option<UserFunction> {
+        self.functions.insert(name.into(), UserFunction { params, body })
+    }
+
+    /// Restituisce la definizione della funzione `name`, se presente.
+    pub fn get(&self, name: &str) -> Option<&UserFunction> {
+        self.functions.get(name)
+    }
+
+    /// Verifica se la funzione `name` è definita.
+    pub fn contains(&self, name: &str) -> bool {
+        self.functions.contains_key(name)
+    }
+
+    /// Rimuove la funzione `name`, restituendone la definizione.
+    pub fn remove(&mut self, name: &str) -> Option<UserFunction> {
+        self.functions.remove(name)
+    }
+
+    /// Restituisce in ordine alfabetico i nomi delle funzioni definite.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.functions.keys().map(String::as_str)
+    }
+
+    /// Numero di funzioni definite.
+    pub fn len(&self) -> usize {
+        self.functions.len()
+    }
+
+    /// Verifica se la tabella è vuota.
+    pub fn is_empty(&self) -> bool {
+        self.functions.is_empty()
+    }
+}
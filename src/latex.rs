@@ -0,0 +1,249 @@
+use crate::builtins::Function;
+use crate::expr::{BinaryOp, Expr, UnaryOp};
+use crate::prelude::*;
+
+/// Livelli di precedenza usati per decidere dove servono le parentesi, dal più debole al più forte.
+const COMPARISON: u8 = 0;
+const SUM: u8 = 1;
+const PRODUCT: u8 = 2;
+const NEGATION: u8 = 3;
+const POWER: u8 = 4;
+const POSTFIX: u8 = 5;
+const ATOM: u8 = 6;
+
+impl Expr {
+    /// Restituisce la rappresentazione LaTeX dell'espressione, da inserire in un documento.
+    ///
+    /// # Regole
+    /// - La divisione diventa `\frac{a}{b}`, la potenza `a^{b}` e la radice `a $ n` diventa `\sqrt[n]{a}`
+    ///   (`\sqrt{a}` per l'indice 2, come `sqrt(a)`).
+    /// - Le parentesi `\left( \right)` vengono inserite solo dove la precedenza lo richiede:
+    ///   `(1+2)*3` → `\left(1 + 2\right) \cdot 3`.
+    /// - Le costanti `pi`, `tau` e `phi` diventano le lettere greche; `abs`, `floor` e `ceil` usano
+    ///   le rispettive delimitazioni, le altre funzioni il loro nome (`\sin`, `\operatorname{gcd}`).
+    /// - Le variabili di più lettere vanno in corsivo come un unico nome (`\mathit{totale}`).
+    ///
+    /// La conversione riguarda solo la visualizzazione: non valuta l'albero e non ne cambia il significato.
+    ///
+    /// # Esempio
+    /// ```
+    /// let f = compile("(x + 1) / 2 ^ 3")?;
+    /// assert_eq!(f.expr().to_latex(), "\\frac{x + 1}{2^{3}}");
+    /// ```
+    pub fn to_latex(&self) -> String {
+        match self {
+            Expr::Number(n) if *n < 0.0 => format!("-{}", -n),
+            Expr::Number(n) => n.to_string(),
+            Expr::Variable(name) => variable_latex(name),
+
+            Expr::Unary(UnaryOp::Neg, operand) => format!("-{}", operand_latex(operand, NEGATION + 1)),
+            Expr::Unary(UnaryOp::Factorial, operand) => format!("{}!", operand_latex(operand, ATOM)),
+
+            Expr::Binary(BinaryOp::Div, lhs, rhs) => format!("\\frac{{{}}}{{{}}}", lhs.to_latex(), rhs.to_latex()),
+            Expr::Binary(BinaryOp::Pow, base, exponent) => {
+                format!("{}^{{{}}}", operand_latex(base, POWER + 1), exponent.to_latex())
+            }
+            Expr::Binary(BinaryOp::Root, radicand, index) => match index.as_ref() {
+                Expr::Number(n) if *n == 2.0 => format!("\\sqrt{{{}}}", radicand.to_latex()),
+                _ => format!("\\sqrt[{}]{{{}}}", index.to_latex(), radicand.to_latex()),
+            },
+            Expr::Binary(op, lhs, rhs) => {
+                let precedence = binary_precedence(*op);
+                // Gli operatori sono associativi a sinistra: a destra serve una precedenza strettamente maggiore.
+                // Un operando negato dopo `+` o `-` viene racchiuso, per non scrivere due segni di fila (`a - (-b)`)
+                let rhs = if matches!(op, BinaryOp::Add | BinaryOp::Sub) && is_negated(rhs) {
+                    format!("\\left({}\\right)", rhs.to_latex())
+                } else {
+                    operand_latex(rhs, precedence + 1)
+                };
+                format!("{} {} {}", operand_latex(lhs, precedence), binary_symbol(*op), rhs)
+            }
+
+            Expr::Call(function, args) => call_latex(*function, args),
+            Expr::UserCall(name, args) => {
+                let shown: Vec<String> = args.iter().map(Expr::to_latex).collect();
+                // I nomi di più lettere vanno in tondo, come gli operatori, per non sembrare un prodotto
+                let name = if name.chars().count() == 1 { name.clone() } else { format!("\\operatorname{{{}}}", name.replace('_', "\\_")) };
+                format!("{}\\left({}\\right)", name, shown.join(", "))
+            }
+        }
+    }
+}
+
+/// Restituisce il livello di precedenza del nodo radice di `expr`.
+fn precedence(expr: &Expr) -> u8 {
+    match expr {
+        Expr::Number(n) if *n < 0.0 => NEGATION,
+        Expr::Number(_) | Expr::Variable(_) => ATOM,
+        Expr::Unary(UnaryOp::Neg, _) => NEGATION,
+        Expr::Unary(UnaryOp::Factorial, _) => POSTFIX,
+        Expr::Binary(BinaryOp::Div | BinaryOp::Root, ..) => ATOM,
+        Expr::Binary(op, ..) => binary_precedence(*op),
+        Expr::Call(Function::Factorial, _) => POSTFIX,
+        Expr::Call(..) | Expr::UserCall(..) => ATOM,
+    }
+}
+
+/// Indica se la forma LaTeX di `expr` inizia con un segno meno.
+fn is_negated(expr: &Expr) -> bool {
+    matches!(expr, Expr::Unary(UnaryOp::Neg, _)) || matches!(expr, Expr::Number(n) if *n < 0.0)
+}
+
+/// Restituisce il livello di precedenza di un operatore binario scritto in linea.
+fn binary_precedence(op: BinaryOp) -> u8 {
+    match op {
+        BinaryOp::Add | BinaryOp::Sub => SUM,
+        BinaryOp::Mul => PRODUCT,
+        BinaryOp::Div | BinaryOp::Root => ATOM,
+        BinaryOp::Pow => POWER,
+        BinaryOp::Less | BinaryOp::LessEqual | BinaryOp::Greater | BinaryOp::GreaterEqual | BinaryOp::Equal => COMPARISON,
+    }
+}
+
+/// Restituisce il simbolo LaTeX di un operatore binario scritto in linea.
+fn binary_symbol(op: BinaryOp) -> &'static str {
+    match op {
+        BinaryOp::Mul => "\\cdot",
+        BinaryOp::LessEqual => "\\le",
+        BinaryOp::GreaterEqual => "\\ge",
+        BinaryOp::Equal => "=",
+        _ => op.symbol(),
+    }
+}
+
+/// Converte un operando, racchiudendolo tra parentesi se la sua precedenza è inferiore a `min_precedence`.
+///
+/// Una frazione come base di una potenza viene comunque racchiusa: `\frac{1}{2}^{2}` sarebbe ambiguo.
+fn operand_latex(expr: &Expr, min_precedence: u8) -> String {
+    let fraction_base = min_precedence > POWER && matches!(expr, Expr::Binary(BinaryOp::Div, ..));
+    if precedence(expr) < min_precedence || fraction_base {
+        format!("\\left({}\\right)", expr.to_latex())
+    } else {
+        expr.to_latex()
+    }
+}
+
+/// Converte il nome di una variabile, usando le lettere greche per le costanti predefinite.
+///
+/// I nomi di più caratteri vanno in `\mathit{}`: scritti di seguito (`totale`) sembrerebbero
+/// il prodotto delle singole lettere.
+fn variable_latex(name: &str) -> String {
+    match name {
+        "pi" => "\\pi".to_string(),
+        "tau" => "\\tau".to_string(),
+        "phi" => "\\phi".to_string(),
+        _ if name.chars().count() == 1 => name.to_string(),
+        _ => format!("\\mathit{{{}}}", name.replace('_', "\\_")),
+    }
+}
+
+/// Converte una chiamata di funzione predefinita.
+fn call_latex(function: Function, args: &[Expr]) -> String {
+    let shown: Vec<String> = args.iter().map(Expr::to_latex).collect();
+    let joined = shown.join(", ");
+
+    match (function, shown.as_slice()) {
+        (Function::Sqrt, _) => format!("\\sqrt{{{}}}", joined),
+        (Function::Abs, _) => format!("\\left|{}\\right|", joined),
+        (Function::Floor, _) => format!("\\left\\lfloor {} \\right\\rfloor", joined),
+        (Function::Ceil, _) => format!("\\left\\lceil {} \\right\\rceil", joined),
+        (Function::Factorial, [_]) => format!("{}!", operand_latex(&args[0], ATOM)),
+        (Function::Log, [value, base]) => format!("\\log_{{{}}}\\left({}\\right)", base, value),
+        (Function::Log10, _) => format!("\\log_{{10}}\\left({}\\right)", joined),
+        _ => {
+            let name = match function {
+                Function::Sin => "\\sin".to_string(),
+                Function::Cos => "\\cos".to_string(),
+                Function::Tan => "\\tan".to_string(),
+                Function::Asin => "\\arcsin".to_string(),
+                Function::Acos => "\\arccos".to_string(),
+                Function::Atan => "\\arctan".to_string(),
+                Function::Sinh => "\\sinh".to_string(),
+                Function::Cosh => "\\cosh".to_string(),
+                Function::Tanh => "\\tanh".to_string(),
+                Function::Ln => "\\ln".to_string(),
+                Function::Log => "\\log".to_string(),
+                Function::Exp => "\\exp".to_string(),
+                Function::Gamma => "\\Gamma".to_string(),
+                Function::Min => "\\min".to_string(),
+                Function::Max => "\\max".to_string(),
+                _ => format!("\\operatorname{{{}}}", function.name().replace('_', "\\_")),
+            };
+            format!("{}\\left({}\\right)", name, joined)
+        }
+    }
+}
+
+/// Modulo di test per la conversione delle espressioni in LaTeX.
+#[cfg(test)]
+mod tests {
+    use super::*; // Importa tutti i membri del modulo superiore (il codice da testare)
+    use crate::compile::compile;
+
+    /// Analizza l'input e ne restituisce la forma LaTeX.
+    fn latex(input: &str) -> String {
+        compile(input).unwrap().expr().to_latex()
+    }
+
+    /// Verifica la divisione come frazione, anche annidata e dentro altre operazioni.
+    #[test]
+    fn test_division_latex() {
+        assert_eq!(latex("1 / 2"), "\\frac{1}{2}");
+        assert_eq!(latex("(a + b) / (c - d)"), "\\frac{a + b}{c - d}");
+        assert_eq!(latex("x / y / 2"), "\\frac{\\frac{x}{y}}{2}");
+        assert_eq!(latex("3 * (1 / x)"), "3 \\cdot \\frac{1}{x}");
+    }
+
+    /// Verifica le potenze, con le parentesi sulla base solo dove servono.
+    #[test]
+    fn test_power_latex() {
+        assert_eq!(latex("x ^ 2"), "x^{2}");
+        assert_eq!(latex("(x + 1) ^ 2"), "\\left(x + 1\\right)^{2}");
+        assert_eq!(latex("2 ^ 3 ^ 2"), "2^{3^{2}}");
+        assert_eq!(latex("(2 ^ 3) ^ 2"), "\\left(2^{3}\\right)^{2}");
+        assert_eq!(latex("(1 / 2) ^ x"), "\\left(\\frac{1}{2}\\right)^{x}");
+        assert_eq!(latex("-x ^ 2"), "-x^{2}");
+        assert_eq!(latex("(-x) ^ 2"), "\\left(-x\\right)^{2}");
+    }
+
+    /// Verifica l'operatore di radice `$` e la funzione `sqrt`.
+    #[test]
+    fn test_root_latex() {
+        assert_eq!(latex("27 $ 3"), "\\sqrt[3]{27}");
+        assert_eq!(latex("(x + 1) $ n"), "\\sqrt[n]{x + 1}");
+        assert_eq!(latex("x $ 2"), "\\sqrt{x}");
+        assert_eq!(latex("sqrt(16) + 1"), "\\sqrt{16} + 1");
+    }
+
+    /// Verifica precedenza e associatività degli operatori in linea, le funzioni e le costanti.
+    #[test]
+    fn test_precedence_and_functions_latex() {
+        assert_eq!(latex("(1 + 2) * 3"), "\\left(1 + 2\\right) \\cdot 3");
+        assert_eq!(latex("1 - (2 - 3)"), "1 - \\left(2 - 3\\right)");
+        assert_eq!(latex("1 - 2 - 3"), "1 - 2 - 3");
+        assert_eq!(latex("2pi"), "2 \\cdot \\pi");
+        assert_eq!(latex("(n + 1)!"), "\\left(n + 1\\right)!");
+        assert_eq!(latex("x + 1 <= 3"), "x + 1 \\le 3");
+        assert_eq!(latex("sin(x) + abs(y)"), "\\sin\\left(x\\right) + \\left|y\\right|");
+        assert_eq!(latex("log(8, 2)"), "\\log_{2}\\left(8\\right)");
+        assert_eq!(latex("gcd(12, 18)"), "\\operatorname{gcd}\\left(12, 18\\right)");
+        assert_eq!(latex("pct_of(20, 50)"), "\\operatorname{pct\\_of}\\left(20, 50\\right)");
+        assert_eq!(latex("max([1, x])"), "\\max\\left(1, x\\right)");
+    }
+
+    /// Verifica che i nomi di variabile di più lettere non sembrino un prodotto di lettere.
+    #[test]
+    fn test_variable_names_latex() {
+        assert_eq!(latex("totale * 2"), "\\mathit{totale} \\cdot 2");
+        assert_eq!(latex("prezzo_netto + x"), "\\mathit{prezzo\\_netto} + x");
+        assert_eq!(latex("2pi"), "2 \\cdot \\pi");
+    }
+
+    /// Verifica le parentesi attorno a un operando negato dopo `+` o `-`.
+    #[test]
+    fn test_negated_operand_latex() {
+        assert_eq!(latex("a - -b"), "a - \\left(-b\\right)");
+        assert_eq!(latex("a + -b"), "a + \\left(-b\\right)");
+        assert_eq!(latex("-a - b"), "-a - b");
+    }
+}
@@ -0,0 +1,80 @@
+//! # MathSolver
+//!
+//! Interprete per espressioni aritmetiche basato su una grammatica formale (CFG), vedi `CFG.txt`.
+//!
+//! La valutazione avviene in due fasi:
+//! 1. [`Tokenizer`]: analisi lessicale della stringa di input in una sequenza di [`Token`].
+//! 2. [`MathExpressionParser`]: analisi sintattica in un albero [`Expr`] secondo la precedenza degli operatori,
+//!    seguita dalla sua valutazione.
+//!
+//! Per valutare più volte la stessa formula, [`compile`] esegue le due fasi una sola volta.
+//!
+//! # Feature
+//! - `std` (predefinita): ambiente delle variabili su `HashMap` e binario a riga di comando.
+//! - `logging`: messaggi di log tramite il crate `log` (disattivati per impostazione predefinita, anche in debug).
+//! - `wasm`: esporta `wasm::eval_expression` verso JavaScript con `wasm-bindgen`.
+//! - `capi`: esporta `capi::mathsolver_eval` verso C/C++ (vedi `include/mathsolver.h`).
+//! - `bigdecimal`: valutazione in precisione arbitraria con `BigDecimal` (`exact::evaluate_exact`).
+//! - `libm`: funzioni matematiche su `f64` tramite `libm`. Senza `std` la libreria usa solo `core`
+//!   e `alloc`, e `libm` è obbligatoria (`--no-default-features --features libm`).
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+// I test usano l'harness di `std` anche quando la libreria è compilata senza
+#[cfg(all(test, not(feature = "std")))]
+extern crate std;
+
+#[cfg(not(any(feature = "std", feature = "libm")))]
+compile_error!("senza la feature `std` è necessaria la feature `libm` per le funzioni matematiche");
+
+#[macro_use]
+mod macros;
+
+#[cfg(not(feature = "std"))]
+mod math;
+mod prelude;
+
+pub mod builtins;
+#[cfg(feature = "capi")]
+pub mod capi;
+pub mod compile;
+pub mod diagnostics;
+pub mod error;
+mod evaluator;
+#[cfg(feature = "bigdecimal")]
+pub mod exact;
+pub mod expr;
+pub mod format;
+pub mod functions;
+pub mod latex;
+pub mod output;
+pub mod parser;
+pub mod simplify;
+pub mod solve;
+pub mod steps;
+pub mod token;
+pub mod tokenizer;
+pub mod wasm;
+
+pub use builtins::Function;
+pub use compile::{compile, compile_with_calls, evaluate_batch, evaluate_str, sample, try_evaluate, CompiledExpr};
+#[cfg(feature = "std")]
+pub use compile::evaluate_timed;
+pub use diagnostics::{explain_error, render_error, suggest_fix};
+#[cfg(feature = "bigdecimal")]
+pub use bigdecimal::BigDecimal;
+pub use error::{CalcError, CalcResult, ErrorCode, MathError, OperandSide, TokenError};
+#[cfg(feature = "bigdecimal")]
+pub use exact::{evaluate_exact, MAX_EXACT_DIGITS};
+pub use expr::{evaluate_over, BinaryOp, Env, Expr, UnaryOp};
+pub use format::{format_auto, format_auto_with_precision, format_engineering, format_integral, format_scientific, nice_round, FormatMode, Notation, DEFAULT_FIXED_RANGE, DEFAULT_PRECISION};
+pub use functions::{FunctionTable, UserFunction, MAX_CALL_DEPTH};
+pub use output::{EvalOutcome, EvalOutput, Outcome, ResultKind};
+pub use parser::{check_balanced, AngleMode, DivByZeroMode, ExponentAssoc, FunctionDigitsPolicy, ImplicitMultPrecedence, MathExpressionParser, ModuloMode, NumericMode, OverflowMode, ParserConfig, Statement, ZeroPowerMode};
+pub use simplify::{expressions_approx_equal, expressions_equal, expressions_within, Tolerance};
+pub use solve::solve_linear;
+pub use steps::{show_work, trace_evaluation};
+pub use token::{tokens_to_string, Assoc, Span, Token, TokenKind};
+pub use tokenizer::{LocaleMode, Tokenizer, WhitespacePolicy};
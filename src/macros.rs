@@ -0,0 +1,72 @@
+/// # Logging macros personalizzate
+///
+/// Questo blocco definisce un set di macro di logging (`trace_log!`, `debug_log!`, `info_log!`, `warn_log!`, `error_log!`) 
+/// che richiamano il crate `log` solo con la feature `logging` attiva, sia in `debug` che in `release`.
+///
+/// Senza la feature, tutte queste macro diventano no-op (non fanno nulla),
+/// eliminando l'overhead del logging in produzione. Gli argomenti restano comunque controllati dal
+/// compilatore, così le variabili usate solo nei log non generano avvisi.
+// MACRO ATTIVE CON LA FEATURE `logging` //
+#[cfg(feature = "logging")]
+#[allow(unused)]
+#[macro_export]
+#[doc(hidden)]
+macro_rules! trace_log { ($($arg:tt)*) => { log::trace!($($arg)*); }; }
+#[cfg(feature = "logging")]
+#[allow(unused)]
+#[macro_export]
+#[doc(hidden)]
+macro_rules! debug_log { ($($arg:tt)*) => { log::debug!($($arg)*); }; }
+#[cfg(feature = "logging")]
+#[allow(unused)]
+#[macro_export]
+#[doc(hidden)]
+macro_rules! info_log { ($($arg:tt)*) => { log::info!($($arg)*); }; }
+#[cfg(feature = "logging")]
+#[allow(unused)]
+#[macro_export]
+#[doc(hidden)]
+macro_rules! warn_log { ($($arg:tt)*) => { log::warn!($($arg)*); }; }
+#[cfg(feature = "logging")]
+#[allow(unused)]
+#[macro_export]
+#[doc(hidden)]
+macro_rules! error_log { ($($arg:tt)*) => { log::error!($($arg)*); }; }
+
+// VERSIONI NO-OP SENZA LA FEATURE `logging`
+#[cfg(not(feature = "logging"))]
+#[macro_export]
+#[doc(hidden)]
+macro_rules! trace_log { ($($arg:tt)*) => { if false { let _ = format_args!($($arg)*); } }; }
+#[cfg(not(feature = "logging"))]
+#[macro_export]
+#[doc(hidden)]
+macro_rules! debug_log { ($($arg:tt)*) => { if false { let _ = format_args!($($arg)*); } }; }
+#[cfg(not(feature = "logging"))]
+#[macro_export]
+#[doc(hidden)]
+macro_rules! info_log { ($($arg:tt)*) => { if false { let _ = format_args!($($arg)*); } }; }
+#[cfg(not(feature = "logging"))]
+#[macro_export]
+#[doc(hidden)]
+macro_rules! warn_log { ($($arg:tt)*) => { if false { let _ = format_args!($($arg)*); } }; }
+#[cfg(not(feature = "logging"))]
+#[macro_export]
+#[doc(hidden)]
+macro_rules! error_log { ($($arg:tt)*) => { if false { let _ = format_args!($($arg)*); } }; }
+
+/// Modulo di test per le macro di logging.
+#[cfg(test)]
+mod tests {
+    /// Verifica che le macro si espandano, con e senza la feature `logging`, usando variabili
+    /// che compaiono solo nei messaggi di log.
+    #[test]
+    fn test_log_macros_expand() {
+        let (value, name) = (42, "x");
+        trace_log!("trace {}", value);
+        debug_log!("debug {} = {}", name, value);
+        info_log!("info {:?}", Some(value));
+        warn_log!("warn");
+        error_log!("error {name}");
+    }
+}
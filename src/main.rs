@@ -1,1151 +1,728 @@
-#[allow(unused)]
-use std::io::{self, Write};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, IsTerminal, Write};
+use std::time::Instant;
 
-/// # Logging macros personalizzate per debug e release
-///
-/// Questo blocco definisce un set di macro di logging (`trace_log!`, `debug_log!`, `info_log!`, `warn_log!`, `error_log!`) 
-/// che funzionano solo in modalità `debug` (quando `cfg(debug_assertions)` è attivo).
-///
-/// In modalità `release`, tutte queste macro diventano no-op (non fanno nulla),
-/// riducendo overhead del logging in produzione.
-// MACRO ATTIVE IN MODALITÀ DEBUG //
-#[cfg(debug_assertions)]
-#[allow(unused)]
-macro_rules! trace_log { ($($arg:tt)*) => { log::trace!($($arg)*); }; }
-#[cfg(debug_assertions)]
-#[allow(unused)]
-macro_rules! debug_log { ($($arg:tt)*) => { log::debug!($($arg)*); }; }
-#[cfg(debug_assertions)]
-#[allow(unused)]
-macro_rules! info_log { ($($arg:tt)*) => { log::info!($($arg)*); }; }
-#[cfg(debug_assertions)]
-#[allow(unused)]
-macro_rules! warn_log { ($($arg:tt)*) => { log::warn!($($arg)*); }; }
-#[cfg(debug_assertions)]
-#[allow(unused)]
-macro_rules! error_log { ($($arg:tt)*) => { log::error!($($arg)*); }; }
-
-// VERSIONI NO-OP IN MODALITÀ RELEASE 
-#[cfg(not(debug_assertions))]
-macro_rules! trace_log { ($($arg:tt)*) => {}; }
-#[cfg(not(debug_assertions))]
-macro_rules! debug_log { ($($arg:tt)*) => {}; }
-#[cfg(not(debug_assertions))]
-macro_rules! info_log { ($($arg:tt)*) => {}; }
-#[cfg(not(debug_assertions))]
-macro_rules! warn_log { ($($arg:tt)*) => {}; }
-#[cfg(not(debug_assertions))]
-macro_rules! error_log { ($($arg:tt)*) => {}; }
-
-/// # Enum `Token`
-///
-/// Rappresenta i token lessicali riconosciuti.
-/// Ogni variante corrisponde a un tipo di simbolo nel linguaggio aritmetico:
-/// - `Number(f64)`: un numero decimale.
-/// - `Plus`, `Minus`, `Multiply`, `Divide`: operatori aritmetici.
-/// - `Caret`, 'Dollar': simboli di potenza e radice.
-/// - `LeftParen`, `RightParen`: parentesi tonde.
-/// - `Equals`: simbolo di fine espressione o assegnazione.
+use mathsolver::{error_log, info_log};
+use mathsolver::builtins::constant;
+use mathsolver::{compile, explain_error, show_work, trace_evaluation, AngleMode, CalcError, CalcResult, Env, EvalOutcome, Function, FunctionTable, MathExpressionParser, Notation, Outcome, Statement, Token, Tokenizer, DEFAULT_PRECISION};
+
+/// Opzioni della riga di comando.
 ///
-/// Derive:
-/// - `Debug`: per la stampa leggibile durante debug/log.
-/// - `Clone` e `Copy`: per duplicare i token, poiché sono tipi leggeri e immutabili.
-/// - `PartialEq`: per confrontare i token tra loro (es parser).
-#[derive(Debug, Clone, Copy, PartialEq)]
-enum Token {
-    /// Numero reale (es. 3.14, 42.0)
-    Number(f64),
-    
-    /// Operatore di somma: '+'
-    Plus,       
-    
-    /// Operatore di sottrazione: '-'
-    Minus,       
-    
-    /// Operatore di moltiplicazione: '*'
-    Multiply,  
-    
-    /// Operatore di divisione: '/'
-    Divide,
-
-    /// Simbolo di potenza: '^'
-    Caret,
-
-    /// Simbolo di radice n-esima: '$'
-    Dollar,
-
-    /// Parentesi aperta: '('
-    LeftParen, 
-    
-    /// Parentesi chiusa: ')'
-    RightParen,  
-    
-    /// Simbolo di fine espressione: '='
-    Equals,
+/// - `expression`: espressione da valutare; se assente viene usata l'espressione di esempio.
+/// - `angle_mode`: modalità angolare scelta esplicitamente con `--assume-radians` / `--assume-degrees`,
+///   usata dalle funzioni trigonometriche (predefinita: radianti).
+/// - `explain_error`: in caso di errore mostra il cursore sulla posizione e un suggerimento (`--explain-error`).
+/// - `explain`: mostra prima del risultato lo sviluppo di fattoriali e potenze (`--explain`).
+/// - `predicate`: il codice di uscita riflette il risultato, per gli script di shell (`--predicate`).
+/// - `file`: file di espressioni da valutare riga per riga (`--file <percorso>`).
+/// - `json`: stampa l'esito come oggetto JSON, per l'uso da parte di altri programmi (`--json`).
+/// - `verbose`: stampa su stderr i token e i passi della valutazione prima del risultato (`--verbose`).
+/// - `precision`: decimali dei risultati, dalla variabile d'ambiente `MATHSOLVER_PRECISION` (vedi `resolve_precision`).
+/// - `notation`: notazione dei risultati, modificabile riga per riga con il comando `:mode` (vedi `parse_command`).
+/// - `time`: mostra la durata della valutazione di ogni riga, attivabile con il comando `:time on`.
+/// - `answer_var_name`: variabile con l'ultimo risultato, modificabile con il comando `:ans` (predefinita: `ans`).
+#[derive(Debug, Default, Clone, PartialEq)]
+struct CliOptions {
+    /// Espressione passata come argomento posizionale.
+    expression: Option<String>,
+    /// Modalità angolare richiesta esplicitamente, usata per annotare l'output.
+    angle_mode: Option<AngleMode>,
+    /// Spiega gli errori con cursore e suggerimento di correzione.
+    explain_error: bool,
+    /// Mostra i passaggi di calcolo di fattoriali e potenze.
+    explain: bool,
+    /// Esce con 0 se il risultato è vero (diverso da zero), 1 se è falso, 2 in caso di errore.
+    predicate: bool,
+    /// Percorso del file da valutare in modalità batch, al posto di `expression`.
+    file: Option<String>,
+    /// Stampa l'esito in JSON (`EvalOutcome::to_json`) invece del testo leggibile.
+    json: bool,
+    /// Stampa su stderr la sequenza di token e la traccia della valutazione.
+    verbose: bool,
+    /// Precisione dei risultati; se assente si usa `DEFAULT_PRECISION` (3 decimali).
+    precision: Option<usize>,
+    /// Notazione dei risultati; quella predefinita sceglie tra decimale e scientifica (`format_auto`).
+    notation: Notation,
+    /// Aggiunge a ogni risultato il tempo di tokenizzazione, analisi e valutazione della riga.
+    time: bool,
+    /// Nome della variabile con l'ultimo risultato; se assente si usa `ANSWER_VAR` (`ans`).
+    answer_var_name: Option<String>,
 }
 
-impl Token {
-    /// Crea un token a partire da un carattere specifico.
-    ///
-    /// Restituisce `Some(Token)` se il carattere corrisponde a un token valido,
-    /// altrimenti `None`.
-    ///
-    /// # Parametri
-    /// - `c`: Il carattere da interpretare come token.
-    ///
-    /// # Esempio
-    /// ```
-    /// assert_eq!(Token::from_char('+'), Some(Token::Plus));
-    /// assert_eq!(Token::from_char('x'), None);
-    /// ```
-    #[inline] // Suggerisce al compilatore di inserire questa funzione inline per efficienza.
-    fn from_char(c: char) -> Option<Self> {
-        match c {
-            '+' => Some(Token::Plus),
-            '-' => Some(Token::Minus),
-            '*' => Some(Token::Multiply),
-            '/' => Some(Token::Divide),
-            '^' => Some(Token::Caret),
-            '$' => Some(Token::Dollar),
-            '(' => Some(Token::LeftParen),
-            ')' => Some(Token::RightParen),
-            '=' => Some(Token::Equals),
-            _ => None, // carattere non riconosciuto come token
-        }
-    }
-
-    /// Verifica se il token è un operatore binario (matematico).
-    ///
-    /// # Esempio
-    /// ```
-    /// assert!(Token::Plus.is_operator());
-    /// assert!(!Token::LeftParen.is_operator());
-    /// ```
-    #[inline]
-    #[allow(unused)]
-    fn is_operator(&self) -> bool {
-        matches!(self, Token::Plus | Token::Minus | Token::Multiply | Token::Divide)
+impl CliOptions {
+    /// Restituisce il nome della variabile con l'ultimo risultato.
+    fn answer_var(&self) -> &str {
+        self.answer_var_name.as_deref().unwrap_or(ANSWER_VAR)
     }
 }
 
-/// Tipi di errore che possono verificarsi durante l'esecuzione di calcoli matematici.
-///
-/// Viene usato nel valutatore di espressioni aritmetiche per segnalare
-/// errori come divisioni per zero o limiti computazionali.
+/// Interpreta gli argomenti della riga di comando (escluso il nome del programma).
 ///
-/// Derive:
-/// - `Debug`: consente la stampa dell'errore per log o debug.
-/// - `PartialEq`: confrontare errori nei test o nel flusso di controllo.
-#[derive(Debug, PartialEq)]
-#[allow(unused)]
-enum MathError {
-    /// Divisione per zero.
-    DivisionByZero,
-
-    /// Il risultato ha superato i limiti superiori rappresentabili.
-    OverflowError,
-
-    /// Il risultato è sceso sotto i limiti inferiori rappresentabili.
-    UnderflowError,
-
-    /// L'espressione contiene troppi elementi o nidificazioni.
-    // Attualmente non implementato
-    ExpressionTooComplex,
-
-    /// Potenza con base o esponente non valido
-    InvalidExponentiation { base: f64, exponent: f64, },
+/// # Ritorna
+/// - `Ok(CliOptions)` se gli argomenti sono validi.
+/// - `Err(String)` con la descrizione dell'errore per flag sconosciuti, flag in conflitto,
+///   `--file` senza percorso o più di un'espressione.
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<CliOptions, String> {
+    let mut options = CliOptions::default();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        let mode = match arg.as_str() {
+            "--assume-radians" => AngleMode::Radians,
+            "--assume-degrees" => AngleMode::Degrees,
+            "--explain-error" => {
+                options.explain_error = true;
+                continue;
+            }
+            "--explain" => {
+                options.explain = true;
+                continue;
+            }
+            "--predicate" => {
+                options.predicate = true;
+                continue;
+            }
+            "--json" => {
+                options.json = true;
+                continue;
+            }
+            "--verbose" => {
+                options.verbose = true;
+                continue;
+            }
+            "--file" => {
+                options.file = Some(args.next().ok_or("L'opzione --file richiede il percorso di un file")?);
+                continue;
+            }
+            flag if flag.starts_with("--") => return Err(format!("Opzione sconosciuta: {}", flag)),
+            _ => {
+                if options.expression.is_some() {
+                    return Err("È possibile valutare una sola espressione alla volta".into());
+                }
+                options.expression = Some(arg);
+                continue;
+            }
+        };
 
-    /// Radice di numero negativo con indice frazionario.
-    NegativeRoot { base: f64, root: f64, },
+        // --assume-radians e --assume-degrees sono mutuamente esclusivi
+        if options.angle_mode.is_some_and(|current| current != mode) {
+            return Err("Le opzioni --assume-radians e --assume-degrees sono mutuamente esclusive".into());
+        }
+        options.angle_mode = Some(mode);
+    }
 
-    /// Radice con indice pari di numero negativo (non definita nei reali).
-    EvenRootOfNegative { base: f64, root: f64, },
+    if options.file.is_some() && options.expression.is_some() {
+        return Err("L'opzione --file non può essere usata insieme a un'espressione".into());
+    }
 
-    /// Radice con base o indice non valido
-    InvalidRoot { base: f64, root: f64, },
+    Ok(options)
 }
 
-/// Tipi di errore che possono verificarsi durante la fase di tokenizzazione o parsing.
-/// Usato per indicare errori di sintassi o input invalido.
-///
-/// Derive:
-/// - `Debug`: consente la stampa dell'errore per log o debug.
-/// - `PartialEq`: confrontare errori nei test o nel flusso di controllo.
-#[derive(Debug, PartialEq)]
-#[allow(unused)]
-enum TokenError {
-    /// Numero malformato o non valido (es. "1..2").
-    InvalidNumber(String),
-
-    /// L'input termina in modo inaspettato (es. Parentesi non chiusa).
-    UnexpectedEnd,
-
-    /// Espressione invalida in senso sintattico.
-    InvalidExpression(String),
-
-    /// Operatore non riconosciuto (es. '%', '^', ecc.).
-    InvalidOperator(char),
+/// Nome della variabile d'ambiente con la precisione predefinita dei risultati.
+const PRECISION_VAR: &str = "MATHSOLVER_PRECISION";
 
-    /// Parentesi chiusa senza apertura o viceversa, include carattere e posizione.
-    UnmatchedParenthesis { found: char, position: usize },
+/// Precisione massima accettata: oltre 17 cifre un `f64` non ha altre cifre significative.
+const MAX_PRECISION: usize = 17;
 
-    /// Token inaspettato trovato in una certa posizione del parsing.
-    UnexpectedToken(Token),
+/// Interpreta il valore della variabile d'ambiente `MATHSOLVER_PRECISION`.
+///
+/// # Ritorna
+/// - `Ok(usize)` con la precisione richiesta, o `DEFAULT_PRECISION` se la variabile non è impostata.
+/// - `Err(String)` con l'avviso da mostrare se il valore non è un intero tra 0 e `MAX_PRECISION`;
+///   il chiamante ricade su `DEFAULT_PRECISION`.
+fn resolve_precision(raw: Option<&str>) -> Result<usize, String> {
+    let Some(raw) = raw else { return Ok(DEFAULT_PRECISION) };
+
+    match raw.trim().parse::<usize>() {
+        Ok(precision) if precision <= MAX_PRECISION => Ok(precision),
+        _ => Err(format!(
+            "Attenzione: {}='{}' non è un intero tra 0 e {}, uso la precisione predefinita ({})",
+            PRECISION_VAR, raw, MAX_PRECISION, DEFAULT_PRECISION
+        )),
+    }
+}
 
-    /// Errore sintattico generico, con descrizione.
-    // Attualmente non implementato
-    SyntaxError(String),
+/// Prepara l'esito di una valutazione per l'output, con la notazione e la precisione delle opzioni.
+fn outcome(result: CalcResult, options: &CliOptions) -> Outcome {
+    Outcome { notation: options.notation, precision: options.precision.unwrap_or(DEFAULT_PRECISION), ..Outcome::new(result) }
 }
 
-/// Implementazione del trait `Display` per `MathError`.
+/// Comando che modifica la formattazione dei risultati per le righe successive.
 ///
-/// Permette la conversione leggibile dell'errore in una stringa,
-/// utile per l'output verso l'utente o log.
-///
-/// Inoltre, ogni ramo logga l'errore con `error_log!`,
-/// che è abilitato solo in modalità `debug_assertions`.
-impl std::fmt::Display for MathError {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        match self {
-            MathError::DivisionByZero => {
-                error_log!("Errore: divisione per zero");
-                write!(f, "Errore matematico: divisione per zero")
-            },
-            MathError::OverflowError => {
-                error_log!("Errore: overflow numerico");
-                write!(f, "Errore matematico: overflow numerico")
-            },
-            MathError::UnderflowError => {
-                error_log!("Errore: underflow numerico");
-                write!(f, "Errore matematico: underflow numerico")
-            },
-            MathError::ExpressionTooComplex => {
-                error_log!("Errore: espressione troppo complessa");
-                write!(f, "Errore: espressione troppo complessa")
-            },
-            MathError::InvalidExponentiation { base, exponent } => {
-                error_log!("Errore: potenza non valida (base: {}, esponente: {})", base, exponent);
-                write!(f, "Errore: potenza non valida ({} ^ {})", base, exponent)
-            },
-            MathError::NegativeRoot { base, root } => {
-                error_log!("Errore: radice con indice frazionario di numero negativo (base: {}, indice: {})", base, root);
-                write!(f, "Errore: radice frazionaria di numero negativo ({} $ {})", base, root)
-            },
-            MathError::EvenRootOfNegative { base, root } => {
-                error_log!("Errore: radice con indice pari di numero negativo (base: {}, indice: {})", base, root);
-                write!(f, "Errore: radice con indice pari di numero negativo ({} $ {})", base, root)
-            },
-            MathError::InvalidRoot { base, root } => {
-                error_log!("Errore: potenza non valida (base: {}, esponente: {})", base, root);
-                write!(f, "Errore: potenza non valida ({} ^ {})", base, root)
-            },
-        }
-    }
+/// Derive:
+/// - `Debug`, `Clone`, `PartialEq`.
+#[derive(Debug, Clone, PartialEq)]
+enum Command {
+    /// `:mode dec|sci|eng|auto`: notazione dei risultati.
+    Mode(Notation),
+    /// `:prec N`: precisione dei risultati, da 0 a `MAX_PRECISION`.
+    Precision(usize),
+    /// `:time on|off`: durata della valutazione accanto ai risultati.
+    Time(bool),
+    /// `:ans NOME`: variabile in cui salvare l'ultimo risultato (es. `prev` o `_`).
+    Answer(String),
 }
 
-/// Implementazione del trait `Display` per `TokenError`.
-///
-/// Permette la conversione leggibile dell'errore in una stringa,
-/// utile per l'output verso l'utente o log.
-///
-/// Inoltre, ogni ramo logga l'errore con `warn_log!`,
-/// che è abilitato solo in modalità `debug_assertions`.
-impl std::fmt::Display for TokenError {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+impl Command {
+    /// Applica il comando alle opzioni, restituendo la conferma da mostrare.
+    fn apply(self, options: &mut CliOptions) -> String {
         match self {
-            TokenError::InvalidNumber(msg) => {
-                error_log!("Numero non valido: {}", msg);
-                write!(f, "Numero non valido: {}", msg)
-            },
-            TokenError::UnmatchedParenthesis { found, position } => {
-                error_log!("Parentesi mancante '{}' alla posizione {}", found, position);
-                write!(f, "Errore: mancante '{}' alla posizione {}", found, position)
-            },
-            TokenError::UnexpectedEnd => {
-                error_log!("Errore: fine espressione inaspettata");
-                write!(f, "Errore: espressione terminata inaspettatamente")
-            },
-            TokenError::InvalidExpression(msg) => {
-                error_log!("Errore: espressione non valida ({})", msg);
-                write!(f, "Errore: espressione non valida - {}", msg)
-            },
-            TokenError::InvalidOperator(op) => {
-                error_log!("Operatore non valido: '{}'", op);
-                write!(f, "Errore: operatore non valido '{}'", op)
-            },
-            TokenError::UnexpectedToken(token) => {
-                error_log!("Token inatteso: {:?}", token);
-                write!(f, "Errore: token inatteso {:?}", token)
-            },
-            TokenError::SyntaxError(msg) => {
-                error_log!("Errore di sintassi: {}", msg);
-                write!(f, "Errore di sintassi: {}", msg)
+            Command::Mode(notation) => {
+                options.notation = notation;
+                let name = match notation {
+                    Notation::Auto => "automatica",
+                    Notation::Decimal => "decimale",
+                    Notation::Scientific => "scientifica",
+                    Notation::Engineering => "ingegneristica",
+                };
+                format!("Notazione: {}", name)
+            }
+            Command::Precision(precision) => {
+                options.precision = Some(precision);
+                format!("Precisione: {}", precision)
+            }
+            Command::Time(enabled) => {
+                options.time = enabled;
+                format!("Tempo di valutazione: {}", if enabled { "attivo" } else { "disattivo" })
+            }
+            Command::Answer(name) => {
+                let shown = format!("Variabile del risultato: {}", name);
+                options.answer_var_name = Some(name);
+                shown
             }
         }
     }
 }
 
-/// Implementazione del trait `Error` per `MathError`.
-///
-/// Consente di trattare `MathError` come un errore standard, 
-/// ad esempio per l'uso con `?`.
-impl std::error::Error for MathError {}
-
-/// Implementazione del trait `Error` per `TokenError`.
+/// Elenco dei comandi disponibili, mostrato per un comando sconosciuto o non valido.
+const COMMAND_HELP: &str = "Comandi disponibili:
+  :mode dec   notazione decimale
+  :mode sci   notazione scientifica
+  :mode eng   notazione ingegneristica (esponenti multipli di 3)
+  :mode auto  decimale o scientifica in base all'ordine di grandezza
+  :prec N     precisione dei risultati, da 0 a 17
+  :time on    mostra la durata della valutazione di ogni riga
+  :time off   nasconde la durata della valutazione
+  :ans NOME   variabile con l'ultimo risultato (predefinita: ans)";
+
+/// Interpreta una riga di comando che inizia con `:` (es. `:mode sci`, `:prec 5`).
 ///
-/// Consente di trattare `TokenError` come un errore standard, 
-/// ad esempio per l'uso con `?`.
-impl std::error::Error for TokenError {}
+/// # Ritorna
+/// - `Ok(Command)` per un comando riconosciuto con argomenti validi.
+/// - `Err(String)` con la descrizione del problema seguita da `COMMAND_HELP` per un comando
+///   sconosciuto, un argomento mancante o non valido.
+fn parse_command(line: &str) -> Result<Command, String> {
+    let mut words = line.trim().trim_start_matches(':').split_whitespace();
+    let (name, argument, extra) = (words.next().unwrap_or(""), words.next(), words.next());
+
+    let command = match (name, argument, extra) {
+        ("mode", Some("dec"), None) => Some(Command::Mode(Notation::Decimal)),
+        ("mode", Some("sci"), None) => Some(Command::Mode(Notation::Scientific)),
+        ("mode", Some("eng"), None) => Some(Command::Mode(Notation::Engineering)),
+        ("mode", Some("auto"), None) => Some(Command::Mode(Notation::Auto)),
+        ("time", Some("on"), None) => Some(Command::Time(true)),
+        ("time", Some("off"), None) => Some(Command::Time(false)),
+        ("ans", Some(name), None) if is_answer_name(name) => Some(Command::Answer(name.to_string())),
+        ("prec", Some(precision), None) => match precision.parse::<usize>() {
+            Ok(precision) if precision <= MAX_PRECISION => Some(Command::Precision(precision)),
+            _ => None,
+        },
+        _ => None,
+    };
 
-/// Rappresenta un errore generico durante il calcolo.
-///
-/// Permette di unificare gli errori matematici (`MathError`)
-/// e gli errori di tokenizzazione/parsing (`TokenError`).
-///
-/// - `Debug`, `PartialEq`.
-#[derive(Debug, PartialEq)]
-enum CalcError {
-    // Errore matematico
-    Math(MathError),
-    // Errore durante il parsing
-    Token(TokenError),
+    command.ok_or_else(|| format!("Comando non valido: '{}'\n{}", line.trim(), COMMAND_HELP))
 }
 
-/// Conversione automatica da `MathError` a `CalcError`.
-/// Permette di usare `?` in funzioni che restituiscono `CalcResult`.
-impl From<MathError> for CalcError {
-    fn from(e: MathError) -> Self {
-        CalcError::Math(e)
-    }
-}
+/// Nome predefinito della variabile con l'ultimo risultato delle righe valutate (`evaluate_lines`).
+const ANSWER_VAR: &str = "ans";
 
-/// Conversione automatica da `CalcError` a `MathError`.
-/// Permette di `?` in funzioni che restituiscono `CalcResult`.
-impl From<TokenError> for CalcError {
-    fn from(e: TokenError) -> Self {
-        CalcError::Token(e)
-    }
+/// Verifica se `name` può essere la variabile dell'ultimo risultato: un identificatore valido
+/// (es. `prev`, `_`) che non sia il nome di una funzione o di una costante predefinita.
+fn is_answer_name(name: &str) -> bool {
+    Tokenizer::new(name).tokenize().is_ok_and(|tokens| tokens == [Token::Identifier(name.to_string())])
+        && Function::from_name(name).is_none()
+        && constant(name).is_none()
 }
 
-/// Implementazione di `Display` per `CalcError`.
+/// Verifica se una riga inizia con un operatore binario e prosegue quindi dall'ultimo risultato
+/// (es. `* 3 =` dopo `10 =` vale 30).
 ///
-/// Produce un messaggio leggibile combinando `MathError` e `TokenError`.
-/// I messaggi dettagliati vengono delegati ai rispettivi `Display`.
-impl std::fmt::Display for CalcError {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        match self {
-            CalcError::Math(e) => write!(f, "Errore matematico: {}", e),
-            CalcError::Token(e) => write!(f, "Errore di parsing: {}", e),
-        }
-    }
+/// Il `-` e il `$` iniziali restano operatori prefissi (`-5 =` vale -5, `$9 =` vale 3): per sottrarre
+/// dall'ultimo risultato o estrarne la radice n-esima si scrive `ans - 5` o `ans $ 3`.
+fn continues_previous(tokens: &[Token]) -> bool {
+    tokens.first().is_some_and(|token| token.is_operator() && !matches!(token, Token::Minus | Token::Dollar))
 }
 
-/// Implementazione del trait `Error` per `CalcError`.
+/// Valuta in modalità batch le righe di `input`, scrivendo su `output` una riga `input => risultato`
+/// (o `input => errore`) per ciascuna.
 ///
-/// Consente di trattare `CalcError` come un errore standard, 
-/// ad esempio per l'uso con `?`.
-impl std::error::Error for CalcError {}
-
-/// Alias per il tipo di risultato restituito dalle funzioni di calcolo.
+/// Le righe vuote e i commenti (righe che iniziano con `#`) vengono ignorati. Ogni riga è una lista
+/// di istruzioni come per `evaluate_all` (`=` finale facoltativo) e le variabili assegnate restano
+/// disponibili nelle righe successive. Un errore non interrompe la valutazione delle altre righe.
 ///
-/// - `Ok(f64)`: rappresenta il risultato numerico del calcolo.
-/// - `Err(CalcError)`: rappresenta un errore che può essere:
-///   - `MathError`: errori aritmetici (es. Divisione per zero, overflow).
-///   - `TokenError`: errori di sintassi o di parsing dell'espressione.
-type CalcResult = Result<f64, CalcError>;
-
+/// # Ritorna
+/// - `Ok(true)` se tutte le righe sono state valutate senza errori, `Ok(false)` altrimenti.
+/// - `Err(io::Error)` se la lettura o la scrittura falliscono.
+fn run_batch(input: impl BufRead, output: &mut impl Write, options: &CliOptions) -> io::Result<bool> {
+    evaluate_lines(input, options, |expression, shown| writeln!(output, "{} => {}", expression, shown))
+}
 
-/// Struttura responsabile dell'analisi lessicale di un'espressione matematica.
+/// Valuta le righe lette da una pipe (es. `echo "2+2=" | MathSolver`), scrivendo su `output` solo i risultati.
 ///
-/// Divide la stringa di input in una sequenza di token riconoscibili.
-/// Tiene traccia della posizione corrente durante la scansione.
-/// - `'a`: Lifetime del riferimento alla stringa di input.
-/// - Utilizza un riferimento immutabile (`&'a str`) per evitare copie non necessarie della stringa.
-/// - `position` tiene traccia dell'indice corrente durante la scansione dei caratteri.
-struct Tokenizer<'a> {
-    /// Slice immutabile della stringa di input contenente l'espressione da analizzare.
-    input: &'a str,
-    /// Posizione corrente nell'input, utilizzata per tracciare l'avanzamento durante la tokenizzazione.
-    position: usize,
+/// A differenza di `run_batch` l'input non viene ripetuto: ogni riga valutata produce una riga con il
+/// risultato o con il messaggio di errore, così l'output resta allineato all'input. Righe vuote,
+/// commenti e variabili seguono le regole di `run_batch`.
+///
+/// # Ritorna
+/// - `Ok(true)` se tutte le righe sono state valutate senza errori, `Ok(false)` altrimenti.
+/// - `Err(io::Error)` se la lettura o la scrittura falliscono.
+fn run_stream(input: impl BufRead, output: &mut impl Write, options: &CliOptions) -> io::Result<bool> {
+    evaluate_lines(input, options, |_, shown| writeln!(output, "{}", shown))
 }
 
-impl<'a> Tokenizer<'a> {
-    /// Crea una nuova istanza di `Tokenizer` per una data stringa di input.
-    ///
-    /// # Parametri
-    /// - `input`: riferimento alla stringa da analizzare.
-    ///
-    /// # Ritorna
-    /// - Istanza inizializzata di `Tokenizer` con posizione iniziale a zero.
-    fn new(input: &'a str) -> Self {
-        info_log!("Tokenizer creato. Input: '{}'", input);
-        Self { input, position: 0 }
-    }
-
-    /// Analizza la stringa di input e produce una sequenza di token.
-    ///
-    /// # Ritorna
-    /// - `Ok(Vec<Token>)` in caso di successo.
-    /// - `Err(TokenError)` se viene rilevato un errore di sintassi o simbolo non valido.
-    fn tokenize(&mut self) -> Result<Vec<Token>, TokenError> {
-        info_log!("Avvio tokenizzazione");
-        let mut tokens = Vec::new();
-
-        // Scorre ogni carattere finché non raggiunge la fine dell'input.
-        while self.position < self.input.len() {
-            let c = self.current_char();
-            
-            match c {
-                // Ignora spazi bianchi.
-                c if c.is_whitespace() => self.advance(),
-
-                // Gestisce sequenze numeriche, inclusi decimali.
-                c if c.is_ascii_digit() || c == '.' => {
-                    let token = self.parse_number()?;
-                    info_log!("Token numero trovato: {:?}", token);
-                    tokens.push(token);
-                }
-
-                // Gestisce simboli e operatori.
-                c => {
-                    // Gestione token riconosciuti.
-                    if let Some(token) = Token::from_char(c) {
-                        info_log!("Token simbolo trovato: {:?}", token);
-                        tokens.push(token);
-                        self.advance();
-                    } 
-                    // Gestisce token non riconosciuti con InvalidOperator, c - carattere non riconosciuto.
-                    else {
-                        return Err(TokenError::InvalidOperator(c));
-                    }
+/// Valuta una riga alla volta le espressioni di `input` con un ambiente e una tabella delle funzioni
+/// condivisi, passando a `report` l'espressione e il testo da mostrare (risultato o errore).
+///
+/// Le righe che iniziano con `:` sono comandi (`parse_command`) che cambiano la formattazione dei
+/// risultati successivi; un comando non valido mostra l'elenco dei comandi e conta come errore.
+///
+/// Ogni risultato viene salvato nella variabile `ans` (o in quella scelta con `:ans`), che diventa
+/// l'operando sinistro implicito di una riga che inizia con un operatore binario (`continues_previous`):
+/// dopo `10 =`, `* 3 =` vale 30. Cambiando nome, la variabile precedente resta una variabile qualsiasi.
+///
+/// Restituisce `Ok(true)` se nessuna riga ha prodotto un errore.
+fn evaluate_lines(
+    input: impl BufRead,
+    options: &CliOptions,
+    mut report: impl FnMut(&str, String) -> io::Result<()>,
+) -> io::Result<bool> {
+    let mut options = options.clone();
+    let mut env = Env::new();
+    let mut functions = FunctionTable::new();
+    let mut all_ok = true;
+
+    for line in input.lines() {
+        let line = line?;
+        let expression = line.trim();
+        if expression.is_empty() || expression.starts_with('#') { continue; }
+
+        if expression.starts_with(':') {
+            let shown = match parse_command(expression) {
+                Ok(command) => command.apply(&mut options),
+                Err(help) => {
+                    all_ok = false;
+                    help
                 }
-            }
+            };
+            report(expression, shown)?;
+            continue;
         }
-        
-        // Tokenizzazione completata, ritorna OK e il vettore di Token da parsare.
-        info_log!("Tokenizzazione completata: {:?}", tokens);
-        Ok(tokens)
-    }
 
-    /// Analizza e costruisce un token numerico a partire dalla posizione corrente.
-    ///
-    /// Supporta numeri interi e decimali. Non sono ammessi più punti decimali.
-    ///
-    /// # Ritorna
-    /// - `Ok(Token::Number(f64))` se il parsing ha successo.
-    /// - `Err(TokenError::InvalidNumber)` in caso di numero malformato.
-    fn parse_number(&mut self) -> Result<Token, TokenError> {
-        let start = self.position;
-        let mut has_decimal = false;
-
-        // Continua a leggere finché i caratteri fanno parte del numero.
-        while self.position < self.input.len() {
-            match self.current_char() {
-                c if c.is_ascii_digit() => self.advance(),
-
-                // Accetta un solo punto decimale.
-                '.' if !has_decimal => {
-                    has_decimal = true;
-                    self.advance();
+        let start = Instant::now();
+        let result = match Tokenizer::new(expression).tokenize() {
+            Ok(mut tokens) => {
+                if continues_previous(&tokens) && env.contains_key(options.answer_var()) {
+                    tokens.insert(0, Token::Identifier(options.answer_var().into()));
                 }
 
-                // Rifiuta numeri con più punti decimali.
-                // Se viene trovato un secondo '.' in un numero già marcato come decimale, viene generato un errore.
-                // Esempio non valido: "2..3"
-                '.' => return Err(TokenError::InvalidNumber("Numero con più punti decimali".into())),
-
-                // Interrompe la lettura alla prima occorrenza non numerica.
-                _ => break,
+                let mut parser = MathExpressionParser::with_env(tokens, env);
+                parser.set_function_table(functions);
+                if let Some(mode) = options.angle_mode { parser.set_angle_mode(mode); }
+                let statement = parser.evaluate_statements()
+                    .map(|mut statements| statements.pop().expect("almeno un'istruzione valutata"));
+                functions = parser.take_function_table();
+                env = parser.into_env();
+
+                // Le definizioni con `define` non hanno un valore e non sostituiscono l'ultimo risultato
+                if let Ok(Statement::Value(value)) = statement { env.insert(options.answer_var().into(), value); }
+                statement
             }
-        }
+            Err(e) => Err(CalcError::Token(e)),
+        };
 
-        // Estrae la sottostringa rappresentante un numero dalla posizione iniziale fino alla posizione corrente.
-        let number_str = &self.input[start..self.position];
+        let elapsed = start.elapsed();
 
-        // Tenta la conversione della sottostringa in un valore numerico `f64`.
-        // In caso di successo, restituisce un token `Token::Number(n)` contenente il valore.
-        // In caso di errore nel parsing, genera un errore `TokenError::InvalidNumber` contenente la stringa non valida.
-        match number_str.parse::<f64>() {
-            Ok(n) => Ok(Token::Number(n)),
-            Err(_) => Err(TokenError::InvalidNumber(number_str.to_string())),
-        }
+        all_ok &= result.is_ok();
+        let mut shown = match result {
+            Ok(Statement::Value(value)) => outcome(Ok(value), &options).to_string(),
+            Ok(defined) => defined.to_string(), // Conferma della definizione (es. `f definita`)
+            Err(e) => outcome(Err(e), &options).to_string(),
+        };
+        if options.time { shown = format!("{} ({:?})", shown, elapsed); }
+        report(expression, shown)?;
     }
 
-    /// Restituisce il carattere corrente dell'input in base alla posizione attuale.
-    /// Utilizza `chars().next().unwrap()` per accedere al primo carattere rimanente,
-    /// assumendo che la posizione sia sempre valida e non oltre la lunghezza dell'input.
-    fn current_char(&self) -> char {
-        self.input[self.position..].chars().next().unwrap()
+    Ok(all_ok)
+}
+
+/// Scrive su `output` la sequenza di token di `input` e la traccia della sua valutazione (`--verbose`).
+///
+/// La traccia (`trace_evaluation`) è disponibile solo per una singola espressione senza assegnazioni,
+/// con gli angoli in radianti; per gli altri input vengono mostrati solo i token.
+fn write_verbose(output: &mut impl Write, input: &str, tokens: &[Token]) -> io::Result<()> {
+    let shown: Vec<String> = tokens.iter().map(Token::to_string).collect();
+    writeln!(output, "Token: [{}]", shown.join(", "))?;
+
+    match compile(input) {
+        Ok(compiled) => {
+            let (steps, _) = trace_evaluation(compiled.expr(), &Env::new());
+            for (i, step) in steps.iter().enumerate() { writeln!(output, "Passo {}: {}", i + 1, step)?; }
+        }
+        Err(_) => writeln!(output, "Passi non disponibili: solo per una singola espressione senza assegnazioni")?,
     }
+    Ok(())
+}
 
-    /// Avanza la posizione corrente di un'unità, spostandosi al carattere successivo dell'input.
-    /// La posizione è basata sugli indici dei caratteri e presuppone che `current_char()` sia stato già valutato.
-    fn advance(&mut self) {
-        self.position += 1;
+/// Formatta il risultato per l'output, annotando la modalità angolare se richiesta esplicitamente.
+///
+/// Il valore è formattato da `Outcome` con la notazione e la precisione delle opzioni: con la notazione
+/// predefinita i valori molto grandi o molto piccoli sono mostrati in notazione scientifica (`format_auto`).
+///
+/// # Esempio
+/// ```
+/// let options = CliOptions { angle_mode: Some(AngleMode::Degrees), ..Default::default() };
+/// assert_eq!(format_output(1.0, &options), "Risultato: 1.000 (degrees)");
+/// ```
+fn format_output(value: f64, options: &CliOptions) -> String {
+    let value = outcome(Ok(value), options);
+    match options.angle_mode {
+        Some(mode) => format!("Risultato: {} ({})", value, mode),
+        None => format!("Risultato: {}", value),
     }
 }
 
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Con la feature `logging` i messaggi vengono stampati su stderr, filtrabili con `RUST_LOG`
+    #[cfg(feature = "logging")]
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let mut options = parse_args(std::env::args().skip(1))?;
 
-/// Parser per espressioni matematiche basate su una sequenza di token.
-/// Gestisce l'analisi sintattica e la valutazione delle espressioni secondo la precedenza degli operatori.
-struct MathExpressionParser {
-    /// Sequenza di token generati dal tokenizer.
-    tokens: Vec<Token>,
-    /// Posizione corrente all'interno del vettore di token.
-    position: usize,
-}
+    // Precisione dei risultati da `MATHSOLVER_PRECISION`: un valore non valido produce un avviso
+    let raw_precision = std::env::var(PRECISION_VAR).ok();
+    options.precision = Some(resolve_precision(raw_precision.as_deref()).unwrap_or_else(|warning| {
+        eprintln!("{}", warning);
+        DEFAULT_PRECISION
+    }));
 
-impl MathExpressionParser {
-    /// Costruisce un nuovo parser partendo da una sequenza di token.
-    ///
-    /// # Parametri
-    /// - `tokens`: Vettore di token pre-analizzati da valutare.
-    ///
-    /// # Ritorna
-    /// Un'istanza inizializzata di `MathExpressionParser` con posizione iniziale a zero.
-    fn new(tokens: Vec<Token>) -> Self {
-        info_log!("Parser inizializzato con tokens: {:?}", tokens);
-        Self { tokens, position: 0 }
+    // Modalità batch: una riga per espressione, codice di uscita 1 se almeno una riga fallisce
+    if let Some(path) = &options.file {
+        let all_ok = run_batch(BufReader::new(File::open(path)?), &mut io::stdout().lock(), &options)?;
+        if !all_ok { std::process::exit(1); }
+        return Ok(());
     }
 
-    /// Valuta un'espressione aritmetica completa secondo la grammatica formale.
-    ///
-    /// Questo metodo rappresenta l'ingresso principale per il parsing e la valutazione
-    /// di una formula, seguendo la regola grammaticale:
-    /// ```
-    /// F → E "="
-    /// ```
-    ///
-    /// # Comportamento
-    /// - Valuta l'espressione tramite `evaluate_expression()`.
-    /// - Verifica la presenza del simbolo `=` alla fine.
-    /// - Restituisce il risultato della valutazione se tutto è corretto, altrimenti segnala un errore.
-    ///
-    /// # Ritorna
-    /// - `Ok(f64)` se l'espressione è valida e terminata correttamente con `=`
-    /// - `Err(CalcError)` in caso di errore sintattico (token inatteso, fine prematura) o semantico
-    ///
-    /// # Esempi
-    /// ```
-    /// let mut parser = Parser::new("2 + 3 * 4 =");
-    /// let result = parser.evaluate();
-    /// assert_eq!(result.unwrap(), 14.0);
-    /// ```
-    ///
-    /// ```
-    /// let mut parser = Parser::new("2 + =");
-    /// let result = parser.evaluate();
-    /// assert!(result.is_err()); // Errore: manca un termine dopo '+'
-    /// ```
-    ///
-    /// # Note
-    /// - Il simbolo `=` è obbligatorio come delimitatore finale, ma non partecipa al calcolo.
-    /// - I log interni aiutano a tracciare lo stato della valutazione.
-    fn evaluate(&mut self) -> CalcResult {
-        info_log!("Inizio valutazione");
-        let result = self.evaluate_e()?; // Analizza e valuta un'espressione intera.
-
-        // Controlla se dopo l'espressione è presente un simbolo '=' (atteso).
-        match self.peek() {
-            Some(&Token::Equals) => {
-                info_log!("Valutazione completata con successo");
-                Ok(result)
-            },
-            Some(token) => {
-                // Errore: token inatteso dopo la fine dell'espressione.
-                error_log!("Token inatteso dopo valutazione: {:?}", token);
-                Err(TokenError::UnexpectedToken(*token).into())
-            },
-            None => {
-                // Errore: espressione terminata senza '=' esplicito.
-                error_log!("Espressione incompleta alla fine");
-                Err(TokenError::UnexpectedEnd.into())
+    // Input da una pipe (es. `echo "2+2=" | MathSolver`): una riga per espressione, solo i risultati
+    if options.expression.is_none() && !io::stdin().is_terminal() {
+        let all_ok = run_stream(io::stdin().lock(), &mut io::stdout().lock(), &options)?;
+        if !all_ok { std::process::exit(1); }
+        return Ok(());
+    }
+
+    // Senza argomenti (e da terminale) viene valutata l'espressione di esempio
+    let input = options.expression.as_deref()
+        .unwrap_or("(3 + 5 * (2 - 3) ^ 2) / (4 - 1) + -2 * (5 + 2) ^ 3 - 10 ="); // = -693.333 GIUSTA
+    info_log!("Input espressione: {}", input);
+
+    let mut tokenizer = Tokenizer::new(input);
+
+    let result = match tokenizer.tokenize() {
+        Ok(tokens) => {
+            if options.verbose { write_verbose(&mut io::stderr().lock(), input, &tokens)?; }
+
+            let has_sequence = tokens.contains(&Token::Semicolon);
+            let mut parser = MathExpressionParser::with_spans(tokens, tokenizer.spans().to_vec());
+            if let Some(mode) = options.angle_mode { parser.set_angle_mode(mode); }
+
+            // Un predicato (es. "3 > 2") può omettere il '=' finale, e una sequenza con ';'
+            // (es. "a = 3; a * 2 =") mostra solo l'ultimo risultato
+            if options.predicate || has_sequence {
+                parser.evaluate_last()
+            } else {
+                parser.evaluate()
             }
         }
-    }
+        Err(e) => Err(CalcError::Token(e))
+    };
 
-    /// Valuta un'espressione aritmetica che può contenere somme e sottrazioni tra termini.
-    ///
-    /// Questo metodo implementa la regola grammaticale:
-    /// ```
-    /// E → P E'
-    /// ```
-    ///
-    /// # Comportamento
-    /// - Valuta un primo termine `P` tramite `evaluate_p()`.
-    /// - Successivamente, passa il risultato parziale a `evaluate_e_prime()` per gestire eventuali
-    ///   somme o sottrazioni definite nella produzione `E'`.
-    /// - L'espressione termina quando non ci sono più operatori `+` o `−`.
-    ///
-    /// # Ritorna
-    /// - `Ok(f64)` con il risultato dell’espressione valutata.
-    /// - `Err(CalcError)` in caso di errore sintattico o semantico.
-    ///
-    /// # Esempi
-    /// ```
-    /// let mut parser = Parser::new("3 + 2 =");
-    /// assert_eq!(parser.evaluate_e().unwrap(), 5.0);
-    /// ```
-    ///
-    /// ```
-    /// let mut parser = Parser::new("7 - 4 =");
-    /// assert_eq!(parser.evaluate_e().unwrap(), 3.0);
-    /// ```
-    fn evaluate_e(&mut self) -> CalcResult {
-        let result = self.evaluate_p()?;
-        self.evaluate_e_prime(result)
+    // In JSON l'esito (valore o errore) va sempre su stdout; il codice di uscita segnala l'errore
+    if options.json {
+        let failed = result.is_err();
+        println!("{}", EvalOutcome { input: input.to_string(), result }.to_json());
+        if failed { std::process::exit(1); }
+        return Ok(());
     }
 
-    /// Valuta la parte ricorsiva di un'espressione (`E'`) che gestisce somme e sottrazioni.
-    ///
-    /// Questo metodo implementa la regola grammaticale:
-    /// ```
-    /// E' → "+" P E'
-    ///     | "−" P E'
-    ///     | ε
-    /// ```
-    ///
-    /// # Parametri
-    /// - `acc`: Il valore accumulato finora, risultato della valutazione di `P` in `E → P E'`.
-    ///
-    /// # Comportamento
-    /// - In un ciclo, controlla se il token corrente è un operatore `+` o `−`.
-    /// - Se è `+`, valuta il termine successivo `P` e lo somma al valore accumulato.
-    /// - Se è `−`, valuta il termine successivo `P` e lo sottrae al valore accumulato.
-    /// - In entrambi i casi, controlla eventuali overflow numerici tramite `check_overflow()`.
-    /// - Se il prossimo token non è un operatore, la funzione termina e restituisce il valore accumulato.
-    ///
-    /// # Ritorna
-    /// - `Ok(f64)` con il risultato aggiornato dell’espressione.
-    /// - `Err(CalcError)` in caso di errori aritmetici (es. overflow).
-    ///
-    /// # Esempio
-    /// ```
-    /// let mut parser = Parser::new("5 + 3 - 2 =");
-    /// assert_eq!(parser.evaluate_e_prime(5.0).unwrap(), 6.0);
-    /// ```
-    fn evaluate_e_prime(&mut self, mut acc: f64) -> CalcResult {
-        loop {
-            match self.peek() {
-                // In entrambi i casi consuma il token
-                Some(Token::Plus) => {
-                    self.advance();
-                    let rhs = self.evaluate_p()?; // Right-Hand Side
-                    
-                    info_log!("Operazione: {} + {}", acc, rhs);
-                    acc = self.check_overflow(acc + rhs)?;
-                }
-                Some(Token::Minus) => {
-                    self.advance();
-                    let rhs = self.evaluate_p()?; // Right-Hand Side
-                    
-                    info_log!("Operazione: {} - {}", acc, rhs);
-                    acc = self.check_overflow(acc - rhs)?;
-                }
-                _ => break,
+    match result {
+        Ok(value) => {
+            // I passaggi sono disponibili per una singola espressione (senza assegnazioni né liste)
+            if options.explain && let Ok(compiled) = compile(input) {
+                for step in show_work(compiled.expr(), &Default::default()) { println!("{}", step); }
             }
+            println!("{}", format_output(value, &options));
+
+            if options.predicate {
+                std::process::exit(if value != 0.0 && !value.is_nan() { 0 } else { 1 });
+            }
+            Ok(())
         }
-        // Restituisce il valore accumulato
-        Ok(acc)
-    }
+        Err(e) => {
+            if options.explain_error || options.predicate {
+                if options.explain_error { eprintln!("{}", explain_error(input, &e)); } else { eprintln!("{}", e); }
 
-    /// Valuta una parte dell'espressione che rappresenta un prodotto, che può includere:
-    /// - Operazioni esplicite di moltiplicazione (`*`) e divisione (`/`)
-    /// - Moltiplicazioni implicite (es. `2(3+4)` → `2 * (3+4)`)
-    ///
-    /// Questo metodo implementa la regola grammaticale:
-    /// ```
-    /// P → U P'
-    /// ```
-    ///
-    /// # Comportamento
-    /// - Chiama `evaluate_u()` per valutare la prima unità dell'espressione.
-    /// - Passa il risultato a `evaluate_p_prime()` per gestire le operazioni successive.
-    ///
-    /// # Ritorna
-    /// - `Ok(f64)` con il valore del prodotto calcolato.
-    /// - `Err(CalcError)` in caso di errore matematico o sintattico.
-    ///
-    /// # Esempi
-    /// ```
-    /// let mut parser = Parser::new("2 * 3 =");
-    /// assert_eq!(parser.evaluate_p().unwrap(), 6.0);
-    /// ```
-    /// 
-    /// ```
-    /// let mut parser = Parser::new("4(1 + 2) =");
-    /// assert_eq!(parser.evaluate_p().unwrap(), 12.0);  // moltiplicazione implicita
-    /// ```
-    fn evaluate_p(&mut self) -> CalcResult {
-        let result = self.evaluate_u()?;
-        self.evaluate_p_prime(result)
-    }
+                // In modalità predicato 1 significa "falso": gli errori usano un codice distinto
+                std::process::exit(if options.predicate { 2 } else { 1 });
+            }
 
-    /// Valuta le operazioni successive di prodotto, inclusi:
-    /// - Moltiplicazione esplicita (`*`)
-    /// - Divisione (`/`)
-    /// - Moltiplicazione implicita (es. `2(3 + 1)` → `2 * (3 + 1)`)
-    ///
-    /// Questo metodo implementa la regola grammaticale:
-    /// ```
-    /// P' → "*" U P'
-    ///     | "/" U P'
-    ///     | ImplicitMult U P'
-    ///     | ε
-    /// ```
-    ///
-    /// # Comportamento
-    /// - Percorre tutti i token che rappresentano una continuazione di `P`.
-    /// - Per `*` o `/`, valuta la parte a destra (`U`) e applica l'operazione sul valore accumulato.
-    /// - Se trova un numero o una parentesi aperta immediatamente dopo un termine valido (`acc`), applica la regola della *moltiplicazione implicita*.
-    /// - L’arresto avviene al primo token che non corrisponde a una continuazione valida.
-    ///
-    /// # Errori gestiti
-    /// - `MathError::DivisionByZero` se viene tentata una divisione per zero.
-    /// - `MathError::OverflowError` o `MathError::UnderflowError` se il risultato eccede i limiti numerici consentiti.
-    ///
-    /// # Ritorna
-    /// - `Ok(f64)` con il valore aggiornato.
-    /// - `Err(CalcError)` in caso di errore semantico o matematico.
-    ///
-    /// # Esempi
-    /// ```
-    /// let mut parser = Parser::new("4 * 2 =");
-    /// assert_eq!(parser.evaluate_p_prime(4.0).unwrap(), 8.0);
-    /// ```
-    ///
-    /// ```
-    /// let mut parser = Parser::new("5(2 + 1) =");
-    /// assert_eq!(parser.evaluate_p_prime(5.0).unwrap(), 15.0);  // moltiplicazione implicita
-    /// ```
-    fn evaluate_p_prime(&mut self, mut acc: f64) -> CalcResult {
-        loop {
-            match self.peek() {
-                // In tutti i casi consuma il token
-                // Gestione esplicita della moltiplicazione
-                Some(Token::Multiply) => {
-                    self.advance();
-                    let rhs = self.evaluate_u()?; // Right-Hand Side
-                    
-                    info_log!("Moltiplicazione: {} * {}", acc, rhs);
-                    acc = self.check_overflow(acc * rhs)?;
-                }
-                // Gestione esplicita della divisione
-                Some(Token::Divide) => {
-                    self.advance();
-                    let rhs = self.evaluate_u()?; // Right-Hand Side
-                    // n / 0 --> Errore
-                    if rhs == 0.0 { return Err(MathError::DivisionByZero.into()); }
-                    
-                    info_log!("Divisione: {} / {}", acc, rhs);
-                    acc = self.check_overflow(acc / rhs)?;
+            match e {
+                CalcError::Math(math_err) => {
+                    error_log!("Errore matematico: {}", math_err);
+                    Err(Box::new(math_err))
                 }
-                // Moltiplicazione implicita: es. `2(3 + 4)` o `4 5`
-                Some(Token::Number(_)) | Some(Token::LeftParen) => {
-                    if self.previous_token_is_paren_or_number() && self.can_apply_implicit_multiplication() {
-                        let rhs = self.evaluate_u()?; // Right-Hand Side
-
-                        info_log!("Moltiplicazione implicita: {} * {}", acc, rhs);
-                        acc = self.check_overflow(acc * rhs)?;
-                    } 
-                    else { break; }
+                CalcError::Token(token_err) => {
+                    error_log!("Errore di tokenizzazione: {}", token_err);
+                    Err(Box::new(token_err))
                 }
-                _ => break,
             }
         }
-        Ok(acc)
     }
+}
 
-    /// Verifica se il token precedente è un numero o una parentesi chiusa.
-    ///
-    /// Questo metodo è utilizzato per determinare se una moltiplicazione implicita
-    /// può essere applicata. La moltiplicazione implicita avviene, ad esempio,
-    /// in espressioni come `2(3 + 1)` o `4 5`, dove non è presente esplicitamente
-    /// l'operatore `*`.
-    ///
-    /// # Ritorna
-    /// - `true` se il token precedente è `Token::Number(_)` o `Token::RightParen`.
-    /// - `false` altrimenti.
-    fn previous_token_is_paren_or_number(&self) -> bool {
-        match self.tokens.get(self.position.wrapping_sub(1)) {
-            Some(Token::Number(_)) | Some(Token::RightParen) => true,
-            _ => false,
-        }
-    }
+/// Modulo di test per l'interpretazione della riga di comando.
+#[cfg(test)]
+mod tests {
+    use super::*; // Importa tutti i membri del modulo superiore (il codice da testare)
 
-    /// Verifica se il token corrente può rappresentare un termine valido
-    /// per una moltiplicazione implicita.
-    ///
-    /// Questo metodo viene tipicamente chiamato subito dopo `previous_token_is_paren_or_number`
-    /// per decidere se applicare una moltiplicazione implicita tra due elementi contigui.
-    ///
-    /// # Ritorna
-    /// - `true` se il token corrente è `Token::Number(_)` o `Token::LeftParen`.
-    /// - `false` altrimenti.
-    fn can_apply_implicit_multiplication(&self) -> bool {
-        match self.peek() {
-            Some(Token::Number(_)) | Some(Token::LeftParen) => true,
-            _ => false,
-        }
+    /// Verifica l'interpretazione dei flag della modalità angolare.
+    #[test]
+    fn test_parse_args_angle_mode() {
+        let args = |list: &[&str]| parse_args(list.iter().map(|a| a.to_string()));
+
+        assert_eq!(args(&["--assume-degrees", "1 ="]).unwrap().angle_mode, Some(AngleMode::Degrees));
+        assert_eq!(args(&["--assume-radians", "1 ="]).unwrap().angle_mode, Some(AngleMode::Radians));
+        assert_eq!(args(&["1 ="]).unwrap().angle_mode, None);
+        assert!(args(&["--assume-degrees", "--assume-radians"]).is_err());
+        assert!(args(&["--unknown"]).is_err());
     }
 
-    /// Valuta un'unità dell'espressione aritmetica, che può essere soggetta a esponenti o radici.
-    ///
-    /// Questo metodo implementa la regola grammaticale:
-    /// ```
-    /// U → B U'
-    /// ```
-    ///
-    /// # Comportamento
-    /// - Valuta prima la base tramite `evaluate_b()`.
-    /// - Poi applica eventuali esponenti o radici tramite `evaluate_u_prime(base)`.
-    ///
-    /// # Ritorna
-    /// - `Ok(f64)` con il valore dell'unità calcolata.
-    /// - `Err(CalcError)` in caso di errori sintattici o matematici (come radice di numero negativo o overflow).
-    ///
-    /// # Esempi
-    /// ```
-    /// let mut parser = Parser::new("2 ^ 3 =");
-    /// assert_eq!(parser.evaluate_u().unwrap(), 8.0);
-    ///
-    /// let mut parser = Parser::new("27 $ 3 =");  // Radice cubica
-    /// assert_eq!(parser.evaluate_u().unwrap(), 3.0);
-    /// ```
-    fn evaluate_u(&mut self) -> CalcResult {
-        let base = self.evaluate_b()?;
-        self.evaluate_u_prime(base)
+    /// Verifica l'interpretazione del flag `--explain-error`.
+    #[test]
+    fn test_parse_args_explain_error() {
+        let options = parse_args(["--explain-error".to_string(), "1 =".to_string()]).unwrap();
+        assert!(options.explain_error);
+        assert_eq!(options.expression.as_deref(), Some("1 ="));
+        assert!(!parse_args(["1 =".to_string()]).unwrap().explain_error);
+        assert!(parse_args(["--explain".to_string(), "5! =".to_string()]).unwrap().explain);
+        assert!(parse_args(["--predicate".to_string(), "3 > 2".to_string()]).unwrap().predicate);
+        assert!(parse_args(["--json".to_string(), "2+2=".to_string()]).unwrap().json);
+        assert!(parse_args(["--verbose".to_string(), "2+2=".to_string()]).unwrap().verbose);
+    }
+
+    /// Verifica l'interpretazione del flag `--file` e dei suoi conflitti.
+    #[test]
+    fn test_parse_args_file() {
+        let args = |list: &[&str]| parse_args(list.iter().map(|a| a.to_string()));
+
+        assert_eq!(args(&["--file", "exprs.txt"]).unwrap().file.as_deref(), Some("exprs.txt"));
+        assert!(args(&["--file"]).is_err());
+        assert!(args(&["--file", "exprs.txt", "1 ="]).is_err());
     }
 
-    /// Valuta gli operatori di potenza o radice applicati alla base già calcolata.
-    ///
-    /// Questo metodo implementa la regola grammaticale:
-    /// ```
-    /// U' → "^" U
-    ///     | "$" U
-    ///     | ε
-    /// ```
-    ///
-    /// # Comportamento
-    /// - Se il token corrente è `^`, valuta ricorsivamente il valore a destra e applica la potenza (`base ^ esponente`).
-    /// - Se il token corrente è `$`, valuta ricorsivamente il valore a destra e applica la radice (`base $ indice` = radice di indice `rhs` di `acc`).
-    /// - In caso di token non compatibile, restituisce il valore della base senza modificarlo (ε).
-    ///
-    /// # Validazioni ed errori
-    /// - Usa `evaluate_exponentiation` per gestire potenze, con validazioni (es. base negativa con esponente frazionario).
-    /// - Usa `evaluate_root` per gestire radici, controllando:
-    ///   - Radice di indice pari su numero negativo → errore `MathError::EvenRootOfNegative`
-    ///   - Indice zero → errore `MathError::DivisionByZero`
-    /// - Qualsiasi valore fuori dai limiti numerici viene gestito tramite `check_overflow`.
-    ///
-    /// # Parametri
-    /// - `acc`: f64 — il valore di partenza su cui applicare l'operatore.
-    ///
-    /// # Ritorna
-    /// - `Ok(f64)` con il risultato dopo l'eventuale applicazione di potenza o radice.
-    /// - `Err(CalcError)` in caso di errore matematico (overflow, radice di numero negativo, ecc.).
-    fn evaluate_u_prime(&mut self, mut acc: f64) -> CalcResult {
-        match self.peek() {
-            // In entrambi i casi consuma il token
-            Some(Token::Caret) => {
-                self.advance();
-                let rhs = self.evaluate_u()?; // Right-Hand Side
-                
-                info_log!("Esponenziale: {} ^ {}", acc, rhs);
-                acc = self.evaluate_exponentiation(acc, rhs)?;
-                Ok(acc)
-            }
+    /// Verifica la valutazione batch di più righe con variabili condivise, commenti ed errori.
+    #[test]
+    fn test_run_batch() {
+        let input = "# costanti\na = 3\n\nb = a * 2\na + b =\n1 / 0\nc + 1\n";
+        let mut output = Vec::new();
 
-            Some(Token::Dollar) => {
-                self.advance();
-                let rhs = self.evaluate_u()?; // Right-Hand Side
-                
-                info_log!("Radice: {} $ {}", acc, rhs);
-                acc = self.evaluate_root(acc, rhs)?;
-                Ok(acc)
-            }
+        let all_ok = run_batch(input.as_bytes(), &mut output, &CliOptions::default()).unwrap();
 
-            _ => Ok(acc),
-        }
+        assert!(!all_ok);
+        let lines: Vec<_> = String::from_utf8(output).unwrap().lines().map(String::from).collect();
+        assert_eq!(lines[..3], ["a = 3 => 3.000", "b = a * 2 => 6.000", "a + b = => 9.000"]);
+        assert!(lines[3].starts_with("1 / 0 => ") && lines[3].contains("divisione per zero"));
+        assert!(lines[4].starts_with("c + 1 => ") && lines[4].contains("'c'"));
+        assert_eq!(lines.len(), 5);
+
+        let mut output = Vec::new();
+        assert!(run_batch("1 + 1\n# fine\n".as_bytes(), &mut output, &CliOptions::default()).unwrap());
     }
 
-    /// Calcola l'esponenziale tra due numeri, ossia `base ^ esponente`.
-    ///
-    /// Questo metodo calcola la potenza della base elevata all'esponente e verifica se il risultato
-    /// è valido (non è `NaN` né infinito). 
-    /// Gestisce anche eventuali overflow o underflow numerici tramite il metodo `check_overflow`.
-    ///
-    /// # Parametri
-    /// - `base`: f64 — la base su cui applicare l'esponenziale.
-    /// - `exponent`: f64 — l'esponente a cui elevare la base.
-    ///
-    /// # Ritorna
-    /// - `Ok(f64)` se il calcolo è valido e il risultato non è fuori dai limiti numerici.
-    /// - `Err(MathError)` in caso di errore, come esponenziali che generano `NaN` o valori infiniti.
-    fn evaluate_exponentiation(&self, base: f64, exponent: f64) -> CalcResult {
-        // Calcola la potenza: base elevato all'esponente
-        let result = base.powf(exponent);
-
-        // Se il risultato è NaN o infinito, restituiamo un errore
-        if result.is_nan() || result.is_infinite() {
-            return Err(MathError::InvalidExponentiation { base, exponent }.into());
-        }
-        
-        self.check_overflow(result)
+    /// Verifica l'output di `--verbose`: token e passi, o solo i token per una lista di istruzioni.
+    #[test]
+    fn test_write_verbose() {
+        let verbose = |input: &str| {
+            let mut output = Vec::new();
+            write_verbose(&mut output, input, &Tokenizer::new(input).tokenize().unwrap()).unwrap();
+            String::from_utf8(output).unwrap()
+        };
+
+        assert_eq!(verbose("2+3*4="), "Token: [2, +, 3, *, 4, =]\nPasso 1: 3 * 4 = 12\nPasso 2: 2 + 12 = 14\n");
+        assert_eq!(
+            verbose("a = 2; a ="),
+            "Token: [a, =, 2, ;, a, =]\nPassi non disponibili: solo per una singola espressione senza assegnazioni\n"
+        );
     }
-    
-    /// Calcola la radice di un numero, ossia `base $ root`.
-    ///
-    /// Questo metodo gestisce il calcolo della radice di `base` con indice `root`. 
-    /// Se la base è negativa e la radice non è un intero dispari, restituisce un errore (`MathError::EvenRootOfNegative`).
-    /// Se la base è negativa e la radice è frazionaria, restituisce un errore (`MathError::NegativeRoot`).
-    /// Inoltre, gestisce il caso della divisione per zero nel caso in cui `root` sia uguale a zero.
-    ///
-    /// # Parametri
-    /// - `base`: f64 — la base su cui calcolare la radice.
-    /// - `root`: f64 — l'indice della radice da calcolare.
-    ///
-    /// # Ritorna
-    /// - `Ok(f64)` se il calcolo è valido e il risultato non è fuori dai limiti numerici.
-    /// - `Err(MathError)` in caso di errore, come divisione per zero o radice di un numero negativo con indice pari.
-    fn evaluate_root(&self, base: f64, root: f64) -> CalcResult {
-        // Controlla se la radice è zero, il che porterebbe a divisione per zero
-        if root == 0.0 { return Err(MathError::DivisionByZero.into()); }
-
-        // Gestisce il caso di base negativa
-        if base < 0.0 {
-            
-            // Se la radice è frazionaria, non possiamo calcolare la radice di un numero negativo
-            if root.fract() != 0.0 { return Err(MathError::NegativeRoot { base, root }.into()); }
-
-            // Se la radice è pari e la base è negativa, restituiamo un errore
-            if (root as i64) % 2 == 0 { return Err(MathError::EvenRootOfNegative { base, root }.into()); }
-
-            // Calcola la radice per base negativa
-            let result = -(-base).powf(1.0 / root);
-            return self.check_overflow(result);
-        }
 
-        // Calcola la radice per base positiva
-        let result = base.powf(1.0 / root);
-        
-        // Se il risultato è NaN o infinito, restituiamo un errore
-        if result.is_nan() || result.is_infinite() { return Err(MathError::InvalidRoot { base, root }.into()); }
-        
-        self.check_overflow(result)
+    /// Verifica che l'input da pipe produca solo i risultati, una riga per espressione.
+    #[test]
+    fn test_run_stream() {
+        let input = "2+2=\n\nx = 5\n# commento\nx * 2\n1 / 0 =\n";
+        let mut output = Vec::new();
+
+        let all_ok = run_stream(input.as_bytes(), &mut output, &CliOptions::default()).unwrap();
+
+        assert!(!all_ok);
+        let lines: Vec<_> = String::from_utf8(output).unwrap().lines().map(String::from).collect();
+        assert_eq!(lines[..3], ["4.000", "5.000", "10.000"]);
+        assert!(lines[3].contains("divisione per zero"));
+        assert_eq!(lines.len(), 4);
     }
 
-    /// Valuta un "fattore" nell'espressione aritmetica, che può essere:
-    /// - Un numero senza segno (es. `3.14`)
-    /// - Un'espressione preceduta da un operatore di negazione (`-`)
-    /// - Un'espressione tra parentesi tonde (es. `(2 + 3)`)
-    ///
-    /// Questo metodo implementa la regola grammaticale:
-    /// ```
-    /// B → "−" B
-    ///    | unsigned number
-    ///    | "(" E ")"
-    /// ```
-    ///
-    /// # Comportamento
-    /// - Se il token corrente è un numero (`Token::Number`), il valore viene restituito direttamente.
-    /// - Se il token corrente è un operatore di negazione (`Token::Minus`), il fattore successivo viene valutato e il risultato viene negato.
-    /// - Se il token corrente è una parentesi aperta `(`, viene valutata un'espressione tramite il metodo `evaluate_e()` fino a trovare la parentesi chiusa `)`.
-    /// - Se viene trovato un token inatteso (come una parentesi chiusa senza apertura o un altro token errato), viene restituito un errore.
-    /// - In caso di un errore generale (token non valido), viene restituito un errore di sintassi.
-    ///
-    /// # Ritorna
-    /// - `Ok(f64)` con il valore del fattore valutato (positivo o negativo, a seconda dei casi).
-    /// - `Err(TokenError)` se viene trovato un errore di sintassi (token inatteso, parentesi non corrispondenti, ecc.).
-    ///
-    /// # Esempi
-    /// ```
-    /// let mut parser = Parser::new("3.14 =");
-    /// assert_eq!(parser.evaluate_b().unwrap(), 3.14);
-    /// ```
-    ///
-    /// ```
-    /// let mut parser = Parser::new("-2.5 =");
-    /// assert_eq!(parser.evaluate_b().unwrap(), -2.5);
-    /// ```
-    fn evaluate_b(&mut self) -> CalcResult {
-        match self.next() {
-            // Caso di numero: restituisce il numero come valore
-            Some(Token::Number(n)) => Ok(n),
-
-            // Caso di negazione: valuta il fattore successivo e lo nega
-            Some(Token::Minus) => {
-                let val = self.evaluate_b()?; // Negazione del fattore
-                
-                info_log!("Negazione di {}", val);
-                Ok(-val)
-            },
-
-            // Caso di parentesi aperta: valuta l'espressione tra parentesi
-            Some(Token::LeftParen) => {
-                let result = self.evaluate_e()?;  // Analizza l'espressione tra parentesi
-
-                match self.next() {
-                    // Verifica che la parentesi chiusa corrisponda alla parentesi aperta
-                    Some(Token::RightParen) => Ok(result),
-
-                    // Se viene trovato un altro token invece di una parentesi chiusa, errore
-                    Some(tok) => {
-                        info_log!("Token inatteso invece di ')': {:?}", tok);
-                        Err(TokenError::UnmatchedParenthesis { found: ')', position: self.position }.into())
-                    },
-
-                    // Se non c'è un token successivo (parentesi chiusa mancante)
-                    None => Err(TokenError::UnmatchedParenthesis { found: '(', position: self.position }.into()),
-                }
-            },
-
-            // Caso di parentesi chiusa senza corrispondente parentesi aperta
-            Some(Token::RightParen) => {
-                info_log!("Parentesi chiusa senza apertura");
-                Err(TokenError::UnmatchedParenthesis { found: ')', position: self.position }.into())
-            },
-
-            // Caso di errore generale: token non valido trovato
-            token => {
-                info_log!("Fattore non valido trovato: {:?}", token);
-                Err(TokenError::InvalidExpression("Espressione non valida".into()).into())
-            }
-        }
+    /// Verifica che le funzioni definite con `define` restino disponibili nelle righe successive.
+    #[test]
+    fn test_run_stream_shares_functions() {
+        let input = "define f(x) = x^2 + 1
+f(3) =
+define g(x, y) = f(x) * y
+g(2, 3)
+";
+        let mut output = Vec::new();
+
+        assert!(run_stream(input.as_bytes(), &mut output, &CliOptions::default()).unwrap());
+        let lines: Vec<_> = String::from_utf8(output).unwrap().lines().map(String::from).collect();
+        assert_eq!(lines[1], "10.000");
+        assert_eq!(lines[3], "15.000");
     }
-    
-    /// Verifica se il valore è valido, controllando eventuali condizioni di overflow o underflow.
-    ///
-    /// # Ritorna
-    /// - `Ok(f64)` se il valore non è né infinito né subnormale.
-    /// - `Err(CalcError)` in caso di overflow (valore infinito) o underflow (valore subnormale).
-    ///
-    /// Questa funzione si occupa di monitorare la validità del valore calcolato, restituendo un errore in caso di:
-    /// - Overflow: se il valore calcolato è infinito.
-    /// - Underflow: se il valore calcolato è un numero subnormale, che può indicare una perdita di precisione o un valore troppo piccolo.
-    ///
-    fn check_overflow(&self, val: f64) -> Result<f64, CalcError> {
-        // Infinito
-        if val.is_infinite() {
-            Err(MathError::OverflowError.into())
-        }
-        // 0    
-        else if val.is_subnormal() {
-            Err(MathError::UnderflowError.into())
-        }
-            
-        else {
-            Ok(val)
+
+    /// Verifica l'interpretazione dei comandi `:mode` e `:prec` e il messaggio per un comando sconosciuto.
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(parse_command(":mode dec"), Ok(Command::Mode(Notation::Decimal)));
+        assert_eq!(parse_command(":mode sci"), Ok(Command::Mode(Notation::Scientific)));
+        assert_eq!(parse_command(":mode eng"), Ok(Command::Mode(Notation::Engineering)));
+        assert_eq!(parse_command(":mode auto"), Ok(Command::Mode(Notation::Auto)));
+        assert_eq!(parse_command(":prec 5"), Ok(Command::Precision(5)));
+        assert_eq!(parse_command("  :prec   0 "), Ok(Command::Precision(0)));
+        assert_eq!(parse_command(":time on"), Ok(Command::Time(true)));
+        assert_eq!(parse_command(":time off"), Ok(Command::Time(false)));
+        assert_eq!(parse_command(":ans prev"), Ok(Command::Answer("prev".into())));
+        assert_eq!(parse_command(":ans _"), Ok(Command::Answer("_".into())));
+
+        for invalid in [":help", ":", ":mode", ":mode hex", ":mode sci eng", ":prec", ":prec 18", ":prec -1", ":prec x", ":time", ":time yes", ":ans", ":ans 2x", ":ans pi", ":ans sin", ":ans a b"] {
+            let help = parse_command(invalid).unwrap_err();
+            assert!(help.contains(invalid.trim()) && help.contains(":mode sci") && help.contains(":prec N"), "{}", help);
         }
     }
-    
-    /// Restituisce il token corrente senza avanzare nella posizione.
-    ///
-    /// # Ritorna
-    /// - `Some(&Token)` se esiste un token alla posizione corrente.
-    /// - `None` se la posizione corrente è fuori dai limiti dell'elenco di token.
-    ///
-    /// Permette di esaminare il token attuale senza spostare la posizione del parser. 
-    /// È utile per fare previsioni sui token successivi o per determinare la posizione attuale nel flusso di token.
-    fn peek(&self) -> Option<&Token> {
-        self.tokens.get(self.position)
-    }
 
-    /// Restituisce e avanza alla posizione successiva nella lista di token.
-    ///
-    /// # Ritorna
-    /// - `Some(Token)` se esiste un token alla posizione corrente e avanza la posizione.
-    /// - `None` se la posizione corrente è fuori dai limiti dell'elenco di token.
-    ///
-    /// Questo metodo restituisce il token attuale e incrementa la posizione, spostando così il parser
-    /// alla posizione successiva. È utile per l'iterazione attraverso la lista di token.
-    fn next(&mut self) -> Option<Token> {
-        let token = self.tokens.get(self.position).copied(); // Poiché prende un riferimento '&Token', .copied() usato per copiare il valore contenuto nell' Option 
-        // Se esiste un token valido
-        if token.is_some() { self.advance(); }
-        token
-    }
+    /// Verifica che i comandi cambino la formattazione delle righe successive.
+    #[test]
+    fn test_run_stream_commands() {
+        let input = "12346 =\n:mode sci\n12346 =\n:mode eng\n:prec 4\n12346 =\n:mode dec\n1/3 =\n:unknown\n";
+        let mut output = Vec::new();
 
-    /// Avanza alla posizione successiva nella lista di token.
-    fn advance(&mut self) {
-        self.position += 1;
-    }
-}
+        let all_ok = run_stream(input.as_bytes(), &mut output, &CliOptions::default()).unwrap();
 
-/// Modulo di test per il parsing e la valutazione delle espressioni matematiche.
-///
-/// Questo modulo contiene test unitari per verificare il comportamento della logica di parsing e valutazione,
-/// con particolare attenzione alla gestione degli errori e alla corretta identificazione dei token.
-#[cfg(test)]
-mod tests {
-    use super::*; // Importa tutti i membri del modulo superiore (il codice da testare)
+        assert!(!all_ok);
+        let text = String::from_utf8(output).unwrap();
+        let lines: Vec<_> = text.lines().collect();
+        assert_eq!(
+            lines[..9],
+            ["12346.000", "Notazione: scientifica", "1.23e+04", "Notazione: ingegneristica", "Precisione: 4", "12.35e+03", "Notazione: decimale", "0.3333", "Comando non valido: ':unknown'"]
+        );
+        assert!(text.ends_with(&format!("{}\n", COMMAND_HELP)));
+    }
 
-    /// Test che simula l'errore di parentesi non corrispondenti.
-    ///
-    /// Questo test verifica come il tokenizer e il parser gestiscono una espressione con
-    /// parentesi mancanti, simulando una situazione di errore nella sintassi dell'espressione.
+    /// Verifica che `:time on` aggiunga la durata ai risultati e agli errori, e che `:time off` la tolga.
     #[test]
-    fn test_unmatched_parentheses_simulated() {
-        let expression = "((1+2))))) ="; 
-        
-        let mut tokenizer = Tokenizer::new(expression); 
-        let result = tokenizer.tokenize(); 
-        let tokens = result.unwrap(); 
-        let mut parser = MathExpressionParser::new(tokens); 
-        
-        println!("{:?}", parser.evaluate()); // Esegue la valutazione e stampa il risultato
-    }
-}
+    fn test_run_stream_time() {
+        let input = "2+2=\n:time on\n2+2=\n1/0=\n:time off\n2+2=\n";
+        let mut output = Vec::new();
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    
-    #[cfg(debug_assertions)]
-    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
-    
-    /*
-    #[cfg(debug_assertions)]
-    {
-        env_logger::Builder::new()
-            .format(|buf, record| {
-                use std::io::Write;
-                writeln!(buf, "[{} {}] {}", record.level(), record.target(), record.args())
-            })
-            .filter_level(log::LevelFilter::Debug)
-            .init();
+        run_stream(input.as_bytes(), &mut output, &CliOptions::default()).unwrap();
+
+        let text = String::from_utf8(output).unwrap();
+        let lines: Vec<_> = text.lines().collect();
+        assert_eq!(lines[..2], ["4.000", "Tempo di valutazione: attivo"]);
+        assert!(lines[2].starts_with("4.000 (") && lines[2].ends_with("s)"), "{}", lines[2]);
+        assert!(lines[3].contains("divisione per zero") && lines[3].ends_with("s)"), "{}", lines[3]);
+        assert_eq!(lines[4..], ["Tempo di valutazione: disattivo", "4.000"]);
     }
-    */
-    
-    let input = "(3 + 5 * (2 - 3) ^ 2) / (4 - 1) + -2 * (5 + 2) ^ 3 - 10 ="; // = -693.333 GIUSTA
-    info_log!("Input espressione: {}", input);
 
-    let mut tokenizer = Tokenizer::new(input);
+    /// Verifica le righe che iniziano con un operatore binario, applicato all'ultimo risultato (`ans`).
+    #[test]
+    fn test_run_stream_continues_previous() {
+        let input = "* 3 =\n10 =\n* 3 =\n+ 5\n/ 7 =\n^ 2 =\n-1 =\nans - 1 =\ndefine f(x) = 2x\n> 0\n$ 16 =\n";
+        let mut output = Vec::new();
 
-    let result = match tokenizer.tokenize() {
-        Ok(tokens) => {
-            let mut parser = MathExpressionParser::new(tokens);
-            parser.evaluate()
-        }
-        Err(e) => Err(CalcError::Token(e))
-    };
+        let all_ok = run_stream(input.as_bytes(), &mut output, &CliOptions::default()).unwrap();
 
-    match result {
-        Ok(value) => {
-            println!("Risultato: {:.3}", value);
-            Ok(())
-        }
-        Err(e) => {
-            // println!("Errore: {}", e);  
-            match e {
-                CalcError::Math(math_err) => {
-                    error_log!("Errore matematico: {}", math_err);
-                    Err(Box::new(math_err))
-                }
-                CalcError::Token(token_err) => {
-                    error_log!("Errore di tokenizzazione: {}", token_err);
-                    Err(Box::new(token_err))
-                }
-            }
+        assert!(!all_ok); // la prima riga non ha un risultato precedente
+        let lines: Vec<_> = String::from_utf8(output).unwrap().lines().map(String::from).collect();
+        assert_eq!(
+            lines[1..],
+            ["10.000", "30.000", "35.000", "5.000", "25.000", "-1.000", "-2.000", "f definita", "0.000", "4.000"]
+        );
+
+        assert!(continues_previous(&[Token::Multiply, Token::Number(3.0)]));
+        assert!(!continues_previous(&[Token::Minus, Token::Number(3.0)]));
+        assert!(!continues_previous(&[Token::Dollar, Token::Number(9.0)]));
+        assert!(!continues_previous(&[]));
+    }
+
+    /// Verifica il cambio della variabile con l'ultimo risultato e il concatenamento dei risultati.
+    #[test]
+    fn test_run_stream_answer_var() {
+        let input = "ans = 100\n:ans prev\n10 =\n* 3 =\nprev + 1 =\n+ ans =\nans =\n:ans _\n2 =\n_ * prev =\n";
+        let mut output = Vec::new();
+
+        let all_ok = run_stream(input.as_bytes(), &mut output, &CliOptions::default()).unwrap();
+
+        assert!(all_ok);
+        let lines: Vec<_> = String::from_utf8(output).unwrap().lines().map(String::from).collect();
+        assert_eq!(
+            lines,
+            [
+                "100.000",
+                "Variabile del risultato: prev",
+                "10.000",
+                "30.000",
+                "31.000",
+                "131.000",
+                "100.000", // la variabile `ans` dell'utente non viene sovrascritta
+                "Variabile del risultato: _",
+                "2.000",
+                "200.000", // `prev` conserva l'ultimo risultato salvato con quel nome (100)
+            ]
+        );
+
+        let options = CliOptions { answer_var_name: Some("prev".into()), ..CliOptions::default() };
+        assert_eq!(options.answer_var(), "prev");
+        assert_eq!(CliOptions::default().answer_var(), ANSWER_VAR);
+    }
+
+    /// Verifica la precisione letta da `MATHSOLVER_PRECISION`: valida, non valida o assente.
+    #[test]
+    fn test_resolve_precision() {
+        assert_eq!(resolve_precision(Some("6")), Ok(6));
+        assert_eq!(resolve_precision(Some(" 0 ")), Ok(0));
+        assert_eq!(resolve_precision(Some("17")), Ok(17));
+        assert_eq!(resolve_precision(None), Ok(DEFAULT_PRECISION));
+
+        for invalid in ["abc", "-1", "2.5", "", "18"] {
+            let warning = resolve_precision(Some(invalid)).unwrap_err();
+            assert!(warning.contains("MATHSOLVER_PRECISION"), "{}", warning);
         }
+
+        let six = CliOptions { precision: Some(6), ..Default::default() };
+        assert_eq!(format_output(1.0 / 3.0, &six), "Risultato: 0.333333");
     }
-}
 
+    /// Verifica che l'annotazione compaia solo con una modalità angolare esplicita.
+    #[test]
+    fn test_format_output_annotation() {
+        let degrees = CliOptions { angle_mode: Some(AngleMode::Degrees), ..Default::default() };
+        assert_eq!(format_output(1.0, &degrees), "Risultato: 1.000 (degrees)");
+        assert_eq!(format_output(1.0, &CliOptions::default()), "Risultato: 1.000");
+        assert_eq!(format_output(693e9, &CliOptions::default()), "Risultato: 6.93e+11");
+    }
+}
@@ -0,0 +1,90 @@
+//! Metodi matematici di `f64` per la compilazione senza `std`.
+//!
+//! In `core` i metodi che richiedono la libreria matematica di sistema (`sin`, `powf`, `ln`, ...)
+//! non esistono: il trait `Float` li reintroduce con lo stesso nome e la stessa semantica usando `libm`,
+//! così il codice di valutazione resta identico con e senza la feature `std`.
+
+/// Metodi di `f64` assenti in `core`, implementati con `libm`.
+///
+/// Nei test `std` è comunque collegata e ha la precedenza: i metodi vanno richiamati esplicitamente (`Float::sin(x)`).
+#[cfg_attr(test, allow(dead_code))]
+pub(crate) trait Float {
+    fn sqrt(self) -> Self;
+    fn sin(self) -> Self;
+    fn cos(self) -> Self;
+    fn tan(self) -> Self;
+    fn asin(self) -> Self;
+    fn acos(self) -> Self;
+    fn atan(self) -> Self;
+    fn atan2(self, other: Self) -> Self;
+    fn sinh(self) -> Self;
+    fn cosh(self) -> Self;
+    fn tanh(self) -> Self;
+    fn asinh(self) -> Self;
+    fn acosh(self) -> Self;
+    fn atanh(self) -> Self;
+    fn exp(self) -> Self;
+    fn ln(self) -> Self;
+    fn log(self, base: Self) -> Self;
+    fn log2(self) -> Self;
+    fn log10(self) -> Self;
+    fn powf(self, exponent: Self) -> Self;
+    fn powi(self, exponent: i32) -> Self;
+    fn floor(self) -> Self;
+    fn ceil(self) -> Self;
+    fn round(self) -> Self;
+    fn trunc(self) -> Self;
+    fn fract(self) -> Self;
+}
+
+impl Float for f64 {
+    fn sqrt(self) -> Self { libm::sqrt(self) }
+    fn sin(self) -> Self { libm::sin(self) }
+    fn cos(self) -> Self { libm::cos(self) }
+    fn tan(self) -> Self { libm::tan(self) }
+    fn asin(self) -> Self { libm::asin(self) }
+    fn acos(self) -> Self { libm::acos(self) }
+    fn atan(self) -> Self { libm::atan(self) }
+    fn atan2(self, other: Self) -> Self { libm::atan2(self, other) }
+    fn sinh(self) -> Self { libm::sinh(self) }
+    fn cosh(self) -> Self { libm::cosh(self) }
+    fn tanh(self) -> Self { libm::tanh(self) }
+    fn asinh(self) -> Self { libm::asinh(self) }
+    fn acosh(self) -> Self { libm::acosh(self) }
+    fn atanh(self) -> Self { libm::atanh(self) }
+    fn exp(self) -> Self { libm::exp(self) }
+    fn ln(self) -> Self { libm::log(self) }
+    fn log(self, base: Self) -> Self { libm::log(self) / libm::log(base) }
+    fn log2(self) -> Self { libm::log2(self) }
+    fn log10(self) -> Self { libm::log10(self) }
+    fn powf(self, exponent: Self) -> Self { libm::pow(self, exponent) }
+    fn powi(self, exponent: i32) -> Self { libm::pow(self, exponent as f64) }
+    fn floor(self) -> Self { libm::floor(self) }
+    fn ceil(self) -> Self { libm::ceil(self) }
+    // Come `f64::round`: le metà vengono arrotondate lontano dallo zero
+    fn round(self) -> Self { libm::round(self) }
+    fn trunc(self) -> Self { libm::trunc(self) }
+    fn fract(self) -> Self { self - libm::trunc(self) }
+}
+
+/// Modulo di test per le funzioni matematiche senza `std`.
+#[cfg(test)]
+mod tests {
+    use super::Float;
+
+    /// Verifica che le implementazioni di `libm` coincidano con i metodi di `f64` della libreria standard.
+    #[test]
+    fn test_libm_matches_std() {
+        for x in [0.1, 0.5, 1.0, 2.5, 10.0] {
+            assert!((Float::sin(x) - x.sin()).abs() < 1e-15);
+            assert!((Float::ln(x) - x.ln()).abs() < 1e-15);
+            assert!((Float::powf(x, 1.5) - x.powf(1.5)).abs() < 1e-12);
+            assert_eq!(Float::sqrt(x), x.sqrt());
+        }
+        assert_eq!(Float::round(2.5), 3.0);
+        assert_eq!(Float::round(-2.5), -3.0);
+        assert_eq!(Float::fract(-1.25), -0.25);
+        assert_eq!(Float::powi(2.0, 10), 1024.0);
+        assert_eq!(Float::log10(1000.0), 3.0);
+    }
+}
@@ -0,0 +1,219 @@
+// Nei test `std` è comunque collegata e i metodi di `f64` sono già disponibili
+#[cfg(not(feature = "std"))]
+#[cfg_attr(test, allow(unused_imports))]
+use crate::math::Float;
+
+use crate::error::{CalcError, CalcResult};
+use crate::format::{Notation, DEFAULT_FIXED_RANGE, DEFAULT_PRECISION};
+use crate::prelude::*;
+
+/// Tipo numerico del risultato di una valutazione.
+///
+/// Derive:
+/// - `Debug`, `Clone`, `Copy`, `PartialEq`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ResultKind {
+    /// Valore intero esatto (es. `6/3 =` → 2).
+    Integer,
+
+    /// Valore in virgola mobile con parte frazionaria, o non finito.
+    Float,
+
+    /// Frazione esatta `p/q`; riservato alle modalità numeriche esatte,
+    /// non viene mai prodotto dalla valutazione in `f64`.
+    Rational,
+}
+
+/// Implementazione del trait `Display` per `ResultKind`.
+impl core::fmt::Display for ResultKind {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            ResultKind::Integer => write!(f, "intero"),
+            ResultKind::Float => write!(f, "decimale"),
+            ResultKind::Rational => write!(f, "razionale"),
+        }
+    }
+}
+
+/// Risultato di una valutazione accompagnato dal suo tipo numerico.
+///
+/// Derive:
+/// - `Debug`, `Clone`, `Copy`, `PartialEq`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EvalOutput {
+    /// Valore calcolato.
+    pub value: f64,
+    /// Tipo numerico del valore.
+    pub kind: ResultKind,
+}
+
+impl EvalOutput {
+    /// Classifica un valore `f64`: è `Integer` se finito e privo di parte frazionaria, altrimenti `Float`.
+    ///
+    /// # Esempio
+    /// ```
+    /// assert_eq!(EvalOutput::from_f64(2.0).kind, ResultKind::Integer);
+    /// assert_eq!(EvalOutput::from_f64(1.5).kind, ResultKind::Float);
+    /// ```
+    pub fn from_f64(value: f64) -> Self {
+        let kind = if value.is_finite() && value.fract() == 0.0 { ResultKind::Integer } else { ResultKind::Float };
+        Self { value, kind }
+    }
+}
+
+/// Esito completo della valutazione di un input, per l'uso da parte di altri programmi.
+///
+/// Derive:
+/// - `Debug`, `PartialEq`.
+#[derive(Debug, PartialEq)]
+pub struct EvalOutcome {
+    /// Input valutato, così come è stato ricevuto.
+    pub input: String,
+    /// Valore calcolato o errore.
+    pub result: CalcResult,
+}
+
+impl EvalOutcome {
+    /// Serializza l'esito in un oggetto JSON su una sola riga, con i campi `input`, `result` ed `error`.
+    ///
+    /// - In caso di successo `error` è `null`; un risultato non finito (`NaN`, infinito) non è
+    ///   rappresentabile in JSON ed è serializzato come `null`.
+    /// - In caso di errore `result` è `null` ed `error` contiene il codice stabile (`CalcError::code`)
+    ///   e il messaggio leggibile.
+    ///
+    /// # Esempio
+    /// ```
+    /// let outcome = EvalOutcome { input: "2+2=".into(), result: Ok(4.0) };
+    /// assert_eq!(outcome.to_json(), r#"{"input":"2+2=","result":4.0,"error":null}"#);
+    /// ```
+    pub fn to_json(&self) -> String {
+        let (result, error) = match &self.result {
+            Ok(value) if value.is_finite() => (format!("{:?}", value), "null".to_string()),
+            Ok(_) => ("null".to_string(), "null".to_string()),
+            Err(err) => ("null".to_string(), error_json(err)),
+        };
+        format!(r#"{{"input":{},"result":{},"error":{}}}"#, json_string(&self.input), result, error)
+    }
+}
+
+/// Esito di una valutazione pronto per essere mostrato: il valore formattato o il messaggio di errore.
+///
+/// Centralizza l'output per l'utente: `Display` formatta il valore con `notation` e `precision`
+/// (come `Notation::format` con `DEFAULT_FIXED_RANGE`) e l'errore con il suo `Display`.
+///
+/// Derive:
+/// - `Debug`, `PartialEq`.
+#[derive(Debug, PartialEq)]
+pub struct Outcome {
+    /// Valore calcolato o errore.
+    pub result: CalcResult,
+    /// Notazione del valore.
+    pub notation: Notation,
+    /// Decimali, o cifre significative in notazione scientifica e ingegneristica.
+    pub precision: usize,
+}
+
+impl Outcome {
+    /// Costruisce l'esito con la notazione automatica e la precisione predefinita (`DEFAULT_PRECISION`).
+    ///
+    /// # Esempio
+    /// ```
+    /// assert_eq!(Outcome::new(Ok(2.5)).to_string(), "2.500");
+    /// let six = Outcome { precision: 6, ..Outcome::new(Ok(1.0 / 3.0)) };
+    /// assert_eq!(six.to_string(), "0.333333");
+    /// ```
+    pub fn new(result: CalcResult) -> Self {
+        Self { result, notation: Notation::default(), precision: DEFAULT_PRECISION }
+    }
+}
+
+/// Implementazione del trait `Display` per `Outcome`.
+impl core::fmt::Display for Outcome {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match &self.result {
+            Ok(value) => write!(f, "{}", self.notation.format(*value, &DEFAULT_FIXED_RANGE, self.precision)),
+            Err(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+/// Serializza un errore come oggetto JSON con codice e messaggio.
+///
+/// Il messaggio è quello dell'errore interno (`MathError` o `TokenError`), che ha già il proprio
+/// prefisso: il `Display` di `CalcError` ne aggiungerebbe un secondo (es. "Errore matematico: Errore matematico: ...").
+fn error_json(err: &CalcError) -> String {
+    let message = match err {
+        CalcError::Math(e) => e.to_string(),
+        CalcError::Token(e) => e.to_string(),
+    };
+    format!(r#"{{"code":{},"message":{}}}"#, json_string(&format!("{:?}", err.code())), json_string(&message))
+}
+
+/// Restituisce `text` come stringa JSON tra virgolette, con i caratteri speciali in escape.
+fn json_string(text: &str) -> String {
+    let mut json = String::with_capacity(text.len() + 2);
+    json.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+/// Modulo di test per la rappresentazione dei risultati.
+#[cfg(test)]
+mod tests {
+    use super::*; // Importa tutti i membri del modulo superiore (il codice da testare)
+    use crate::error::MathError;
+
+    /// Verifica la visualizzazione di un risultato con notazione e precisione scelte.
+    #[test]
+    fn test_outcome_display_success() {
+        assert_eq!(Outcome::new(Ok(4.0)).to_string(), "4.000");
+        assert_eq!(Outcome::new(Ok(693e9)).to_string(), "6.93e+11");
+
+        let outcome = Outcome { notation: Notation::Engineering, precision: 4, ..Outcome::new(Ok(12346.0)) };
+        assert_eq!(outcome.to_string(), "12.35e+03");
+        assert_eq!(format!("Risultato: {}", Outcome { precision: 1, ..Outcome::new(Ok(-2.26)) }), "Risultato: -2.3");
+    }
+
+    /// Verifica che un errore sia mostrato con il suo messaggio, indipendentemente dalla formattazione.
+    #[test]
+    fn test_outcome_display_error() {
+        let err = CalcError::from(MathError::DivisionByZero);
+        let message = err.to_string();
+
+        let outcome = Outcome { notation: Notation::Scientific, ..Outcome::new(Err(err)) };
+        assert_eq!(outcome.to_string(), message);
+        assert!(message.contains("divisione per zero"));
+    }
+
+    /// Verifica la serializzazione JSON di un risultato valido e di uno non finito.
+    #[test]
+    fn test_outcome_json_success() {
+        let outcome = EvalOutcome { input: "2+2=".into(), result: Ok(4.0) };
+        assert_eq!(outcome.to_json(), r#"{"input":"2+2=","result":4.0,"error":null}"#);
+
+        let outcome = EvalOutcome { input: "0.1".into(), result: Ok(f64::INFINITY) };
+        assert_eq!(outcome.to_json(), r#"{"input":"0.1","result":null,"error":null}"#);
+    }
+
+    /// Verifica la serializzazione JSON di un errore, con codice, messaggio ed escape dell'input.
+    #[test]
+    fn test_outcome_json_error() {
+        let err = CalcError::from(MathError::DivisionByZero);
+        let outcome = EvalOutcome { input: "1/0 \"x\"\n".into(), result: Err(err) };
+        assert_eq!(
+            outcome.to_json(),
+            r#"{"input":"1/0 \"x\"\n","result":null,"error":{"code":"DivByZero","message":"Errore matematico: divisione per zero"}}"#
+        );
+    }
+}
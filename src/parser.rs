@@ -0,0 +1,3106 @@
+use core::cell::Cell;
+
+use crate::builtins::{constant, split_function_digits, Function};
+use crate::error::{CalcError, CalcResult, OperandSide, TokenError};
+use crate::evaluator::Evaluator;
+use crate::expr::{BinaryOp, Env, Expr, UnaryOp};
+use crate::format::nice_round;
+use crate::functions::FunctionTable;
+use crate::output::EvalOutput;
+use crate::prelude::*;
+use crate::token::{Span, Token, TokenKind};
+
+/// Parola chiave che introduce la definizione di una funzione (`define f(x) = x^2 + 1`).
+const DEFINE_KEYWORD: &str = "define";
+
+/// Unità di misura degli angoli usata nel calcolo trigonometrico.
+///
+/// Derive:
+/// - `Debug`, `Clone`, `Copy`, `PartialEq`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AngleMode {
+    /// Angoli espressi in radianti.
+    Radians,
+
+    /// Angoli espressi in gradi sessagesimali.
+    Degrees,
+}
+
+/// Implementazione del trait `Display` per `AngleMode`.
+///
+/// Produce l'etichetta usata per annotare il risultato (es. `Risultato: 1.000 (degrees)`).
+impl core::fmt::Display for AngleMode {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            AngleMode::Radians => write!(f, "radians"),
+            AngleMode::Degrees => write!(f, "degrees"),
+        }
+    }
+}
+
+/// Comportamento della divisione per zero.
+///
+/// Derive:
+/// - `Debug`, `Clone`, `Copy`, `PartialEq`, `Default`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum DivByZeroMode {
+    /// La divisione per zero è un errore (`MathError::DivisionByZero`).
+    #[default]
+    Error,
+
+    /// Semantica IEEE 754: `1/0` → `inf`, `-1/0` → `-inf`.
+    /// Gli infiniti vengono accettati anche dai controlli di overflow; le forme indeterminate
+    /// (`0/0`, `inf - inf`, `0 * inf`) sono un errore `MathError::NotANumber`.
+    Infinity,
+
+    /// Ogni divisione per zero produce `NaN`.
+    Nan,
+}
+
+/// Convenzione per la potenza `0^0`.
+///
+/// In ogni modalità una potenza di zero con esponente negativo (es. `0^-1`) è una divisione per zero,
+/// gestita secondo `DivByZeroMode`.
+///
+/// Derive:
+/// - `Debug`, `Clone`, `Copy`, `PartialEq`, `Default`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ZeroPowerMode {
+    /// `0^0` è una forma indeterminata (`MathError::IndeterminateForm`).
+    #[default]
+    Indeterminate,
+
+    /// `0^0` vale `1`, come in combinatoria e per `f64::powf`.
+    One,
+}
+
+/// Segno del resto calcolato da `mod(a, m)` quando gli operandi hanno segni diversi.
+///
+/// Derive:
+/// - `Debug`, `Clone`, `Copy`, `PartialEq`, `Default`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ModuloMode {
+    /// Divisione troncata, come l'operatore `%` di C e Rust: il resto ha il segno del dividendo
+    /// (`mod(-10, 3)` → -1, `mod(10, -3)` → 1).
+    #[default]
+    Truncated,
+
+    /// Divisione euclidea, come `f64::rem_euclid`: il resto è sempre non negativo
+    /// (`mod(-10, 3)` → 2, `mod(10, -3)` → 1).
+    Euclidean,
+}
+
+/// Comportamento dei controlli di overflow e underflow sui risultati.
+///
+/// In tutte le modalità i risultati subnormali sono validi: sono piccoli ma rappresentabili.
+///
+/// Derive:
+/// - `Debug`, `Clone`, `Copy`, `PartialEq`, `Default`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum OverflowMode {
+    /// Un risultato infinito è un errore di overflow; un prodotto, quoziente o potenza di operandi
+    /// non nulli che si azzera è un errore di underflow.
+    /// Le potenze con risultato infinito restano `MathError::InvalidExponentiation`.
+    #[default]
+    Strict,
+
+    /// I risultati infiniti vengono limitati a `f64::MAX` / `f64::MIN`; quelli azzerati restano zero.
+    Saturate,
+
+    /// Infiniti e azzeramenti vengono restituiti così come sono.
+    Allow,
+}
+
+/// Associatività degli operatori di potenza (`^`) e radice (`$`).
+///
+/// Derive:
+/// - `Debug`, `Clone`, `Copy`, `PartialEq`, `Default`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ExponentAssoc {
+    /// Convenzione matematica: `2^3^2` → `2^(3^2)` = 512.
+    #[default]
+    Right,
+
+    /// Come in alcune calcolatrici: `2^3^2` → `(2^3)^2` = 64.
+    Left,
+}
+
+/// Precedenza della moltiplicazione implicita (per giustapposizione) rispetto a `*` e `/`.
+///
+/// Decide espressioni ambigue come `6/2(1+2)`.
+///
+/// Derive:
+/// - `Debug`, `Clone`, `Copy`, `PartialEq`, `Default`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ImplicitMultPrecedence {
+    /// Stessa precedenza di `*` e `/`, da sinistra a destra: `6/2(1+2)` → `(6/2)*(1+2)` = 9.
+    #[default]
+    SameAsExplicit,
+
+    /// La giustapposizione lega più di `*` e `/`: il loro operando destro comprende i fattori
+    /// giustapposti che seguono, quindi `6/2(1+2)` → `6/(2(1+2))` = 1 e `1/2x` → `1/(2x)`.
+    /// Potenze e negazione restano più forti: `2x^2` → `2(x^2)`.
+    Tighter,
+}
+
+/// Aritmetica usata per valutare le espressioni.
+///
+/// Nelle modalità intere ogni valore (letterali, variabili, risultati intermedi) deve essere un intero
+/// rappresentabile nel tipo scelto: le operazioni sono controllate (`checked_*`) e un risultato fuori
+/// dai limiti è sempre `MathError::OverflowError`, indipendentemente da `OverflowMode`.
+///
+/// Derive:
+/// - `Debug`, `Clone`, `Copy`, `PartialEq`, `Default`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum NumericMode {
+    /// Numeri in virgola mobile `f64`.
+    #[default]
+    Float,
+
+    /// Interi a 64 bit (`i64`): `20!` è il fattoriale più grande rappresentabile.
+    Int64,
+
+    /// Interi a 128 bit (`i128`): prodotti e fattoriali restano esatti fino a `33!`.
+    Int128,
+}
+
+/// Interpretazione di un identificatore formato dal nome di una funzione seguito da cifre (es. `sin2`).
+///
+/// Derive:
+/// - `Debug`, `Clone`, `Copy`, `PartialEq`, `Default`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum FunctionDigitsPolicy {
+    /// `sin2` resta un unico identificatore, risolto come variabile durante la valutazione
+    /// (`TokenError::UnknownVariable` se non definita).
+    #[default]
+    Identifier,
+
+    /// `sin2` viene diviso nella chiamata `sin(2)`.
+    Split,
+}
+
+/// Esito di un'istruzione valutata: un valore numerico oppure la definizione di una funzione.
+///
+/// Una definizione (`define f(x) = 2x`) registra la funzione senza calcolare nulla, quindi non ha
+/// un valore: è distinta da un risultato `NaN` ottenuto per esempio con `DivByZeroMode::Nan`.
+///
+/// Derive:
+/// - `Debug`, `Clone`, `PartialEq`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement {
+    /// Valore di un'espressione o di un'assegnazione.
+    Value(f64),
+
+    /// Definizione di una funzione, con il suo nome.
+    Defined(String),
+}
+
+impl Statement {
+    /// Restituisce il valore dell'istruzione, `None` per una definizione.
+    pub fn value(&self) -> Option<f64> {
+        match self {
+            Statement::Value(value) => Some(*value),
+            Statement::Defined(_) => None,
+        }
+    }
+
+    /// Converte l'istruzione nel suo valore, con un errore per una definizione.
+    fn into_value(self) -> CalcResult {
+        match self {
+            Statement::Value(value) => Ok(value),
+            Statement::Defined(name) => {
+                Err(TokenError::SyntaxError(format!("La definizione della funzione '{}' non ha un valore", name)).into())
+            }
+        }
+    }
+}
+
+/// Implementazione del trait `Display` per `Statement`.
+///
+/// Un valore è mostrato così com'è, una definizione con la sua conferma (es. `f definita`).
+impl core::fmt::Display for Statement {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            Statement::Value(value) => write!(f, "{}", value),
+            Statement::Defined(name) => write!(f, "{} definita", name),
+        }
+    }
+}
+
+/// Configurazione completa del parser: tutte le modalità di analisi e valutazione in un unico valore.
+///
+/// `ParserConfig::default()` riproduce il comportamento di `MathExpressionParser::new`; i singoli
+/// campi si sovrascrivono con la sintassi di aggiornamento delle struct.
+///
+/// # Esempio
+/// ```
+/// let config = ParserConfig { angle_mode: AngleMode::Degrees, implicit_mult: false, ..ParserConfig::default() };
+/// let mut parser = MathExpressionParser::with_config(Tokenizer::new("sin(90) =").tokenize()?, config);
+/// assert_eq!(parser.evaluate()?, 1.0);
+/// ```
+///
+/// Derive:
+/// - `Debug`, `Clone`, `Copy`, `PartialEq`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParserConfig {
+    /// Unità degli angoli per le funzioni trigonometriche (predefinita: radianti).
+    pub angle_mode: AngleMode,
+    /// Comportamento della divisione per zero (predefinito: errore).
+    pub div_by_zero: DivByZeroMode,
+    /// Comportamento dei controlli di overflow e underflow (predefinito: errore).
+    pub overflow_mode: OverflowMode,
+    /// Convenzione per `0^0` (predefinita: forma indeterminata).
+    pub zero_power: ZeroPowerMode,
+    /// Segno del resto di `mod` (predefinito: divisione troncata).
+    pub modulo: ModuloMode,
+    /// Interpretazione di identificatori come `sin2` (predefinito: identificatore unico).
+    pub function_digits: FunctionDigitsPolicy,
+    /// Abilita la moltiplicazione implicita (`2(3)`, `4 5`, `2x`); predefinito: attiva.
+    pub implicit_mult: bool,
+    /// Precedenza della moltiplicazione implicita rispetto a `*` e `/` (predefinita: la stessa).
+    pub implicit_mult_precedence: ImplicitMultPrecedence,
+    /// Aritmetica usata nella valutazione (predefinita: `f64`).
+    pub numeric_mode: NumericMode,
+    /// Associatività di `^` e `$` (predefinita: a destra).
+    pub exponent_assoc: ExponentAssoc,
+    /// Verifica il bilanciamento delle parentesi prima dell'analisi (`check_balanced`); predefinito: disattivata.
+    pub balance_check: bool,
+    /// Analizza `nome(...)` con un nome sconosciuto come chiamata `Expr::UserCall`, da risolvere con
+    /// `Expr::eval_with_callbacks`; predefinito: disattivata (`double(x)` è `double * (x)`).
+    pub external_calls: bool,
+    /// Valore assoluto massimo dei risultati (predefinito: nessun limite).
+    pub max_magnitude: Option<f64>,
+    /// Numero massimo di operazioni valutate per parser (predefinito: nessun limite).
+    pub step_budget: Option<usize>,
+}
+
+/// Valori predefiniti di tutte le modalità, gli stessi di `MathExpressionParser::new`.
+impl Default for ParserConfig {
+    fn default() -> Self {
+        Self {
+            angle_mode: AngleMode::Radians,
+            div_by_zero: DivByZeroMode::default(),
+            overflow_mode: OverflowMode::default(),
+            zero_power: ZeroPowerMode::default(),
+            modulo: ModuloMode::default(),
+            function_digits: FunctionDigitsPolicy::default(),
+            implicit_mult: true,
+            implicit_mult_precedence: ImplicitMultPrecedence::default(),
+            numeric_mode: NumericMode::default(),
+            exponent_assoc: ExponentAssoc::default(),
+            balance_check: false,
+            external_calls: false,
+            max_magnitude: None,
+            step_budget: None,
+        }
+    }
+}
+
+/// Parser per espressioni matematiche basate su una sequenza di token.
+/// Gestisce l'analisi sintattica, che produce un albero `Expr` secondo la precedenza degli operatori,
+/// e la valutazione delle espressioni.
+/// Mantiene un ambiente di variabili, condiviso tra le espressioni di una stessa lista.
+pub struct MathExpressionParser {
+    /// Sequenza di token generati dal tokenizer.
+    tokens: Vec<Token>,
+    /// Ambiente delle variabili assegnate (nome → valore).
+    env: Env,
+    /// Funzioni definite con `define` (nome → parametri e corpo).
+    functions: FunctionTable,
+    /// Intervalli nel sorgente dei token, usati per riportare la posizione negli errori (può essere vuoto).
+    spans: Vec<Span>,
+    /// Modalità di analisi e valutazione (vedi `ParserConfig`).
+    config: ParserConfig,
+    /// Operazioni valutate finora, confrontate con `ParserConfig::step_budget`.
+    steps: Cell<usize>,
+    /// Posizione corrente all'interno del vettore di token.
+    position: usize,
+}
+
+impl MathExpressionParser {
+    /// Costruisce un nuovo parser partendo da una sequenza di token.
+    ///
+    /// # Parametri
+    /// - `tokens`: Vettore di token pre-analizzati da valutare.
+    ///
+    /// # Ritorna
+    /// Un'istanza inizializzata di `MathExpressionParser` con posizione iniziale a zero.
+    pub fn new(tokens: Vec<Token>) -> Self {
+        info_log!("Parser inizializzato con tokens: {:?}", tokens);
+        Self { tokens, env: Env::new(), functions: FunctionTable::new(), spans: Vec::new(), config: ParserConfig::default(), steps: Cell::new(0), position: 0 }
+    }
+
+    /// Costruisce un nuovo parser associando a ogni token la sua posizione nel sorgente.
+    ///
+    /// # Parametri
+    /// - `tokens`: Vettore di token pre-analizzati da valutare.
+    /// - `spans`: Intervalli nel sorgente dei token, ottenuti da `Tokenizer::spans()`.
+    ///
+    /// # Ritorna
+    /// Un parser che riporta negli errori la posizione del carattere invece dell'indice del token.
+    pub fn with_spans(tokens: Vec<Token>, spans: Vec<Span>) -> Self {
+        let mut parser = Self::new(tokens);
+        parser.spans = spans;
+        parser
+    }
+
+    /// Costruisce un nuovo parser le cui variabili vengono risolte nell'ambiente `env` fornito dal chiamante.
+    ///
+    /// Utile per valutare un'espressione modello con valori esterni (es. `x^2` con `x = 10`).
+    /// Le variabili assenti da `env` restano `TokenError::UnknownVariable`; le assegnazioni
+    /// nell'espressione modificano solo la copia posseduta dal parser.
+    ///
+    /// # Esempio
+    /// ```
+    /// let env = Env::from([("x".to_string(), 10.0)]);
+    /// let mut parser = MathExpressionParser::with_env(Tokenizer::new("x^2 =").tokenize()?, env);
+    /// assert_eq!(parser.evaluate()?, 100.0);
+    /// ```
+    pub fn with_env(tokens: Vec<Token>, env: Env) -> Self {
+        let mut parser = Self::new(tokens);
+        parser.env = env;
+        parser
+    }
+
+    /// Consuma il parser e restituisce il suo ambiente, con le assegnazioni eseguite durante la valutazione.
+    ///
+    /// Insieme a `with_env` permette di condividere le variabili tra input valutati separatamente
+    /// (es. le righe di un file).
+    ///
+    /// # Esempio
+    /// ```
+    /// let mut parser = MathExpressionParser::new(Tokenizer::new("a = 3 =").tokenize()?);
+    /// parser.evaluate()?;
+    /// let mut next = MathExpressionParser::with_env(Tokenizer::new("a * 2 =").tokenize()?, parser.into_env());
+    /// assert_eq!(next.evaluate()?, 6.0);
+    /// ```
+    pub fn into_env(self) -> Env {
+        self.env
+    }
+
+    /// Imposta le funzioni definite dall'utente disponibili nelle espressioni, sostituendo quelle correnti.
+    ///
+    /// Insieme a `take_function_table` permette di condividere le definizioni tra input valutati
+    /// separatamente, come `with_env` e `into_env` per le variabili.
+    ///
+    /// # Esempio
+    /// ```
+    /// let mut parser = MathExpressionParser::new(Tokenizer::new("define f(x) = x^2 + 1 =").tokenize()?);
+    /// parser.evaluate_statements()?;
+    /// let mut next = MathExpressionParser::new(Tokenizer::new("f(3) =").tokenize()?);
+    /// next.set_function_table(parser.take_function_table());
+    /// assert_eq!(next.evaluate()?, 10.0);
+    /// ```
+    pub fn set_function_table(&mut self, functions: FunctionTable) {
+        self.functions = functions;
+    }
+
+    /// Estrae le funzioni definite dall'utente, comprese quelle definite durante la valutazione,
+    /// lasciando al parser una tabella vuota.
+    pub fn take_function_table(&mut self) -> FunctionTable {
+        core::mem::take(&mut self.functions)
+    }
+
+    /// Costruisce un nuovo parser con tutte le modalità di `config`, invece di impostarle una alla volta.
+    ///
+    /// `MathExpressionParser::with_config(tokens, ParserConfig::default())` equivale a `new(tokens)`.
+    pub fn with_config(tokens: Vec<Token>, config: ParserConfig) -> Self {
+        let mut parser = Self::new(tokens);
+        parser.config = config;
+        parser
+    }
+
+    /// Restituisce la configurazione corrente, comprese le modifiche dei singoli `set_*`.
+    pub fn config(&self) -> &ParserConfig {
+        &self.config
+    }
+
+    /// Restituisce il valore minimo e massimo che la valutazione accetta con la configurazione corrente,
+    /// ad esempio per avvisare l'utente prima di un overflow.
+    ///
+    /// - Con `NumericMode::Float` l'intervallo è `[-limit, limit]` se è impostato `set_max_magnitude`,
+    ///   altrimenti `[f64::MIN, f64::MAX]`.
+    /// - Con le modalità intere l'intervallo è quello del tipo (`i64` o `i128`), convertito in `f64`:
+    ///   in aritmetica intera il limite di `set_max_magnitude` non si applica.
+    ///
+    /// # Esempio
+    /// ```
+    /// let mut parser = MathExpressionParser::new(Tokenizer::new("2^10 =").tokenize()?);
+    /// assert_eq!(parser.numeric_limits(), (f64::MIN, f64::MAX));
+    /// parser.set_max_magnitude(1e6);
+    /// assert_eq!(parser.numeric_limits(), (-1e6, 1e6));
+    /// ```
+    pub fn numeric_limits(&self) -> (f64, f64) {
+        match self.config.numeric_mode {
+            NumericMode::Float => self.config.max_magnitude.map_or((f64::MIN, f64::MAX), |limit| (-limit, limit)),
+            NumericMode::Int64 => (i64::MIN as f64, i64::MAX as f64),
+            NumericMode::Int128 => (i128::MIN as f64, i128::MAX as f64),
+        }
+    }
+
+    /// Imposta l'unità degli angoli usata da `sin`, `cos`, `tan` e dalle loro inverse.
+    pub fn set_angle_mode(&mut self, angle_mode: AngleMode) {
+        self.config.angle_mode = angle_mode;
+    }
+
+    /// Imposta il comportamento della divisione per zero.
+    pub fn set_div_by_zero_mode(&mut self, mode: DivByZeroMode) {
+        self.config.div_by_zero = mode;
+    }
+
+    /// Imposta il comportamento dei controlli di overflow e underflow.
+    pub fn set_overflow_mode(&mut self, mode: OverflowMode) {
+        self.config.overflow_mode = mode;
+    }
+
+    /// Imposta la convenzione per `0^0` (vedi `ZeroPowerMode`).
+    pub fn set_zero_power_mode(&mut self, mode: ZeroPowerMode) {
+        self.config.zero_power = mode;
+    }
+
+    /// Imposta il segno del resto di `mod` con operandi negativi (vedi `ModuloMode`).
+    pub fn set_modulo_mode(&mut self, mode: ModuloMode) {
+        self.config.modulo = mode;
+    }
+
+    /// Imposta il valore assoluto massimo ammesso per i risultati delle operazioni (es. `1e100` per un servizio condiviso).
+    ///
+    /// Ogni risultato intermedio di un'operazione o di una funzione il cui valore assoluto supera `limit`
+    /// produce `MathError::MagnitudeExceeded`, anche se finito; il valore `limit` stesso è ammesso.
+    /// Per impostazione predefinita non c'è alcun limite oltre a quelli di `OverflowMode`.
+    pub fn set_max_magnitude(&mut self, limit: f64) {
+        self.config.max_magnitude = Some(limit);
+    }
+
+    /// Restituisce il parser con il valore assoluto massimo `limit` impostato, come `set_max_magnitude`,
+    /// per concatenarlo alla costruzione.
+    ///
+    /// # Esempio
+    /// ```
+    /// let mut parser = MathExpressionParser::new(Tokenizer::new("10^101 =").tokenize()?).with_max_magnitude(1e100);
+    /// assert!(parser.evaluate().is_err());
+    /// ```
+    pub fn with_max_magnitude(mut self, limit: f64) -> Self {
+        self.set_max_magnitude(limit);
+        self
+    }
+
+    /// Limita il numero di operazioni valutate (es. per le espressioni non fidate di un servizio).
+    ///
+    /// Ogni operazione (operatore, negazione, fattoriale, chiamata di funzione) consuma un passo, anche nei
+    /// corpi delle funzioni definite dall'utente; numeri e variabili non ne consumano. Il limite vale per
+    /// tutte le istruzioni valutate dal parser: superarlo produce `MathError::ExpressionTooComplex`.
+    /// Per impostazione predefinita non c'è alcun limite.
+    ///
+    /// # Esempio
+    /// ```
+    /// let mut parser = MathExpressionParser::new(Tokenizer::new("2 * 3 * 4 =").tokenize()?);
+    /// parser.set_step_budget(1);
+    /// assert_eq!(parser.evaluate(), Err(MathError::ExpressionTooComplex.into()));
+    /// ```
+    pub fn set_step_budget(&mut self, budget: usize) {
+        self.config.step_budget = Some(budget);
+    }
+
+    /// Abilita o disabilita la moltiplicazione implicita.
+    ///
+    /// Disabilitandola, due operandi adiacenti senza operatore (es. `4 5 =`, spesso un errore di battitura)
+    /// producono `TokenError::UnexpectedToken` sul secondo operando invece di essere moltiplicati.
+    pub fn set_implicit_mult(&mut self, enabled: bool) {
+        self.config.implicit_mult = enabled;
+    }
+
+    /// Imposta la precedenza della moltiplicazione implicita rispetto a `*` e `/` (vedi `ImplicitMultPrecedence`).
+    pub fn set_implicit_mult_precedence(&mut self, precedence: ImplicitMultPrecedence) {
+        self.config.implicit_mult_precedence = precedence;
+    }
+
+    /// Imposta l'associatività degli operatori di potenza e radice (`2^3^2` → 512 o 64).
+    pub fn set_exponent_assoc(&mut self, assoc: ExponentAssoc) {
+        self.config.exponent_assoc = assoc;
+    }
+
+    /// Abilita o disabilita la verifica preliminare del bilanciamento delle parentesi in `evaluate()`.
+    ///
+    /// Con la verifica attiva, una parentesi sbilanciata viene segnalata con `check_balanced` prima
+    /// dell'analisi, anche se l'input contiene altri errori più avanti.
+    pub fn set_balance_check(&mut self, enabled: bool) {
+        self.config.balance_check = enabled;
+    }
+
+    /// Abilita o disabilita le chiamate di funzioni esterne: un identificatore sconosciuto seguito da `(`
+    /// diventa `Expr::UserCall` invece di una moltiplicazione implicita.
+    ///
+    /// Pensata per gli alberi valutati con `Expr::eval_with_callbacks`, che risolve le chiamate con la
+    /// callback `func` (vedi `compile_with_calls`). Le funzioni predefinite e quelle definite con `define`
+    /// hanno comunque la precedenza.
+    pub fn set_external_calls(&mut self, enabled: bool) {
+        self.config.external_calls = enabled;
+    }
+
+    /// Imposta l'aritmetica usata nella valutazione (`f64`, `i64` o `i128`).
+    pub fn set_numeric_mode(&mut self, mode: NumericMode) {
+        self.config.numeric_mode = mode;
+    }
+
+    /// Imposta l'interpretazione degli identificatori formati da una funzione seguita da cifre (es. `sin2`).
+    pub fn set_function_digits_policy(&mut self, policy: FunctionDigitsPolicy) {
+        self.config.function_digits = policy;
+    }
+
+    /// Valuta un'espressione aritmetica completa secondo la grammatica formale.
+    ///
+    /// Questo metodo rappresenta l'ingresso principale per il parsing e la valutazione
+    /// di una formula, seguendo la regola grammaticale:
+    /// ```
+    /// F → S "="
+    /// ```
+    ///
+    /// # Comportamento
+    /// - Analizza e valuta l'istruzione (assegnazione o espressione) tramite `evaluate_statement()`.
+    /// - Verifica la presenza del simbolo `=` alla fine.
+    /// - Restituisce il risultato della valutazione se tutto è corretto, altrimenti segnala un errore.
+    ///
+    /// # Ritorna
+    /// - `Ok(f64)` se l'espressione è valida e terminata correttamente con `=`
+    /// - `Err(CalcError)` in caso di errore sintattico (token inatteso, fine prematura) o semantico;
+    ///   una `)` in eccesso (es. `(1+2)) =`) è segnalata come `UnmatchedParenthesis`
+    /// - `Err(TokenError::SyntaxError)` per una definizione (`define f(x) = 2x =`), che registra la
+    ///   funzione ma non ha un valore: per distinguerla si usa `evaluate_statements()`
+    ///
+    /// # Esempi
+    /// ```
+    /// let mut parser = Parser::new("2 + 3 * 4 =");
+    /// let result = parser.evaluate();
+    /// assert_eq!(result.unwrap(), 14.0);
+    /// ```
+    ///
+    /// ```
+    /// let mut parser = Parser::new("2 + =");
+    /// let result = parser.evaluate();
+    /// assert!(result.is_err()); // Errore: manca un termine dopo '+'
+    /// ```
+    ///
+    /// # Note
+    /// - Il simbolo `=` è obbligatorio come delimitatore finale, ma non partecipa al calcolo.
+    /// - I log interni aiutano a tracciare lo stato della valutazione.
+    pub fn evaluate(&mut self) -> CalcResult {
+        info_log!("Inizio valutazione");
+        if self.config.balance_check {
+            check_balanced(&self.tokens).map_err(|err| self.locate_parenthesis(err))?;
+        }
+        let statement = self.evaluate_statement()?; // Analizza e valuta un'istruzione intera.
+        self.expect_equals()?;
+
+        info_log!("Valutazione completata con successo");
+        statement.into_value()
+    }
+
+    /// Valuta l'espressione come `evaluate()` in aritmetica intera, restituendo il risultato esatto.
+    ///
+    /// I limiti sono quelli di `NumericMode::Int64` se impostata, altrimenti di `i128`.
+    /// A differenza di `evaluate()`, il risultato non passa per `f64`: `25! =` restituisce
+    /// `15511210043330985984000000` senza perdita di precisione.
+    ///
+    /// # Esempio
+    /// ```
+    /// let mut parser = Parser::new("25! =");
+    /// parser.set_numeric_mode(NumericMode::Int128);
+    /// assert_eq!(parser.evaluate_integer().unwrap(), 15_511_210_043_330_985_984_000_000);
+    /// ```
+    pub fn evaluate_integer(&mut self) -> Result<i128, CalcError> {
+        let (target, expr, start) = self.parse_statement()?;
+        let value = self.evaluator().eval_integer(&expr).map_err(|e| self.locate_error(e, start))?;
+        self.assign(target, value as f64);
+        self.expect_equals()?;
+        Ok(value)
+    }
+
+    /// Verifica che l'istruzione appena valutata sia seguita dal simbolo `=` finale.
+    fn expect_equals(&mut self) -> Result<(), CalcError> {
+        match self.peek() {
+            Some(Token::Equals) => Ok(()),
+            Some(_) => {
+                // Errore: token inatteso dopo la fine dell'espressione.
+                error_log!("Token inatteso dopo valutazione: {:?}", self.peek());
+                Err(self.unexpected_token(self.tokens_after_operand(&[TokenKind::Equals])).into())
+            },
+            None => {
+                // Errore: espressione terminata senza '=' esplicito.
+                error_log!("Espressione incompleta alla fine");
+                Err(TokenError::UnexpectedEnd { expected: self.tokens_after_operand(&[TokenKind::Equals]) }.into())
+            }
+        }
+    }
+
+    /// Valuta una lista di istruzioni separate da `;`, restituendo l'esito di ciascuna in ordine.
+    ///
+    /// Questo metodo implementa la regola grammaticale:
+    /// ```
+    /// L → S (";" S)* [";"] ["="]
+    /// ```
+    ///
+    /// # Comportamento
+    /// - Ogni istruzione `S` è un'assegnazione (`x = E`), un'espressione `E` o una definizione (`define`).
+    /// - L'ambiente delle variabili è condiviso: le assegnazioni precedenti sono visibili nelle successive.
+    /// - Il valore di un'assegnazione è il valore assegnato; una definizione produce `Statement::Defined`.
+    /// - La lista termina con un `=` finale o con la fine dell'input; un `;` finale è ammesso.
+    ///
+    /// # Ritorna
+    /// - `Ok(Vec<Statement>)` con un esito per ogni istruzione.
+    /// - `Err(CalcError)` con il primo errore incontrato, anche se le altre istruzioni sono valide.
+    ///
+    /// # Esempio
+    /// ```
+    /// let mut parser = Parser::new("define f(x) = 2x; f(3) =");
+    /// assert_eq!(parser.evaluate_statements()?, vec![Statement::Defined("f".into()), Statement::Value(6.0)]);
+    /// ```
+    pub fn evaluate_statements(&mut self) -> Result<Vec<Statement>, CalcError> {
+        info_log!("Inizio valutazione lista");
+        let mut statements = Vec::new();
+
+        loop {
+            statements.push(self.evaluate_statement()?);
+
+            match self.peek() {
+                // Separatore: prosegue, salvo che la lista termini subito dopo
+                Some(Token::Semicolon) => {
+                    self.advance();
+                    if matches!(self.peek(), None | Some(Token::Equals)) { break; }
+                }
+                Some(Token::Equals) | None => break,
+                Some(_) => {
+                    let expected = self.tokens_after_operand(&[TokenKind::Semicolon, TokenKind::Equals]);
+                    return Err(self.unexpected_token(expected).into());
+                }
+            }
+        }
+
+        // Il '=' finale, se presente, deve chiudere l'input
+        if self.peek() == Some(&Token::Equals) { self.advance(); }
+        if self.peek().is_some() { return Err(self.unexpected_token(Vec::new()).into()); }
+
+        info_log!("Lista valutata: {:?}", statements);
+        Ok(statements)
+    }
+
+    /// Valuta una lista di espressioni separate da `;` come `evaluate_statements`, restituendo tutti i valori in ordine.
+    ///
+    /// # Comportamento
+    /// - Il valore di un'assegnazione è il valore assegnato e compare tra i risultati.
+    /// - Le definizioni (`define`) registrano la funzione ma non compaiono tra i risultati.
+    ///
+    /// # Ritorna
+    /// - `Ok(Vec<f64>)` con un risultato per ogni istruzione che ha un valore.
+    /// - `Err(CalcError)` con il primo errore incontrato, anche se le altre espressioni sono valide.
+    ///
+    /// # Esempio
+    /// ```
+    /// let mut parser = Parser::new("a = 2; a + 1; a ^ 2 =");
+    /// assert_eq!(parser.evaluate_all().unwrap(), vec![2.0, 3.0, 4.0]);
+    /// ```
+    pub fn evaluate_all(&mut self) -> Result<Vec<f64>, CalcError> {
+        let statements = self.evaluate_statements()?;
+        Ok(statements.iter().filter_map(Statement::value).collect())
+    }
+
+    /// Valuta una sequenza di istruzioni separate da `;` come `evaluate_all`, restituendo solo l'ultimo valore.
+    ///
+    /// # Comportamento
+    /// - Le assegnazioni intermedie aggiornano l'ambiente ma il loro valore viene scartato:
+    ///   `a = 3; b = a + 1; b * 2 =` restituisce `8` e lascia `a = 3`, `b = 4` nell'ambiente.
+    /// - Se l'ultima istruzione è un'assegnazione, il risultato è il valore assegnato
+    ///   (`a = 3; b = a + 1 =` → `4`), come per un'assegnazione singola in `evaluate()`.
+    /// - Un `;` finale non aggiunge istruzioni: `a = 3;` restituisce `3`.
+    /// - Le definizioni intermedie registrano la funzione: `define f(x) = 2x; f(3) =` restituisce `6`.
+    ///
+    /// # Ritorna
+    /// - `Ok(f64)` con il valore dell'ultima istruzione.
+    /// - `Err(TokenError::SyntaxError)` se l'ultima istruzione è una definizione, che non ha un valore.
+    /// - `Err(CalcError)` con il primo errore incontrato; le assegnazioni precedenti all'errore restano nell'ambiente.
+    ///
+    /// # Esempio
+    /// ```
+    /// let mut parser = Parser::new("a = 3; b = a + 1; b * 2 =");
+    /// assert_eq!(parser.evaluate_last().unwrap(), 8.0);
+    /// ```
+    pub fn evaluate_last(&mut self) -> CalcResult {
+        let mut statements = self.evaluate_statements()?;
+        statements.pop().expect("evaluate_statements valuta almeno un'istruzione").into_value()
+    }
+
+    /// Valuta una lista di istruzioni come `evaluate_all`, proseguendo dopo gli errori per segnalarli tutti.
+    ///
+    /// # Comportamento
+    /// - Il punto di sincronizzazione è il `;`: dopo un errore i token vengono saltati fino al `;`
+    ///   successivo e la valutazione riprende dall'istruzione seguente.
+    /// - Le assegnazioni riuscite restano nell'ambiente anche se altre istruzioni falliscono.
+    /// - Come in `evaluate_all`, la lista può terminare con `;` e con un `=` finale.
+    ///
+    /// # Ritorna
+    /// - Il valore dell'ultima istruzione, se è stata valutata senza errori (`None` altrimenti, o se è
+    ///   una definizione).
+    /// - Tutti gli errori incontrati, nell'ordine delle istruzioni (vuoto se l'input è valido).
+    ///
+    /// # Esempio
+    /// ```
+    /// let mut parser = Parser::new("1 +; 2 * 3; 4 / 0 =");
+    /// let (value, errors) = parser.evaluate_collecting();
+    /// assert_eq!((value, errors.len()), (None, 2));
+    /// ```
+    pub fn evaluate_collecting(&mut self) -> (Option<f64>, Vec<CalcError>) {
+        info_log!("Inizio valutazione con raccolta degli errori");
+        let mut errors = Vec::new();
+
+        let last = loop {
+            let value = match self.evaluate_statement() {
+                Ok(statement) if self.at_statement_end() => statement.value(),
+                Ok(_) => {
+                    let expected = self.tokens_after_operand(&[TokenKind::Semicolon, TokenKind::Equals]);
+                    errors.push(self.unexpected_token(expected).into());
+                    None
+                }
+                Err(err) => {
+                    errors.push(err);
+                    None
+                }
+            };
+
+            // Sincronizzazione: salta il resto dell'istruzione fino al ';' successivo
+            while !self.at_statement_end() { self.advance(); }
+
+            if self.peek() != Some(&Token::Semicolon) { break value; }
+            self.advance();
+            if matches!(self.peek(), None | Some(Token::Equals)) { break value; }
+        };
+
+        info_log!("Valutazione completata con {} errori", errors.len());
+        (last, errors)
+    }
+
+    /// Verifica se il token corrente chiude un'istruzione della lista: `;`, il `=` finale o la fine dell'input.
+    fn at_statement_end(&self) -> bool {
+        match self.peek() {
+            None | Some(Token::Semicolon) => true,
+            Some(Token::Equals) => self.peek_nth(1).is_none(),
+            Some(_) => false,
+        }
+    }
+
+    /// Valuta l'input in modalità multi-risultato, dove ogni `=` chiude un'espressione e ne apre una nuova.
+    ///
+    /// Questo metodo implementa la regola grammaticale:
+    /// ```
+    /// M → (S "=")+
+    /// ```
+    ///
+    /// # Comportamento
+    /// - Il simbolo `=` viene reinterpretato da terminatore a separatore: `2+3 = 4*5 =` produce due risultati.
+    /// - Ogni espressione deve essere chiusa dal proprio `=`; come in `evaluate()`, la sua assenza è un errore.
+    /// - Le variabili assegnate sono visibili nelle espressioni successive (`x = 2 = x * 3 =` → `[2, 6]`).
+    /// - È una modalità opzionale: `evaluate()` continua a restituire un solo risultato.
+    ///
+    /// # Ritorna
+    /// - `Ok(Vec<f64>)` con un risultato per ogni `=`.
+    /// - `Err(CalcError)` con il primo errore incontrato.
+    ///
+    /// # Esempio
+    /// ```
+    /// let mut parser = Parser::new("2+3 = 4*5 =");
+    /// assert_eq!(parser.evaluate_each().unwrap(), vec![5.0, 20.0]);
+    /// ```
+    pub fn evaluate_each(&mut self) -> Result<Vec<f64>, CalcError> {
+        info_log!("Inizio valutazione multi-risultato");
+        let mut results = Vec::new();
+
+        loop {
+            results.push(self.evaluate()?); // Richiede il '=' finale di ogni espressione
+            self.advance(); // Consuma il '='
+
+            if self.peek().is_none() { break; }
+        }
+
+        info_log!("Risultati: {:?}", results);
+        Ok(results)
+    }
+
+    /// Analizza l'input come singola espressione, senza valutarla.
+    ///
+    /// Questo metodo implementa la regola grammaticale:
+    /// ```
+    /// X → R ["="]
+    /// ```
+    ///
+    /// # Comportamento
+    /// - Il `=` finale è facoltativo, ma se presente deve chiudere l'input.
+    /// - Le assegnazioni non sono ammesse: l'albero viene valutato in seguito con un ambiente esterno.
+    ///
+    /// # Ritorna
+    /// - `Ok(Expr)` con l'albero sintattico dell'espressione.
+    /// - `Err(CalcError)` in caso di errore sintattico.
+    pub fn parse_expression(&mut self) -> Result<Expr, CalcError> {
+        let expr = self.parse_r()?;
+
+        if self.peek() == Some(&Token::Equals) {
+            self.advance();
+            // Dopo il '=' finale non è ammesso alcun token
+            if self.peek().is_some() { return Err(self.unexpected_token(Vec::new()).into()); }
+        } else if self.peek().is_some() {
+            return Err(self.unexpected_token(self.tokens_after_operand(&[TokenKind::Equals])).into());
+        }
+
+        info_log!("Espressione analizzata: {:?}", expr);
+        Ok(expr)
+    }
+
+    /// Analizza e valuta un'istruzione: un'assegnazione di variabile oppure un'espressione.
+    ///
+    /// Questo metodo implementa la regola grammaticale:
+    /// ```
+    /// S → "define" identifier "(" [identifier ("," identifier)*] ")" "=" R
+    ///    | identifier "=" R
+    ///    | R
+    /// ```
+    ///
+    /// # Comportamento
+    /// - `identifier "="` è un'assegnazione solo se dopo `=` segue un'espressione; in `x =` il
+    ///   simbolo `=` resta il terminatore e `x` viene valutata.
+    /// - L'espressione viene prima analizzata per intero (`parse_statement`), poi valutata nell'ambiente
+    ///   corrente con l'aritmetica di `NumericMode`.
+    /// - L'assegnazione salva il valore nell'ambiente e lo restituisce.
+    /// - Una definizione (`parse_definition`) registra la funzione senza valutarla: non ha un valore
+    ///   numerico e restituisce `Statement::Defined`.
+    fn evaluate_statement(&mut self) -> Result<Statement, CalcError> {
+        if self.at_definition() {
+            return self.parse_definition().map(Statement::Defined);
+        }
+
+        let (target, expr, start) = self.parse_statement()?;
+        let value = match self.config.numeric_mode {
+            NumericMode::Float => self.evaluator().eval(&expr),
+            NumericMode::Int64 | NumericMode::Int128 => self.evaluator().eval_integer(&expr).map(|n| n as f64),
+        };
+        let value = value.map_err(|e| self.locate_error(e, start))?;
+
+        self.assign(target, value);
+        Ok(Statement::Value(value))
+    }
+
+    /// Analizza un'istruzione senza valutarla.
+    ///
+    /// # Ritorna
+    /// La variabile da assegnare (se presente), l'albero dell'espressione e l'indice del suo primo token,
+    /// usato da `locate_error`.
+    fn parse_statement(&mut self) -> Result<(Option<String>, Expr, usize), CalcError> {
+        let mut target = None;
+
+        if let (Some(Token::Identifier(name)), Some(Token::Equals)) = (self.peek(), self.peek_nth(1))
+            && !matches!(self.peek_nth(2), None | Some(Token::Equals) | Some(Token::Semicolon))
+        {
+            target = Some(name.clone());
+            self.position += 2; // Consuma identificatore e '='
+        }
+
+        let start = self.position;
+        let expr = self.parse_r()?;
+        Ok((target, expr, start))
+    }
+
+    /// Verifica se l'istruzione corrente è una definizione di funzione: `define`, un nome e `(`.
+    ///
+    /// Altrove `define` resta un identificatore qualsiasi (es. una variabile).
+    fn at_definition(&self) -> bool {
+        matches!(self.peek(), Some(Token::Identifier(keyword)) if keyword == DEFINE_KEYWORD)
+            && matches!(self.peek_nth(1), Some(Token::Identifier(_)))
+            && self.peek_nth(2) == Some(&Token::LeftParen)
+    }
+
+    /// Analizza la definizione di una funzione e la registra nella tabella delle funzioni.
+    ///
+    /// # Comportamento
+    /// - I parametri sono nomi distinti, anche nessuno (`define k() = 42`).
+    /// - Il corpo non viene valutato: le variabili che non sono parametri vengono risolte
+    ///   nell'ambiente al momento della chiamata.
+    /// - La funzione è visibile nel proprio corpo, quindi può richiamare sé stessa; la profondità
+    ///   delle chiamate è limitata da `MAX_CALL_DEPTH`.
+    /// - Una nuova definizione con lo stesso nome sostituisce la precedente.
+    ///
+    /// # Ritorna
+    /// - `Ok(String)` con il nome della funzione, se la definizione è valida.
+    /// - `Err(TokenError::SyntaxError)` se il nome è una funzione o costante predefinita, se i parametri
+    ///   non sono nomi distinti o se manca `=` dopo l'intestazione.
+    /// - Gli errori di sintassi del corpo; in questo caso la tabella resta invariata.
+    fn parse_definition(&mut self) -> Result<String, CalcError> {
+        self.advance(); // Consuma 'define'
+        let Some(Token::Identifier(name)) = self.next() else {
+            unreachable!("at_definition garantisce il nome della funzione");
+        };
+        if Function::from_name(&name).is_some() || constant(&name).is_some() {
+            let message = format!("'{}' è una funzione o costante predefinita e non può essere ridefinita", name);
+            return Err(TokenError::SyntaxError(message).into());
+        }
+
+        let open = self.position;
+        self.advance(); // Consuma '('
+        let mut params: Vec<String> = Vec::new();
+        while self.peek() != Some(&Token::RightParen) {
+            match self.next() {
+                Some(Token::Identifier(param)) if !params.contains(&param) => params.push(param),
+                Some(Token::Identifier(param)) => {
+                    let message = format!("Il parametro '{}' compare più volte nella definizione di '{}'", param, name);
+                    return Err(TokenError::SyntaxError(message).into());
+                }
+                _ => {
+                    let message = format!("I parametri della funzione '{}' devono essere nomi separati da ','", name);
+                    return Err(TokenError::SyntaxError(message).into());
+                }
+            }
+            if self.peek() != Some(&Token::Comma) { break; }
+            self.advance();
+        }
+        self.expect_right_paren(open)?;
+
+        if self.next() != Some(Token::Equals) {
+            let message = format!("Manca '=' dopo l'intestazione della funzione '{}'", name);
+            return Err(TokenError::SyntaxError(message).into());
+        }
+
+        // La funzione è registrata prima del corpo, così che possa richiamare sé stessa
+        let previous = self.functions.define(name.clone(), params.clone(), Expr::Number(f64::NAN));
+        match self.parse_r() {
+            Ok(body) => {
+                info_log!("Funzione definita: {}({}) = {:?}", name, params.join(", "), body);
+                self.functions.define(name.clone(), params, body);
+                Ok(name)
+            }
+            Err(err) => {
+                match previous {
+                    Some(previous) => self.functions.define(name, previous.params, previous.body),
+                    None => self.functions.remove(&name),
+                };
+                Err(err)
+            }
+        }
+    }
+
+    /// Salva nell'ambiente il valore di un'assegnazione, se l'istruzione ne contiene una.
+    fn assign(&mut self, target: Option<String>, value: f64) {
+        if let Some(name) = target {
+            info_log!("Assegnazione: {} = {}", name, value);
+            self.env.insert(name, value);
+        }
+    }
+
+    /// Costruisce il valutatore con l'ambiente e le impostazioni correnti del parser.
+    fn evaluator(&self) -> Evaluator<'_> {
+        let evaluator = match self.config.step_budget {
+            Some(budget) => Evaluator::new(&self.env).with_step_budget(&self.steps, budget),
+            None => Evaluator::new(&self.env),
+        };
+        evaluator
+            .with_functions(&self.functions)
+            .with_angle_mode(self.config.angle_mode)
+            .with_div_by_zero_mode(self.config.div_by_zero)
+            .with_overflow_mode(self.config.overflow_mode)
+            .with_zero_power_mode(self.config.zero_power)
+            .with_modulo_mode(self.config.modulo)
+            .with_numeric_mode(self.config.numeric_mode)
+            .with_max_magnitude(self.config.max_magnitude)
+    }
+
+    /// Completa un errore di valutazione con la posizione nel sorgente, ove possibile.
+    ///
+    /// L'albero sintattico non conserva le posizioni: per `TokenError::UnknownVariable` si cerca
+    /// la prima occorrenza della variabile tra i token dell'istruzione appena analizzata (da `start`).
+    fn locate_error(&self, error: CalcError, start: usize) -> CalcError {
+        match error {
+            CalcError::Token(TokenError::UnknownVariable { name, position: None }) => {
+                let position = (start..self.position)
+                    .find(|&i| matches!(&self.tokens[i], Token::Identifier(id) if *id == name))
+                    .map(|i| self.source_position(i));
+                TokenError::UnknownVariable { name, position }.into()
+            }
+            other => other,
+        }
+    }
+
+    /// Valuta l'espressione come `evaluate()`, restituendo anche il tipo numerico del risultato.
+    ///
+    /// Nella modalità `f64` un risultato senza parte frazionaria è classificato `ResultKind::Integer`,
+    /// ogni altro valore `ResultKind::Float`.
+    ///
+    /// # Esempio
+    /// ```
+    /// let mut parser = Parser::new("6 / 4 =");
+    /// assert_eq!(parser.evaluate_with_kind().unwrap().kind, ResultKind::Float);
+    /// ```
+    pub fn evaluate_with_kind(&mut self) -> Result<EvalOutput, CalcError> {
+        self.evaluate().map(EvalOutput::from_f64)
+    }
+
+    /// Valuta l'espressione e arrotonda il risultato al numero "gradevole" più vicino.
+    ///
+    /// Equivale a `evaluate()` seguito da `nice_round()`.
+    ///
+    /// # Esempio
+    /// ```
+    /// let mut parser = Parser::new("8.6 / 2 =");
+    /// assert_eq!(parser.evaluate_nice().unwrap(), 5.0); // 4.3 → 5
+    /// ```
+    pub fn evaluate_nice(&mut self) -> CalcResult {
+        self.evaluate().map(nice_round)
+    }
+
+    /// Analizza un confronto tra due espressioni aritmetiche, oppure una singola espressione.
+    ///
+    /// Questo metodo implementa la regola grammaticale:
+    /// ```
+    /// R → E [("<" | "<=" | ">" | ">=" | "==") E]
+    /// ```
+    ///
+    /// # Comportamento
+    /// - Il confronto ha la precedenza minima e vale `1` se vero, `0` se falso (`3 > 2` → `1`).
+    /// - Non è associativo: in `1 < 2 < 3` il secondo `<` è un token inatteso; si usano le parentesi.
+    ///
+    /// # Ritorna
+    /// - `Ok(Expr)` con l'albero del confronto o dell'espressione.
+    /// - `Err(CalcError)` in caso di errore sintattico.
+    fn parse_r(&mut self) -> Result<Expr, CalcError> {
+        let lhs = self.parse_e()?; // Left-Hand Side
+
+        let Some(op) = self.peek().and_then(BinaryOp::comparison) else { return Ok(lhs); };
+        self.advance();
+
+        let rhs = self.parse_e()?; // Right-Hand Side
+        Ok(Expr::Binary(op, Box::new(lhs), Box::new(rhs)))
+    }
+
+    /// Analizza un'espressione aritmetica che può contenere somme e sottrazioni tra termini.
+    ///
+    /// Questo metodo implementa la regola grammaticale:
+    /// ```
+    /// E → P E'
+    /// ```
+    ///
+    /// # Comportamento
+    /// - Analizza un primo termine `P` tramite `parse_p()`.
+    /// - Successivamente, passa l'albero parziale a `parse_e_prime()` per gestire eventuali
+    ///   somme o sottrazioni definite nella produzione `E'`.
+    /// - L'espressione termina quando non ci sono più operatori `+` o `−`.
+    ///
+    /// # Ritorna
+    /// - `Ok(Expr)` con l'albero dell'espressione analizzata.
+    /// - `Err(CalcError)` in caso di errore sintattico.
+    ///
+    /// # Esempi
+    /// ```
+    /// let mut parser = Parser::new("3 + 2 =");
+    /// assert_eq!(parser.parse_e().unwrap(), Expr::Binary(BinaryOp::Add, Box::new(Expr::Number(3.0)), Box::new(Expr::Number(2.0))));
+    /// ```
+    fn parse_e(&mut self) -> Result<Expr, CalcError> {
+        let lhs = self.parse_p()?;
+        self.parse_e_prime(lhs)
+    }
+
+    /// Analizza la parte ricorsiva di un'espressione (`E'`) che gestisce somme e sottrazioni.
+    ///
+    /// Questo metodo implementa la regola grammaticale:
+    /// ```
+    /// E' → "+" P E'
+    ///     | "−" P E'
+    ///     | ε
+    /// ```
+    ///
+    /// # Parametri
+    /// - `acc`: L'albero accumulato finora, risultato dell'analisi di `P` in `E → P E'`.
+    ///
+    /// # Comportamento
+    /// - In un ciclo, controlla se il token corrente è un operatore `+` o `−`.
+    /// - In entrambi i casi analizza il termine successivo `P` e lo combina con l'albero accumulato,
+    ///   ottenendo l'associatività a sinistra (`5 - 3 - 2` → `(5 - 3) - 2`).
+    /// - Se il prossimo token non è un operatore, la funzione termina e restituisce l'albero accumulato.
+    ///
+    /// # Ritorna
+    /// - `Ok(Expr)` con l'albero aggiornato dell'espressione.
+    /// - `Err(CalcError)` in caso di errore sintattico.
+    fn parse_e_prime(&mut self, mut acc: Expr) -> Result<Expr, CalcError> {
+        loop {
+            let op = match self.peek() {
+                Some(Token::Plus) => BinaryOp::Add,
+                Some(Token::Minus) => BinaryOp::Sub,
+                _ => break,
+            };
+            self.advance();
+
+            let rhs = self.parse_p()?; // Right-Hand Side
+            acc = Expr::Binary(op, Box::new(acc), Box::new(rhs));
+        }
+        // Restituisce l'albero accumulato
+        Ok(acc)
+    }
+
+    /// Analizza una parte dell'espressione che rappresenta un prodotto, che può includere:
+    /// - Operazioni esplicite di moltiplicazione (`*`) e divisione (`/`)
+    /// - Moltiplicazioni implicite (es. `2(3+4)` → `2 * (3+4)`)
+    ///
+    /// Questo metodo implementa la regola grammaticale:
+    /// ```
+    /// P → U P'
+    /// ```
+    ///
+    /// # Comportamento
+    /// - Chiama `parse_u()` per analizzare la prima unità dell'espressione.
+    /// - Passa l'albero a `parse_p_prime()` per gestire le operazioni successive.
+    ///
+    /// # Ritorna
+    /// - `Ok(Expr)` con l'albero del prodotto.
+    /// - `Err(CalcError)` in caso di errore sintattico.
+    fn parse_p(&mut self) -> Result<Expr, CalcError> {
+        let lhs = self.parse_u()?;
+        self.parse_p_prime(lhs)
+    }
+
+    /// Analizza le operazioni successive di prodotto, inclusi:
+    /// - Moltiplicazione esplicita (`*`)
+    /// - Divisione (`/`)
+    /// - Moltiplicazione implicita (es. `2(3 + 1)` → `2 * (3 + 1)`)
+    ///
+    /// Questo metodo implementa la regola grammaticale:
+    /// ```
+    /// P' → "*" U P'
+    ///     | "/" U P'
+    ///     | ImplicitMult U P'
+    ///     | ε
+    /// ```
+    ///
+    /// # Comportamento
+    /// - Percorre tutti i token che rappresentano una continuazione di `P`.
+    /// - Per `*` o `/`, analizza la parte a destra (`U`) e la combina con l'albero accumulato.
+    /// - Se trova un numero, un identificatore o una parentesi aperta immediatamente dopo un termine valido,
+    ///   applica la regola della *moltiplicazione implicita*, se abilitata (`set_implicit_mult`).
+    /// - Con `ImplicitMultPrecedence::Tighter` l'operando destro di `*` e `/` è una catena di fattori
+    ///   giustapposti (`parse_juxtaposition`): `6/2(1+2)` → `6 / (2 * (1+2))`.
+    /// - L’arresto avviene al primo token che non corrisponde a una continuazione valida.
+    ///
+    /// # Ritorna
+    /// - `Ok(Expr)` con l'albero aggiornato.
+    /// - `Err(CalcError)` in caso di errore sintattico.
+    ///
+    /// # Esempi
+    /// ```
+    /// let mut parser = Parser::new("5(2 + 1) =");
+    /// assert_eq!(parser.parse_p().unwrap().eval(&Env::new()).unwrap(), 15.0);  // moltiplicazione implicita
+    /// ```
+    fn parse_p_prime(&mut self, mut acc: Expr) -> Result<Expr, CalcError> {
+        loop {
+            let (op, explicit) = match self.peek() {
+                // Gestione esplicita di moltiplicazione e divisione: consuma il token
+                Some(Token::Multiply) => { self.advance(); (BinaryOp::Mul, true) }
+                Some(Token::Divide) => { self.advance(); (BinaryOp::Div, true) }
+                // Moltiplicazione implicita: es. `2(3 + 4)`, `4 5` o `2x`
+                _ if self.at_implicit_multiplication() => (BinaryOp::Mul, false),
+                _ => break,
+            };
+
+            // Right-Hand Side
+            let rhs = if explicit && self.config.implicit_mult_precedence == ImplicitMultPrecedence::Tighter {
+                self.parse_juxtaposition()?
+            } else {
+                self.parse_u()?
+            };
+            acc = Expr::Binary(op, Box::new(acc), Box::new(rhs));
+        }
+        Ok(acc)
+    }
+
+    /// Analizza un'unità seguita dai fattori giustapposti, moltiplicati implicitamente (es. `2(1+2)`, `3y`).
+    ///
+    /// Usato come operando destro di `*` e `/` con `ImplicitMultPrecedence::Tighter`.
+    fn parse_juxtaposition(&mut self) -> Result<Expr, CalcError> {
+        let mut acc = self.parse_u()?;
+        while self.at_implicit_multiplication() {
+            let rhs = self.parse_u()?;
+            acc = Expr::Binary(BinaryOp::Mul, Box::new(acc), Box::new(rhs));
+        }
+        Ok(acc)
+    }
+
+    /// Verifica se la posizione corrente è una moltiplicazione implicita: abilitata, dopo un termine
+    /// valido e prima di un numero, un identificatore o una parentesi aperta.
+    fn at_implicit_multiplication(&self) -> bool {
+        self.config.implicit_mult && self.previous_token_is_paren_or_number() && self.can_apply_implicit_multiplication()
+    }
+
+    /// Verifica se il token precedente è un numero, un identificatore o una parentesi chiusa.
+    ///
+    /// Questo metodo è utilizzato per determinare se una moltiplicazione implicita
+    /// può essere applicata. La moltiplicazione implicita avviene, ad esempio,
+    /// in espressioni come `2(3 + 1)`, `4 5` o `2x`, dove non è presente esplicitamente
+    /// l'operatore `*`.
+    ///
+    /// # Ritorna
+    /// - `true` se il token precedente è `Token::Number(_)`, `Token::Identifier(_)` o `Token::RightParen`.
+    /// - `false` altrimenti.
+    fn previous_token_is_paren_or_number(&self) -> bool {
+        matches!(
+            self.tokens.get(self.position.wrapping_sub(1)),
+            Some(Token::Number(_)) | Some(Token::Identifier(_)) | Some(Token::RightParen)
+        )
+    }
+
+    /// Verifica se il token corrente può rappresentare un termine valido
+    /// per una moltiplicazione implicita.
+    ///
+    /// Questo metodo viene tipicamente chiamato subito dopo `previous_token_is_paren_or_number`
+    /// per decidere se applicare una moltiplicazione implicita tra due elementi contigui.
+    ///
+    /// # Ritorna
+    /// - `true` se il token corrente è `Token::Number(_)`, `Token::Identifier(_)`, `Token::LeftParen` o `Token::Radical`.
+    /// - `false` altrimenti.
+    fn can_apply_implicit_multiplication(&self) -> bool {
+        matches!(self.peek(), Some(Token::Number(_)) | Some(Token::Identifier(_)) | Some(Token::LeftParen) | Some(Token::Radical))
+    }
+
+    /// Analizza un'unità dell'espressione aritmetica, che può essere soggetta a esponenti o radici.
+    ///
+    /// Questo metodo implementa la regola grammaticale:
+    /// ```
+    /// U → "−" U
+    ///    | B U'
+    /// ```
+    ///
+    /// # Comportamento
+    /// - Se il token corrente è un operatore di negazione (`Token::Minus`), l'unità successiva viene analizzata e negata.
+    ///   La negazione lega meno della potenza, come nella notazione matematica: `-2^2` → `-(2^2)` = -4,
+    ///   mentre `(-2)^2` = 4.
+    /// - Altrimenti analizza la base tramite `parse_b()` e applica eventuali esponenti o radici tramite `parse_u_prime(base)`.
+    ///
+    /// # Ritorna
+    /// - `Ok(Expr)` con l'albero dell'unità.
+    /// - `Err(CalcError)` in caso di errori sintattici.
+    fn parse_u(&mut self) -> Result<Expr, CalcError> {
+        if self.peek() == Some(&Token::Minus) {
+            self.advance();
+            let operand = self.parse_u()?; // Negazione dell'intera potenza
+            return Ok(Expr::Unary(UnaryOp::Neg, Box::new(operand)));
+        }
+
+        let base = self.parse_b()?;
+        self.parse_u_prime(base)
+    }
+
+    /// Analizza una base eventualmente negata, senza gli esponenti che la seguono.
+    ///
+    /// È l'operando destro di `^` e `$` con `ExponentAssoc::Left` (`2^-1^2` → `(2^(-1))^2`):
+    /// ```
+    /// N → "−" N
+    ///    | B
+    /// ```
+    fn parse_signed_b(&mut self) -> Result<Expr, CalcError> {
+        if self.peek() == Some(&Token::Minus) {
+            self.advance();
+            let operand = self.parse_signed_b()?;
+            return Ok(Expr::Unary(UnaryOp::Neg, Box::new(operand)));
+        }
+        self.parse_b()
+    }
+
+    /// Analizza gli operatori di potenza o radice applicati alla base già analizzata.
+    ///
+    /// Questo metodo implementa la regola grammaticale:
+    /// ```
+    /// U' → "^" U
+    ///     | "$" U
+    ///     | ε
+    /// ```
+    ///
+    /// # Comportamento
+    /// - Se il token corrente è `^`, analizza ricorsivamente la parte a destra e costruisce la potenza (`base ^ esponente`).
+    /// - Se il token corrente è `$`, analizza ricorsivamente la parte a destra e costruisce la radice (`base $ indice`).
+    /// - La ricorsione su `U` rende entrambi gli operatori associativi a destra (`2^3^2` → `2^(3^2)`).
+    /// - Con `ExponentAssoc::Left` gli operatori vengono invece applicati da sinistra a destra,
+    ///   analizzando solo la base successiva, eventualmente negata (`2^3^2` → `(2^3)^2`).
+    /// - In caso di token non compatibile, restituisce la base senza modificarla (ε).
+    ///
+    /// Le validazioni numeriche (base negativa con esponente frazionario, radice pari di un numero
+    /// negativo, indice zero, overflow) avvengono durante la valutazione.
+    ///
+    /// # Parametri
+    /// - `base`: l'albero su cui applicare l'operatore.
+    ///
+    /// # Ritorna
+    /// - `Ok(Expr)` con l'albero risultante.
+    /// - `Err(CalcError)` in caso di errore sintattico.
+    fn parse_u_prime(&mut self, mut base: Expr) -> Result<Expr, CalcError> {
+        loop {
+            let op = match self.peek() {
+                Some(Token::Caret) => BinaryOp::Pow,
+                Some(Token::Dollar) => BinaryOp::Root,
+                _ => return Ok(base),
+            };
+            self.advance();
+
+            if self.config.exponent_assoc == ExponentAssoc::Right {
+                let rhs = self.parse_u()?; // Right-Hand Side
+                return Ok(Expr::Binary(op, Box::new(base), Box::new(rhs)));
+            }
+
+            let rhs = self.parse_signed_b()?; // Right-Hand Side
+            base = Expr::Binary(op, Box::new(base), Box::new(rhs));
+        }
+    }
+
+    /// Analizza un "fattore" nell'espressione aritmetica, che può essere:
+    /// - Un numero senza segno (es. `3.14`)
+    /// - Una variabile o una costante (es. `x`, `pi`)
+    /// - La chiamata di una funzione predefinita (es. `sqrt(16)`)
+    /// - Un'espressione tra parentesi tonde (es. `(2 + 3)`)
+    ///
+    /// Ogni fattore può essere seguito dal fattoriale o da funzioni unarie
+    /// in forma postfissa (es. `5!`, `16 sqrt`). La negazione è gestita da `parse_u()`.
+    ///
+    /// Questo metodo implementa la regola grammaticale:
+    /// ```
+    /// B → C F*
+    /// C → unsigned number
+    ///    | identifier
+    ///    | function "(" R ("," R)* ")"
+    ///    | ("√" | "$") B
+    ///    | "(" R ")"
+    /// ```
+    ///
+    /// # Comportamento
+    /// - Se il token corrente è un numero (`Token::Number`), restituisce il numero letterale.
+    /// - Se il token corrente è `√` o `$`, restituisce la radice quadrata del fattore successivo
+    ///   (`$9` = `9 $ 2`): in questa posizione `$` non ha una base ed è quindi prefisso, non la radice n-esima.
+    /// - Se il token corrente è il nome di una funzione predefinita, ne analizza la chiamata con `parse_call()`.
+    /// - Se il token corrente è il nome di una funzione definita con `define` seguito da `(`, ne analizza
+    ///   la chiamata con `parse_user_call()`; lo stesso vale per ogni nome seguito da `(` se le chiamate
+    ///   esterne sono abilitate (`set_external_calls`).
+    /// - Se il token corrente è un altro identificatore, restituisce il riferimento alla variabile,
+    ///   risolto solo durante la valutazione.
+    /// - Se il token corrente è una parentesi aperta `(`, viene analizzata un'espressione tramite il metodo `parse_r()` fino a trovare la parentesi chiusa `)`.
+    /// - Se viene trovato un operatore binario al posto di un operando (es. `2 ++ 3`), viene restituito `TokenError::MissingOperand`.
+    ///   Lo stesso errore segnala un operatore binario in sospeso alla fine dell'input (es. `2 +`), alla posizione dell'operatore.
+    /// - Se viene trovato un token inatteso (come una parentesi chiusa senza apertura o un altro token errato), viene restituito un errore.
+    /// - In caso di un errore generale (token non valido), viene restituito un errore di sintassi.
+    ///
+    /// # Ritorna
+    /// - `Ok(Expr)` con l'albero del fattore.
+    /// - `Err(TokenError)` se viene trovato un errore di sintassi (token inatteso, parentesi non corrispondenti, ecc.).
+    ///
+    /// # Esempi
+    /// ```
+    /// let mut parser = Parser::new("2.5 =");
+    /// assert_eq!(parser.parse_b().unwrap(), Expr::Number(2.5));
+    /// ```
+    fn parse_b(&mut self) -> Result<Expr, CalcError> {
+        let primary = match self.next() {
+            // Caso di numero: restituisce il numero letterale
+            Some(Token::Number(n)) => Expr::Number(n),
+
+            // Caso di identificatore: chiamata prefissa di una funzione predefinita,
+            // altrimenti variabile il cui valore viene risolto durante la valutazione
+            Some(Token::Identifier(name)) => match Function::from_name(&name) {
+                Some(function) => self.parse_call(function)?,
+                None if self.peek() == Some(&Token::LeftParen) && (self.functions.contains(&name) || self.config.external_calls) => {
+                    self.parse_user_call(name)?
+                }
+                None => self.identifier_expr(name),
+            },
+
+            // Caso di parentesi aperta: analizza l'espressione tra parentesi
+            Some(Token::LeftParen) => {
+                let open = self.position - 1;
+                let inner = self.parse_r()?;  // Analizza l'espressione tra parentesi
+                self.expect_right_paren(open)?;
+                inner
+            },
+
+            // Caso di radice quadrata prefissa: si applica al fattore successivo (es. "√16", "$9", "√(9)", "√√16").
+            // Dove è atteso un operando `$` non può essere la radice n-esima infissa (`27 $ 3`), a cui manca la base:
+            // come `√` indica la radice quadrata, valutata da `evaluate_root` con indice 2
+            Some(Token::Radical | Token::Dollar) => {
+                let radicand = self.parse_b()?;
+                Expr::Binary(BinaryOp::Root, Box::new(radicand), Box::new(Expr::Number(2.0)))
+            }
+
+            // Caso di lista fuori dagli argomenti di una funzione di aggregazione (es. "[1, 2] =")
+            Some(Token::LeftBracket) => {
+                let message = "Una lista tra parentesi quadre può comparire solo come argomento di sum, product, mean, min o max";
+                return Err(TokenError::SyntaxError(message.into()).into());
+            },
+
+            // Caso di operatore binario dove era atteso un operando (es. "2 ++ 3", "(* 3)" o "* 3")
+            Some(token) if token.is_operator() => {
+                return Err(self.missing_operand(token).into());
+            }
+
+            // Caso di 'of' senza una percentuale che lo precede (es. "of 50")
+            Some(Token::Of) => {
+                return Err(self.misplaced_of(self.position - 1).into());
+            }
+
+            // Caso di parentesi chiusa senza corrispondente parentesi aperta
+            Some(Token::RightParen) => {
+                info_log!("Parentesi chiusa senza apertura");
+                let position = self.source_position(self.position - 1);
+                return Err(TokenError::UnmatchedParenthesis { found: ')', position, expected: Self::operand_tokens() }.into());
+            },
+
+            // Caso di token che non può iniziare un operando (es. "2 + ;")
+            Some(token) => {
+                info_log!("Fattore non valido trovato: {:?}", token);
+                let position = self.source_position(self.position - 1);
+                return Err(TokenError::UnexpectedToken { token, position, expected: Self::operand_tokens() }.into());
+            }
+
+            // Caso di input terminato dove era atteso un operando: dopo un operatore binario (es. "2 +")
+            // manca il suo operando destro, segnalato alla posizione dell'operatore rimasto in sospeso
+            None => {
+                info_log!("Input terminato al posto di un operando");
+                return Err(match self.position.checked_sub(1).map(|index| (index, &self.tokens[index])) {
+                    Some((index, operator)) if operator.is_operator() => TokenError::MissingOperand {
+                        operator: operator.clone(),
+                        side: OperandSide::Right,
+                        position: self.source_position(index),
+                        expected: Self::operand_tokens(),
+                    },
+                    _ => TokenError::UnexpectedEnd { expected: Self::operand_tokens() },
+                }.into());
+            }
+        };
+
+        self.parse_postfix(primary)
+    }
+
+    /// Costruisce il fattore per un identificatore che non è il nome di una funzione.
+    ///
+    /// Con `FunctionDigitsPolicy::Split`, un nome come `sin2` diventa la chiamata `sin(2)`;
+    /// altrimenti l'identificatore è una variabile.
+    fn identifier_expr(&self, name: String) -> Expr {
+        if self.config.function_digits == FunctionDigitsPolicy::Split
+            && let Some((function, value)) = split_function_digits(&name)
+            && function.is_unary()
+        {
+            info_log!("Identificatore '{}' diviso in {}({})", name, function, value);
+            return Expr::Call(function, vec![Expr::Number(value)]);
+        }
+        Expr::Variable(name)
+    }
+
+    /// Analizza la chiamata in forma prefissa di una funzione il cui nome è appena stato consumato.
+    ///
+    /// Questo metodo implementa la regola grammaticale:
+    /// ```
+    /// C → function "(" R ("," R)* ")"
+    /// ```
+    ///
+    /// Per le funzioni trigonometriche è ammessa anche la notazione `sin^2(x)`, che eleva il risultato
+    /// della funzione: `function "^" unsigned number "(" R ")"` equivale a `(function(R))^n`.
+    /// L'esponente deve essere un numero senza segno, così `sin^-1` (l'arcoseno in alcune notazioni) resta un errore.
+    ///
+    /// # Ritorna
+    /// - `Ok(Expr::Call)` con gli argomenti analizzati (o la sua potenza per `sin^2(x)`).
+    /// - `Err(TokenError::SyntaxError)` se il nome non è seguito da `(` (es. `sqrt 16`)
+    ///   o se il numero di argomenti non corrisponde a `Function::arity()`.
+    /// - `Err(TokenError::UnmatchedParenthesis)` se manca la parentesi chiusa.
+    fn parse_call(&mut self, function: Function) -> Result<Expr, CalcError> {
+        if function.is_trigonometric()
+            && self.peek() == Some(&Token::Caret)
+            && let Some(&Token::Number(exponent)) = self.peek_nth(1)
+            && self.peek_nth(2) == Some(&Token::LeftParen)
+        {
+            self.position += 2; // Consuma '^' e l'esponente
+            info_log!("Potenza di funzione: {}^{}", function, exponent);
+            let call = self.parse_call(function)?;
+            return Ok(Expr::Binary(BinaryOp::Pow, Box::new(call), Box::new(Expr::Number(exponent))));
+        }
+
+        if function.is_aggregate() { return self.parse_aggregate_call(function); }
+
+        if self.peek() != Some(&Token::LeftParen) {
+            let message = format!("La funzione '{}' richiede gli argomenti tra parentesi", function);
+            return Err(TokenError::SyntaxError(message).into());
+        }
+        let args = self.parse_arguments()?;
+
+        let arity = function.arity();
+        if !arity.contains(&args.len()) {
+            let expected = if arity.start() == arity.end() {
+                arity.start().to_string()
+            } else {
+                format!("da {} a {}", arity.start(), arity.end())
+            };
+            let message = format!("La funzione '{}' richiede {} argomenti, trovati {}", function, expected, args.len());
+            return Err(TokenError::SyntaxError(message).into());
+        }
+
+        Ok(Expr::Call(function, args))
+    }
+
+    /// Analizza la chiamata di una funzione di aggregazione, il cui unico argomento è una lista.
+    ///
+    /// Questo metodo implementa la regola grammaticale:
+    /// ```
+    /// C → aggregate "(" "[" R ("," R)* "]" ")"
+    /// ```
+    ///
+    /// Gli elementi della lista diventano gli argomenti della chiamata: `mean([1, 2])` produce lo
+    /// stesso albero di una funzione a due argomenti.
+    ///
+    /// # Ritorna
+    /// - `Ok(Expr::Call)` con un argomento per ogni elemento.
+    /// - `Err(TokenError::SyntaxError)` se l'argomento non è una lista, se la lista è vuota o non è chiusa da `]`.
+    /// - `Err(TokenError::UnmatchedParenthesis)` se manca la parentesi chiusa.
+    fn parse_aggregate_call(&mut self, function: Function) -> Result<Expr, CalcError> {
+        if self.peek() != Some(&Token::LeftParen) || self.peek_nth(1) != Some(&Token::LeftBracket) {
+            let message = format!("La funzione '{}' richiede una lista tra parentesi quadre, es. '{}([1, 2, 3])'", function, function);
+            return Err(TokenError::SyntaxError(message).into());
+        }
+        let open = self.position;
+        self.position += 2; // Consuma '(' e '['
+
+        if self.peek() == Some(&Token::RightBracket) {
+            let message = format!("La funzione '{}' richiede una lista con almeno un elemento", function);
+            return Err(TokenError::SyntaxError(message).into());
+        }
+        let mut items = vec![self.parse_r()?];
+        while self.peek() == Some(&Token::Comma) {
+            self.advance();
+            items.push(self.parse_r()?);
+        }
+
+        if self.peek() != Some(&Token::RightBracket) {
+            let message = format!("Manca ']' alla fine della lista di '{}'", function);
+            return Err(TokenError::SyntaxError(message).into());
+        }
+        self.advance(); // Consuma ']'
+        self.expect_right_paren(open)?;
+
+        Ok(Expr::Call(function, items))
+    }
+
+    /// Analizza la chiamata di una funzione definita con `define` o, con `set_external_calls`, di una
+    /// funzione esterna, il cui nome è appena stato consumato.
+    ///
+    /// Le funzioni senza parametri si chiamano con le parentesi vuote (`k()`).
+    ///
+    /// # Ritorna
+    /// - `Ok(Expr::UserCall)` con gli argomenti analizzati.
+    /// - `Err(TokenError::SyntaxError)` se il numero di argomenti non corrisponde ai parametri di una
+    ///   funzione definita con `define`.
+    fn parse_user_call(&mut self, name: String) -> Result<Expr, CalcError> {
+        let args = if self.peek_nth(1) == Some(&Token::RightParen) {
+            self.position += 2; // Consuma '(' e ')'
+            Vec::new()
+        } else {
+            self.parse_arguments()?
+        };
+
+        // Il numero di argomenti di una funzione esterna è verificato dalla callback che la risolve
+        if let Some(function) = self.functions.get(&name) && args.len() != function.params.len() {
+            let message = format!("La funzione '{}' richiede {} argomenti, trovati {}", name, function.params.len(), args.len());
+            return Err(TokenError::SyntaxError(message).into());
+        }
+
+        Ok(Expr::UserCall(name, args))
+    }
+
+    /// Analizza gli argomenti di una chiamata, dalla `(` iniziale alla `)` finale.
+    ///
+    /// ```
+    /// A → "(" R ("," R)* ")"
+    /// ```
+    fn parse_arguments(&mut self) -> Result<Vec<Expr>, CalcError> {
+        let open = self.position;
+        self.advance(); // Consuma '('
+
+        let mut args = vec![self.parse_r()?];
+        while self.peek() == Some(&Token::Comma) {
+            self.advance();
+            args.push(self.parse_r()?);
+        }
+        self.expect_right_paren(open)?;
+        Ok(args)
+    }
+
+    /// Applica gli operatori postfissi che seguono un fattore: il fattoriale (`5!`), la percentuale
+    /// (`20%`, eventualmente seguita da `of`) e le funzioni unarie (es. `16 sqrt`).
+    ///
+    /// Questo metodo implementa la regola grammaticale:
+    /// ```
+    /// F → "!"
+    ///    | "%" ["of" U]
+    ///    | function       - solo funzioni con un argomento, non seguite da "("
+    /// ```
+    ///
+    /// # Comportamento
+    /// - `x%` vale `x / 100`; `x% of y` moltiplica la percentuale per l'unità `y` che segue
+    ///   (`20% of 50` → `10`, `20% of 2^3 + 1` → `(0.2 * 8) + 1`).
+    /// - `of` è ammesso solo subito dopo `%`: altrove (es. `20 of 50`) produce `TokenError::SyntaxError`.
+    /// - Un nome di funzione seguito da `(` non è postfisso: inizia una chiamata prefissa,
+    ///   moltiplicata implicitamente (`2 sqrt(16)` → `2 * sqrt(16)`).
+    /// - Gli identificatori che non sono funzioni (variabili, `pi`) restano fattori della
+    ///   moltiplicazione implicita (`2 pi` → `2 * pi`).
+    /// - Più operatori si applicano da sinistra a destra (`256 sqrt sqrt` → `4`, `3!!` → `(3!)!`).
+    fn parse_postfix(&mut self, mut operand: Expr) -> Result<Expr, CalcError> {
+        loop {
+            match self.peek() {
+                Some(Token::Bang) => {
+                    self.advance();
+                    info_log!("Fattoriale postfisso");
+                    operand = Expr::Unary(UnaryOp::Factorial, Box::new(operand));
+                }
+                Some(Token::Percent) => {
+                    self.advance();
+                    info_log!("Percentuale postfissa");
+                    operand = Expr::Binary(BinaryOp::Div, Box::new(operand), Box::new(Expr::Number(100.0)));
+
+                    if self.peek() == Some(&Token::Of) {
+                        self.advance();
+                        let value = self.parse_u()?;
+                        operand = Expr::Binary(BinaryOp::Mul, Box::new(operand), Box::new(value));
+                    }
+                }
+                Some(Token::Of) => return Err(self.misplaced_of(self.position).into()),
+                Some(Token::Identifier(name))
+                    if let Some(function) = Function::from_name(name)
+                        && function.is_unary()
+                        && self.peek_nth(1) != Some(&Token::LeftParen) =>
+                {
+                    self.advance();
+                    info_log!("Funzione postfissa: {}", function);
+                    operand = Expr::Call(function, vec![operand]);
+                }
+                _ => return Ok(operand),
+            }
+        }
+    }
+
+    /// Costruisce l'errore per la parola chiave `of` all'indice `index`, non preceduta da una percentuale.
+    fn misplaced_of(&self, index: usize) -> TokenError {
+        TokenError::SyntaxError(format!(
+            "La parola chiave 'of' (posizione {}) deve seguire una percentuale, es. '20% of 50' (con la modalità percentuale attiva)",
+            self.source_position(index)
+        ))
+    }
+
+    /// Consuma la parentesi chiusa che termina un'espressione tra parentesi o gli argomenti di una funzione.
+    ///
+    /// # Parametri
+    /// - `open`: indice del token `(` corrispondente, riportato nell'errore.
+    ///
+    /// # Ritorna
+    /// - `Ok(())` se il token corrente è `)`.
+    /// - `Err(TokenError::UnmatchedParenthesis)` con la posizione della `(` non chiusa
+    ///   se viene trovato un altro token o l'input termina.
+    fn expect_right_paren(&mut self, open: usize) -> Result<(), CalcError> {
+        match self.peek() {
+            // Verifica che la parentesi chiusa corrisponda alla parentesi aperta
+            Some(Token::RightParen) => {
+                self.advance();
+                Ok(())
+            }
+
+            // Se viene trovato un altro token invece di una parentesi chiusa, oppure l'input termina, errore.
+            // Il token non viene consumato: `evaluate_collecting` può riprendere da un ';' successivo
+            token => {
+                info_log!("Token inatteso invece di ')': {:?}", token);
+                let expected = self.tokens_after_operand(&[TokenKind::RightParen]);
+                Err(TokenError::UnmatchedParenthesis { found: '(', position: self.source_position(open), expected }.into())
+            },
+        }
+    }
+
+    /// Restituisce i tipi di token che possono iniziare un operando (vedi `parse_b`).
+    fn operand_tokens() -> Vec<TokenKind> {
+        vec![
+            TokenKind::Number,
+            TokenKind::Identifier,
+            TokenKind::LeftParen,
+            TokenKind::Minus,
+            TokenKind::Radical,
+            TokenKind::Dollar,
+        ]
+    }
+
+    /// Restituisce i tipi di token che possono seguire un operando completo: gli operatori binari,
+    /// il fattoriale, l'inizio di un fattore giustapposto (se la moltiplicazione implicita è attiva)
+    /// e i token di chiusura `closers` ammessi nel contesto (es. `)` o `=`).
+    fn tokens_after_operand(&self, closers: &[TokenKind]) -> Vec<TokenKind> {
+        let mut expected = vec![
+            TokenKind::Plus,
+            TokenKind::Minus,
+            TokenKind::Multiply,
+            TokenKind::Divide,
+            TokenKind::Caret,
+            TokenKind::Dollar,
+            TokenKind::Less,
+            TokenKind::LessEqual,
+            TokenKind::Greater,
+            TokenKind::GreaterEqual,
+            TokenKind::EqualEqual,
+            TokenKind::Bang,
+        ];
+        if self.config.implicit_mult {
+            expected.extend([TokenKind::Number, TokenKind::Identifier, TokenKind::LeftParen, TokenKind::Radical]);
+        }
+        expected.extend_from_slice(closers);
+        expected
+    }
+
+    /// Costruisce l'errore per un operatore binario `found` appena consumato al posto di un operando.
+    ///
+    /// - Se il token precedente è a sua volta un operatore binario (es. `2 */ 3`), manca l'operando
+    ///   destro di quest'ultimo.
+    /// - Se `found` è il primo token dell'input (es. `* 3`, `^3`), non c'è alcuna espressione a cui
+    ///   applicarlo: l'errore è un `TokenError::SyntaxError` che descrive il problema e la sua posizione.
+    /// - Altrimenti (es. `(* 3)`), manca l'operando sinistro di `found`.
+    ///
+    /// La posizione riportata è quella in cui era atteso l'operando, cioè di `found`.
+    fn missing_operand(&self, found: Token) -> TokenError {
+        let index = self.position - 1;
+        let position = self.source_position(index);
+
+        match index.checked_sub(1).and_then(|i| self.tokens.get(i)) {
+            Some(previous) if previous.is_operator() => {
+                TokenError::MissingOperand { operator: previous.clone(), side: OperandSide::Right, position, expected: Self::operand_tokens() }
+            }
+            None => TokenError::SyntaxError(format!(
+                "L'espressione non può iniziare con l'operatore '{}' (posizione {}): manca l'operando sinistro",
+                found, position
+            )),
+            _ => TokenError::MissingOperand { operator: found, side: OperandSide::Left, position, expected: Self::operand_tokens() },
+        }
+    }
+
+    /// Converte la posizione di un errore di `check_balanced`, espressa come indice del token,
+    /// nella posizione nel sorgente.
+    fn locate_parenthesis(&self, err: TokenError) -> TokenError {
+        match err {
+            TokenError::UnmatchedParenthesis { found, position, expected } => {
+                TokenError::UnmatchedParenthesis { found, position: self.source_position(position), expected }
+            }
+            other => other,
+        }
+    }
+
+    /// Restituisce la posizione nel sorgente del token all'indice `index`.
+    ///
+    /// Se il parser è stato creato senza spans (`new`), ricade sull'indice del token stesso.
+    fn source_position(&self, index: usize) -> usize {
+        self.spans.get(index).map_or(index, |span| span.start)
+    }
+
+    /// Costruisce l'errore per il token corrente rimasto dopo un'espressione completa, con la sua posizione nel sorgente
+    /// e i tipi di token `expected` che sarebbero stati ammessi al suo posto.
+    ///
+    /// A questo punto tutte le parentesi aperte sono state chiuse: una `)` (es. `((1+2))) =`) non ha
+    /// una `(` corrispondente e produce `TokenError::UnmatchedParenthesis`; ogni altro token `UnexpectedToken`.
+    ///
+    /// Deve essere chiamato solo quando esiste un token alla posizione corrente.
+    fn unexpected_token(&self, expected: Vec<TokenKind>) -> TokenError {
+        let position = self.source_position(self.position);
+        match &self.tokens[self.position] {
+            Token::RightParen => TokenError::UnmatchedParenthesis { found: ')', position, expected },
+            token => TokenError::UnexpectedToken { token: token.clone(), position, expected },
+        }
+    }
+
+    /// Restituisce il token corrente senza avanzare nella posizione.
+    ///
+    /// # Ritorna
+    /// - `Some(&Token)` se esiste un token alla posizione corrente.
+    /// - `None` se la posizione corrente è fuori dai limiti dell'elenco di token.
+    ///
+    /// Permette di esaminare il token attuale senza spostare la posizione del parser. 
+    /// È utile per fare previsioni sui token successivi o per determinare la posizione attuale nel flusso di token.
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    /// Restituisce il token `n` posizioni dopo quello corrente, senza avanzare.
+    fn peek_nth(&self, n: usize) -> Option<&Token> {
+        self.tokens.get(self.position + n)
+    }
+
+    /// Restituisce e avanza alla posizione successiva nella lista di token.
+    ///
+    /// # Ritorna
+    /// - `Some(Token)` se esiste un token alla posizione corrente e avanza la posizione.
+    /// - `None` se la posizione corrente è fuori dai limiti dell'elenco di token.
+    ///
+    /// Questo metodo restituisce il token attuale e incrementa la posizione, spostando così il parser
+    /// alla posizione successiva. È utile per l'iterazione attraverso la lista di token.
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned(); // Poiché prende un riferimento '&Token', .cloned() usato per copiare il valore contenuto nell' Option 
+        // Se esiste un token valido
+        if token.is_some() { self.advance(); }
+        token
+    }
+
+    /// Avanza alla posizione successiva nella lista di token.
+    fn advance(&mut self) {
+        self.position += 1;
+    }
+}
+
+/// Verifica in un'unica scansione lineare che le parentesi dei token siano bilanciate.
+///
+/// È un controllo economico da eseguire prima del parsing: riporta la parentesi responsabile
+/// invece di scoprire lo sbilanciamento in profondità nella discesa ricorsiva.
+///
+/// # Ritorna
+/// - `Ok(())` se ogni `(` ha una `)` corrispondente e viceversa.
+/// - `Err(TokenError::UnmatchedParenthesis)` con la prima parentesi responsabile: la prima `)` senza
+///   apertura (`found: ')'`) oppure, a fine scansione, la prima `(` rimasta aperta (`found: '('`).
+///   La posizione è l'indice del token in `tokens` (senza spans non è nota la colonna nel sorgente).
+///
+/// # Esempio
+/// ```
+/// let tokens = Tokenizer::new("(1 + 2)) =").tokenize()?;
+/// assert_eq!(check_balanced(&tokens), Err(TokenError::UnmatchedParenthesis { found: ')', position: 5, expected: vec![] }));
+/// ```
+pub fn check_balanced(tokens: &[Token]) -> Result<(), TokenError> {
+    let mut open = Vec::new();
+
+    for (index, token) in tokens.iter().enumerate() {
+        match token {
+            Token::LeftParen => open.push(index),
+            Token::RightParen if open.pop().is_none() => {
+                return Err(TokenError::UnmatchedParenthesis { found: ')', position: index, expected: Vec::new() });
+            }
+            _ => {}
+        }
+    }
+
+    match open.first() {
+        Some(&position) => Err(TokenError::UnmatchedParenthesis { found: '(', position, expected: Vec::new() }),
+        None => Ok(()),
+    }
+}
+
+/// Modulo di test per il parsing e la valutazione delle espressioni matematiche.
+///
+/// Questo modulo contiene test unitari per verificare il comportamento della logica di parsing e valutazione,
+/// con particolare attenzione alla gestione degli errori e alla corretta identificazione dei token.
+#[cfg(test)]
+mod tests {
+    use super::*; // Importa tutti i membri del modulo superiore (il codice da testare)
+    use crate::error::MathError;
+    use crate::output::ResultKind;
+    use crate::tokenizer::Tokenizer;
+
+    /// Verifica che le parentesi chiuse in eccesso producano un errore, anziché essere ignorate
+    /// perché la valutazione si ferma al primo `=`.
+    #[test]
+    fn test_unmatched_parentheses() {
+        let tokens = Tokenizer::new("((1+2)))))=").tokenize().unwrap();
+        assert_eq!(
+            MathExpressionParser::new(tokens).evaluate(),
+            Err(CalcError::Token(TokenError::UnmatchedParenthesis { found: ')', position: 7, expected: after_operand(&[TokenKind::Equals]) }))
+        );
+        assert_eq!(
+            evaluate_input("((1+2))))) ="),
+            Err(CalcError::Token(TokenError::UnmatchedParenthesis { found: ')', position: 7, expected: after_operand(&[TokenKind::Equals]) }))
+        );
+        assert_eq!(
+            evaluate_input("((1+2) ="),
+            Err(CalcError::Token(TokenError::UnmatchedParenthesis { found: '(', position: 0, expected: after_operand(&[TokenKind::RightParen]) }))
+        );
+        assert_eq!(evaluate_input("((1+2)) ="), Ok(3.0));
+    }
+
+    /// Verifica la scansione preliminare delle parentesi, con le posizioni come indici dei token.
+    #[test]
+    fn test_check_balanced() {
+        let check = |input: &str| check_balanced(&Tokenizer::new(input).tokenize().unwrap());
+
+        assert_eq!(check("((1 + 2) * (3)) ="), Ok(()));
+        assert_eq!(check("2 + 3 ="), Ok(()));
+        assert_eq!(check("((1 + 2) * 3 ="), Err(TokenError::UnmatchedParenthesis { found: '(', position: 0, expected: vec![] }));
+        assert_eq!(check("1 + (2 * (3 ="), Err(TokenError::UnmatchedParenthesis { found: '(', position: 2, expected: vec![] }));
+        assert_eq!(check("(1 + 2)) * (3 ="), Err(TokenError::UnmatchedParenthesis { found: ')', position: 5, expected: vec![] }));
+        assert_eq!(check(")(1 ="), Err(TokenError::UnmatchedParenthesis { found: ')', position: 0, expected: vec![] }));
+    }
+
+    /// Verifica che la verifica preliminare in `evaluate` riporti la posizione nel sorgente
+    /// prima di altri errori di sintassi.
+    #[test]
+    fn test_evaluate_with_balance_check() {
+        let checked = |input: &str| evaluate_configured(input, |parser| parser.set_balance_check(true));
+
+        assert_eq!(checked("(1 + 2) * 3 ="), Ok(9.0));
+        assert_eq!(
+            checked("2 ++ (3 ="),
+            Err(TokenError::UnmatchedParenthesis { found: '(', position: 5, expected: vec![] }.into())
+        );
+        assert_eq!(
+            checked("(1 + 2)) ="),
+            Err(TokenError::UnmatchedParenthesis { found: ')', position: 7, expected: vec![] }.into())
+        );
+        assert!(matches!(evaluate_input("2 ++ (3 ="), Err(CalcError::Token(TokenError::MissingOperand { .. }))));
+    }
+
+    /// Verifica che una o più parentesi chiuse in eccesso prima del `=` riportino la posizione della prima.
+    #[test]
+    fn test_trailing_close_parens() {
+        let extra_paren = |position| {
+            CalcError::Token(TokenError::UnmatchedParenthesis { found: ')', position, expected: after_operand(&[TokenKind::Equals]) })
+        };
+
+        assert_eq!(evaluate_input("(1+2))="), Err(extra_paren(5)));
+        assert_eq!(evaluate_input("(1+2))))="), Err(extra_paren(5)));
+        assert_eq!(evaluate_input("2 + 3) * 4 ="), Err(extra_paren(5)));
+        assert_eq!(
+            evaluate_list("1; (2)) ="),
+            Err(TokenError::UnmatchedParenthesis { found: ')', position: 6, expected: after_operand(&[TokenKind::Semicolon, TokenKind::Equals]) }.into())
+        );
+        assert_eq!(evaluate_each_input("1 = 2) ="), Err(extra_paren(5)));
+        assert_eq!(crate::compile::compile("(x))").unwrap_err(), extra_paren(3));
+    }
+
+    /// Verifica il risultato di ciascun operatore, con precedenza e associatività.
+    #[test]
+    fn test_operators() {
+        assert_eq!(evaluate_input("2 + 3 ="), Ok(5.0));
+        assert_eq!(evaluate_input("2 - 3 ="), Ok(-1.0));
+        assert_eq!(evaluate_input("2 * 3 ="), Ok(6.0));
+        assert_eq!(evaluate_input("3 / 2 ="), Ok(1.5));
+        assert_eq!(evaluate_input("2 ^ 3 ="), Ok(8.0));
+        assert_eq!(evaluate_input("27 $ 3 ="), Ok(3.0));
+        assert_eq!(evaluate_input("-2 ="), Ok(-2.0));
+        assert_eq!(evaluate_input("2 + 3 * 4 ="), Ok(14.0));
+        assert_eq!(evaluate_input("8 - 4 - 2 ="), Ok(2.0));
+        assert_eq!(evaluate_input("2 ^ 3 ^ 2 ="), Ok(512.0));
+        assert_eq!(evaluate_input("(1 + 2)(4 - 1) ="), Ok(9.0));
+    }
+
+    /// Verifica la distinzione tra notazione scientifica e costante `e`.
+    #[test]
+    fn test_scientific_notation_and_constant_e() {
+        let e = core::f64::consts::E;
+        assert_eq!(evaluate_input("2e3 ="), Ok(2000.0));
+        assert_eq!(evaluate_input("2*e ="), Ok(2.0 * e));
+        assert_eq!(evaluate_input("2 e ="), Ok(2.0 * e));
+        assert_eq!(evaluate_input("2e ="), Ok(2.0 * e));
+        assert_eq!(evaluate_input("2e - 3 ="), Ok(2.0 * e - 3.0));
+        assert_eq!(evaluate_input("1.5e-3 * 2 ="), Ok(0.003));
+    }
+
+    /// Verifica che l'ambiente restituito da `into_env` conservi le assegnazioni.
+    #[test]
+    fn test_into_env_carries_assignments() {
+        let mut parser = MathExpressionParser::new(Tokenizer::new("a = 3; b = a + 1").tokenize().unwrap());
+        parser.evaluate_all().unwrap();
+
+        let mut next = MathExpressionParser::with_env(Tokenizer::new("a * b =").tokenize().unwrap(), parser.into_env());
+        assert_eq!(next.evaluate(), Ok(12.0));
+    }
+
+    /// Verifica che una sequenza di assegnazioni restituisca l'ultimo valore conservando le variabili.
+    #[test]
+    fn test_evaluate_last_chained_assignments() {
+        let mut parser = MathExpressionParser::new(Tokenizer::new("a = 3; b = a + 1; b * 2 =").tokenize().unwrap());
+        assert_eq!(parser.evaluate_last(), Ok(8.0));
+        let env = parser.into_env();
+        assert_eq!((env.get("a"), env.get("b")), (Some(&3.0), Some(&4.0)));
+
+        let evaluate_last = |input: &str| MathExpressionParser::new(Tokenizer::new(input).tokenize().unwrap()).evaluate_last();
+        assert_eq!(evaluate_last("x = 2; x = x * x; x = x * x; x + 1 ="), Ok(17.0)); // riassegnazione
+        assert_eq!(evaluate_last("a = 3; b = a + 1 ="), Ok(4.0)); // assegnazione finale: valore assegnato
+        assert_eq!(evaluate_last("a = 3;"), Ok(3.0));
+        assert_eq!(evaluate_last("2 + 3 ="), Ok(5.0));
+        assert!(evaluate_last("a = 3; b + 1 =").is_err());
+    }
+
+    /// Verifica che la negazione leghi meno di potenze e radici, ma più di prodotti e somme.
+    #[test]
+    fn test_negation_precedence() {
+        assert_eq!(evaluate_input("-2^2 ="), Ok(-4.0));
+        assert_eq!(evaluate_input("(-2)^2 ="), Ok(4.0));
+        assert_eq!(evaluate_input("-4 $ 2 ="), Ok(-2.0));
+        assert_eq!(evaluate_input("2^-2 ="), Ok(0.25));
+        assert_eq!(evaluate_input("--2^2 ="), Ok(4.0));
+        assert_eq!(evaluate_input("3 - -2^2 ="), Ok(7.0));
+        assert_eq!(evaluate_input("2 * -3^2 ="), Ok(-18.0));
+        assert_eq!(evaluate_input("-3! ="), Ok(-6.0));
+
+        let left = |input: &str| evaluate_configured(input, |parser| parser.set_exponent_assoc(ExponentAssoc::Left));
+        assert_eq!(left("-2^2 ="), Ok(-4.0));
+        assert_eq!(left("2^-1^2 ="), Ok(0.25));
+    }
+
+    /// Verifica la parità dell'indice delle radici di numeri negativi, anche per indici molto grandi.
+    #[test]
+    fn test_negative_base_root_parity() {
+        // La negazione lega meno di `$`: la base negativa va tra parentesi
+        assert_eq!(evaluate_input("(-8) $ 3 ="), Ok(-2.0));
+        assert_eq!(evaluate_input("(-32) $ -5 ="), Ok(-0.5));
+        assert_eq!(evaluate_input("(-8) $ 2 ="), Err(MathError::EvenRootOfNegative { base: -8.0, root: 2.0 }.into()));
+        assert_eq!(evaluate_input("(-8) $ -2 ="), Err(MathError::EvenRootOfNegative { base: -8.0, root: -2.0 }.into()));
+
+        // 2^53 + 1 non è rappresentabile: la parità degli indici oltre 2^53 non è affidabile
+        assert_eq!(evaluate_input("(-8) $ 9007199254740991 ="), Ok(-(8f64.powf(1.0 / 9007199254740991.0))));
+        assert_eq!(evaluate_input("(-8) $ 1e30 ="), Err(MathError::InvalidRoot { base: -8.0, root: 1e30 }.into()));
+        assert_eq!(evaluate_input("(-8) $ -1e30 ="), Err(MathError::InvalidRoot { base: -8.0, root: -1e30 }.into()));
+        assert!((evaluate_input("8 $ 1e30 =").unwrap() - 1.0).abs() < 1e-12); // base positiva: nessuna parità
+    }
+
+    /// Verifica che ciascun tipo di errore venga prodotto dall'input corrispondente.
+    #[test]
+    fn test_error_types() {
+        assert_eq!(evaluate_input("1 / 0 ="), Err(MathError::DivisionByZero.into()));
+        assert_eq!(evaluate_input("(-4) $ 2 ="), Err(MathError::EvenRootOfNegative { base: -4.0, root: 2.0 }.into()));
+        assert_eq!(evaluate_input("2 + 3"), Err(TokenError::UnexpectedEnd { expected: after_operand(&[TokenKind::Equals]) }.into()));
+        assert_eq!(
+            evaluate_input("2 % 3 ="),
+            Err(TokenError::InvalidOperator { operator: '%', position: 2 }.into())
+        );
+        assert_eq!(
+            evaluate_input("1.2.3 ="),
+            Err(TokenError::InvalidNumber("Numero con più punti decimali".to_string()).into())
+        );
+        assert_eq!(
+            evaluate_input("x + 1 ="),
+            Err(TokenError::UnknownVariable { name: "x".to_string(), position: Some(0) }.into())
+        );
+    }
+
+    /// Tokenizza e valuta un'espressione mantenendo le posizioni nel sorgente.
+    fn evaluate_input(input: &str) -> CalcResult {
+        let mut tokenizer = Tokenizer::new(input);
+        let tokens = tokenizer.tokenize()?;
+        MathExpressionParser::with_spans(tokens, tokenizer.spans().to_vec()).evaluate()
+    }
+
+    /// Tipi di token attesi negli errori dove manca un operando.
+    fn operand() -> Vec<TokenKind> {
+        MathExpressionParser::operand_tokens()
+    }
+
+    /// Tipi di token attesi negli errori dopo un operando completo, con la configurazione predefinita.
+    fn after_operand(closers: &[TokenKind]) -> Vec<TokenKind> {
+        MathExpressionParser::new(Vec::new()).tokens_after_operand(closers)
+    }
+
+    /// Verifica che un token inatteso dopo l'espressione riporti la sua posizione nel sorgente.
+    #[test]
+    fn test_unexpected_token_reports_source_position() {
+        let result = evaluate_input("2 * 3 , =");
+        assert_eq!(
+            result,
+            Err(CalcError::Token(TokenError::UnexpectedToken { token: Token::Comma, position: 6, expected: after_operand(&[TokenKind::Equals]) }))
+        );
+    }
+
+    /// Verifica che due operatori consecutivi segnalino l'operando destro mancante del primo.
+    #[test]
+    fn test_consecutive_operators_missing_operand() {
+        assert_eq!(
+            evaluate_input("2 ++ 3 ="),
+            Err(CalcError::Token(TokenError::MissingOperand { operator: Token::Plus, side: OperandSide::Right, position: 3, expected: operand() }))
+        );
+        assert_eq!(
+            evaluate_input("2 */ 3 ="),
+            Err(CalcError::Token(TokenError::MissingOperand { operator: Token::Multiply, side: OperandSide::Right, position: 3, expected: operand() }))
+        );
+    }
+
+    /// Verifica che un operatore a inizio espressione produca un errore sintattico generico,
+    /// mentre dopo una parentesi aperta segnali l'operando sinistro mancante.
+    #[test]
+    fn test_leading_operator_missing_operand() {
+        assert_eq!(
+            evaluate_input("* 2 ="),
+            Err(TokenError::SyntaxError(
+                "L'espressione non può iniziare con l'operatore '*' (posizione 0): manca l'operando sinistro".into()
+            ).into())
+        );
+        assert_eq!(
+            evaluate_input("^3="),
+            Err(TokenError::SyntaxError(
+                "L'espressione non può iniziare con l'operatore '^' (posizione 0): manca l'operando sinistro".into()
+            ).into())
+        );
+        assert_eq!(
+            evaluate_input("(* 3) ="),
+            Err(CalcError::Token(TokenError::MissingOperand { operator: Token::Multiply, side: OperandSide::Left, position: 1, expected: operand() }))
+        );
+    }
+
+    /// Verifica che il meno unario dopo un operatore resti valido.
+    #[test]
+    fn test_unary_minus_after_operator_is_allowed() {
+        assert_eq!(evaluate_input("2 +- 3 ="), Ok(-1.0));
+    }
+
+    /// Verifica la valutazione con arrotondamento "gradevole".
+    #[test]
+    fn test_evaluate_nice() {
+        let mut tokenizer = Tokenizer::new("8.6 / 2 =");
+        let mut parser = MathExpressionParser::new(tokenizer.tokenize().unwrap());
+        assert_eq!(parser.evaluate_nice(), Ok(5.0));
+    }
+
+    /// Verifica la classificazione del tipo numerico del risultato.
+    #[test]
+    fn test_evaluate_with_kind() {
+        let output = |input: &str| {
+            let mut tokenizer = Tokenizer::new(input);
+            MathExpressionParser::new(tokenizer.tokenize().unwrap()).evaluate_with_kind().unwrap()
+        };
+
+        assert_eq!(output("6/3 ="), EvalOutput { value: 2.0, kind: ResultKind::Integer });
+        assert_eq!(output("6/4 ="), EvalOutput { value: 1.5, kind: ResultKind::Float });
+        assert_eq!(output("-2^3 ="), EvalOutput { value: -8.0, kind: ResultKind::Integer });
+    }
+
+    /// Tokenizza e valuta un'espressione dopo aver configurato il parser con `configure`.
+    fn evaluate_configured(input: &str, configure: impl FnOnce(&mut MathExpressionParser)) -> CalcResult {
+        let mut tokenizer = Tokenizer::new(input);
+        let mut parser = MathExpressionParser::with_spans(tokenizer.tokenize()?, tokenizer.spans().to_vec());
+        configure(&mut parser);
+        parser.evaluate()
+    }
+
+    /// Verifica che ogni modalità di una configurazione completa abbia effetto in `with_config`.
+    #[test]
+    fn test_with_config() {
+        let config = ParserConfig {
+            angle_mode: AngleMode::Degrees,
+            div_by_zero: DivByZeroMode::Nan,
+            overflow_mode: OverflowMode::Strict,
+            zero_power: ZeroPowerMode::One,
+            modulo: ModuloMode::Euclidean,
+            function_digits: FunctionDigitsPolicy::Split,
+            implicit_mult: false,
+            implicit_mult_precedence: ImplicitMultPrecedence::Tighter,
+            numeric_mode: NumericMode::Float,
+            exponent_assoc: ExponentAssoc::Left,
+            balance_check: true,
+            external_calls: true,
+            max_magnitude: Some(1e6),
+            step_budget: Some(50),
+        };
+        let evaluate = |input: &str| MathExpressionParser::with_config(Tokenizer::new(input).tokenize().unwrap(), config).evaluate();
+
+        assert!((evaluate("sin(90) =").unwrap() - 1.0).abs() < 1e-12); // gradi
+        assert!(evaluate("1 / 0 =").unwrap().is_nan());
+        assert_eq!(evaluate("0 ^ 0 ="), Ok(1.0));
+        assert_eq!(evaluate("mod(-10, 3) ="), Ok(2.0));
+        assert_eq!(evaluate("2 ^ 3 ^ 2 ="), Ok(64.0)); // associatività a sinistra
+        assert!((evaluate("sin90 =").unwrap() - 1.0).abs() < 1e-12); // sin90 → sin(90)
+        assert!(matches!(evaluate("4 5 ="), Err(CalcError::Token(TokenError::UnexpectedToken { .. }))));
+        assert_eq!(evaluate("10 ^ 7 ="), Err(MathError::MagnitudeExceeded { value: 1e7, limit: 1e6 }.into()));
+        assert!(matches!(evaluate("(1 + 2 ="), Err(CalcError::Token(TokenError::UnmatchedParenthesis { .. }))));
+        assert_eq!(evaluate(&format!("{} =", vec!["1"; 60].join(" + "))), Err(MathError::ExpressionTooComplex.into()));
+
+        let integer = ParserConfig { numeric_mode: NumericMode::Int64, ..ParserConfig::default() };
+        assert_eq!(MathExpressionParser::with_config(Tokenizer::new("7 / 2 =").tokenize().unwrap(), integer).evaluate(), Ok(3.0));
+
+        // La configurazione predefinita è quella di `new`
+        let parser = MathExpressionParser::new(Vec::new());
+        assert_eq!(*parser.config(), ParserConfig::default());
+
+        // Il setter e il builder aggiornano la configurazione usata nella valutazione
+        let mut parser = MathExpressionParser::new(Tokenizer::new("sin(90) =").tokenize().unwrap());
+        parser.set_angle_mode(AngleMode::Degrees);
+        assert_eq!(parser.config().angle_mode, AngleMode::Degrees);
+        assert!((parser.evaluate().unwrap() - 1.0).abs() < 1e-12);
+        let mut parser = MathExpressionParser::new(Tokenizer::new("10 ^ 7 =").tokenize().unwrap()).with_max_magnitude(1e6);
+        assert_eq!(parser.config().max_magnitude, Some(1e6));
+        assert_eq!(parser.evaluate(), Err(MathError::MagnitudeExceeded { value: 1e7, limit: 1e6 }.into()));
+    }
+
+    /// Verifica la precedenza della moltiplicazione implicita con le due regole.
+    #[test]
+    fn test_implicit_mult_precedence() {
+        let tighter = |input: &str| {
+            evaluate_configured(input, |parser| parser.set_implicit_mult_precedence(ImplicitMultPrecedence::Tighter))
+        };
+
+        assert_eq!(evaluate_input("6/2(1+2) ="), Ok(9.0)); // (6/2)(1+2)
+        assert_eq!(tighter("6/2(1+2) ="), Ok(1.0)); // 6/(2(1+2))
+
+        let last = |input: &str, precedence: ImplicitMultPrecedence| {
+            let config = ParserConfig { implicit_mult_precedence: precedence, ..ParserConfig::default() };
+            MathExpressionParser::with_config(Tokenizer::new(input).tokenize().unwrap(), config).evaluate_last()
+        };
+        assert_eq!(last("x = 4; 1/2x =", ImplicitMultPrecedence::SameAsExplicit), Ok(2.0));
+        assert_eq!(last("x = 4; 1/2x =", ImplicitMultPrecedence::Tighter), Ok(0.125));
+        assert_eq!(last("x = 3; 18/2x^2 =", ImplicitMultPrecedence::SameAsExplicit), Ok(81.0));
+        assert_eq!(last("x = 3; 18/2x^2 =", ImplicitMultPrecedence::Tighter), Ok(1.0)); // 18/(2(x^2))
+
+        // Solo l'operando destro di '*' e '/' cambia; potenze e somme mantengono la loro precedenza
+        assert_eq!(tighter("2(3)/4 ="), Ok(1.5));
+        assert_eq!(tighter("12/2*3 ="), Ok(18.0)); // nessuna giustapposizione
+        assert_eq!(tighter("12/2 3 ="), Ok(2.0));
+        assert_eq!(tighter("36/2^2(3) ="), Ok(3.0)); // 36/((2^2)*3)
+        assert_eq!(tighter("6/2(1+2) - 6/3 ="), Ok(-1.0));
+    }
+
+    /// Verifica le modalità di gestione della divisione per zero.
+    #[test]
+    fn test_div_by_zero_modes() {
+        let evaluate_with = |input: &str, mode| evaluate_configured(input, |parser| parser.set_div_by_zero_mode(mode));
+
+        assert_eq!(evaluate_input("1/0 ="), Err(MathError::DivisionByZero.into()));
+        assert_eq!(evaluate_with("1/0 =", DivByZeroMode::Error), Err(MathError::DivisionByZero.into()));
+
+        assert_eq!(evaluate_with("1/0 =", DivByZeroMode::Infinity), Ok(f64::INFINITY));
+        assert_eq!(evaluate_with("-1/0 + 1 =", DivByZeroMode::Infinity), Ok(f64::NEG_INFINITY));
+        assert_eq!(evaluate_with("0/0 =", DivByZeroMode::Infinity), Err(MathError::NotANumber { op: "/".into() }.into()));
+
+        assert!(evaluate_with("1/0 =", DivByZeroMode::Nan).unwrap().is_nan());
+        assert!(evaluate_with("0/0 =", DivByZeroMode::Nan).unwrap().is_nan());
+    }
+
+    /// Verifica le potenze di zero: `0^0` secondo `ZeroPowerMode`, `0^-1` come divisione per zero.
+    #[test]
+    fn test_zero_power() {
+        let indeterminate = || CalcError::from(MathError::IndeterminateForm { form: "0^0".into() });
+        assert_eq!(evaluate_input("0^0 ="), Err(indeterminate()));
+        assert_eq!(evaluate_input("(1 - 1)^(2 - 2) ="), Err(indeterminate()));
+        assert_eq!(evaluate_configured("0^0 =", |parser| parser.set_zero_power_mode(ZeroPowerMode::One)), Ok(1.0));
+
+        assert_eq!(evaluate_input("0^2 ="), Ok(0.0));
+        assert_eq!(evaluate_input("0^0.5 ="), Ok(0.0));
+        assert_eq!(evaluate_input("0^-1 ="), Err(MathError::DivisionByZero.into()));
+        let infinity = evaluate_configured("0^-1 =", |parser| parser.set_div_by_zero_mode(DivByZeroMode::Infinity));
+        assert_eq!(infinity, Ok(f64::INFINITY));
+
+        // Stesse regole in aritmetica intera
+        let integer = |input: &str, mode| {
+            let mut parser = MathExpressionParser::new(Tokenizer::new(input).tokenize().unwrap());
+            parser.set_numeric_mode(NumericMode::Int64);
+            parser.set_zero_power_mode(mode);
+            parser.evaluate_integer()
+        };
+        assert_eq!(integer("0^0 =", ZeroPowerMode::Indeterminate), Err(indeterminate()));
+        assert_eq!(integer("0^0 =", ZeroPowerMode::One), Ok(1));
+        assert_eq!(integer("0^-2 =", ZeroPowerMode::One), Err(MathError::DivisionByZero.into()));
+        assert_eq!(integer("0^3 =", ZeroPowerMode::Indeterminate), Ok(0));
+    }
+
+    /// Verifica che le operazioni indeterminate tra infiniti producano `NotANumber`,
+    /// mentre un `NaN` richiesto con `DivByZeroMode::Nan` si propaga senza errore.
+    #[test]
+    fn test_not_a_number_operations() {
+        let infinity = |input: &str| evaluate_configured(input, |parser| parser.set_div_by_zero_mode(DivByZeroMode::Infinity));
+        let nan = |op: &str| Err(MathError::NotANumber { op: op.into() }.into());
+
+        assert_eq!(infinity("1/0 - 1/0 ="), nan("-"));
+        assert_eq!(infinity("1/0 + -1/0 ="), nan("+"));
+        assert_eq!(infinity("0 * (1/0) ="), nan("*"));
+        assert_eq!(infinity("(1/0) / (1/0) ="), nan("/"));
+        assert_eq!(infinity("1/0 + 1/0 ="), Ok(f64::INFINITY));
+
+        let propagated = evaluate_configured("0/0 + 1 =", |parser| parser.set_div_by_zero_mode(DivByZeroMode::Nan));
+        assert!(propagated.unwrap().is_nan());
+    }
+
+    /// Verifica le modalità di overflow su risultati subnormali (`f64::MIN_POSITIVE / 2`) e potenze enormi.
+    #[test]
+    fn test_overflow_modes() {
+        let evaluate_with = |input: &str, mode| evaluate_configured(input, |parser| parser.set_overflow_mode(mode));
+        let half_min_positive = f64::MIN_POSITIVE / 2.0;
+
+        // 2^-1022 = f64::MIN_POSITIVE, il più piccolo normale
+        assert_eq!(evaluate_with("2^-1022 / 2 =", OverflowMode::Strict), Ok(half_min_positive));
+        assert_eq!(evaluate_with("10^300 * 10^300 =", OverflowMode::Strict), Err(MathError::OverflowError.into()));
+        assert_eq!(
+            evaluate_with("10^400 =", OverflowMode::Strict),
+            Err(MathError::InvalidExponentiation { base: 10.0, exponent: 400.0 }.into())
+        );
+
+        assert_eq!(evaluate_with("2^-1022 / 2 =", OverflowMode::Saturate), Ok(half_min_positive));
+        assert_eq!(evaluate_with("10^400 =", OverflowMode::Saturate), Ok(f64::MAX));
+        assert_eq!(evaluate_with("-(10^300 * 10^300) =", OverflowMode::Saturate), Ok(f64::MIN));
+
+        assert_eq!(evaluate_with("2^-1022 / 2 =", OverflowMode::Allow), Ok(half_min_positive));
+        assert_eq!(evaluate_with("10^400 =", OverflowMode::Allow), Ok(f64::INFINITY));
+
+        // La modalità predefinita è `Strict`: accetta i subnormali ma segnala l'overflow
+        assert_eq!(evaluate_input("2^-1022 / 2 ="), Ok(half_min_positive));
+        assert_eq!(evaluate_input("1e308 * 10 ="), Err(MathError::OverflowError.into()));
+        assert_eq!(evaluate_input("171! ="), Err(MathError::OverflowError.into()));
+        assert_eq!(evaluate_input("exp(1000) ="), Err(MathError::OverflowError.into()));
+        assert_eq!(evaluate_input("10^400 ="), Err(MathError::InvalidExponentiation { base: 10.0, exponent: 400.0 }.into()));
+    }
+
+    /// Verifica il limite sul valore assoluto dei risultati attorno alla soglia.
+    #[test]
+    fn test_max_magnitude() {
+        let evaluate_limited = |input: &str, limit| evaluate_configured(input, |parser| parser.set_max_magnitude(limit));
+        let exceeded = |value, limit| Err(MathError::MagnitudeExceeded { value, limit }.into());
+
+        assert_eq!(evaluate_limited("2^10 =", 1024.0), Ok(1024.0));
+        assert_eq!(evaluate_limited("0 - 2^10 =", 1024.0), Ok(-1024.0));
+        assert_eq!(evaluate_limited("2^10 + 1 =", 1024.0), exceeded(1025.0, 1024.0));
+        assert_eq!(evaluate_limited("0 - 2^10 - 1 =", 1024.0), exceeded(-1025.0, 1024.0));
+        assert_eq!(evaluate_limited("10^101 =", 1e100), exceeded(1e101, 1e100));
+        assert_eq!(evaluate_limited("10^101 / 10^100 =", 1e100), exceeded(1e101, 1e100)); // anche i risultati intermedi
+        let saturated = evaluate_configured("10^400 =", |parser| {
+            parser.set_overflow_mode(OverflowMode::Saturate);
+            parser.set_max_magnitude(1e100);
+        });
+        assert_eq!(saturated, exceeded(f64::MAX, 1e100)); // dopo la saturazione
+
+        // Il costruttore concatenabile equivale al setter
+        let mut parser = MathExpressionParser::new(Tokenizer::new("2^10 + 1 =").tokenize().unwrap()).with_max_magnitude(1024.0);
+        assert_eq!(parser.config().max_magnitude, Some(1024.0));
+        assert_eq!(parser.evaluate(), exceeded(1025.0, 1024.0));
+
+        // Senza limite il comportamento resta invariato
+        assert_eq!(evaluate_input("10^101 ="), Ok(1e101));
+    }
+
+    /// Verifica i limiti dichiarati da `numeric_limits`, con e senza un valore assoluto massimo.
+    #[test]
+    fn test_numeric_limits() {
+        let mut parser = MathExpressionParser::new(Tokenizer::new("2^10 + 1 =").tokenize().unwrap());
+        assert_eq!(parser.numeric_limits(), (f64::MIN, f64::MAX));
+
+        // Il limite dichiarato coincide con quello applicato dalla valutazione
+        parser.set_max_magnitude(1024.0);
+        assert_eq!(parser.numeric_limits(), (-1024.0, 1024.0));
+        assert_eq!(parser.evaluate(), Err(MathError::MagnitudeExceeded { value: 1025.0, limit: 1024.0 }.into()));
+
+        // In aritmetica intera valgono i limiti del tipo
+        parser.set_numeric_mode(NumericMode::Int64);
+        assert_eq!(parser.numeric_limits(), (i64::MIN as f64, i64::MAX as f64));
+        parser.set_numeric_mode(NumericMode::Int128);
+        assert_eq!(parser.numeric_limits(), (i128::MIN as f64, i128::MAX as f64));
+
+        let config = ParserConfig { max_magnitude: Some(1e100), ..ParserConfig::default() };
+        assert_eq!(MathExpressionParser::with_config(Vec::new(), config).numeric_limits(), (-1e100, 1e100));
+    }
+
+    /// Verifica il segno del resto di `mod` con operandi negativi in entrambe le modalità.
+    #[test]
+    fn test_modulo_mode() {
+        let modulo = |input: &str, mode| evaluate_configured(input, |parser| parser.set_modulo_mode(mode));
+
+        // Stesso risultato con operandi positivi
+        for mode in [ModuloMode::Truncated, ModuloMode::Euclidean] {
+            assert_eq!(modulo("mod(10, 3) =", mode), Ok(1.0));
+            assert_eq!(modulo("mod(7.5, 2) =", mode), Ok(1.5));
+            assert_eq!(modulo("mod(-9, 3) =", mode), Ok(0.0));
+            assert_eq!(modulo("mod(5, 0) =", mode), Err(MathError::DivisionByZero.into()));
+        }
+
+        // Troncato (predefinito): il resto ha il segno del dividendo
+        assert_eq!(evaluate_input("mod(-10, 3) ="), Ok(-1.0));
+        assert_eq!(modulo("mod(-10, 3) =", ModuloMode::Truncated), Ok(-1.0));
+        assert_eq!(modulo("mod(10, -3) =", ModuloMode::Truncated), Ok(1.0));
+        assert_eq!(modulo("mod(-10, -3) =", ModuloMode::Truncated), Ok(-1.0));
+        assert_eq!(modulo("mod(-7.5, 2) =", ModuloMode::Truncated), Ok(-1.5));
+
+        // Euclideo: il resto è sempre non negativo
+        assert_eq!(modulo("mod(-10, 3) =", ModuloMode::Euclidean), Ok(2.0));
+        assert_eq!(modulo("mod(10, -3) =", ModuloMode::Euclidean), Ok(1.0));
+        assert_eq!(modulo("mod(-10, -3) =", ModuloMode::Euclidean), Ok(2.0));
+        assert_eq!(modulo("mod(-7.5, 2) =", ModuloMode::Euclidean), Ok(0.5));
+
+        // Anche in aritmetica intera
+        let integer = evaluate_configured("mod(-10, 3) =", |parser| {
+            parser.set_numeric_mode(NumericMode::Int64);
+            parser.set_modulo_mode(ModuloMode::Euclidean);
+        });
+        assert_eq!(integer, Ok(2.0));
+    }
+
+    /// Verifica il limite sul numero di operazioni, anche nei corpi delle funzioni e tra più istruzioni.
+    #[test]
+    fn test_step_budget() {
+        let evaluate_limited = |input: &str, budget| evaluate_configured(input, |parser| parser.set_step_budget(budget));
+        let too_complex = Err(MathError::ExpressionTooComplex.into());
+
+        // 5 operazioni: +, *, -, / e ^
+        assert_eq!(evaluate_limited("1 + 2 * 3 - 4 / 2 ^ 1 =", 3), too_complex);
+        assert_eq!(evaluate_limited("1 + 2 * 3 - 4 / 2 ^ 1 =", 4), too_complex);
+        assert_eq!(evaluate_limited("1 + 2 * 3 - 4 / 2 ^ 1 =", 5), Ok(5.0));
+        assert_eq!(evaluate_limited("-sqrt(x) * 2 =", 2), too_complex); // negazione e chiamata contano
+        assert_eq!(evaluate_limited("42 =", 0), Ok(42.0)); // i numeri non consumano passi
+
+        // Moltiplicazioni implicite in catena
+        assert_eq!(evaluate_limited(&format!("2{} =", " 2".repeat(200)), 100), too_complex);
+
+        // Il limite è condiviso da tutte le istruzioni del parser, comprese le chiamate di funzioni utente
+        let mut parser = MathExpressionParser::new(Tokenizer::new("define f(x) = x * x; a = f(2) + 1; a * 2 =").tokenize().unwrap());
+        parser.set_step_budget(3);
+        assert_eq!(parser.evaluate_last(), too_complex);
+        let mut parser = MathExpressionParser::new(Tokenizer::new("define f(x) = x * x; a = f(2) + 1; a * 2 =").tokenize().unwrap());
+        parser.set_step_budget(4);
+        assert_eq!(parser.evaluate_last(), Ok(10.0));
+
+        assert_eq!(
+            evaluate_configured("7 / 2 + 1 =", |parser| {
+                parser.set_numeric_mode(NumericMode::Int64);
+                parser.set_step_budget(1);
+            }),
+            too_complex
+        );
+        assert_eq!(evaluate_input("1 + 2 * 3 - 4 / 2 ^ 1 ="), Ok(5.0)); // senza limite
+    }
+
+    /// Verifica i token attesi riportati negli errori per alcuni input incompleti.
+    #[test]
+    fn test_expected_tokens() {
+        let expected = |input: &str, configure: fn(&mut MathExpressionParser)| {
+            let mut tokenizer = Tokenizer::new(input);
+            let mut parser = MathExpressionParser::with_spans(tokenizer.tokenize().unwrap(), tokenizer.spans().to_vec());
+            configure(&mut parser);
+            parser.evaluate().expect_err("errore atteso").expected_tokens().to_vec()
+        };
+
+        // Dopo un operatore serve un operando
+        let operand = expected("2 + =", |_| {});
+        for kind in [TokenKind::Number, TokenKind::Identifier, TokenKind::LeftParen, TokenKind::Minus, TokenKind::Radical] {
+            assert!(operand.contains(&kind), "{:?} mancante in {:?}", kind, operand);
+        }
+        assert!(!operand.contains(&TokenKind::Plus));
+        assert_eq!(expected("(*", |_| {}), operand);
+        assert_eq!(expected("2 +", |_| {}), operand);
+
+        // Dopo un'espressione completa: un operatore o la chiusura
+        let after_sum = expected("2 + 3", |_| {});
+        assert!(after_sum.contains(&TokenKind::Plus) && after_sum.contains(&TokenKind::Bang));
+        assert!(after_sum.contains(&TokenKind::Equals) && !after_sum.contains(&TokenKind::RightParen));
+        let in_paren = expected("(2 + 3 =", |_| {});
+        assert!(in_paren.contains(&TokenKind::RightParen) && !in_paren.contains(&TokenKind::Equals));
+
+        // Senza moltiplicazione implicita un operando non può seguire un altro operando
+        let explicit = expected("4 5 =", |parser| parser.set_implicit_mult(false));
+        assert!(!explicit.contains(&TokenKind::Number) && explicit.contains(&TokenKind::Multiply));
+
+        // Il set accompagna l'errore anche fuori da `evaluate`; gli errori di altro tipo non ne hanno
+        assert_eq!(crate::compile::compile("(2 + 3").unwrap_err().expected_tokens(), in_paren);
+        assert_eq!(crate::compile::evaluate_str("2 * (").unwrap_err().0.expected_tokens(), operand);
+        assert!(expected("sqrt(1, 2) =", |_| {}).is_empty());
+        assert!(expected("1 / 0 =", |_| {}).is_empty());
+    }
+
+    /// Verifica che i risultati subnormali siano validi e che l'azzeramento di un prodotto sia rilevabile.
+    #[test]
+    fn test_subnormal_results_and_underflow_to_zero() {
+        let strict = |input: &str| evaluate_configured(input, |parser| parser.set_overflow_mode(OverflowMode::Strict));
+
+        // 2^-1030 è subnormale: deve essere restituito in ogni modalità
+        assert_eq!(strict("2^-1030 * 1 ="), Ok(2f64.powf(-1030.0)));
+        assert_eq!(evaluate_input("2^-1030 * 1 ="), Ok(2f64.powf(-1030.0)));
+        assert_eq!(strict("2^-1074 ="), Ok(f64::from_bits(1)));
+
+        // Operandi non nulli con risultato zero: underflow in modalità rigorosa, zero altrimenti
+        assert_eq!(strict("2^-600 * 2^-600 ="), Err(MathError::UnderflowError.into()));
+        assert_eq!(strict("2^-1074 / 4 ="), Err(MathError::UnderflowError.into()));
+        assert_eq!(strict("10^-400 ="), Err(MathError::UnderflowError.into()));
+        assert_eq!(evaluate_configured("2^-600 * 2^-600 =", |parser| parser.set_overflow_mode(OverflowMode::Saturate)), Ok(0.0));
+
+        // Uno zero esatto non è un underflow
+        assert_eq!(strict("0 * 5 ="), Ok(0.0));
+        assert_eq!(strict("1 - 1 ="), Ok(0.0));
+    }
+
+    /// Verifica l'interpretazione di `sin2` con entrambe le politiche.
+    #[test]
+    fn test_function_digits_policy() {
+        let split = |input: &str| evaluate_configured(input, |parser| parser.set_function_digits_policy(FunctionDigitsPolicy::Split));
+
+        assert_eq!(
+            evaluate_input("sin2 ="),
+            Err(CalcError::Token(TokenError::UnknownVariable { name: "sin2".into(), position: Some(0) }))
+        );
+        assert_eq!(evaluate_list("sin2 = 5; sin2 * 2 ="), Ok(vec![5.0, 10.0]));
+        assert_eq!(split("sin2 ="), Ok(2f64.sin()));
+        assert_eq!(split("3 sqrt16 ="), Ok(12.0));
+        assert!(matches!(split("sin2x ="), Err(CalcError::Token(TokenError::UnknownVariable { .. }))));
+    }
+
+    /// Verifica l'interruttore della moltiplicazione implicita.
+    #[test]
+    fn test_implicit_mult_toggle() {
+        let strict = |input: &str| evaluate_configured(input, |parser| parser.set_implicit_mult(false));
+
+        assert!(matches!(
+            strict("4 5 ="),
+            Err(CalcError::Token(TokenError::UnexpectedToken { token: Token::Number(5.0), position: 2, .. }))
+        ));
+        assert!(strict("2(3) =").is_err());
+        assert_eq!(strict("4 * 5 ="), Ok(20.0));
+
+        assert_eq!(evaluate_input("4 5 ="), Ok(20.0));
+        assert_eq!(evaluate_input("2(3) ="), Ok(6.0));
+    }
+
+    /// Verifica gli operatori di confronto, la loro precedenza e la non associatività.
+    #[test]
+    fn test_comparisons() {
+        assert_eq!(evaluate_input("3 > 2 ="), Ok(1.0));
+        assert_eq!(evaluate_input("2 >= 3 ="), Ok(0.0));
+        assert_eq!(evaluate_input("2 <= 2 ="), Ok(1.0));
+        assert_eq!(evaluate_input("1 + 1 == 2 ="), Ok(1.0));
+        assert_eq!(evaluate_input("(3 > 2) * 5 ="), Ok(5.0));
+        assert_eq!(evaluate_list("x = 4; x == 4 ="), Ok(vec![4.0, 1.0]));
+        assert_eq!(
+            evaluate_input("1 < 2 < 3 ="),
+            Err(CalcError::Token(TokenError::UnexpectedToken { token: Token::Less, position: 6, expected: after_operand(&[TokenKind::Equals]) }))
+        );
+        assert_eq!(
+            evaluate_configured("2^62 < 2^62 + 1 =", |parser| parser.set_numeric_mode(NumericMode::Int64)),
+            Ok(1.0)
+        );
+    }
+
+    /// Verifica il fattoriale postfisso, la sua precedenza e i suoi errori.
+    #[test]
+    fn test_factorial() {
+        assert_eq!(evaluate_input("5! ="), Ok(120.0));
+        assert_eq!(evaluate_input("0! ="), Ok(1.0));
+        assert_eq!(evaluate_input("3!! ="), Ok(720.0));
+        assert_eq!(evaluate_input("2 * 3! ="), Ok(12.0));
+        assert_eq!(evaluate_input("2^3! ="), Ok(64.0));
+        assert_eq!(evaluate_input("-3! ="), Ok(-6.0));
+        assert_eq!(evaluate_input("(-3)! ="), Err(MathError::InvalidFactorial { value: -3.0 }.into()));
+        assert_eq!(evaluate_input("2.5! ="), Err(MathError::InvalidFactorial { value: 2.5 }.into()));
+        assert_eq!(
+            evaluate_configured("171! =", |parser| parser.set_overflow_mode(OverflowMode::Strict)),
+            Err(MathError::OverflowError.into())
+        );
+    }
+
+    /// Verifica le funzioni `gamma` e `factorial`, definite anche per i non interi.
+    #[test]
+    fn test_gamma_and_factorial_functions() {
+        assert_eq!(evaluate_input("factorial(5) ="), Ok(120.0));
+        assert_eq!(evaluate_input("gamma(5) ="), Ok(24.0));
+        assert_eq!(evaluate_input("factorial(0) ="), Ok(1.0));
+
+        let close = |input: &str, expected: f64| (evaluate_input(input).unwrap() - expected).abs() <= 1e-12 * expected.abs();
+        assert!(close("gamma(0.5) =", core::f64::consts::PI.sqrt()));
+        assert!(close("factorial(0.5) =", core::f64::consts::PI.sqrt() / 2.0)); // ≈ 0.886
+        assert!(close("gamma(-0.5) =", -2.0 * core::f64::consts::PI.sqrt()));
+        assert!(close("factorial(10.5) =", 10.5 * evaluate_input("factorial(9.5) =").unwrap()));
+        assert!(evaluate_input("factorial(170.5) =").unwrap().is_finite()); // ≈ 9.5e307, senza overflow intermedio
+
+        assert_eq!(evaluate_input("factorial(-1) ="), Err(MathError::InvalidFactorial { value: -1.0 }.into()));
+        assert_eq!(evaluate_input("gamma(0) ="), Err(MathError::InvalidFactorial { value: -1.0 }.into()));
+        assert_eq!(evaluate_input("gamma(-3) ="), Err(MathError::InvalidFactorial { value: -4.0 }.into()));
+        assert_eq!(evaluate_input("2.5! ="), Err(MathError::InvalidFactorial { value: 2.5 }.into())); // `!` resta intero
+    }
+
+    /// Verifica la notazione `sin^2(x)` per le funzioni trigonometriche e i casi esclusi.
+    #[test]
+    fn test_trigonometric_power_notation() {
+        assert_eq!(evaluate_input("sin^2(0) ="), Ok(0.0));
+        assert_eq!(evaluate_input("cos^2(0) ="), Ok(1.0));
+        assert_eq!(evaluate_input("2 tan^3(0) + 1 ="), Ok(1.0));
+        assert_eq!(evaluate_input("sin^2(1) + cos^2(1) ="), Ok(1.0_f64.sin().powi(2) + 1.0_f64.cos().powi(2)));
+
+        let syntax_error = |input: &str| matches!(evaluate_input(input), Err(CalcError::Token(TokenError::SyntaxError(_))));
+        assert!(syntax_error("sqrt^2(16) =")); // solo funzioni trigonometriche
+        assert!(syntax_error("sin^-1(0) ="));
+        assert!(syntax_error("sin^2 0 ="));
+    }
+
+    /// Verifica `pct_change` per aumenti, diminuzioni e valore iniziale nullo, e `pct_of`.
+    #[test]
+    fn test_percent_functions() {
+        assert_eq!(evaluate_input("pct_change(100, 150) ="), Ok(50.0));
+        assert_eq!(evaluate_input("pct_change(200, 150) ="), Ok(-25.0));
+        assert_eq!(evaluate_input("pct_change(-50, -25) ="), Ok(-50.0)); // rispetto al valore iniziale
+        assert_eq!(
+            evaluate_input("pct_change(0, 10) ="),
+            Err(MathError::DomainError { function: Function::PctChange, argument: 0.0 }.into())
+        );
+
+        assert_eq!(evaluate_input("pct_of(20, 50) ="), Ok(10.0));
+        assert_eq!(evaluate_input("pct_of(150, 8) ="), Ok(12.0));
+        assert!(matches!(evaluate_input("pct_of(20) ="), Err(CalcError::Token(TokenError::SyntaxError(_)))));
+    }
+
+    /// Verifica la definizione e la chiamata di funzioni utente, anche annidate e con più parametri.
+    #[test]
+    fn test_user_defined_functions() {
+        let last = |input: &str| -> CalcResult {
+            let mut tokenizer = Tokenizer::new(input);
+            let tokens = tokenizer.tokenize()?;
+            MathExpressionParser::with_spans(tokens, tokenizer.spans().to_vec()).evaluate_last()
+        };
+
+        assert_eq!(last("define f(x) = x^2 + 1; f(3) ="), Ok(10.0));
+        assert_eq!(last("define area(b, h) = b * h / 2; area(4, 5) ="), Ok(10.0));
+        assert_eq!(last("define k() = 42; 2k() + 1 ="), Ok(85.0));
+
+        // Una definizione non ha un valore: è un esito distinto, non un `NaN`
+        let mut parser = MathExpressionParser::new(Tokenizer::new("define f(x) = 2x; f(2); 0 / 0 =").tokenize().unwrap());
+        parser.set_div_by_zero_mode(DivByZeroMode::Nan);
+        let statements = parser.evaluate_statements().unwrap();
+        assert_eq!(statements[..2], [Statement::Defined("f".into()), Statement::Value(4.0)]);
+        assert!(statements[2].value().unwrap().is_nan());
+        assert_eq!(statements[0].to_string(), "f definita");
+        assert!(matches!(last("define f(x) = 2x ="), Err(CalcError::Token(TokenError::SyntaxError(_)))));
+        let mut parser = MathExpressionParser::new(Tokenizer::new("define f(x) = 2x; f(1); define g(x) = x; f(2) =").tokenize().unwrap());
+        assert_eq!(parser.evaluate_all(), Ok(vec![2.0, 4.0]));
+
+        // Funzioni annidate: nel corpo e negli argomenti
+        assert_eq!(last("define f(x) = x + 1; define g(x) = 2 * f(x); g(f(1)) ="), Ok(6.0));
+        assert_eq!(last("define sq(x) = x * x; define hyp(a, b) = sqrt(sq(a) + sq(b)); hyp(3, 4) ="), Ok(5.0));
+
+        // I parametri nascondono le variabili globali, visibili nel corpo solo se non sono parametri
+        assert_eq!(last("x = 100; c = 5; define f(x) = x + c; f(1) + x ="), Ok(106.0));
+        assert_eq!(last("define f(x) = x; define g(y) = f(2) + y; g(1) ="), Ok(3.0));
+
+        // Ridefinizione e aritmetica intera
+        assert_eq!(last("define f(x) = x; define f(x) = -x; f(2) ="), Ok(-2.0));
+        let mut parser = MathExpressionParser::new(Tokenizer::new("define half(n) = n / 2; half(7) =").tokenize().unwrap());
+        parser.set_numeric_mode(NumericMode::Int64);
+        assert_eq!(parser.evaluate_last(), Ok(3.0));
+    }
+
+    /// Verifica gli errori delle funzioni utente: ricorsione infinita, argomenti e definizioni non valide.
+    #[test]
+    fn test_user_defined_function_errors() {
+        let last = |input: &str| -> Result<Vec<Statement>, CalcError> {
+            let mut parser = MathExpressionParser::new(Tokenizer::new(input).tokenize()?);
+            parser.evaluate_statements()
+        };
+        let syntax_error = |input: &str| matches!(last(input), Err(CalcError::Token(TokenError::SyntaxError(_))));
+
+        assert_eq!(last("define f(x) = f(x - 1) + 1; f(3) ="), Err(MathError::ExpressionTooComplex.into()));
+        assert!(syntax_error("define f(x) = x; f(1, 2) ="));
+        assert!(syntax_error("define f(x, y) = x; f(1) ="));
+        assert!(syntax_error("define sin(x) = x ="));
+        assert!(syntax_error("define pi() = 3 ="));
+        assert!(syntax_error("define f(x, x) = x ="));
+        assert!(syntax_error("define f(2) = 2 ="));
+        assert!(syntax_error("define f(x) 2x ="));
+
+        // Un corpo non valido non lascia la funzione definita
+        let mut parser = MathExpressionParser::new(Tokenizer::new("define f(x) = x + ; 1 =").tokenize().unwrap());
+        assert!(parser.evaluate_last().is_err());
+        assert!(parser.take_function_table().is_empty());
+
+        // Senza parentesi il nome resta una variabile
+        assert!(matches!(last("define f(x) = x; f ="), Err(CalcError::Token(TokenError::UnknownVariable { .. }))));
+    }
+
+    /// Verifica gli operatori Unicode copiati da documenti e la radice quadrata prefissa `√`.
+    #[test]
+    fn test_unicode_operators() {
+        assert_eq!(evaluate_input("6 × 7 ="), Ok(42.0));
+        assert_eq!(evaluate_input("8 ÷ 2 ="), Ok(4.0));
+        assert_eq!(evaluate_input("5 − 3 ="), Ok(2.0));
+        assert_eq!(evaluate_input("−2^2 ="), Ok(-4.0));
+        assert_eq!(evaluate_input("√16 ="), Ok(4.0));
+        assert_eq!(evaluate_input("√(9) ="), Ok(3.0));
+        assert_eq!(evaluate_input("√(7 + 9) × 2 ="), Ok(8.0));
+        assert_eq!(evaluate_input("2√16 ="), Ok(8.0)); // moltiplicazione implicita
+        assert_eq!(evaluate_input("√16^2 ="), Ok(16.0)); // la radice si applica al solo fattore
+        assert_eq!(evaluate_input("√√16 ="), Ok(2.0));
+
+        assert!(matches!(evaluate_input("√ ="), Err(CalcError::Token(_))));
+        assert!(matches!(evaluate_input("√−4 ="), Err(CalcError::Token(TokenError::MissingOperand { .. }))));
+    }
+
+    /// Verifica `$` prefisso (radice quadrata) accanto a `$` infisso (radice n-esima) e a `√`.
+    #[test]
+    fn test_prefix_root() {
+        assert_eq!(evaluate_input("√9 ="), Ok(3.0));
+        assert_eq!(evaluate_input("$9 ="), Ok(3.0));
+        assert_eq!(evaluate_input("27 $ 3 ="), Ok(3.0));
+        assert_eq!(evaluate_input("$(27 $ 3) * 2 ="), Ok(2.0 * 3f64.sqrt()));
+        assert_eq!(evaluate_input("2 * $16 ="), Ok(8.0));
+        assert_eq!(evaluate_input("-$16 ="), Ok(-4.0));
+        assert_eq!(evaluate_input("$$16 ="), Ok(2.0));
+        assert!((evaluate_input("64 $ $9 =").unwrap() - 4.0).abs() < 1e-12); // radice cubica di 64
+        assert_eq!(evaluate_input("$(-4) ="), Err(MathError::EvenRootOfNegative { base: -4.0, root: 2.0 }.into()));
+        assert_eq!(evaluate_input("√(-4) ="), Err(MathError::EvenRootOfNegative { base: -4.0, root: 2.0 }.into()));
+
+        // Lo stesso albero della radice infissa con indice 2
+        assert_eq!(crate::compile::compile("$x").unwrap().expr(), crate::compile::compile("x $ 2").unwrap().expr());
+    }
+
+    /// Verifica le funzioni di aggregazione su liste tra parentesi quadre.
+    #[test]
+    fn test_list_aggregates() {
+        assert_eq!(evaluate_input("mean([1, 2, 3, 4]) ="), Ok(2.5));
+        assert_eq!(evaluate_input("sum([1, 2, 3]) ="), Ok(6.0));
+        assert_eq!(evaluate_input("product([2, 3, 4]) ="), Ok(24.0));
+        assert_eq!(evaluate_input("min([3, -1, 2]) ="), Ok(-1.0));
+        assert_eq!(evaluate_input("max([3, -1, 2]) ="), Ok(3.0));
+        assert_eq!(evaluate_input("sum([7]) ="), Ok(7.0));
+
+        // Gli elementi sono espressioni qualsiasi, e il risultato si combina con il resto
+        assert_eq!(evaluate_input("max([2^3, sqrt(81), 1 + 1]) * 2 ="), Ok(18.0));
+        assert_eq!(evaluate_input("2 sum([1, 2])! ="), Ok(12.0));
+
+        let syntax_error = |input: &str| matches!(evaluate_input(input), Err(CalcError::Token(TokenError::SyntaxError(_))));
+        assert_eq!(
+            evaluate_input("mean([]) ="),
+            Err(TokenError::SyntaxError("La funzione 'mean' richiede una lista con almeno un elemento".into()).into())
+        );
+        assert!(syntax_error("sum([]) ="));
+        assert!(syntax_error("sum(1, 2) =")); // serve una lista
+        assert!(syntax_error("[1, 2] =")); // lista isolata
+        assert!(syntax_error("1 + [2] ="));
+        assert!(syntax_error("max([1, 2) ="));
+        assert!(syntax_error("5 sum =")); // nessuna forma postfissa
+    }
+
+    /// Verifica le conversioni `deg` e `rad`, indipendenti dalla modalità angolare.
+    #[test]
+    fn test_deg_and_rad_functions() {
+        let close = |input: &str, expected: f64| (evaluate_input(input).unwrap() - expected).abs() < 1e-12;
+        assert!(close("deg(pi) =", 180.0));
+        assert!(close("rad(180) =", core::f64::consts::PI));
+        assert!(close("deg(rad(45)) =", 45.0));
+        assert!(close("90 rad =", core::f64::consts::FRAC_PI_2));
+
+        let degrees = evaluate_configured("sin(deg(pi / 6)) =", |parser| parser.set_angle_mode(AngleMode::Degrees));
+        assert!((degrees.unwrap() - 0.5).abs() < 1e-12);
+    }
+
+    /// Verifica le funzioni trigonometriche inverse nelle due modalità angolari e il dominio di `asin`/`acos`.
+    #[test]
+    fn test_inverse_trigonometric_functions() {
+        use core::f64::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+        let close = |result: CalcResult, expected: f64| (result.unwrap() - expected).abs() < 1e-12;
+        let degrees = |input: &str| evaluate_configured(input, |parser| parser.set_angle_mode(AngleMode::Degrees));
+
+        assert!(close(evaluate_input("asin(1) ="), FRAC_PI_2));
+        assert!(close(degrees("asin(1) ="), 90.0));
+        assert!(close(evaluate_input("acos(-1) ="), PI));
+        assert!(close(degrees("atan(1) ="), 45.0));
+
+        assert!(close(evaluate_input("atan2(1, 1) ="), FRAC_PI_4));
+        assert!(close(degrees("atan2(1, 1) ="), 45.0));
+        assert!(close(evaluate_input("atan2(1, -1) ="), 3.0 * FRAC_PI_4)); // secondo quadrante, a differenza di atan(-1)
+        assert!(close(degrees("atan2(-1, 0) ="), -90.0));
+        assert!(matches!(evaluate_input("atan2(1) ="), Err(CalcError::Token(TokenError::SyntaxError(_)))));
+
+        let domain_error = |function, argument| Err(MathError::DomainError { function, argument }.into());
+        assert_eq!(evaluate_input("asin(2) ="), domain_error(Function::Asin, 2.0));
+        assert_eq!(evaluate_input("acos(-1.5) ="), domain_error(Function::Acos, -1.5));
+    }
+
+    /// Verifica le funzioni iperboliche e le loro inverse, con i casi fuori dominio.
+    #[test]
+    fn test_hyperbolic_functions() {
+        let close = |input: &str, expected: f64| (evaluate_input(input).unwrap() - expected).abs() < 1e-12;
+        assert!(close("sinh(0) =", 0.0));
+        assert!(close("cosh(0) =", 1.0));
+        assert!(close("tanh(1) =", 1.0_f64.tanh()));
+        assert!(close("cosh(2)^2 - sinh(2)^2 =", 1.0));
+        assert!(close("asinh(sinh(1.5)) =", 1.5));
+        assert!(close("acosh(1) =", 0.0));
+        assert!(close("atanh(0.5) =", 0.5_f64.atanh()));
+
+        // Indipendenti dalla modalità angolare
+        let degrees = evaluate_configured("sinh(1) =", |parser| parser.set_angle_mode(AngleMode::Degrees));
+        assert_eq!(degrees, Ok(1.0_f64.sinh()));
+
+        let domain_error = |function, argument| Err(MathError::DomainError { function, argument }.into());
+        assert_eq!(evaluate_input("acosh(0.5) ="), domain_error(Function::Acosh, 0.5));
+        assert_eq!(evaluate_input("atanh(1) ="), domain_error(Function::Atanh, 1.0));
+        assert_eq!(evaluate_input("atanh(-2) ="), domain_error(Function::Atanh, -2.0));
+    }
+
+    /// Verifica `clamp` dentro e fuori dall'intervallo e `sign` di positivi, negativi e zero.
+    #[test]
+    fn test_clamp_and_sign() {
+        assert_eq!(evaluate_input("clamp(5, 0, 10) ="), Ok(5.0));
+        assert_eq!(evaluate_input("clamp(-3, 0, 10) ="), Ok(0.0));
+        assert_eq!(evaluate_input("clamp(12, 0, 10) ="), Ok(10.0));
+        assert_eq!(evaluate_input("clamp(7, 2, 2) ="), Ok(2.0));
+        assert_eq!(
+            evaluate_input("clamp(5, 10, 0) ="),
+            Err(MathError::DomainError { function: Function::Clamp, argument: 10.0 }.into())
+        );
+        assert!(matches!(evaluate_input("clamp(5, 10) ="), Err(CalcError::Token(TokenError::SyntaxError(_)))));
+
+        assert_eq!(evaluate_input("sign(42) ="), Ok(1.0));
+        assert_eq!(evaluate_input("sign(-0.5) ="), Ok(-1.0));
+        assert_eq!(evaluate_input("sign(0) ="), Ok(0.0));
+        let negative_zero = evaluate_input("sign(-0) =").unwrap();
+        assert!(negative_zero == 0.0 && negative_zero.is_sign_positive());
+    }
+
+    /// Verifica la percentuale postfissa e la parola chiave `of`, anche usata senza percentuale.
+    #[test]
+    fn test_percent_of() {
+        let evaluate_percent = |input: &str| {
+            let mut tokenizer = Tokenizer::new(input);
+            tokenizer.set_percent_mode(true);
+            let tokens = tokenizer.tokenize().unwrap();
+            MathExpressionParser::with_spans(tokens, tokenizer.spans().to_vec()).evaluate_last()
+        };
+
+        assert_eq!(evaluate_percent("20% of 50 ="), Ok(10.0));
+        assert_eq!(evaluate_percent("50% ="), Ok(0.5));
+        assert_eq!(evaluate_percent("100 + 20% of 50 * 2 ="), Ok(120.0));
+        assert_eq!(evaluate_percent("10% of 2^3 ="), Ok(0.8));
+        assert_eq!(evaluate_percent("x = 40; 25% of x ="), Ok(10.0));
+
+        let misplaced = |position: usize| Err(TokenError::SyntaxError(format!(
+            "La parola chiave 'of' (posizione {}) deve seguire una percentuale, es. '20% of 50' (con la modalità percentuale attiva)",
+            position
+        )).into());
+        assert_eq!(evaluate_percent("20 of 50 ="), misplaced(3));
+        assert_eq!(evaluate_percent("of 50 ="), misplaced(0));
+        assert_eq!(evaluate_input("20 of 50 ="), misplaced(3)); // senza modalità percentuale
+    }
+
+    /// Confronta le modalità intere `i64` e `i128` su valori che superano i limiti di `i64`.
+    #[test]
+    fn test_integer_modes() {
+        let int64 = |input: &str| evaluate_configured(input, |parser| parser.set_numeric_mode(NumericMode::Int64));
+        let int128 = |input: &str| evaluate_configured(input, |parser| parser.set_numeric_mode(NumericMode::Int128));
+
+        assert_eq!(int64("20! ="), Ok(2_432_902_008_176_640_000.0));
+        assert_eq!(int64("21! ="), Err(MathError::OverflowError.into()));
+        assert_eq!(int128("21! ="), Ok(51_090_942_171_709_440_000.0));
+        assert_eq!(int64("2^63 ="), Err(MathError::OverflowError.into()));
+        assert_eq!(int128("2^63 ="), Ok(9_223_372_036_854_775_808.0));
+
+        assert_eq!(int64("7 / 2 ="), Ok(3.0));
+        assert_eq!(int64("sqrt(16) + 1 ="), Ok(5.0));
+        assert_eq!(int64("1.5 + 1 ="), Err(MathError::NonInteger { value: 1.5 }.into()));
+        assert_eq!(int64("2 ^ -1 ="), Err(MathError::InvalidExponentiation { base: 2.0, exponent: -1.0 }.into()));
+    }
+
+    /// Verifica che `evaluate_integer` restituisca il risultato esatto, senza passare per `f64`.
+    #[test]
+    fn test_evaluate_integer_is_exact() {
+        let evaluate_integer = |input: &str, mode: NumericMode| {
+            let mut parser = MathExpressionParser::new(Tokenizer::new(input).tokenize().unwrap());
+            parser.set_numeric_mode(mode);
+            parser.evaluate_integer()
+        };
+
+        assert_eq!(evaluate_integer("25! =", NumericMode::Int128), Ok(15_511_210_043_330_985_984_000_000));
+        assert_eq!(evaluate_integer("25! =", NumericMode::Int64), Err(MathError::OverflowError.into()));
+        assert_eq!(evaluate_integer("34! =", NumericMode::Int128), Err(MathError::OverflowError.into()));
+    }
+
+    /// Verifica la risoluzione delle variabili in un ambiente fornito dal chiamante.
+    #[test]
+    fn test_with_env() {
+        let evaluate_with = |input: &str| {
+            let env = Env::from([("x".to_string(), 10.0)]);
+            MathExpressionParser::with_env(Tokenizer::new(input).tokenize().unwrap(), env).evaluate()
+        };
+
+        assert_eq!(evaluate_with("x^2 ="), Ok(100.0));
+        assert_eq!(evaluate_with("2x + 1 ="), Ok(21.0));
+        assert_eq!(
+            evaluate_with("x + y ="),
+            Err(TokenError::UnknownVariable { name: "y".to_string(), position: Some(2) }.into())
+        );
+    }
+
+    /// Verifica entrambe le associatività della potenza.
+    #[test]
+    fn test_exponent_assoc() {
+        let left = |input: &str| evaluate_configured(input, |parser| parser.set_exponent_assoc(ExponentAssoc::Left));
+
+        assert_eq!(evaluate_input("2^3^2 ="), Ok(512.0));
+        assert_eq!(left("2^3^2 ="), Ok(64.0));
+        assert_eq!(left("2^(3^2) ="), Ok(512.0));
+        assert_eq!(left("64 $ 2 $ 3 ="), Ok(2.0));
+        assert_eq!(left("2 * 2^3^2 + 1 ="), Ok(129.0));
+        assert_eq!(left("2^-1^2 ="), Ok(0.25));
+    }
+
+    /// Verifica le funzioni di arrotondamento su valori positivi, negativi e a metà strada.
+    #[test]
+    fn test_rounding_functions() {
+        let cases = [
+            ("floor", [(2.7, 2.0), (-2.7, -3.0), (2.5, 2.0), (-2.5, -3.0)]),
+            ("ceil", [(2.1, 3.0), (-2.1, -2.0), (2.5, 3.0), (-2.5, -2.0)]),
+            ("round", [(2.4, 2.0), (-2.4, -2.0), (2.5, 3.0), (-2.5, -3.0)]),
+            ("trunc", [(2.9, 2.0), (-2.9, -2.0), (2.5, 2.0), (-2.5, -2.0)]),
+        ];
+
+        for (function, values) in cases {
+            for (input, expected) in values {
+                assert_eq!(evaluate_input(&format!("{}({}) =", function, input)), Ok(expected), "{}({})", function, input);
+            }
+        }
+    }
+
+    /// Verifica massimo comun divisore e minimo comune multiplo, con i casi limite.
+    #[test]
+    fn test_gcd_lcm() {
+        assert_eq!(evaluate_input("gcd(12, 18) ="), Ok(6.0));
+        assert_eq!(evaluate_input("lcm(4, 6) ="), Ok(12.0));
+        assert_eq!(evaluate_input("gcd(-12, 18) ="), Ok(6.0));
+        assert_eq!(evaluate_input("lcm(-4, 6) ="), Ok(12.0));
+        assert_eq!(evaluate_input("gcd(7, 13) ="), Ok(1.0));
+        assert_eq!(evaluate_input("gcd(0, 0) ="), Ok(0.0));
+        assert_eq!(evaluate_input("gcd(0, 5) ="), Ok(5.0));
+        assert_eq!(evaluate_input("lcm(0, 5) ="), Ok(0.0));
+        assert_eq!(
+            evaluate_input("gcd(2.5, 5) ="),
+            Err(MathError::NonIntegerArgument { function: Function::Gcd, argument: 2.5 }.into())
+        );
+        assert!(evaluate_input("lcm(4) =").is_err());
+    }
+
+    /// Verifica il logaritmo in base arbitraria e le sue forme a un argomento.
+    #[test]
+    fn test_logarithm_with_base() {
+        assert_eq!(evaluate_input("log(8, 2) ="), Ok(3.0));
+        assert_eq!(evaluate_input("log(1000, 10) ="), Ok(3.0));
+        assert_eq!(evaluate_input("log(81, 3) ="), Ok(4.0));
+        assert_eq!(evaluate_input("log(0.125, 2) ="), Ok(-3.0));
+        assert_eq!(evaluate_input("log(100) ="), Ok(2.0));
+        assert_eq!(evaluate_input("log10(1000) ="), Ok(3.0));
+        assert_eq!(evaluate_input("ln(e) ="), Ok(1.0));
+
+        let invalid = |value, base| Err(MathError::InvalidLogarithm { value, base }.into());
+        assert_eq!(evaluate_input("log(0, 2) ="), invalid(0.0, 2.0));
+        assert_eq!(evaluate_input("log(-8, 2) ="), invalid(-8.0, 2.0));
+        assert_eq!(evaluate_input("log(8, 0) ="), invalid(8.0, 0.0));
+        assert_eq!(evaluate_input("log(8, -2) ="), invalid(8.0, -2.0));
+        assert_eq!(evaluate_input("log(8, 1) ="), invalid(8.0, 1.0));
+        assert_eq!(
+            evaluate_input("log(8, 2, 3) ="),
+            Err(TokenError::SyntaxError("La funzione 'log' richiede da 1 a 2 argomenti, trovati 3".into()).into())
+        );
+    }
+
+    /// Verifica la potenza modulare e i suoi errori.
+    #[test]
+    fn test_powmod() {
+        assert_eq!(evaluate_input("powmod(2, 10, 1000) ="), Ok(24.0));
+        // 3^10000 in virgola mobile è infinito, ma il calcolo modulare resta esatto: 3^6 ≡ 1 (mod 7)
+        assert_eq!(evaluate_input("powmod(3, 10000, 7) ="), Ok(4.0));
+        assert_eq!(evaluate_input("powmod(-2, 3, 5) ="), Ok(2.0));
+
+        let domain_error = |argument| Err(CalcError::Math(MathError::DomainError { function: Function::Powmod, argument }));
+        assert_eq!(evaluate_input("powmod(2, 10, 0) ="), domain_error(0.0));
+        assert_eq!(evaluate_input("powmod(2.5, 10, 7) ="), domain_error(2.5));
+        assert!(matches!(evaluate_input("powmod(2, 10) ="), Err(CalcError::Token(TokenError::SyntaxError(_)))));
+        assert!(matches!(evaluate_input("5 powmod ="), Err(CalcError::Token(TokenError::SyntaxError(_)))));
+    }
+
+    /// Verifica le costanti `tau` e `phi`, anche nella moltiplicazione implicita.
+    #[test]
+    fn test_tau_and_phi_constants() {
+        let phi = (1.0 + 5.0_f64.sqrt()) / 2.0;
+        assert_eq!(evaluate_input("tau ="), Ok(core::f64::consts::TAU));
+        assert_eq!(evaluate_input("phi ="), Ok(phi));
+        assert_eq!(evaluate_input("2phi ="), Ok(2.0 * phi));
+        assert_eq!(evaluate_input("2tau ="), Ok(2.0 * core::f64::consts::TAU));
+        assert!((evaluate_input("phi^2 - phi =").unwrap() - 1.0).abs() < 1e-12);
+        assert!(matches!(evaluate_input("PHI ="), Err(CalcError::Token(TokenError::UnknownVariable { .. }))));
+    }
+
+    /// Verifica che una funzione unaria dopo un valore sia applicata in forma postfissa,
+    /// mentre una costante resta un fattore della moltiplicazione implicita.
+    #[test]
+    fn test_postfix_function_and_implicit_multiplication() {
+        assert_eq!(evaluate_input("16 sqrt ="), Ok(4.0));
+        assert!((evaluate_input("2 pi =").unwrap() - core::f64::consts::TAU).abs() < 1e-12);
+
+        assert_eq!(evaluate_input("sqrt(16) ="), Ok(4.0));
+        assert_eq!(evaluate_input("2 sqrt(16) ="), Ok(8.0));
+        assert_eq!(evaluate_input("256 sqrt sqrt ="), Ok(4.0));
+        assert_eq!(evaluate_input("-16 sqrt ="), Ok(-4.0));
+        assert_eq!(evaluate_input("(7 + 9) sqrt ^ 2 ="), Ok(16.0));
+        assert!(matches!(evaluate_input("sqrt 16 ="), Err(CalcError::Token(TokenError::SyntaxError(_)))));
+    }
+
+    /// Tokenizza e valuta una lista di espressioni separate da `;`.
+    fn evaluate_list(input: &str) -> Result<Vec<f64>, CalcError> {
+        let mut tokenizer = Tokenizer::new(input);
+        let tokens = tokenizer.tokenize()?;
+        MathExpressionParser::with_spans(tokens, tokenizer.spans().to_vec()).evaluate_all()
+    }
+
+    /// Verifica la valutazione di più espressioni separate da `;`.
+    #[test]
+    fn test_evaluate_all_multiple_expressions() {
+        assert_eq!(evaluate_list("1+1; 2*2; 3^2 ="), Ok(vec![2.0, 4.0, 9.0]));
+        assert_eq!(evaluate_list("1+1; 2*2;"), Ok(vec![2.0, 4.0]));
+    }
+
+    /// Verifica che le assegnazioni siano visibili nelle espressioni successive.
+    #[test]
+    fn test_evaluate_all_shares_environment() {
+        assert_eq!(evaluate_list("a = 3; b = a + 1; a * b ="), Ok(vec![3.0, 4.0, 12.0]));
+        assert_eq!(evaluate_list("x = 4; 2x ="), Ok(vec![4.0, 8.0]));
+    }
+
+    /// Verifica che un'espressione errata tra espressioni valide faccia fallire la lista.
+    #[test]
+    fn test_evaluate_all_reports_bad_expression() {
+        assert_eq!(
+            evaluate_list("1+1; 2 ++ 3; 4 ="),
+            Err(CalcError::Token(TokenError::MissingOperand { operator: Token::Plus, side: OperandSide::Right, position: 8, expected: operand() }))
+        );
+        assert_eq!(
+            evaluate_list("y + 1; y = 2 ="),
+            Err(CalcError::Token(TokenError::UnknownVariable { name: "y".into(), position: Some(0) }))
+        );
+    }
+
+    /// Verifica che la raccolta degli errori prosegua oltre le istruzioni malformate.
+    #[test]
+    fn test_evaluate_collecting() {
+        let collect = |input: &str| {
+            let mut tokenizer = Tokenizer::new(input);
+            let tokens = tokenizer.tokenize().unwrap();
+            MathExpressionParser::with_spans(tokens, tokenizer.spans().to_vec()).evaluate_collecting()
+        };
+
+        // Due istruzioni su tre sono malformate: entrambi gli errori vengono riportati
+        let (value, errors) = collect("2 ++ 3; (1 + 2; 4 * 5 =");
+        assert_eq!(value, Some(20.0));
+        assert_eq!(errors, vec![
+            CalcError::Token(TokenError::MissingOperand { operator: Token::Plus, side: OperandSide::Right, position: 3, expected: operand() }),
+            CalcError::Token(TokenError::UnmatchedParenthesis { found: '(', position: 8, expected: after_operand(&[TokenKind::RightParen]) }),
+        ]);
+
+        // Un errore nell'ultima istruzione lascia il valore assente; le assegnazioni valide restano visibili
+        let (value, errors) = collect("a = 2; a 3 4); a / 0; a * 10");
+        assert_eq!(value, Some(20.0));
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[1], MathError::DivisionByZero.into());
+        assert_eq!(collect("1; x + 1 ="), (None, vec![TokenError::UnknownVariable { name: "x".into(), position: Some(3) }.into()]));
+
+        assert_eq!(collect("1 + 1; 2 * 3;"), (Some(6.0), vec![]));
+    }
+
+    /// Verifica che `x =` valuti la variabile invece di assegnarla.
+    #[test]
+    fn test_statement_assignment_and_terminator() {
+        assert_eq!(evaluate_list("x = 5; x ="), Ok(vec![5.0, 5.0]));
+        assert_eq!(evaluate_input("x = 5 ="), Ok(5.0));
+    }
+
+    /// Tokenizza e valuta l'input in modalità multi-risultato.
+    fn evaluate_each_input(input: &str) -> Result<Vec<f64>, CalcError> {
+        let mut tokenizer = Tokenizer::new(input);
+        let tokens = tokenizer.tokenize()?;
+        MathExpressionParser::with_spans(tokens, tokenizer.spans().to_vec()).evaluate_each()
+    }
+
+    /// Verifica che ogni `=` chiuda un'espressione in modalità multi-risultato.
+    #[test]
+    fn test_evaluate_each_equals_as_separator() {
+        assert_eq!(evaluate_each_input("2+3=4*5="), Ok(vec![5.0, 20.0]));
+        assert_eq!(evaluate_each_input("2+3 ="), Ok(vec![5.0]));
+        assert_eq!(evaluate_each_input("x = 2 = x * 3 ="), Ok(vec![2.0, 6.0]));
+    }
+
+    /// Verifica gli errori in modalità multi-risultato e che la modalità predefinita resti invariata.
+    #[test]
+    fn test_evaluate_each_errors_and_default_mode() {
+        assert_eq!(evaluate_each_input("2+3=4*5"), Err(CalcError::Token(TokenError::UnexpectedEnd { expected: after_operand(&[TokenKind::Equals]) })));
+        assert_eq!(evaluate_input("2+3=4*5="), Ok(5.0));
+    }
+}
@@ -0,0 +1,15 @@
+//! Import comuni ai moduli della libreria.
+//!
+//! Con `std` i tipi di `alloc` fanno già parte del prelude del linguaggio; senza, vanno importati
+//! esplicitamente, insieme al trait `Float` che fornisce i metodi matematici di `f64`.
+
+pub(crate) use alloc::boxed::Box;
+pub(crate) use alloc::format;
+pub(crate) use alloc::string::{String, ToString};
+pub(crate) use alloc::vec;
+pub(crate) use alloc::vec::Vec;
+
+// Nei test `std` è comunque collegata e i metodi di `f64` sono già disponibili
+#[cfg(not(feature = "std"))]
+#[cfg_attr(test, allow(unused_imports))]
+pub(crate) use crate::math::Float;
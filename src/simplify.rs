@@ -0,0 +1,353 @@
+use crate::compile::compile;
+use crate::error::CalcError;
+use crate::expr::{BinaryOp, Env, Expr, UnaryOp};
+use crate::prelude::*;
+
+impl Expr {
+    /// Riscrive l'albero in una forma canonica, con lo stesso valore per ogni ambiente.
+    ///
+    /// # Regole
+    /// - Gli operatori i cui operandi sono tutti numeri letterali vengono sostituiti dal loro valore
+    ///   (`2^3` → `8`), salvo che la valutazione produca un errore o un valore non finito (`1/0` resta invariato).
+    /// - Le catene di `+` e di `*` vengono appiattite: gli operandi numerici sono raccolti in un'unica
+    ///   costante in testa, gli altri ordinati in modo canonico (`x*2` e `2*x` → `2*x`).
+    ///   L'elemento neutro viene omesso (`x + 0` → `x`, `1 * x` → `x`).
+    /// - Le chiamate di funzione non vengono valutate: il risultato di `sin` dipende dalla modalità angolare.
+    ///
+    /// Raccogliere le costanti cambia l'ordine delle operazioni in virgola mobile: il valore della
+    /// forma canonica può differire dall'originale per errori di arrotondamento.
+    ///
+    /// # Esempio
+    /// ```
+    /// let f = compile("x * 2 + 1 + 2")?;
+    /// assert_eq!(f.expr().simplify(), compile("3 + 2x")?.expr().clone());
+    /// ```
+    pub fn simplify(&self) -> Expr {
+        match self {
+            Expr::Number(_) | Expr::Variable(_) => self.clone(),
+            Expr::Unary(op, operand) => fold_literals(Expr::Unary(*op, Box::new(operand.simplify()))),
+            Expr::Binary(op @ (BinaryOp::Add | BinaryOp::Mul), ..) => simplify_commutative(*op, self),
+            Expr::Binary(op, lhs, rhs) => fold_literals(Expr::Binary(*op, Box::new(lhs.simplify()), Box::new(rhs.simplify()))),
+            Expr::Call(function, args) => Expr::Call(*function, args.iter().map(Expr::simplify).collect()),
+            Expr::UserCall(name, args) => Expr::UserCall(name.clone(), args.iter().map(Expr::simplify).collect()),
+        }
+    }
+
+    /// Elimina le negazioni ridondanti dell'albero, lasciando invariato il suo valore.
+    ///
+    /// # Regole
+    /// - Una doppia negazione si annulla: `- - x` → `x`.
+    /// - Una negazione di un numero letterale diventa il numero opposto: `-(3)` → `-3`.
+    /// - Le altre negazioni restano (`-x`, `-2^2` = `-(2^2)`); la regola si applica a tutti i sottoalberi.
+    ///
+    /// A differenza di `simplify()` non riordina né valuta gli altri operatori: l'albero resta
+    /// riconoscibile per la visualizzazione.
+    ///
+    /// # Esempio
+    /// ```
+    /// let f = compile("-(-(-3))")?;
+    /// assert_eq!(f.expr().clone().simplify_negations(), Expr::Number(-3.0));
+    /// ```
+    pub fn simplify_negations(self) -> Expr {
+        match self {
+            Expr::Number(_) | Expr::Variable(_) => self,
+            Expr::Unary(UnaryOp::Neg, operand) => match operand.simplify_negations() {
+                Expr::Unary(UnaryOp::Neg, inner) => *inner,
+                Expr::Number(n) => Expr::Number(-n),
+                operand => Expr::Unary(UnaryOp::Neg, Box::new(operand)),
+            },
+            Expr::Unary(op, operand) => Expr::Unary(op, Box::new(operand.simplify_negations())),
+            Expr::Binary(op, lhs, rhs) => {
+                Expr::Binary(op, Box::new(lhs.simplify_negations()), Box::new(rhs.simplify_negations()))
+            }
+            Expr::Call(function, args) => Expr::Call(function, args.into_iter().map(Expr::simplify_negations).collect()),
+            Expr::UserCall(name, args) => Expr::UserCall(name, args.into_iter().map(Expr::simplify_negations).collect()),
+        }
+    }
+
+    /// Riscrive sottrazioni e divisioni con le operazioni inverse, lasciando solo `+` e `*` tra i termini.
+    ///
+    /// # Regole
+    /// - `a - b` → `a + (-b)`.
+    /// - `a / b` → `a * (1/b)`: il reciproco resta l'unica divisione, con numeratore `1`.
+    /// - La riscrittura si applica a tutti i sottoalberi, compresi gli argomenti delle funzioni.
+    ///
+    /// La negazione è esatta in virgola mobile, quindi le sottrazioni non cambiano il valore; un prodotto
+    /// per il reciproco può invece differire dalla divisione originale per un errore di arrotondamento
+    /// (`3 / 3` = 1, `3 * (1/3)` = 1 o 0.999...). Una divisione per zero resta un errore anche dopo la riscrittura.
+    ///
+    /// # Esempio
+    /// ```
+    /// let f = compile("a - b / c")?;
+    /// assert_eq!(f.expr().clone().normalize(), compile("a + -(b * (1 / c))")?.expr().clone());
+    /// ```
+    pub fn normalize(self) -> Expr {
+        match self {
+            Expr::Number(_) | Expr::Variable(_) => self,
+            Expr::Unary(op, operand) => Expr::Unary(op, Box::new(operand.normalize())),
+            Expr::Binary(BinaryOp::Sub, lhs, rhs) => lhs.normalize() + rhs.normalize().negate(),
+            Expr::Binary(BinaryOp::Div, lhs, rhs) => lhs.normalize() * rhs.normalize().reciprocal(),
+            Expr::Binary(op, lhs, rhs) => Expr::Binary(op, Box::new(lhs.normalize()), Box::new(rhs.normalize())),
+            Expr::Call(function, args) => Expr::Call(function, args.into_iter().map(Expr::normalize).collect()),
+            Expr::UserCall(name, args) => Expr::UserCall(name, args.into_iter().map(Expr::normalize).collect()),
+        }
+    }
+}
+
+/// Verifica se due espressioni sono equivalenti, confrontandone le forme canoniche (`Expr::simplify`).
+///
+/// L'uguaglianza è strutturale dopo la semplificazione: riconosce commutatività e associatività di
+/// `+` e `*` e l'uguaglianza delle costanti numeriche, ma non identità algebriche come `x + x = 2x`.
+///
+/// # Ritorna
+/// - `Ok(true)` se le forme canoniche coincidono.
+/// - `Ok(false)` altrimenti.
+/// - `Err(CalcError)` se una delle due espressioni non è valida (vedi `compile`).
+///
+/// # Esempio
+/// ```
+/// assert!(expressions_equal("2+3", "3+2")?);
+/// assert!(expressions_equal("x*2", "2*x")?);
+/// assert!(!expressions_equal("x-1", "1-x")?);
+/// ```
+pub fn expressions_equal(a: &str, b: &str) -> Result<bool, CalcError> {
+    let (a, b) = (compile(a)?, compile(b)?);
+    Ok(a.expr().simplify() == b.expr().simplify())
+}
+
+/// Tolleranza per il confronto numerico di due valori.
+///
+/// Due valori finiti sono uguali se `|a - b| <= max(absolute, relative * max(|a|, |b|))`:
+/// la tolleranza assoluta conta vicino allo zero, quella relativa per i valori grandi.
+///
+/// Derive:
+/// - `Debug`, `Clone`, `Copy`, `PartialEq`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tolerance {
+    /// Differenza massima ammessa in valore assoluto.
+    pub absolute: f64,
+    /// Differenza massima ammessa in proporzione al maggiore dei due valori.
+    pub relative: f64,
+    /// Se `true`, due `NaN` sono considerati uguali (per impostazione predefinita non lo sono, come in IEEE 754).
+    pub nan_equal: bool,
+}
+
+impl Tolerance {
+    /// Costruisce una tolleranza con lo stesso valore `tol` assoluto e relativo; due `NaN` sono diversi.
+    pub fn new(tol: f64) -> Self {
+        Self { absolute: tol, relative: tol, nan_equal: false }
+    }
+
+    /// Verifica se `a` e `b` sono uguali entro la tolleranza.
+    ///
+    /// - `NaN` è uguale solo a `NaN`, e solo con `nan_equal`.
+    /// - Un infinito è uguale solo all'infinito con lo stesso segno.
+    pub fn matches(&self, a: f64, b: f64) -> bool {
+        if a.is_nan() || b.is_nan() { return self.nan_equal && a.is_nan() && b.is_nan(); }
+        if a.is_infinite() || b.is_infinite() { return a == b; }
+
+        (a - b).abs() <= self.absolute.max(self.relative * a.abs().max(b.abs()))
+    }
+}
+
+/// Verifica se due espressioni senza variabili hanno lo stesso valore entro la tolleranza `tol`
+/// (assoluta e relativa, vedi `Tolerance::new`).
+///
+/// A differenza di `expressions_equal` le espressioni vengono valutate: è utile per verificare
+/// riscritture algebriche (`1/3*3` e `1` differiscono solo per l'arrotondamento).
+///
+/// # Ritorna
+/// - `Ok(bool)` con l'esito del confronto.
+/// - `Err(CalcError)` se una delle due espressioni non è valida o la sua valutazione fallisce.
+///
+/// # Esempio
+/// ```
+/// assert!(expressions_approx_equal("1/3*3", "1", 1e-12)?);
+/// ```
+pub fn expressions_approx_equal(a: &str, b: &str, tol: f64) -> Result<bool, CalcError> {
+    expressions_within(a, b, Tolerance::new(tol))
+}
+
+/// Come `expressions_approx_equal`, con una `Tolerance` completa (es. tolleranze assoluta e relativa
+/// diverse, o `NaN` considerati uguali).
+pub fn expressions_within(a: &str, b: &str, tolerance: Tolerance) -> Result<bool, CalcError> {
+    let env = Env::new();
+    let (a, b) = (compile(a)?.eval(&env)?, compile(b)?.eval(&env)?);
+    Ok(tolerance.matches(a, b))
+}
+
+/// Sostituisce un operatore con il suo valore se tutti i suoi operandi sono numeri letterali.
+fn fold_literals(expr: Expr) -> Expr {
+    let literal_operands = match &expr {
+        Expr::Unary(_, operand) => matches!(**operand, Expr::Number(_)),
+        Expr::Binary(_, lhs, rhs) => matches!((&**lhs, &**rhs), (Expr::Number(_), Expr::Number(_))),
+        _ => false,
+    };
+    if !literal_operands { return expr; }
+
+    match expr.eval(&Env::new()) {
+        Ok(value) if value.is_finite() => Expr::Number(value),
+        _ => expr,
+    }
+}
+
+/// Semplifica una catena dell'operatore commutativo `op` (`+` o `*`).
+fn simplify_commutative(op: BinaryOp, expr: &Expr) -> Expr {
+    let mut operands = Vec::new();
+    flatten(op, expr, &mut operands);
+
+    // Raccoglie le costanti in un unico valore, partendo dall'elemento neutro
+    let identity = if op == BinaryOp::Add { 0.0 } else { 1.0 };
+    let mut constant = identity;
+    let mut others = Vec::new();
+    for operand in operands {
+        match operand {
+            Expr::Number(n) => constant = if op == BinaryOp::Add { constant + n } else { constant * n },
+            other => others.push(other),
+        }
+    }
+
+    // Come in `fold_literals`, una costante non finita non viene raccolta (es. `x*1e200*1e200`): la catena
+    // conserva la struttura originale, così che l'overflow dipenda dagli stessi prodotti parziali
+    if !constant.is_finite() {
+        let Expr::Binary(_, lhs, rhs) = expr else { unreachable!("una catena di `op` è un nodo binario") };
+        return Expr::Binary(op, Box::new(lhs.simplify()), Box::new(rhs.simplify()));
+    }
+
+    // Ordine canonico degli operandi non numerici, indipendente da quello del sorgente
+    others.sort_by_cached_key(|operand| format!("{:?}", operand));
+
+    let mut terms = others.into_iter();
+    let first = match terms.next() {
+        None => return Expr::Number(constant),
+        Some(first) if constant == identity => first,
+        Some(first) => Expr::Binary(op, Box::new(Expr::Number(constant)), Box::new(first)),
+    };
+    terms.fold(first, |acc, term| Expr::Binary(op, Box::new(acc), Box::new(term)))
+}
+
+/// Raccoglie in `operands` gli operandi semplificati di una catena di `op` (es. `a + (b + c)` → `[a, b, c]`).
+fn flatten(op: BinaryOp, expr: &Expr, operands: &mut Vec<Expr>) {
+    match expr {
+        Expr::Binary(inner, lhs, rhs) if *inner == op => {
+            flatten(op, lhs, operands);
+            flatten(op, rhs, operands);
+        }
+        other => operands.push(other.simplify()),
+    }
+}
+
+/// Modulo di test per la semplificazione e il confronto di espressioni.
+#[cfg(test)]
+mod tests {
+    use super::*; // Importa tutti i membri del modulo superiore (il codice da testare)
+
+    /// Verifica l'eliminazione delle doppie negazioni e la negazione dei numeri letterali.
+    #[test]
+    fn test_simplify_negations() {
+        let simplified = |input: &str| compile(input).unwrap().expr().clone().simplify_negations();
+
+        assert_eq!(simplified("--3"), Expr::Number(3.0));
+        assert_eq!(simplified("-(-(-3))"), Expr::Number(-3.0));
+        assert_eq!(simplified("- - x"), Expr::Variable("x".into()));
+        assert_eq!(simplified("---x"), Expr::Variable("x".into()).negate());
+        assert_eq!(simplified("2 * --x + sqrt(--4)"), compile("2 * x + sqrt(4)").unwrap().expr().clone());
+
+        // La negazione di una potenza non diventa un letterale: -2^2 = -(2^2)
+        let power = simplified("--(-2^2)");
+        assert_eq!(power, compile("-2^2").unwrap().expr().clone());
+        assert_eq!(power.eval(&Env::new()), Ok(-4.0));
+    }
+
+    /// Verifica che `normalize` lasci solo `+` e `*` tra i termini, con lo stesso valore dell'originale.
+    #[test]
+    fn test_normalize() {
+        let tree = |input: &str| compile(input).unwrap().expr().clone();
+
+        assert_eq!(tree("a - b").normalize(), Expr::var("a") + Expr::var("b").negate());
+        assert_eq!(tree("a / b").normalize(), Expr::var("a") * Expr::var("b").reciprocal());
+        assert_eq!(tree("a - b / c").normalize(), tree("a + -(b * (1 / c))"));
+        assert_eq!(tree("sqrt(x - 1) ^ 2").normalize(), tree("sqrt(x + -1) ^ 2"));
+        assert_eq!(tree("x * 2 + 1").normalize(), tree("x * 2 + 1")); // già normalizzata
+
+        let env = Env::from_iter([("x".to_string(), 7.0), ("y".to_string(), -2.5), ("z".to_string(), 0.3)]);
+        for input in ["x - y - z", "x / y / z", "(x - 3) / (y - z) - x / 4", "2^(x - 5) / sin(y / z)", "-(x - y)! / 3"] {
+            let original = tree(input).eval(&env);
+            let normalized = tree(input).normalize().eval(&env);
+            match (original, normalized) {
+                (Ok(a), Ok(b)) => assert!((a - b).abs() <= 1e-12 * a.abs().max(1.0), "{}: {} != {}", input, a, b),
+                (a, b) => assert_eq!(a.is_ok(), b.is_ok(), "{}", input),
+            }
+        }
+
+        // La divisione per zero resta un errore
+        assert_eq!(tree("1 / (x - x)").normalize().eval(&env), Err(crate::error::MathError::DivisionByZero.into()));
+    }
+
+    /// Verifica l'uguaglianza di espressioni che differiscono per l'ordine degli operandi.
+    #[test]
+    fn test_commutative_equality() {
+        assert_eq!(expressions_equal("2+3", "3+2"), Ok(true));
+        assert_eq!(expressions_equal("x*2", "2*x"), Ok(true));
+        assert_eq!(expressions_equal("a + b + 1", "1 + (b + a)"), Ok(true));
+        assert_eq!(expressions_equal("2^3 * y", "y * 8"), Ok(true));
+        assert_eq!(expressions_equal("x * 1 + 0", "x"), Ok(true));
+    }
+
+    /// Verifica che le costanti il cui prodotto o somma non è finito non vengano raccolte.
+    #[test]
+    fn test_commutative_overflow_not_folded() {
+        for source in ["x*1e200*1e200", "1e308 + y + 1e308", "1e200 * (x * 1e200)"] {
+            let tree = compile(source).unwrap().expr().clone();
+            let simplified = tree.simplify();
+            assert!(!format!("{:?}", simplified).contains("inf"), "{}: {:?}", source, simplified);
+
+            // Stesso valore (o stesso errore di overflow) per ogni ambiente
+            for value in [0.0, 1.0, -2.0] {
+                let env = Env::from_iter([("x".to_string(), value), ("y".to_string(), value)]);
+                assert_eq!(simplified.eval(&env), tree.eval(&env), "{} con {}", source, value);
+            }
+        }
+        assert_eq!(compile("x*1e200*1e200").unwrap().eval(&Env::from_iter([("x".to_string(), 1.0)])), Err(crate::error::MathError::OverflowError.into()));
+        assert_eq!(expressions_equal("2 * x * 3", "6 * x"), Ok(true)); // le costanti finite restano raccolte
+    }
+
+    /// Verifica che espressioni diverse non risultino uguali e che gli errori vengano propagati.
+    #[test]
+    fn test_unequal_expressions() {
+        assert_eq!(expressions_equal("x + 1", "x + 2"), Ok(false));
+        assert_eq!(expressions_equal("x - 1", "1 - x"), Ok(false));
+        assert_eq!(expressions_equal("x / 2", "2 / x"), Ok(false));
+        assert!(expressions_equal("2 +", "2").is_err());
+    }
+
+    /// Verifica il confronto numerico di espressioni entro una tolleranza.
+    #[test]
+    fn test_expressions_approx_equal() {
+        assert_eq!(expressions_approx_equal("2+2", "4", 0.0), Ok(true));
+        assert_eq!(expressions_approx_equal("1/3*3", "1", 1e-12), Ok(true));
+        assert_eq!(expressions_approx_equal("0.1 + 0.2", "0.3", 1e-12), Ok(true));
+        assert_eq!(expressions_approx_equal("1", "1.1", 1e-3), Ok(false));
+        assert_eq!(expressions_approx_equal("10^9", "10^9 + 1", 1e-6), Ok(true)); // tolleranza relativa
+        assert!(expressions_approx_equal("1 / 0", "1", 1e-3).is_err());
+    }
+
+    /// Verifica il trattamento di `NaN` e infiniti.
+    #[test]
+    fn test_tolerance_nan_and_infinity() {
+        let tolerance = Tolerance::new(1e-9);
+        assert!(!tolerance.matches(f64::NAN, f64::NAN));
+        assert!(Tolerance { nan_equal: true, ..tolerance }.matches(f64::NAN, f64::NAN));
+        assert!(!Tolerance { nan_equal: true, ..tolerance }.matches(f64::NAN, 1.0));
+        assert!(tolerance.matches(f64::INFINITY, f64::INFINITY));
+        assert!(!tolerance.matches(f64::INFINITY, f64::NEG_INFINITY));
+        assert!(!tolerance.matches(f64::INFINITY, f64::MAX));
+    }
+
+    /// Verifica che le operazioni con errore non vengano valutate durante la semplificazione.
+    #[test]
+    fn test_simplify_keeps_errors() {
+        let division = compile("1 / 0").unwrap();
+        assert_eq!(division.expr().simplify(), division.expr().clone());
+    }
+}
@@ -0,0 +1,162 @@
+use crate::error::{CalcError, CalcResult, MathError, TokenError};
+use crate::expr::{BinaryOp, Env, Expr, UnaryOp};
+use crate::parser::MathExpressionParser;
+use crate::prelude::*;
+use crate::token::Token;
+use crate::tokenizer::Tokenizer;
+
+/// Risolve un'equazione lineare in una sola incognita, es. `2*x + 3 = 7` → `x = 2`.
+///
+/// Qui `=` è il segno di uguaglianza tra due espressioni, non il terminatore: deve comparire una sola
+/// volta, con un'espressione su entrambi i lati. Ogni lato viene ridotto alla forma `a*var + b`
+/// (`linear_form`), poi l'equazione `a1*var + b1 = a2*var + b2` viene risolta come
+/// `var = (b2 - b1) / (a1 - a2)`.
+///
+/// # Regole
+/// - Le parti che non contengono `var` vengono valutate (es. `sqrt(16) * x = 8`), con gli angoli in radianti.
+/// - `var` può essere moltiplicata o divisa per costanti, ma non moltiplicata per sé stessa, usata come
+///   divisore, esponente o argomento di una funzione: `x^2 = 4` e `1/x = 2` non sono lineari.
+/// - Le costanti predefinite (`pi`, `e`, ...) sono ammesse; ogni altra variabile è un errore.
+///
+/// # Ritorna
+/// - `Ok(f64)` con il valore dell'incognita.
+/// - `Err(TokenError::InvalidExpression)` con la descrizione del problema se l'input non è un'equazione,
+///   non è lineare, contiene altre variabili, oppure non ha soluzioni o ne ha infinite.
+/// - `Err(CalcError)` per gli errori di sintassi dei due lati o di valutazione delle parti costanti.
+///
+/// # Esempio
+/// ```
+/// assert_eq!(solve_linear("2*x + 3 = 7", "x"), Ok(2.0));
+/// assert!(solve_linear("x^2 = 4", "x").is_err());
+/// ```
+pub fn solve_linear(input: &str, var: &str) -> CalcResult {
+    let mut tokenizer = Tokenizer::new(input);
+    let tokens = tokenizer.tokenize()?;
+    let spans = tokenizer.spans();
+
+    let equals: Vec<usize> = tokens.iter().enumerate().filter(|(_, token)| **token == Token::Equals).map(|(i, _)| i).collect();
+    let split = match equals.as_slice() {
+        [split] if *split > 0 && *split + 1 < tokens.len() => *split,
+        _ => return Err(invalid("serve un'equazione della forma 'sinistra = destra', con un solo '='".into())),
+    };
+
+    // Ogni lato è analizzato separatamente, conservando le posizioni nel sorgente
+    let side = |range: core::ops::Range<usize>| {
+        let spans = spans.get(range.clone()).map(<[_]>::to_vec).unwrap_or_default();
+        MathExpressionParser::with_spans(tokens[range].to_vec(), spans).parse_expression()
+    };
+    let (lhs, rhs) = (side(0..split)?, side(split + 1..tokens.len())?);
+
+    let others: Vec<String> = lhs.variables().union(&rhs.variables()).filter(|name| *name != var).cloned().collect();
+    if !others.is_empty() {
+        return Err(invalid(format!(
+            "l'equazione contiene variabili diverse da '{}' ({}): è possibile risolvere una sola incognita",
+            var,
+            others.join(", ")
+        )));
+    }
+
+    let (a1, b1) = linear_form(&lhs, var)?;
+    let (a2, b2) = linear_form(&rhs, var)?;
+    let (coefficient, constant) = (a1 - a2, b2 - b1);
+
+    if coefficient == 0.0 {
+        let message = if constant == 0.0 {
+            format!("l'equazione è un'identità: ogni valore di '{}' è una soluzione", var)
+        } else {
+            format!("l'equazione non ha soluzioni: il coefficiente di '{}' si annulla", var)
+        };
+        return Err(invalid(message));
+    }
+
+    info_log!("Equazione lineare: {} * {} = {}", coefficient, var, constant);
+    Ok(constant / coefficient)
+}
+
+/// Riduce `expr` alla forma `a*var + b`, restituendo la coppia `(a, b)`.
+///
+/// # Ritorna
+/// - `Ok((a, b))` se `expr` è lineare in `var`.
+/// - `Err(TokenError::InvalidExpression)` se `var` compare in modo non lineare (es. `x*x`, `1/x`, `sin(x)`).
+/// - `Err(MathError::DivisionByZero)` per una divisione per una costante nulla.
+fn linear_form(expr: &Expr, var: &str) -> Result<(f64, f64), CalcError> {
+    // Un sottoalbero senza l'incognita è una costante, valutata direttamente
+    if !expr.variables().contains(var) {
+        return Ok((0.0, expr.eval(&Env::new())?));
+    }
+
+    match expr {
+        Expr::Variable(_) => Ok((1.0, 0.0)),
+        Expr::Unary(UnaryOp::Neg, operand) => {
+            let (a, b) = linear_form(operand, var)?;
+            Ok((-a, -b))
+        }
+        Expr::Binary(op @ (BinaryOp::Add | BinaryOp::Sub), lhs, rhs) => {
+            let ((a1, b1), (a2, b2)) = (linear_form(lhs, var)?, linear_form(rhs, var)?);
+            Ok(if *op == BinaryOp::Add { (a1 + a2, b1 + b2) } else { (a1 - a2, b1 - b2) })
+        }
+        Expr::Binary(BinaryOp::Mul, lhs, rhs) => match (linear_form(lhs, var)?, linear_form(rhs, var)?) {
+            ((0.0, k), (a, b)) | ((a, b), (0.0, k)) => Ok((a * k, b * k)),
+            _ => Err(not_linear(var, "l'incognita è moltiplicata per sé stessa")),
+        },
+        Expr::Binary(BinaryOp::Div, lhs, rhs) => match (linear_form(lhs, var)?, linear_form(rhs, var)?) {
+            (_, (0.0, 0.0)) => Err(MathError::DivisionByZero.into()),
+            ((a, b), (0.0, k)) => Ok((a / k, b / k)),
+            _ => Err(not_linear(var, "l'incognita compare al denominatore")),
+        },
+        Expr::Binary(BinaryOp::Pow, base, exponent) if matches!(**exponent, Expr::Number(n) if n == 1.0) => {
+            linear_form(base, var)
+        }
+        _ => Err(not_linear(var, "l'incognita compare in una potenza, radice, funzione o confronto")),
+    }
+}
+
+/// Costruisce l'errore per un'equazione non valida o senza un'unica soluzione.
+fn invalid(message: String) -> CalcError {
+    TokenError::InvalidExpression(message).into()
+}
+
+/// Costruisce l'errore per un'equazione non lineare nell'incognita `var`.
+fn not_linear(var: &str, reason: &str) -> CalcError {
+    invalid(format!("l'equazione non è lineare in '{}': {}", var, reason))
+}
+
+/// Modulo di test per la risoluzione di equazioni lineari.
+#[cfg(test)]
+mod tests {
+    use super::*; // Importa tutti i membri del modulo superiore (il codice da testare)
+
+    /// Verifica la risoluzione di equazioni lineari, con l'incognita su uno o entrambi i lati.
+    #[test]
+    fn test_solve_linear_equations() {
+        assert_eq!(solve_linear("2*x + 3 = 7", "x"), Ok(2.0));
+        assert_eq!(solve_linear("7 = 2x + 3", "x"), Ok(2.0));
+        assert_eq!(solve_linear("3(y - 1) = y + 5", "y"), Ok(4.0));
+        assert_eq!(solve_linear("x / 4 - 1 = 2", "x"), Ok(12.0));
+        assert_eq!(solve_linear("-x = 5", "x"), Ok(-5.0));
+        assert_eq!(solve_linear("sqrt(16) * t = 2^3", "t"), Ok(2.0));
+        assert!((solve_linear("2 pi r = 1", "r").unwrap() - 1.0 / core::f64::consts::TAU).abs() < 1e-15);
+    }
+
+    /// Verifica il rifiuto di equazioni non lineari, con altre variabili o senza un'unica soluzione.
+    #[test]
+    fn test_solve_linear_rejections() {
+        let message = |input: &str| match solve_linear(input, "x") {
+            Err(CalcError::Token(TokenError::InvalidExpression(message))) => message,
+            other => panic!("errore descrittivo atteso per '{}', ottenuto {:?}", input, other),
+        };
+
+        assert!(message("x^2 - 4 = 0").contains("non è lineare"));
+        assert!(message("x * x = 4").contains("moltiplicata per sé stessa"));
+        assert!(message("1 / x = 2").contains("denominatore"));
+        assert!(message("sin(x) = 0").contains("non è lineare"));
+        assert!(message("x + y = 3").contains("y"));
+        assert!(message("x + 1 = x + 2").contains("non ha soluzioni"));
+        assert!(message("2x = x + x").contains("identità"));
+        assert!(message("2x + 3").contains("sinistra = destra"));
+        assert!(message("x = 1 = 2").contains("un solo '='"));
+
+        assert_eq!(solve_linear("x / 0 = 1", "x"), Err(MathError::DivisionByZero.into()));
+        assert!(matches!(solve_linear("2 * = 4", "x"), Err(CalcError::Token(_))));
+    }
+}
@@ -0,0 +1,188 @@
+use crate::error::CalcResult;
+use crate::expr::{BinaryOp, Env, Expr, UnaryOp};
+use crate::prelude::*;
+
+/// Numero massimo di fattori mostrati nello sviluppo di un fattoriale o di una potenza.
+///
+/// Oltre questo limite (es. `20!`, `2^64`) lo sviluppo non è più leggibile e il passaggio viene omesso.
+pub const MAX_EXPANDED_FACTORS: usize = 12;
+
+/// Mostra i passaggi di calcolo di fattoriali e potenze intere, a scopo didattico.
+///
+/// I passaggi seguono l'ordine di valutazione: prima gli operandi, poi l'operazione che li usa.
+/// - Fattoriale: `5! = 5*4*3*2*1 = 120`.
+/// - Potenza con esponente intero piccolo: `2^3 = 2*2*2 = 8`.
+///
+/// Sono omessi i casi banali (`0!`, `1!`, esponenti minori di 2), quelli con più di
+/// `MAX_EXPANDED_FACTORS` fattori e le operazioni la cui valutazione produce un errore.
+///
+/// # Esempio
+/// ```
+/// let f = compile("2^3 + 5! =")?;
+/// assert_eq!(show_work(f.expr(), &Env::new()), vec!["2^3 = 2*2*2 = 8", "5! = 5*4*3*2*1 = 120"]);
+/// ```
+pub fn show_work(expr: &Expr, env: &Env) -> Vec<String> {
+    let mut steps = Vec::new();
+    collect_steps(expr, env, &mut steps);
+    steps
+}
+
+/// Traccia la valutazione dell'albero operazione per operazione, nell'ordine in cui viene eseguita.
+///
+/// Ogni passo riporta un'operazione con gli operandi già valutati e il suo risultato (`3 * 4 = 12`);
+/// le variabili compaiono con il loro valore (`x = 2`). I valori letterali non producono passi.
+/// Gli angoli delle funzioni trigonometriche sono in radianti, come in `Expr::eval`.
+///
+/// # Ritorna
+/// I passi eseguiti e il risultato finale: in caso di errore i passi si fermano all'operazione che lo ha prodotto.
+///
+/// # Esempio
+/// ```
+/// let f = compile("2 + 3 * 4")?;
+/// let (steps, result) = trace_evaluation(f.expr(), &Env::new());
+/// assert_eq!(steps, vec!["3 * 4 = 12", "2 + 12 = 14"]);
+/// assert_eq!(result, Ok(14.0));
+/// ```
+pub fn trace_evaluation(expr: &Expr, env: &Env) -> (Vec<String>, CalcResult) {
+    let mut steps = Vec::new();
+    let result = trace_node(expr, env, &mut steps);
+    (steps, result)
+}
+
+/// Valuta un nodo dopo i suoi operandi, aggiungendo a `steps` l'operazione eseguita.
+///
+/// Ogni operazione viene valutata sui valori già calcolati degli operandi, così da non ripetere la visita dei sottoalberi.
+fn trace_node(expr: &Expr, env: &Env, steps: &mut Vec<String>) -> CalcResult {
+    let (step, result) = match expr {
+        Expr::Number(n) => return Ok(*n),
+
+        Expr::Variable(name) => {
+            let value = expr.eval(env)?;
+            (name.clone(), Ok(value))
+        }
+
+        Expr::Unary(op, operand) => {
+            let value = trace_node(operand, env, steps)?;
+            let step = match op {
+                UnaryOp::Neg => format!("-({})", value),
+                UnaryOp::Factorial => format!("{}!", value),
+            };
+            (step, Expr::Unary(*op, Box::new(Expr::Number(value))).eval(env))
+        }
+
+        Expr::Binary(op, lhs, rhs) => {
+            let (lhs, rhs) = (trace_node(lhs, env, steps)?, trace_node(rhs, env, steps)?);
+            let node = Expr::Binary(*op, Box::new(Expr::Number(lhs)), Box::new(Expr::Number(rhs)));
+            (format!("{} {} {}", lhs, op.symbol(), rhs), node.eval(env))
+        }
+
+        Expr::Call(function, args) => {
+            let values = args.iter().map(|arg| trace_node(arg, env, steps)).collect::<Result<Vec<_>, _>>()?;
+            let shown: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+            let node = Expr::Call(*function, values.into_iter().map(Expr::Number).collect());
+            (format!("{}({})", function, shown.join(", ")), node.eval(env))
+        }
+
+        Expr::UserCall(name, args) => {
+            let values = args.iter().map(|arg| trace_node(arg, env, steps)).collect::<Result<Vec<_>, _>>()?;
+            let shown: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+            let node = Expr::UserCall(name.clone(), values.into_iter().map(Expr::Number).collect());
+            (format!("{}({})", name, shown.join(", ")), node.eval(env))
+        }
+    };
+
+    let value = result?;
+    steps.push(format!("{} = {}", step, value));
+    Ok(value)
+}
+
+/// Visita l'albero in post-ordine, aggiungendo a `steps` lo sviluppo di ogni fattoriale e potenza.
+fn collect_steps(expr: &Expr, env: &Env, steps: &mut Vec<String>) {
+    match expr {
+        Expr::Number(_) | Expr::Variable(_) => {}
+
+        Expr::Unary(op, operand) => {
+            collect_steps(operand, env, steps);
+
+            if *op == UnaryOp::Factorial
+                && let Ok(n) = operand.eval(env)
+                && let Some(count) = factor_count(n)
+                && let Ok(result) = expr.eval(env)
+            {
+                let factors: Vec<String> = (1..=count).rev().map(|k| k.to_string()).collect();
+                steps.push(format!("{}! = {} = {}", n, factors.join("*"), result));
+            }
+        }
+
+        Expr::Binary(op, lhs, rhs) => {
+            collect_steps(lhs, env, steps);
+            collect_steps(rhs, env, steps);
+
+            if *op == BinaryOp::Pow
+                && let (Ok(base), Ok(exponent)) = (lhs.eval(env), rhs.eval(env))
+                && let Some(count) = factor_count(exponent)
+                && let Ok(result) = expr.eval(env)
+            {
+                // Le basi negative vanno tra parentesi: (-2)^3 = (-2)*(-2)*(-2)
+                let factor = if base < 0.0 { format!("({})", base) } else { base.to_string() };
+                steps.push(format!("{}^{} = {} = {}", factor, exponent, vec![factor.as_str(); count].join("*"), result));
+            }
+        }
+
+        Expr::Call(_, args) | Expr::UserCall(_, args) => {
+            for arg in args { collect_steps(arg, env, steps); }
+        }
+    }
+}
+
+/// Restituisce il numero di fattori dello sviluppo se `value` è un intero in `[2, MAX_EXPANDED_FACTORS]`.
+fn factor_count(value: f64) -> Option<usize> {
+    let in_range = (2.0..=MAX_EXPANDED_FACTORS as f64).contains(&value) && value.fract() == 0.0;
+    in_range.then_some(value as usize)
+}
+
+/// Modulo di test per i passaggi di calcolo.
+#[cfg(test)]
+mod tests {
+    use super::*; // Importa tutti i membri del modulo superiore (il codice da testare)
+    use crate::compile::compile;
+
+    /// Restituisce i passaggi di calcolo dell'espressione, senza variabili.
+    fn steps(input: &str) -> Vec<String> {
+        show_work(compile(input).unwrap().expr(), &Env::new())
+    }
+
+    /// Verifica lo sviluppo del fattoriale e della potenza.
+    #[test]
+    fn test_show_work_expansions() {
+        assert_eq!(steps("5! ="), vec!["5! = 5*4*3*2*1 = 120"]);
+        assert_eq!(steps("2^3 ="), vec!["2^3 = 2*2*2 = 8"]);
+        assert_eq!(steps("(-2)^3 ="), vec!["(-2)^3 = (-2)*(-2)*(-2) = -8"]);
+        assert_eq!(steps("2^3! ="), vec!["3! = 3*2*1 = 6", "2^6 = 2*2*2*2*2*2 = 64"]);
+    }
+
+    /// Verifica la traccia delle operazioni, anche quando la valutazione si interrompe per un errore.
+    #[test]
+    fn test_trace_evaluation() {
+        let trace = |input: &str, env: &Env| trace_evaluation(compile(input).unwrap().expr(), env);
+
+        assert_eq!(trace("2 + 3 * 4", &Env::new()), (vec!["3 * 4 = 12".to_string(), "2 + 12 = 14".into()], Ok(14.0)));
+        assert_eq!(
+            trace("-x^2 + sqrt(16)", &Env::from([("x".to_string(), 3.0)])),
+            (vec!["x = 3".to_string(), "3 ^ 2 = 9".into(), "-(9) = -9".into(), "sqrt(16) = 4".into(), "-9 + 4 = -5".into()], Ok(-5.0))
+        );
+        assert_eq!(trace("3! * 2", &Env::new()).0, vec!["3! = 6", "6 * 2 = 12"]);
+
+        let (steps, result) = trace("(1 + 1) / (2 - 2) + 5", &Env::new());
+        assert_eq!(steps, vec!["1 + 1 = 2", "2 - 2 = 0"]);
+        assert_eq!(result, Err(crate::error::MathError::DivisionByZero.into()));
+    }
+
+    /// Verifica che i casi banali, troppo lunghi o non validi vengano omessi.
+    #[test]
+    fn test_show_work_skips() {
+        assert!(steps("1! + 2^1 + 2^0.5 =").is_empty());
+        assert!(steps("20! + 2^64 =").is_empty());
+        assert!(steps("2 + 3 =").is_empty());
+    }
+}
@@ -0,0 +1,431 @@
+use crate::prelude::*;
+
+/// Associatività di un operatore binario, restituita da `Token::associativity`.
+///
+/// Derive:
+/// - `Debug`, `Clone`, `Copy`, `PartialEq`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Assoc {
+    /// `a - b - c` → `(a - b) - c`.
+    Left,
+
+    /// `a ^ b ^ c` → `a ^ (b ^ c)`.
+    Right,
+
+    /// L'operatore non può essere concatenato: `1 < 2 < 3` è un errore di sintassi.
+    NonAssociative,
+}
+
+/// # Enum `Token`
+///
+/// Rappresenta i token lessicali riconosciuti.
+/// Ogni variante corrisponde a un tipo di simbolo nel linguaggio aritmetico:
+/// - `Number(f64)`: un numero decimale.
+/// - `Plus`, `Minus`, `Multiply`, `Divide`: operatori aritmetici.
+/// - `Caret`, 'Dollar': simboli di potenza e radice.
+/// - `Radical`: radice quadrata prefissa (`√16`).
+/// - `Bang`: fattoriale postfisso.
+/// - `Percent`, `Of`: percentuale postfissa e parola chiave `of` (solo con `Tokenizer::set_percent_mode`).
+/// - `Less`, `LessEqual`, `Greater`, `GreaterEqual`, `EqualEqual`: operatori di confronto.
+/// - `Identifier(String)`: nome di variabile.
+/// - `LeftParen`, `RightParen`: parentesi tonde.
+/// - `LeftBracket`, `RightBracket`: parentesi quadre delle liste (es. `mean([1, 2, 3])`).
+/// - `Semicolon`: separatore tra espressioni in una lista.
+/// - `Comma`: separatore tra gli argomenti di una funzione.
+/// - `Equals`: simbolo di fine espressione o assegnazione.
+///
+/// Derive:
+/// - `Debug`: per la stampa leggibile durante debug/log.
+/// - `Clone`: per duplicare i token (non `Copy`, poiché gli identificatori possiedono una `String`).
+/// - `PartialEq`: per confrontare i token tra loro (es parser).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    /// Numero reale (es. 3.14, 42.0)
+    Number(f64),
+
+    /// Identificatore di variabile (es. x, totale, a_1)
+    Identifier(String),
+    
+    /// Operatore di somma: '+'
+    Plus,       
+    
+    /// Operatore di sottrazione: '-'
+    Minus,       
+    
+    /// Operatore di moltiplicazione: '*'
+    Multiply,  
+    
+    /// Operatore di divisione: '/'
+    Divide,
+
+    /// Simbolo di potenza: '^'
+    Caret,
+
+    /// Simbolo di radice n-esima: '$'
+    Dollar,
+
+    /// Radice quadrata prefissa: '√' (es. `√16` = 4)
+    Radical,
+
+    /// Fattoriale postfisso: '!'
+    Bang,
+
+    /// Percentuale postfissa: '%' (solo con `Tokenizer::set_percent_mode`)
+    Percent,
+
+    /// Parola chiave 'of', che applica la percentuale precedente a un valore (es. `20% of 50`)
+    Of,
+
+    /// Confronto minore: '<'
+    Less,
+
+    /// Confronto minore o uguale: '<='
+    LessEqual,
+
+    /// Confronto maggiore: '>'
+    Greater,
+
+    /// Confronto maggiore o uguale: '>='
+    GreaterEqual,
+
+    /// Confronto di uguaglianza: '=='
+    EqualEqual,
+
+    /// Parentesi aperta: '('
+    LeftParen, 
+    
+    /// Parentesi chiusa: ')'
+    RightParen,  
+
+    /// Parentesi quadra aperta, inizio di una lista: '['
+    LeftBracket,
+
+    /// Parentesi quadra chiusa, fine di una lista: ']'
+    RightBracket,
+
+    /// Separatore tra espressioni: ';'
+    Semicolon,
+
+    /// Separatore tra argomenti di funzione: ','
+    Comma,
+    
+    /// Simbolo di fine espressione: '='
+    Equals,
+}
+
+/// Tipo di un token, senza il valore associato (il numero o il nome dell'identificatore).
+///
+/// Usato per descrivere i token ammessi in un punto dell'input, es. in
+/// `CalcError::expected_tokens` per il completamento automatico.
+/// Le varianti corrispondono una a una a quelle di `Token` (vedi `Token::kind`).
+///
+/// Derive:
+/// - `Debug`, `Clone`, `Copy`, `PartialEq`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TokenKind {
+    Number,
+    Identifier,
+    Plus,
+    Minus,
+    Multiply,
+    Divide,
+    Caret,
+    Dollar,
+    Radical,
+    Bang,
+    Percent,
+    Of,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    EqualEqual,
+    LeftParen,
+    RightParen,
+    LeftBracket,
+    RightBracket,
+    Semicolon,
+    Comma,
+    Equals,
+}
+
+impl Token {
+    /// Crea un token a partire da un carattere specifico.
+    ///
+    /// Restituisce `Some(Token)` se il carattere corrisponde a un token valido,
+    /// altrimenti `None`. Per il testo copiato da documenti sono accettati anche gli operatori
+    /// Unicode `×` (moltiplicazione), `÷` (divisione), `−` (U+2212, meno) e `√` (radice quadrata).
+    ///
+    /// # Parametri
+    /// - `c`: Il carattere da interpretare come token.
+    ///
+    /// # Esempio
+    /// ```
+    /// assert_eq!(Token::from_char('+'), Some(Token::Plus));
+    /// assert_eq!(Token::from_char('x'), None);
+    /// ```
+    #[inline] // Suggerisce al compilatore di inserire questa funzione inline per efficienza.
+    pub(crate) fn from_char(c: char) -> Option<Self> {
+        match c {
+            '+' => Some(Token::Plus),
+            '-' | '−' => Some(Token::Minus),
+            '*' | '×' => Some(Token::Multiply),
+            '/' | '÷' => Some(Token::Divide),
+            '√' => Some(Token::Radical),
+            '^' => Some(Token::Caret),
+            '$' => Some(Token::Dollar),
+            '!' => Some(Token::Bang),
+            '<' => Some(Token::Less),
+            '>' => Some(Token::Greater),
+            '(' => Some(Token::LeftParen),
+            ')' => Some(Token::RightParen),
+            '[' => Some(Token::LeftBracket),
+            ']' => Some(Token::RightBracket),
+            ';' => Some(Token::Semicolon),
+            ',' => Some(Token::Comma),
+            '=' => Some(Token::Equals),
+            _ => None, // carattere non riconosciuto come token
+        }
+    }
+
+    /// Verifica se il token è un operatore binario (matematico o di confronto).
+    ///
+    /// # Esempio
+    /// ```
+    /// assert!(Token::Plus.is_operator());
+    /// assert!(Token::Caret.is_operator());
+    /// assert!(!Token::LeftParen.is_operator());
+    /// ```
+    #[inline]
+    pub fn is_operator(&self) -> bool {
+        matches!(self, Token::Plus | Token::Minus | Token::Multiply | Token::Divide | Token::Caret | Token::Dollar) || self.is_comparison()
+    }
+
+    /// Verifica se il token è un operatore di confronto (`<`, `<=`, `>`, `>=`, `==`).
+    #[inline]
+    pub fn is_comparison(&self) -> bool {
+        matches!(self, Token::Less | Token::LessEqual | Token::Greater | Token::GreaterEqual | Token::EqualEqual)
+    }
+
+    /// Restituisce la precedenza dell'operatore binario, come nella grammatica (`CFG.txt`):
+    /// più alto il valore, più forte il legame.
+    ///
+    /// - `1`: confronti (`R`)
+    /// - `2`: `+` e `-` (`E`)
+    /// - `3`: `*` e `/` (`P`)
+    /// - `4`: `^` e `$` (`U`)
+    ///
+    /// Il `-` è considerato nella forma binaria: la negazione prefissa lega più di `*` ma meno di `^` (`-2^2` = -4).
+    ///
+    /// # Ritorna
+    /// - `Some(u8)` per gli operatori binari.
+    /// - `None` per gli altri token.
+    ///
+    /// # Esempio
+    /// ```
+    /// assert!(Token::Plus.precedence() < Token::Multiply.precedence());
+    /// assert_eq!(Token::LeftParen.precedence(), None);
+    /// ```
+    pub fn precedence(&self) -> Option<u8> {
+        match self {
+            token if token.is_comparison() => Some(1),
+            Token::Plus | Token::Minus => Some(2),
+            Token::Multiply | Token::Divide => Some(3),
+            Token::Caret | Token::Dollar => Some(4),
+            _ => None,
+        }
+    }
+
+    /// Restituisce l'associatività dell'operatore binario, come nella grammatica (`CFG.txt`).
+    ///
+    /// `^` e `$` sono associativi a destra con la configurazione predefinita
+    /// (`MathExpressionParser::set_exponent_assoc` può renderli associativi a sinistra).
+    ///
+    /// # Ritorna
+    /// - `Some(Assoc)` per gli operatori binari.
+    /// - `None` per gli altri token.
+    pub fn associativity(&self) -> Option<Assoc> {
+        match self {
+            token if token.is_comparison() => Some(Assoc::NonAssociative),
+            Token::Plus | Token::Minus | Token::Multiply | Token::Divide => Some(Assoc::Left),
+            Token::Caret | Token::Dollar => Some(Assoc::Right),
+            _ => None,
+        }
+    }
+
+    /// Restituisce il tipo del token, senza il valore associato.
+    ///
+    /// # Esempio
+    /// ```
+    /// assert_eq!(Token::Number(2.0).kind(), TokenKind::Number);
+    /// assert_eq!(Token::Plus.kind(), TokenKind::Plus);
+    /// ```
+    pub fn kind(&self) -> TokenKind {
+        match self {
+            Token::Number(_) => TokenKind::Number,
+            Token::Identifier(_) => TokenKind::Identifier,
+            Token::Plus => TokenKind::Plus,
+            Token::Minus => TokenKind::Minus,
+            Token::Multiply => TokenKind::Multiply,
+            Token::Divide => TokenKind::Divide,
+            Token::Caret => TokenKind::Caret,
+            Token::Dollar => TokenKind::Dollar,
+            Token::Radical => TokenKind::Radical,
+            Token::Bang => TokenKind::Bang,
+            Token::Percent => TokenKind::Percent,
+            Token::Of => TokenKind::Of,
+            Token::Less => TokenKind::Less,
+            Token::LessEqual => TokenKind::LessEqual,
+            Token::Greater => TokenKind::Greater,
+            Token::GreaterEqual => TokenKind::GreaterEqual,
+            Token::EqualEqual => TokenKind::EqualEqual,
+            Token::LeftParen => TokenKind::LeftParen,
+            Token::RightParen => TokenKind::RightParen,
+            Token::LeftBracket => TokenKind::LeftBracket,
+            Token::RightBracket => TokenKind::RightBracket,
+            Token::Semicolon => TokenKind::Semicolon,
+            Token::Comma => TokenKind::Comma,
+            Token::Equals => TokenKind::Equals,
+        }
+    }
+}
+
+/// Implementazione del trait `Display` per `Token`.
+///
+/// Restituisce la rappresentazione testuale del token così come appare nel sorgente,
+/// utile nei messaggi di errore (es. `token inatteso '*'`).
+impl core::fmt::Display for Token {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            Token::Number(n) => write!(f, "{}", n),
+            Token::Identifier(name) => write!(f, "{}", name),
+            Token::Plus => write!(f, "+"),
+            Token::Minus => write!(f, "-"),
+            Token::Multiply => write!(f, "*"),
+            Token::Divide => write!(f, "/"),
+            Token::Caret => write!(f, "^"),
+            Token::Dollar => write!(f, "$"),
+            Token::Radical => write!(f, "√"),
+            Token::Bang => write!(f, "!"),
+            Token::Percent => write!(f, "%"),
+            Token::Of => write!(f, "of"),
+            Token::Less => write!(f, "<"),
+            Token::LessEqual => write!(f, "<="),
+            Token::Greater => write!(f, ">"),
+            Token::GreaterEqual => write!(f, ">="),
+            Token::EqualEqual => write!(f, "=="),
+            Token::LeftParen => write!(f, "("),
+            Token::RightParen => write!(f, ")"),
+            Token::LeftBracket => write!(f, "["),
+            Token::RightBracket => write!(f, "]"),
+            Token::Semicolon => write!(f, ";"),
+            Token::Comma => write!(f, ","),
+            Token::Equals => write!(f, "="),
+        }
+    }
+}
+
+/// Ricostruisce una stringa sorgente canonica da una sequenza di token, utile per log e test.
+///
+/// Ogni token è scritto con il suo `Display`, separato dal precedente da un solo spazio, tranne
+/// dopo `(` e `[`, prima di `)`, `]`, `,`, `;`, `!` e `%` e tra un nome di funzione e la sua `(`
+/// (es. `(2+3)*4=` → `(2 + 3) * 4 =`). Il risultato,
+/// tokenizzato di nuovo, produce la stessa sequenza di token.
+///
+/// # Esempio
+/// ```
+/// let tokens = Tokenizer::new("sqrt(16)+2^3!=").tokenize()?;
+/// assert_eq!(tokens_to_string(&tokens), "sqrt(16) + 2 ^ 3! =");
+/// ```
+pub fn tokens_to_string(tokens: &[Token]) -> String {
+    let mut source = String::new();
+    let mut previous: Option<&Token> = None;
+
+    for token in tokens {
+        let glued = matches!(previous, None | Some(Token::LeftParen) | Some(Token::LeftBracket) | Some(Token::Radical))
+            || matches!(token, Token::RightParen | Token::RightBracket | Token::Comma | Token::Semicolon | Token::Bang | Token::Percent)
+            || matches!((previous, token), (Some(Token::Identifier(_)), Token::LeftParen));
+        if !glued { source.push(' '); }
+        source.push_str(&token.to_string());
+        previous = Some(token);
+    }
+    source
+}
+
+/// Intervallo di caratteri `[start, end)` occupato da un token nella stringa di input.
+pub type Span = core::ops::Range<usize>;
+
+/// Modulo di test per i token.
+#[cfg(test)]
+mod tests {
+    use super::*; // Importa tutti i membri del modulo superiore (il codice da testare)
+    use crate::compile::compile;
+    use crate::expr::Expr;
+    use crate::tokenizer::Tokenizer;
+
+    /// Operatori binari della grammatica.
+    const BINARY_OPERATORS: [Token; 11] = [
+        Token::Plus, Token::Minus, Token::Multiply, Token::Divide, Token::Caret, Token::Dollar,
+        Token::Less, Token::LessEqual, Token::Greater, Token::GreaterEqual, Token::EqualEqual,
+    ];
+
+    /// Verifica che precedenza e associatività descrivano l'albero prodotto dal parser per `a op1 b op2 c`.
+    #[test]
+    fn test_precedence_matches_grammar() {
+        for first in &BINARY_OPERATORS {
+            for second in &BINARY_OPERATORS {
+                let source = format!("a {} b {} c", first, second);
+                let (p1, p2) = (first.precedence().unwrap(), second.precedence().unwrap());
+
+                // Operatore atteso alla radice: quello che lega meno, o a pari livello quello indicato dall'associatività
+                let root = match (p1.cmp(&p2), first.associativity().unwrap()) {
+                    (core::cmp::Ordering::Less, _) => first,
+                    (core::cmp::Ordering::Greater, _) => second,
+                    (_, Assoc::Left) => second,
+                    (_, Assoc::Right) => first,
+                    (_, Assoc::NonAssociative) => {
+                        assert!(compile(&source).is_err(), "{}", source);
+                        continue;
+                    }
+                };
+                match compile(&source).unwrap().expr() {
+                    Expr::Binary(op, ..) => assert_eq!(op.symbol(), root.to_string(), "{}", source),
+                    other => panic!("operazione binaria attesa per '{}', ottenuto {:?}", source, other),
+                }
+            }
+        }
+    }
+
+    /// Verifica l'ordinamento dei livelli e l'assenza di precedenza per i token che non sono operatori binari.
+    #[test]
+    fn test_precedence_levels() {
+        assert!(Token::Less.precedence() < Token::Plus.precedence());
+        assert!(Token::Minus.precedence() < Token::Divide.precedence());
+        assert!(Token::Multiply.precedence() < Token::Caret.precedence());
+        assert_eq!(Token::Caret.precedence(), Token::Dollar.precedence());
+        assert_eq!(Token::Caret.associativity(), Some(Assoc::Right));
+        assert_eq!(Token::Minus.associativity(), Some(Assoc::Left));
+
+        for token in [Token::Number(1.0), Token::Identifier("x".into()), Token::Bang, Token::Percent, Token::LeftParen, Token::Equals, Token::Comma] {
+            assert_eq!((token.precedence(), token.associativity()), (None, None), "{}", token);
+        }
+    }
+
+    /// Verifica che la stringa ricostruita, tokenizzata di nuovo, produca gli stessi token.
+    #[test]
+    fn test_tokens_to_string_round_trip() {
+        for (input, expected) in [
+            ("2+3*4=", "2 + 3 * 4 ="),
+            ("(2+3)*-4=", "(2 + 3) * - 4 ="),
+            ("max(1,2)+3!=", "max(1, 2) + 3! ="),
+            ("x=2;x^2>=4=", "x = 2; x ^ 2 >= 4 ="),
+            ("mean([1,2])=", "mean([1, 2]) ="),
+            ("2√(9)×3=", "2 √(9) * 3 ="),
+        ] {
+            let tokens = Tokenizer::new(input).tokenize().unwrap();
+            let source = tokens_to_string(&tokens);
+            assert_eq!(source, expected);
+            assert_eq!(Tokenizer::new(&source).tokenize().unwrap(), tokens);
+        }
+    }
+}
@@ -0,0 +1,701 @@
+use crate::error::TokenError;
+use crate::prelude::*;
+use crate::token::{Span, Token};
+
+/// Separatore decimale dei numeri letterali.
+///
+/// Con `Comma` la virgola non separa più gli argomenti delle funzioni: le due convenzioni sono
+/// incompatibili, e le funzioni con più argomenti (`log(8, 2)`, `gcd`, `powmod`) non possono essere
+/// scritte. Una virgola non seguita da una cifra produce `TokenError::SyntaxError`, invece di essere
+/// confusa con un separatore di argomenti.
+///
+/// Derive:
+/// - `Debug`, `Clone`, `Copy`, `PartialEq`, `Default`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum LocaleMode {
+    /// Punto decimale (`3.14`); la virgola separa gli argomenti delle funzioni.
+    #[default]
+    Dot,
+
+    /// Virgola decimale (`3,14`), come nella notazione italiana; il punto non è ammesso nei numeri.
+    Comma,
+}
+
+impl LocaleMode {
+    /// Restituisce il carattere usato come separatore decimale.
+    pub fn decimal_separator(&self) -> char {
+        match self {
+            LocaleMode::Dot => '.',
+            LocaleMode::Comma => ',',
+        }
+    }
+}
+
+/// Caratteri riconosciuti come spazi bianchi tra i token.
+///
+/// Derive:
+/// - `Debug`, `Clone`, `Copy`, `PartialEq`, `Default`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum WhitespacePolicy {
+    /// Solo spazio, tabulazione, `\r` e `\n`: gli altri spazi Unicode (es. lo spazio non separabile
+    /// `U+00A0`, frequente nei testi copiati) producono `TokenError::InvalidOperator`.
+    Ascii,
+
+    /// Ogni carattere con `char::is_whitespace`, compresi gli spazi Unicode.
+    #[default]
+    Unicode,
+}
+
+impl WhitespacePolicy {
+    /// Verifica se `c` è uno spazio bianco secondo la politica.
+    pub fn is_whitespace(&self, c: char) -> bool {
+        match self {
+            WhitespacePolicy::Ascii => matches!(c, ' ' | '\t' | '\r' | '\n'),
+            WhitespacePolicy::Unicode => c.is_whitespace(),
+        }
+    }
+}
+
+/// Struttura responsabile dell'analisi lessicale di un'espressione matematica.
+///
+/// Divide la stringa di input in una sequenza di token riconoscibili.
+/// Tiene traccia della posizione corrente durante la scansione.
+/// - `'a`: Lifetime del riferimento alla stringa di input.
+/// - Utilizza un riferimento immutabile (`&'a str`) per evitare copie non necessarie della stringa.
+/// - L'input viene scorso una sola volta sui byte: l'accesso al carattere corrente e l'avanzamento
+///   costano O(1) (decodifica UTF-8 solo per i caratteri non ASCII).
+/// - `position` tiene traccia dell'indice (in byte) corrente, `column` del corrispondente indice in caratteri.
+/// - `spans` memorizza, per ogni token prodotto, l'intervallo occupato nel sorgente (in caratteri).
+pub struct Tokenizer<'a> {
+    /// Slice immutabile della stringa di input contenente l'espressione da analizzare.
+    input: &'a str,
+    /// Posizione corrente nell'input (indice di byte), utilizzata per tracciare l'avanzamento durante la tokenizzazione.
+    position: usize,
+    /// Posizione corrente nell'input espressa in caratteri, usata per gli intervalli dei token.
+    column: usize,
+    /// Intervalli nel sorgente dei token prodotti, paralleli al vettore restituito da `tokenize()`.
+    spans: Vec<Span>,
+    /// Modalità rigorosa: le tabulazioni non sono ammesse come spazi bianchi.
+    strict_whitespace: bool,
+    /// Caratteri ammessi come spazi bianchi (predefinita: tutti gli spazi Unicode).
+    whitespace: WhitespacePolicy,
+    /// Separatore decimale dei numeri (predefinito: punto).
+    locale: LocaleMode,
+    /// Modalità percentuale: `%` è la percentuale postfissa invece di un operatore non valido.
+    percent: bool,
+}
+
+impl<'a> Tokenizer<'a> {
+    /// Crea una nuova istanza di `Tokenizer` per una data stringa di input.
+    ///
+    /// # Parametri
+    /// - `input`: riferimento alla stringa da analizzare.
+    ///
+    /// # Ritorna
+    /// - Istanza inizializzata di `Tokenizer` con posizione iniziale a zero.
+    pub fn new(input: &'a str) -> Self {
+        info_log!("Tokenizer creato. Input: '{}'", input);
+        Self { input, position: 0, column: 0, spans: Vec::new(), strict_whitespace: false, whitespace: WhitespacePolicy::default(), locale: LocaleMode::default(), percent: false }
+    }
+
+    /// Attiva o disattiva la modalità rigorosa sugli spazi bianchi.
+    ///
+    /// In modalità rigorosa una tabulazione produce `TokenError::SyntaxError` con la sua posizione,
+    /// per imporre la sola indentazione con spazi (es. nei file di formule salvate).
+    /// Per impostazione predefinita le tabulazioni sono normali spazi bianchi.
+    pub fn set_strict_whitespace(&mut self, strict: bool) {
+        self.strict_whitespace = strict;
+    }
+
+    /// Imposta i caratteri ammessi come spazi bianchi (vedi `WhitespacePolicy`).
+    ///
+    /// Con `WhitespacePolicy::Ascii` uno spazio Unicode copiato per errore viene segnalato invece di essere ignorato.
+    pub fn set_whitespace_policy(&mut self, policy: WhitespacePolicy) {
+        self.whitespace = policy;
+    }
+
+    /// Imposta il separatore decimale dei numeri (vedi `LocaleMode` per il conflitto con gli argomenti delle funzioni).
+    pub fn set_locale_mode(&mut self, locale: LocaleMode) {
+        self.locale = locale;
+    }
+
+    /// Attiva o disattiva la modalità percentuale.
+    ///
+    /// Se attiva, `%` produce `Token::Percent` (`20%` = 0.2, `20% of 50` = 10); altrimenti resta un
+    /// operatore non valido. La parola chiave `of` è riconosciuta in ogni caso, così che il parser
+    /// possa segnalarne l'uso senza percentuale.
+    pub fn set_percent_mode(&mut self, enabled: bool) {
+        self.percent = enabled;
+    }
+
+    /// Restituisce gli intervalli nel sorgente dei token prodotti dall'ultima `tokenize()`.
+    ///
+    /// L'elemento `i` corrisponde al token `i` del vettore restituito da `tokenize()`.
+    pub fn spans(&self) -> &[Span] {
+        &self.spans
+    }
+
+    /// Restituisce il numero di caratteri occupati nel sorgente dal token all'indice `index`
+    /// dell'ultima `tokenize()`, utile per sottolineare un token nei messaggi di errore.
+    ///
+    /// La larghezza è quella del testo originale, non della forma canonica del token:
+    /// `3.14e2` occupa 6 colonne anche se il token è `Number(314.0)`.
+    ///
+    /// # Ritorna
+    /// - `Some(usize)` con la larghezza in caratteri.
+    /// - `None` se `index` è fuori dai token prodotti.
+    ///
+    /// # Esempio
+    /// ```
+    /// let mut tokenizer = Tokenizer::new("3.14e2 + 1");
+    /// tokenizer.tokenize()?;
+    /// assert_eq!((tokenizer.token_width(0), tokenizer.token_width(1)), (Some(6), Some(1)));
+    /// ```
+    pub fn token_width(&self, index: usize) -> Option<usize> {
+        self.spans.get(index).map(|span| span.len())
+    }
+
+    /// Analizza la stringa di input e produce una sequenza di token.
+    ///
+    /// # Ritorna
+    /// - `Ok(Vec<Token>)` in caso di successo.
+    /// - `Err(TokenError)` se viene rilevato un errore di sintassi o simbolo non valido.
+    pub fn tokenize(&mut self) -> Result<Vec<Token>, TokenError> {
+        info_log!("Avvio tokenizzazione");
+        let mut tokens = Vec::new();
+        let mut spans = Vec::new();
+        self.position = 0;
+        self.column = 0;
+
+        // Scorre ogni carattere, saltando spazi bianchi e commenti, finché non raggiunge la fine dell'input.
+        loop {
+            self.skip_whitespace()?;
+            if self.skip_comment()? { continue; }
+            let Some(c) = self.peek_char() else { break };
+
+            match c {
+                // Gestisce sequenze numeriche, inclusi decimali.
+                c if c.is_ascii_digit() || self.starts_fraction(c) => {
+                    let start = self.column;
+                    let token = self.parse_number()?;
+                    info_log!("Token numero trovato: {:?}", token);
+                    tokens.push(token);
+                    spans.push(start..self.column);
+                }
+
+                // Gestisce identificatori di variabile (lettera o '_' seguiti da lettere, cifre o '_').
+                c if c.is_ascii_alphabetic() || c == '_' => {
+                    let start = self.column;
+                    let token = self.parse_identifier();
+                    info_log!("Token identificatore trovato: {:?}", token);
+                    tokens.push(token);
+                    spans.push(start..self.column);
+                }
+
+                // Con la virgola decimale, una virgola isolata non può separare argomenti (vedi `LocaleMode`).
+                ',' if self.locale == LocaleMode::Comma => {
+                    let message = format!(
+                        "Virgola non seguita da una cifra alla posizione {}: con la virgola decimale gli argomenti non possono essere separati",
+                        self.column
+                    );
+                    return Err(TokenError::SyntaxError(message));
+                }
+
+                // Percentuale postfissa, solo in modalità percentuale.
+                '%' if self.percent => {
+                    tokens.push(Token::Percent);
+                    spans.push(self.column..self.column + 1);
+                    self.advance_ascii();
+                }
+
+                // Gestisce gli operatori di confronto di due caratteri ('<=', '>=', '==').
+                '<' | '>' | '=' if self.input.as_bytes().get(self.position + 1) == Some(&b'=') => {
+                    let token = match c {
+                        '<' => Token::LessEqual,
+                        '>' => Token::GreaterEqual,
+                        _ => Token::EqualEqual,
+                    };
+                    info_log!("Token confronto trovato: {:?}", token);
+                    tokens.push(token);
+                    spans.push(self.column..self.column + 2);
+                    self.advance_ascii();
+                    self.advance_ascii();
+                }
+
+                // Gestisce simboli e operatori.
+                c => {
+                    // Gestione token riconosciuti.
+                    if let Some(token) = Token::from_char(c) {
+                        info_log!("Token simbolo trovato: {:?}", token);
+                        tokens.push(token);
+                        spans.push(self.column..self.column + 1);
+                        self.advance(c);
+                    } 
+                    // Gestisce token non riconosciuti con InvalidOperator, c - carattere non riconosciuto.
+                    else {
+                        return Err(TokenError::InvalidOperator { operator: c, position: self.column });
+                    }
+                }
+            }
+        }
+        
+        // Tokenizzazione completata, ritorna OK e il vettore di Token da parsare.
+        info_log!("Tokenizzazione completata: {:?}", tokens);
+        self.spans = spans;
+        Ok(tokens)
+    }
+
+    /// Analizza e costruisce un token numerico a partire dalla posizione corrente.
+    ///
+    /// Supporta numeri interi e decimali. Non sono ammessi più punti decimali.
+    ///
+    /// Il carattere `_` è ammesso come separatore delle cifre (es. `1_000.5`), ma solo tra due cifre:
+    /// forme come `1_.5`, `1._5`, `1_.`, `1__0` o `1_` vengono rifiutate.
+    ///
+    /// Il separatore decimale dipende da `LocaleMode`: con `Comma` la virgola sostituisce il punto
+    /// (`3,14`) e deve essere seguita da una cifra, altrimenti chiude il numero.
+    ///
+    /// La notazione scientifica è riconosciuta solo all'interno di un numero: `e`/`E` seguita da cifre,
+    /// con segno facoltativo, è l'esponente (`2e3` = 2000, `1.5E-4` = 0.00015). Una `e` non seguita
+    /// da cifre non fa parte del numero ed è l'identificatore `e` (la costante di Nepero):
+    /// `2e` e `2 e` sono la moltiplicazione implicita `2 * e`, così come `2e - 3` è `2 * e - 3`.
+    ///
+    /// # Ritorna
+    /// - `Ok(Token::Number(f64))` se il parsing ha successo.
+    /// - `Err(TokenError::InvalidNumber)` in caso di numero malformato.
+    fn parse_number(&mut self) -> Result<Token, TokenError> {
+        let start = self.position;
+        let separator = self.locale.decimal_separator() as u8;
+        let mut has_decimal = false;
+        let mut has_exponent = false;
+        let mut has_separator = false;
+
+        // Continua a leggere finché i caratteri fanno parte del numero.
+        while let Some(byte) = self.current_byte() {
+            match byte {
+                b'0'..=b'9' => self.advance_ascii(),
+
+                // L'esponente è intero: nessun punto decimale dopo 'e'. Esempio non valido: "2e3.5"
+                b if b == separator && has_exponent => return Err(TokenError::InvalidNumber("Esponente non intero".into())),
+
+                // La virgola decimale deve essere seguita da una cifra (es. "3,14" ma non "3,)")
+                b',' if !self.digit_follows() => break,
+
+                // Accetta un solo punto decimale.
+                b if b == separator && !has_decimal => {
+                    has_decimal = true;
+                    self.advance_ascii();
+                }
+
+                // Rifiuta numeri con più punti decimali.
+                // Se viene trovato un secondo '.' in un numero già marcato come decimale, viene generato un errore.
+                // Esempio non valido: "2..3"
+                b if b == separator => return Err(TokenError::InvalidNumber("Numero con più punti decimali".into())),
+
+                // Separatore delle cifre: valido solo se preceduto e seguito da una cifra.
+                // Esempi non validi: "1_.5", "1._5", "1_.", "1__0"
+                b'_' => {
+                    let bytes = self.input.as_bytes();
+                    let after_digit = self.position > start && bytes[self.position - 1].is_ascii_digit();
+                    let before_digit = bytes.get(self.position + 1).is_some_and(u8::is_ascii_digit);
+
+                    if !(after_digit && before_digit) {
+                        return Err(TokenError::InvalidNumber("Separatore '_' non compreso tra due cifre".into()));
+                    }
+                    has_separator = true;
+                    self.advance_ascii();
+                }
+
+                // Esponente in notazione scientifica: 'e'/'E' seguita da cifre, con segno facoltativo.
+                // Altrimenti la 'e' non appartiene al numero e inizia un identificatore (es. "2e" → 2 * e).
+                b'e' | b'E' if !has_exponent && self.exponent_follows() => {
+                    has_exponent = true;
+                    self.advance_ascii();
+                    if matches!(self.current_byte(), Some(b'+' | b'-')) { self.advance_ascii(); }
+                }
+
+                // Interrompe la lettura alla prima occorrenza non numerica.
+                _ => break,
+            }
+        }
+
+        // Estrae la sottostringa rappresentante un numero dalla posizione iniziale fino alla posizione corrente.
+        let number_str = &self.input[start..self.position];
+
+        // Rimuove gli eventuali separatori e normalizza la virgola decimale, non accettati da `parse::<f64>()`.
+        let mut number_str = if has_separator { number_str.replace('_', "") } else { number_str.to_string() };
+        if self.locale == LocaleMode::Comma { number_str = number_str.replace(',', "."); }
+
+        // Tenta la conversione della sottostringa in un valore numerico `f64`.
+        // In caso di successo, restituisce un token `Token::Number(n)` contenente il valore.
+        // In caso di errore nel parsing, genera un errore `TokenError::InvalidNumber` contenente la stringa non valida.
+        match number_str.parse::<f64>() {
+            Ok(n) => Ok(Token::Number(n)),
+            Err(_) => Err(TokenError::InvalidNumber(number_str)),
+        }
+    }
+
+    /// Verifica se il carattere `c` alla posizione corrente inizia un numero senza parte intera
+    /// (es. `.5`, oppure `,5` con la virgola decimale).
+    fn starts_fraction(&self, c: char) -> bool {
+        match self.locale {
+            LocaleMode::Dot => c == '.',
+            LocaleMode::Comma => c == ',' && self.digit_follows(),
+        }
+    }
+
+    /// Verifica se il carattere successivo a quello corrente è una cifra.
+    fn digit_follows(&self) -> bool {
+        self.input.as_bytes().get(self.position + 1).is_some_and(u8::is_ascii_digit)
+    }
+
+    /// Verifica se la `e`/`E` alla posizione corrente introduce un esponente, cioè se è seguita
+    /// da una cifra, eventualmente preceduta dal segno (`e3`, `e-3`, `E+3`).
+    fn exponent_follows(&self) -> bool {
+        let bytes = self.input.as_bytes();
+        let digits_at = match bytes.get(self.position + 1) {
+            Some(b'+' | b'-') => self.position + 2,
+            _ => self.position + 1,
+        };
+        bytes.get(digits_at).is_some_and(u8::is_ascii_digit)
+    }
+
+    /// Analizza e costruisce un token identificatore a partire dalla posizione corrente.
+    ///
+    /// Un identificatore inizia con una lettera ASCII o `_` e prosegue con lettere, cifre o `_`
+    /// (es. `x`, `totale`, `a_1`). La parola riservata `of` produce `Token::Of`.
+    fn parse_identifier(&mut self) -> Token {
+        let start = self.position;
+
+        while let Some(byte) = self.current_byte() {
+            match byte {
+                b if b.is_ascii_alphanumeric() || b == b'_' => self.advance_ascii(),
+                _ => break,
+            }
+        }
+
+        match &self.input[start..self.position] {
+            "of" => Token::Of,
+            name => Token::Identifier(name.to_string()),
+        }
+    }
+
+    /// Restituisce il carattere alla posizione corrente della scansione, senza consumarlo.
+    ///
+    /// Per i byte ASCII la conversione è diretta; altrimenti decodifica il solo carattere corrente.
+    /// Prima di `tokenize()` è il primo carattere dell'input, dopo una tokenizzazione riuscita la scansione
+    /// è terminata e il risultato è `None`.
+    ///
+    /// # Ritorna
+    /// - `Some(char)` con il carattere corrente.
+    /// - `None` a fine input (mai un panic, anche oltre la fine).
+    pub fn peek_char(&self) -> Option<char> {
+        let byte = *self.input.as_bytes().get(self.position)?;
+        if byte.is_ascii() {
+            Some(byte as char)
+        } else {
+            self.input.get(self.position..)?.chars().next()
+        }
+    }
+
+    /// Salta gli spazi bianchi, secondo `WhitespacePolicy`, a partire dalla posizione corrente.
+    ///
+    /// # Ritorna
+    /// - `Ok(())` quando il carattere corrente non è uno spazio bianco o l'input è terminato.
+    /// - `Err(TokenError::SyntaxError)` per una tabulazione in modalità rigorosa (vedi `set_strict_whitespace`).
+    fn skip_whitespace(&mut self) -> Result<(), TokenError> {
+        while let Some(c) = self.peek_char().filter(|&c| self.whitespace.is_whitespace(c)) {
+            // Rifiuta le tabulazioni in modalità rigorosa.
+            if c == '\t' && self.strict_whitespace {
+                let message = format!("Tabulazione non ammessa alla posizione {}", self.column);
+                return Err(TokenError::SyntaxError(message));
+            }
+            self.advance(c);
+        }
+        Ok(())
+    }
+
+    /// Salta il commento che inizia alla posizione corrente, senza produrre token.
+    ///
+    /// - `#` commenta fino alla fine della riga (il `\n` finale resta uno spazio bianco).
+    /// - `/* ... */` commenta il testo racchiuso, anche su più righe; i commenti non si annidano.
+    ///
+    /// # Ritorna
+    /// - `Ok(true)` se è stato saltato un commento, `Ok(false)` se il carattere corrente non ne inizia uno.
+    /// - `Err(TokenError::UnexpectedEnd)` per un commento `/*` non chiuso, senza token attesi.
+    fn skip_comment(&mut self) -> Result<bool, TokenError> {
+        let rest = &self.input[self.position..];
+        let length = if rest.starts_with('#') {
+            rest.find('\n').unwrap_or(rest.len())
+        } else if let Some(body) = rest.strip_prefix("/*") {
+            let end = body.find("*/").ok_or_else(|| TokenError::UnexpectedEnd { expected: Vec::new() })?;
+            end + 4 // '/*', il testo e '*/'
+        } else {
+            return Ok(false);
+        };
+
+        info_log!("Commento saltato: '{}'", &rest[..length]);
+        self.column += rest[..length].chars().count();
+        self.position += length;
+        Ok(true)
+    }
+
+    /// Restituisce il byte corrente dell'input, o `None` a fine input.
+    ///
+    /// Usato nelle scansioni di numeri e identificatori, composti solo da caratteri ASCII.
+    fn current_byte(&self) -> Option<u8> {
+        self.input.as_bytes().get(self.position).copied()
+    }
+
+    /// Avanza di un carattere sapendo che il carattere corrente è ASCII (un solo byte).
+    fn advance_ascii(&mut self) {
+        self.position += 1;
+        self.column += 1;
+    }
+
+    /// Avanza la posizione corrente oltre il carattere `c`, spostandosi al carattere successivo dell'input.
+    /// La posizione in byte avanza della lunghezza UTF-8 di `c`, la colonna di uno.
+    /// Presuppone che `c` sia il carattere restituito da `peek_char()`.
+    fn advance(&mut self, c: char) {
+        self.position += c.len_utf8();
+        self.column += 1;
+    }
+}
+
+/// Modulo di test per il tokenizer.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    /// Verifica la virgola decimale con `LocaleMode::Comma` e il suo conflitto con gli argomenti.
+    #[test]
+    fn test_tokenize_comma_locale() {
+        let tokens = |input: &str| {
+            let mut tokenizer = Tokenizer::new(input);
+            tokenizer.set_locale_mode(LocaleMode::Comma);
+            tokenizer.tokenize()
+        };
+
+        assert_eq!(tokens("3,14"), Ok(vec![Token::Number("3.14".parse().unwrap())]));
+        assert_eq!(tokens(",5 + 1_000,25e1"), Ok(vec![Token::Number(0.5), Token::Plus, Token::Number(10002.5)]));
+        assert_eq!(tokens("1,2,3"), Err(TokenError::InvalidNumber("Numero con più punti decimali".into())));
+        assert_eq!(tokens("3.14"), Err(TokenError::InvalidOperator { operator: '.', position: 1 }));
+        assert!(matches!(tokens("log(8, 2)"), Err(TokenError::SyntaxError(_))));
+        assert!(matches!(tokens("(3,)"), Err(TokenError::SyntaxError(_))));
+
+        // Nella modalità predefinita la virgola resta il separatore degli argomenti
+        assert_eq!(Tokenizer::new("3,14").tokenize(), Ok(vec![Token::Number(3.0), Token::Comma, Token::Number(14.0)]));
+    }
+
+    /// Verifica `peek_char` all'inizio, a fine input e su un carattere non ASCII.
+    #[test]
+    fn test_peek_char() {
+        let mut tokenizer = Tokenizer::new("€2 + 1");
+        assert_eq!(tokenizer.peek_char(), Some('€'));
+        assert!(tokenizer.tokenize().is_err()); // '€' non è un operatore: la scansione si ferma lì
+        assert_eq!(tokenizer.peek_char(), Some('€'));
+
+        let mut tokenizer = Tokenizer::new("  2 + 1  ");
+        assert_eq!(tokenizer.peek_char(), Some(' '));
+        assert!(tokenizer.tokenize().is_ok());
+        assert_eq!(tokenizer.peek_char(), None); // a fine input
+        assert_eq!(tokenizer.peek_char(), None); // ripetuto oltre la fine, senza panic
+
+        assert_eq!(Tokenizer::new("").peek_char(), None);
+    }
+
+    /// Verifica la larghezza nel sorgente di numeri, identificatori e operatori.
+    #[test]
+    fn test_token_width() {
+        let mut tokenizer = Tokenizer::new("3.14e2 + 1_000 * totale <= (.5)");
+        tokenizer.tokenize().unwrap();
+
+        let widths: Vec<_> = (0..9).map(|i| tokenizer.token_width(i)).collect();
+        assert_eq!(widths, [6, 1, 5, 1, 6, 2, 1, 2, 1].map(Some));
+        assert_eq!(tokenizer.token_width(9), None);
+    }
+
+    /// Verifica `%` in modalità percentuale e la parola chiave `of`.
+    #[test]
+    fn test_tokenize_percent_mode() {
+        let mut tokenizer = Tokenizer::new("20% of 50");
+        tokenizer.set_percent_mode(true);
+        assert_eq!(tokenizer.tokenize(), Ok(vec![Token::Number(20.0), Token::Percent, Token::Of, Token::Number(50.0)]));
+        assert_eq!(tokenizer.spans(), [0..2, 2..3, 4..6, 7..9]);
+
+        assert_eq!(Tokenizer::new("20% of 50").tokenize(), Err(TokenError::InvalidOperator { operator: '%', position: 2 }));
+        assert_eq!(Tokenizer::new("of offset").tokenize(), Ok(vec![Token::Of, Token::Identifier("offset".into())]));
+    }
+
+    /// Verifica la notazione scientifica all'interno dei numeri e la `e` come identificatore.
+    #[test]
+    fn test_tokenize_scientific_notation() {
+        let tokens = |input: &str| Tokenizer::new(input).tokenize();
+
+        assert_eq!(tokens("2e3"), Ok(vec![Token::Number(2000.0)]));
+        assert_eq!(tokens("1.5E-4"), Ok(vec![Token::Number(1.5e-4)]));
+        assert_eq!(tokens("1_000e+1_0"), Ok(vec![Token::Number(1e13)]));
+        assert_eq!(tokens("2e"), Ok(vec![Token::Number(2.0), Token::Identifier("e".into())]));
+        assert_eq!(tokens("2 e"), Ok(vec![Token::Number(2.0), Token::Identifier("e".into())]));
+        assert_eq!(tokens("2ex"), Ok(vec![Token::Number(2.0), Token::Identifier("ex".into())]));
+        assert_eq!(
+            tokens("2e-x"),
+            Ok(vec![Token::Number(2.0), Token::Identifier("e".into()), Token::Minus, Token::Identifier("x".into())])
+        );
+        assert_eq!(tokens("2e3.5"), Err(TokenError::InvalidNumber("Esponente non intero".into())));
+
+        let mut tokenizer = Tokenizer::new("1e3 + e");
+        tokenizer.tokenize().unwrap();
+        assert_eq!(tokenizer.spans(), &[0..3, 4..5, 6..7]);
+    }
+
+    /// Verifica gli operatori di confronto di due caratteri e i loro intervalli.
+    #[test]
+    fn test_tokenize_comparisons() {
+        let mut tokenizer = Tokenizer::new("x<=2==y =");
+        assert_eq!(
+            tokenizer.tokenize().unwrap(),
+            vec![
+                Token::Identifier("x".into()), Token::LessEqual, Token::Number(2.0),
+                Token::EqualEqual, Token::Identifier("y".into()), Token::Equals,
+            ]
+        );
+        assert_eq!(tokenizer.spans(), &[0..1, 1..3, 3..4, 4..6, 6..7, 8..9]);
+    }
+
+    /// Verifica token e intervalli prodotti per un'espressione semplice.
+    #[test]
+    fn test_tokenize_tokens_and_spans() {
+        let mut tokenizer = Tokenizer::new("12.5 * (x - 3) =");
+        let tokens = tokenizer.tokenize().unwrap();
+
+        assert_eq!(tokens, vec![
+            Token::Number(12.5), Token::Multiply, Token::LeftParen, Token::Identifier("x".into()),
+            Token::Minus, Token::Number(3.0), Token::RightParen, Token::Equals,
+        ]);
+        assert_eq!(tokenizer.spans(), &[0..4, 5..6, 7..8, 8..9, 10..11, 12..13, 13..14, 15..16]);
+    }
+
+    /// Verifica che gli errori del tokenizer restino invariati.
+    #[test]
+    fn test_tokenize_errors() {
+        assert_eq!(
+            Tokenizer::new("2..3 =").tokenize(),
+            Err(TokenError::InvalidNumber("Numero con più punti decimali".into()))
+        );
+        assert_eq!(Tokenizer::new("2 % 3 =").tokenize(), Err(TokenError::InvalidOperator { operator: '%', position: 2 }));
+    }
+
+    /// Verifica che i commenti `#` e `/* ... */` vengano saltati, conservando le posizioni dei token successivi.
+    #[test]
+    fn test_comments() {
+        let mut tokenizer = Tokenizer::new("2 + 3 /* tre */ =");
+        assert_eq!(tokenizer.tokenize(), Ok(vec![Token::Number(2.0), Token::Plus, Token::Number(3.0), Token::Equals]));
+        assert_eq!(tokenizer.spans(), &[0..1, 2..3, 4..5, 16..17]);
+
+        let mut tokenizer = Tokenizer::new("1 /* è\nlungo */*4 # fine riga\n+ 2 # ultimo");
+        assert_eq!(
+            tokenizer.tokenize(),
+            Ok(vec![Token::Number(1.0), Token::Multiply, Token::Number(4.0), Token::Plus, Token::Number(2.0)])
+        );
+        assert_eq!(tokenizer.spans()[1], 15..16);
+
+        assert_eq!(Tokenizer::new("# solo commento").tokenize(), Ok(vec![]));
+        assert_eq!(Tokenizer::new("6/*/2*/3").tokenize(), Ok(vec![Token::Number(6.0), Token::Number(3.0)]));
+        assert_eq!(Tokenizer::new("6 / 2").tokenize(), Ok(vec![Token::Number(6.0), Token::Divide, Token::Number(2.0)]));
+    }
+
+    /// Verifica che un commento `/*` non chiuso produca `UnexpectedEnd`.
+    #[test]
+    fn test_unterminated_block_comment() {
+        assert_eq!(Tokenizer::new("2 + 3 /* tre =").tokenize(), Err(TokenError::UnexpectedEnd { expected: vec![] }));
+        assert_eq!(Tokenizer::new("2 /*/").tokenize(), Err(TokenError::UnexpectedEnd { expected: vec![] }));
+        assert_eq!(Tokenizer::new("2 */ 3").tokenize(), Ok(vec![Token::Number(2.0), Token::Multiply, Token::Divide, Token::Number(3.0)]));
+    }
+
+    /// Verifica che le tabulazioni siano rifiutate solo in modalità rigorosa.
+    #[test]
+    fn test_tabs_in_strict_mode() {
+        assert_eq!(Tokenizer::new("1\t+ 2 =").tokenize().unwrap().len(), 4);
+
+        let mut tokenizer = Tokenizer::new("1 +\t2 =");
+        tokenizer.set_strict_whitespace(true);
+        assert_eq!(tokenizer.tokenize(), Err(TokenError::SyntaxError("Tabulazione non ammessa alla posizione 3".into())));
+
+        let mut tokenizer = Tokenizer::new("1 + 2 =");
+        tokenizer.set_strict_whitespace(true);
+        assert!(tokenizer.tokenize().is_ok());
+    }
+
+    /// Verifica lo spazio non separabile con le due politiche sugli spazi bianchi.
+    #[test]
+    fn test_whitespace_policy() {
+        let input = "2\u{a0}+ 3 =";
+        assert_eq!(Tokenizer::new(input).tokenize().unwrap().len(), 4); // Unicode (predefinita)
+
+        let mut tokenizer = Tokenizer::new(input);
+        tokenizer.set_whitespace_policy(WhitespacePolicy::Ascii);
+        assert_eq!(tokenizer.tokenize(), Err(TokenError::InvalidOperator { operator: '\u{a0}', position: 1 }));
+
+        let mut tokenizer = Tokenizer::new("1\t+\r\n2 =");
+        tokenizer.set_whitespace_policy(WhitespacePolicy::Ascii);
+        assert_eq!(tokenizer.tokenize(), Ok(vec![Token::Number(1.0), Token::Plus, Token::Number(2.0), Token::Equals]));
+
+        assert!(WhitespacePolicy::Unicode.is_whitespace('\u{2003}'));
+        assert!(!WhitespacePolicy::Ascii.is_whitespace('\u{2003}'));
+    }
+
+    /// Verifica che i caratteri non ASCII vengano gestiti senza panico e con posizioni in caratteri.
+    #[test]
+    fn test_tokenize_non_ascii_input() {
+        let mut tokenizer = Tokenizer::new("2\u{a0}+ 3 =");
+        assert_eq!(tokenizer.tokenize().unwrap(), vec![Token::Number(2.0), Token::Plus, Token::Number(3.0), Token::Equals]);
+        assert_eq!(tokenizer.spans()[1], 2..3);
+
+        assert_eq!(Tokenizer::new("2 € 3 =").tokenize(), Err(TokenError::InvalidOperator { operator: '€', position: 2 }));
+    }
+
+    /// Verifica gli operatori Unicode `×`, `÷`, `−` e `√`, con gli intervalli in caratteri.
+    #[test]
+    fn test_tokenize_unicode_operators() {
+        let mut tokenizer = Tokenizer::new("6×7÷2−√9");
+        assert_eq!(
+            tokenizer.tokenize().unwrap(),
+            vec![Token::Number(6.0), Token::Multiply, Token::Number(7.0), Token::Divide, Token::Number(2.0), Token::Minus, Token::Radical, Token::Number(9.0)]
+        );
+        assert_eq!(tokenizer.spans(), &[0..1, 1..2, 2..3, 3..4, 4..5, 5..6, 6..7, 7..8]);
+    }
+
+    /// Verifica che un input di grandi dimensioni (~100k caratteri) venga tokenizzato in tempo lineare.
+    #[test]
+    fn test_tokenize_large_input_timing() {
+        let input = format!("{}1 =", "1.5+".repeat(25_000));
+        assert!(input.len() > 100_000);
+
+        let start = Instant::now();
+        let tokens = Tokenizer::new(&input).tokenize().unwrap();
+        let elapsed = start.elapsed();
+
+        assert_eq!(tokens.len(), 50_002);
+        assert!(elapsed < Duration::from_secs(1), "tokenizzazione troppo lenta: {:?}", elapsed);
+    }
+
+    /// Verifica i separatori `_` validi tra le cifre.
+    #[test]
+    fn test_digit_separators() {
+        assert_eq!(Tokenizer::new("1_000.5").tokenize(), Ok(vec![Token::Number(1000.5)]));
+        assert_eq!(Tokenizer::new("1_000_000").tokenize(), Ok(vec![Token::Number(1_000_000.0)]));
+        assert_eq!(Tokenizer::new("0.000_1").tokenize(), Ok(vec![Token::Number(0.0001)]));
+    }
+
+    /// Verifica che i separatori adiacenti al punto decimale o non seguiti da una cifra vengano rifiutati.
+    #[test]
+    fn test_invalid_digit_separators() {
+        let error = Err(TokenError::InvalidNumber("Separatore '_' non compreso tra due cifre".into()));
+
+        for input in ["1_.5", "1._5", "1_.", "1__0", "1_", "1_ + 2"] {
+            assert_eq!(Tokenizer::new(input).tokenize(), error, "input: {}", input);
+        }
+    }
+}
@@ -0,0 +1,60 @@
+//! Punto di ingresso per l'uso della libreria da JavaScript (WebAssembly).
+//!
+//! Con la feature `wasm`, `eval_expression` viene esportata con `wasm-bindgen`; senza, il modulo
+//! contiene solo `evaluate_expression`, la funzione nativa su cui si appoggia.
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::compile::compile;
+use crate::expr::Env;
+use crate::prelude::*;
+
+/// Valuta una singola espressione senza variabili, restituendo l'eventuale errore come messaggio.
+///
+/// L'input segue le regole di `compile` (`=` finale facoltativo, niente assegnazioni).
+/// L'errore è il testo di `CalcError` (il suo `Display`), adatto a essere mostrato
+/// così com'è in un'interfaccia web.
+///
+/// # Esempio
+/// ```
+/// assert_eq!(evaluate_expression("2 + 3 * 4"), Ok(14.0));
+/// assert!(evaluate_expression("1 / 0").is_err());
+/// ```
+pub fn evaluate_expression(input: &str) -> Result<f64, String> {
+    compile(input)
+        .and_then(|expr| expr.eval(&Env::new()))
+        .map_err(|err| err.to_string())
+}
+
+/// Versione di `evaluate_expression` esportata verso JavaScript: in caso di errore lancia
+/// un'eccezione con il messaggio come stringa.
+///
+/// # Esempio (JavaScript)
+/// ```js
+/// import init, { eval_expression } from "./pkg/mathsolver.js";
+/// await init();
+/// eval_expression("2 ^ 10"); // 1024
+/// ```
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn eval_expression(input: &str) -> Result<f64, JsValue> {
+    evaluate_expression(input).map_err(|message| JsValue::from_str(&message))
+}
+
+/// Modulo di test per il punto di ingresso WebAssembly.
+#[cfg(test)]
+mod tests {
+    use super::*; // Importa tutti i membri del modulo superiore (il codice da testare)
+    use crate::error::{CalcError, MathError};
+
+    /// Verifica la valutazione e la conversione degli errori in messaggi.
+    #[test]
+    fn test_evaluate_expression() {
+        assert_eq!(evaluate_expression("2 + 3 * 4"), Ok(14.0));
+        assert_eq!(evaluate_expression("(1 + 1) ^ 10 ="), Ok(1024.0));
+        assert_eq!(evaluate_expression("1 / 0"), Err(CalcError::from(MathError::DivisionByZero).to_string()));
+        assert!(evaluate_expression("2 +").is_err());
+        assert!(evaluate_expression("x + 1").is_err()); // nessuna variabile definita
+    }
+}
@@ -0,0 +1,130 @@
+//! Test di integrazione dell'eseguibile da riga di comando.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Esegue il binario con gli argomenti indicati e restituisce lo stdout.
+fn run(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_MathSolver"))
+        .args(args)
+        .output()
+        .expect("impossibile avviare il binario");
+
+    String::from_utf8(output.stdout).expect("output non UTF-8")
+}
+
+/// Esegue il binario con gli argomenti indicati e restituisce codice di uscita e stderr.
+fn run_failing(args: &[&str]) -> (Option<i32>, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_MathSolver"))
+        .args(args)
+        .output()
+        .expect("impossibile avviare il binario");
+
+    (output.status.code(), String::from_utf8(output.stderr).expect("output non UTF-8"))
+}
+
+/// Verifica che la modalità angolare esplicita venga riportata accanto al risultato.
+#[test]
+fn test_angle_mode_annotation() {
+    assert_eq!(run(&["--assume-degrees", "2 + 3 ="]).trim(), "Risultato: 5.000 (degrees)");
+    assert_eq!(run(&["--assume-radians", "2 + 3 ="]).trim(), "Risultato: 5.000 (radians)");
+}
+
+/// Verifica che senza flag il risultato non venga annotato.
+#[test]
+fn test_no_annotation_by_default() {
+    assert_eq!(run(&["2 + 3 ="]).trim(), "Risultato: 5.000");
+}
+
+/// Verifica la precisione dei risultati impostata con `MATHSOLVER_PRECISION`, e il ripiego su 3 decimali.
+#[test]
+fn test_precision_env_var() {
+    let run_with = |precision: &str| {
+        Command::new(env!("CARGO_BIN_EXE_MathSolver"))
+            .arg("1/3=")
+            .env("MATHSOLVER_PRECISION", precision)
+            .output()
+            .expect("impossibile avviare il binario")
+    };
+
+    let output = run_with("6");
+    assert_eq!(String::from_utf8(output.stdout).unwrap().trim(), "Risultato: 0.333333");
+
+    let output = run_with("molti");
+    assert_eq!(String::from_utf8(output.stdout).unwrap().trim(), "Risultato: 0.333");
+    assert!(String::from_utf8(output.stderr).unwrap().contains("MATHSOLVER_PRECISION"));
+}
+
+/// Verifica che la modalità angolare sia usata dalle funzioni trigonometriche.
+#[test]
+fn test_angle_mode_applies_to_trigonometry() {
+    assert_eq!(run(&["--assume-degrees", "sin(90) ="]).trim(), "Risultato: 1.000 (degrees)");
+    assert_eq!(run(&["cos(pi) ="]).trim(), "Risultato: -1.000");
+}
+
+/// Verifica che `--explain-error` mostri errore, cursore e suggerimento per una parentesi non chiusa.
+#[test]
+fn test_explain_error_unmatched_parenthesis() {
+    let (code, stderr) = run_failing(&["--explain-error", "2 * (1 + 2 ="]);
+
+    assert_eq!(code, Some(1));
+    assert!(stderr.contains("2 * (1 + 2 =\n    ^ "), "stderr: {}", stderr);
+    assert!(stderr.contains("parentesi '(' non bilanciata"), "stderr: {}", stderr);
+    assert!(stderr.contains("Suggerimento: Hai dimenticato una parentesi chiusa ')'?"), "stderr: {}", stderr);
+}
+
+/// Verifica che `--explain` mostri lo sviluppo del fattoriale prima del risultato.
+#[test]
+fn test_explain_shows_work() {
+    assert_eq!(run(&["--explain", "5! ="]).trim(), "5! = 5*4*3*2*1 = 120\nRisultato: 120.000");
+}
+
+/// Verifica i codici di uscita di `--predicate`: 0 se vero, 1 se falso, 2 in caso di errore.
+#[test]
+fn test_predicate_exit_codes() {
+    assert_eq!(run_failing(&["--predicate", "3 > 2"]).0, Some(0));
+    assert_eq!(run_failing(&["--predicate", "2 >= 3 ="]).0, Some(1));
+    assert_eq!(run_failing(&["--predicate", "1 / 0"]).0, Some(2));
+}
+
+/// Verifica la notazione scientifica per risultati molto grandi o molto piccoli.
+#[test]
+fn test_scientific_output() {
+    assert_eq!(run(&["10^12 * 1.5 ="]).trim(), "Risultato: 1.50e+12");
+    assert_eq!(run(&["1 / 10^8 ="]).trim(), "Risultato: 1.00e-08");
+}
+
+/// Verifica l'output JSON per un risultato valido e per un errore, con il codice di uscita.
+#[test]
+fn test_json_output() {
+    assert_eq!(run(&["--json", "2+2="]).trim(), r#"{"input":"2+2=","result":4.0,"error":null}"#);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_MathSolver"))
+        .args(["--json", "1/0="])
+        .output()
+        .expect("impossibile avviare il binario");
+    let stdout = String::from_utf8(output.stdout).expect("output non UTF-8");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout.starts_with(r#"{"input":"1/0=","result":null,"error":{"code":"DivByZero","message":"#));
+}
+
+/// Verifica che una sequenza di assegnazioni mostri solo il valore dell'ultima istruzione.
+#[test]
+fn test_chained_assignment_output() {
+    assert_eq!(run(&["a = 3; b = a + 1; b * 2 ="]).trim(), "Risultato: 8.000");
+}
+
+/// Verifica la lettura delle espressioni da una pipe, senza argomenti.
+#[test]
+fn test_stdin_pipe() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_MathSolver"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("impossibile avviare il binario");
+    child.stdin.take().expect("stdin non disponibile").write_all(b"2+2=\n3 * 3\n").expect("scrittura fallita");
+
+    let output = child.wait_with_output().expect("esecuzione fallita");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8(output.stdout).expect("output non UTF-8"), "4.000\n9.000\n");
+}